@@ -0,0 +1,36 @@
+//! Compares [`all_short_bsd_info`] against querying each pid with [`proc_pidinfo`].
+
+#[cfg(target_vendor = "apple")]
+mod bench {
+    use criterion::{criterion_group, Criterion};
+    use proc_pidinfo::*;
+
+    fn short_info(c: &mut Criterion) {
+        let mut group = c.benchmark_group("short_bsd_info");
+        group.bench_function("naive", |b| {
+            b.iter(|| {
+                proc_listallpids()
+                    .unwrap()
+                    .into_iter()
+                    .filter_map(|pid| proc_pidinfo::<ProcBSDShortInfo>(pid).ok().flatten())
+                    .collect::<Vec<_>>()
+            })
+        });
+        group.bench_function("all_short_bsd_info", |b| {
+            b.iter(|| all_short_bsd_info().unwrap())
+        });
+        let (mut pids, mut infos) = (Vec::new(), Vec::new());
+        group.bench_function("all_short_bsd_info_into", |b| {
+            b.iter(|| all_short_bsd_info_into(&mut pids, &mut infos).unwrap())
+        });
+        group.finish();
+    }
+
+    criterion_group!(benches, short_info);
+}
+
+#[cfg(target_vendor = "apple")]
+criterion::criterion_main!(bench::benches);
+
+#[cfg(not(target_vendor = "apple"))]
+fn main() {}
@@ -0,0 +1,295 @@
+//! A small command-line tool that exercises the library. Requires the `cli` feature.
+//!
+//! ```text
+//! pidinfo [--json] fds <pid>
+//! pidinfo [--json] task <pid>
+//! pidinfo [--json] tree
+//! pidinfo [--json] sockets <pid>
+//! ```
+
+#[cfg(target_vendor = "apple")]
+mod cli {
+    use std::process::ExitCode;
+
+    use proc_pidinfo::*;
+    use serde_json::{json, Value};
+
+    const USAGE: &str =
+        "usage: pidinfo [--json] <fds|task|sockets> <pid>\n       pidinfo [--json] tree";
+
+    /// Rows of a table, printed either aligned or as a JSON array of objects.
+    struct Table {
+        columns: &'static [&'static str],
+        rows: Vec<Vec<Value>>,
+    }
+
+    impl Table {
+        fn new(columns: &'static [&'static str]) -> Self {
+            Self {
+                columns,
+                rows: vec![],
+            }
+        }
+
+        fn push(&mut self, row: Vec<Value>) {
+            debug_assert_eq!(row.len(), self.columns.len());
+            self.rows.push(row);
+        }
+
+        fn print(&self, as_json: bool) {
+            if as_json {
+                let rows = self
+                    .rows
+                    .iter()
+                    .map(|row| {
+                        let object = self
+                            .columns
+                            .iter()
+                            .map(|column| column.to_string())
+                            .zip(row.iter().cloned())
+                            .collect::<serde_json::Map<_, _>>();
+                        Value::Object(object)
+                    })
+                    .collect::<Vec<_>>();
+                println!("{}", Value::Array(rows));
+                return;
+            }
+
+            let cells = self
+                .rows
+                .iter()
+                .map(|row| row.iter().map(cell).collect::<Vec<_>>())
+                .collect::<Vec<_>>();
+            let widths = self
+                .columns
+                .iter()
+                .enumerate()
+                .map(|(i, column)| {
+                    cells
+                        .iter()
+                        .map(|row| row[i].len())
+                        .chain([column.len()])
+                        .max()
+                        .unwrap_or(0)
+                })
+                .collect::<Vec<_>>();
+            let header = self.columns.iter().map(|column| column.to_uppercase());
+            print_row(header, &widths);
+            for row in cells {
+                print_row(row.into_iter(), &widths);
+            }
+        }
+    }
+
+    fn cell(value: &Value) -> String {
+        match value {
+            Value::Null => "-".to_owned(),
+            Value::String(s) => s.clone(),
+            value => value.to_string(),
+        }
+    }
+
+    fn print_row(cells: impl Iterator<Item = String>, widths: &[usize]) {
+        let line = cells
+            .zip(widths)
+            .map(|(cell, width)| format!("{cell:width$}"))
+            .collect::<Vec<_>>()
+            .join("  ");
+        println!("{}", line.trim_end());
+    }
+
+    fn fd_type_name(fd: &ProcFDInfo) -> String {
+        match fd.fd_type() {
+            ProcFDType::Unknown(value) => format!("unknown({value})"),
+            fd_type => format!("{fd_type:?}"),
+        }
+    }
+
+    fn fds(pid: Pid) -> Result<Table, std::io::Error> {
+        let mut table = Table::new(&["fd", "type", "detail"]);
+        for fd in pid.fds()? {
+            let detail = match fd.fd_type() {
+                ProcFDType::VNODE => pid
+                    .fd_info::<VnodeFdInfoWithPath>(fd.proc_fd)
+                    .ok()
+                    .flatten()
+                    .and_then(|info| Some(info.path().ok()?.display().to_string())),
+                ProcFDType::PIPE => {
+                    pid.fd_info::<PipeFdInfo>(fd.proc_fd)
+                        .ok()
+                        .flatten()
+                        .map(|info| {
+                            format!(
+                                "{:#x} -> {:#x}",
+                                info.pipe_info.pipe_handle, info.pipe_info.pipe_peerhandle
+                            )
+                        })
+                }
+                _ => None,
+            };
+            table.push(vec![
+                json!(fd.proc_fd.0),
+                json!(fd_type_name(&fd)),
+                json!(detail),
+            ]);
+        }
+        Ok(table)
+    }
+
+    fn family_name(family: libc::c_int) -> String {
+        match family {
+            libc::AF_INET => "inet".to_owned(),
+            libc::AF_INET6 => "inet6".to_owned(),
+            libc::AF_UNIX => "unix".to_owned(),
+            libc::AF_ROUTE => "route".to_owned(),
+            libc::AF_SYSTEM => "system".to_owned(),
+            libc::AF_NDRV => "ndrv".to_owned(),
+            family => family.to_string(),
+        }
+    }
+
+    fn protocol_name(info: &SocketInfo) -> String {
+        if info.tcp().is_some() {
+            return "tcp".to_owned();
+        }
+        if info.inet().is_some() && info.soi_protocol == libc::IPPROTO_UDP {
+            return "udp".to_owned();
+        }
+        match info.soi_type {
+            libc::SOCK_STREAM => "stream".to_owned(),
+            libc::SOCK_DGRAM => "dgram".to_owned(),
+            libc::SOCK_RAW => "raw".to_owned(),
+            ty => ty.to_string(),
+        }
+    }
+
+    fn sockets(pid: Pid) -> Result<Table, std::io::Error> {
+        let mut table = Table::new(&["fd", "family", "protocol", "local", "foreign", "state"]);
+        for (fd, info) in open_sockets(pid)? {
+            let info = info.psi;
+            let (local, foreign) = if let Some(inet) = info.inet() {
+                (
+                    Some(inet.local_addr().to_string()),
+                    Some(inet.foreign_addr().to_string()),
+                )
+            } else if let Some(unix) = info.unix() {
+                let path = |path: Option<&std::path::Path>| Some(path?.display().to_string());
+                (path(unix.path()), path(unix.peer_path()))
+            } else {
+                (None, None)
+            };
+            let state = info.tcp().map(|tcp| match tcp.state() {
+                Ok(state) => format!("{state:?}"),
+                Err(_) => format!("unknown({})", tcp.tcpsi_state),
+            });
+            table.push(vec![
+                json!(fd.0),
+                json!(family_name(info.soi_family)),
+                json!(protocol_name(&info)),
+                json!(local),
+                json!(foreign),
+                json!(state),
+            ]);
+        }
+        Ok(table)
+    }
+
+    fn task(pid: Pid) -> Result<Table, std::io::Error> {
+        let info = pid
+            .task_info()?
+            .ok_or_else(|| std::io::Error::from_raw_os_error(libc::ESRCH))?;
+        let mut table = Table::new(&["metric", "value"]);
+        let metrics = [
+            ("virtual_size", info.pti_virtual_size),
+            ("resident_size", info.pti_resident_size),
+            ("total_user_ns", info.pti_total_user),
+            ("total_system_ns", info.pti_total_system),
+            ("threads", info.pti_threadnum as u64),
+            ("running_threads", info.pti_numrunning as u64),
+            ("faults", info.pti_faults as u64),
+            ("pageins", info.pti_pageins as u64),
+            ("context_switches", info.pti_csw as u64),
+            ("syscalls_unix", info.pti_syscalls_unix as u64),
+            ("syscalls_mach", info.pti_syscalls_mach as u64),
+            ("priority", info.pti_priority as u64),
+        ];
+        for (name, value) in metrics {
+            table.push(vec![json!(name), json!(value)]);
+        }
+        Ok(table)
+    }
+
+    fn tree() -> Result<Table, std::io::Error> {
+        let tree = ProcessTree::snapshot()?;
+        let mut table = Table::new(&["pid", "ppid", "depth", "command"]);
+        let mut stack = tree
+            .roots()
+            .into_iter()
+            .rev()
+            .map(|pid| (pid, 0))
+            .collect::<Vec<_>>();
+        while let Some((pid, depth)) = stack.pop() {
+            let info = tree.get(pid).expect("pid is in the tree");
+            let comm = info.comm().unwrap_or("?");
+            table.push(vec![
+                json!(info.pbsi_pid.0),
+                json!(info.pbsi_ppid.0),
+                json!(depth),
+                json!(format!("{}{comm}", "  ".repeat(depth))),
+            ]);
+            stack.extend(
+                tree.children(pid)
+                    .iter()
+                    .rev()
+                    .map(|&child| (child, depth + 1)),
+            );
+        }
+        Ok(table)
+    }
+
+    fn parse_pid(arg: &str) -> Result<Pid, String> {
+        arg.parse()
+            .map(Pid)
+            .map_err(|_| format!("invalid pid: {arg}"))
+    }
+
+    pub fn main() -> ExitCode {
+        let mut args = std::env::args().skip(1).collect::<Vec<_>>();
+        let as_json = args.iter().any(|arg| arg == "--json");
+        args.retain(|arg| arg != "--json");
+        let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+
+        let table = match args.as_slice() {
+            ["fds", pid] => parse_pid(pid).map(fds),
+            ["task", pid] => parse_pid(pid).map(task),
+            ["sockets", pid] => parse_pid(pid).map(sockets),
+            ["tree"] => Ok(tree()),
+            _ => Err(USAGE.to_owned()),
+        };
+        match table {
+            Ok(Ok(table)) => {
+                table.print(as_json);
+                ExitCode::SUCCESS
+            }
+            Ok(Err(err)) => {
+                eprintln!("pidinfo: {err}");
+                ExitCode::FAILURE
+            }
+            Err(message) => {
+                eprintln!("{message}");
+                ExitCode::from(2)
+            }
+        }
+    }
+}
+
+#[cfg(target_vendor = "apple")]
+fn main() -> std::process::ExitCode {
+    cli::main()
+}
+
+#[cfg(not(target_vendor = "apple"))]
+fn main() {
+    eprintln!("pidinfo is only supported on Apple platforms");
+    std::process::exit(1);
+}
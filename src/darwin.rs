@@ -1,9 +1,211 @@
-use std::{ffi::OsStr, path::Path};
+use std::{
+    ffi::{OsStr, OsString},
+    os::unix::ffi::{OsStrExt, OsStringExt},
+    path::{Path, PathBuf},
+};
 
 use libc::{c_char, c_int, c_void};
 
+mod ancestors;
+mod arch;
+mod besteffort;
+mod capabilities;
+mod channel;
+mod coalition;
+mod codesign;
+mod diagnose;
+mod environment;
+mod exitreason;
+#[cfg(feature = "metrics")]
+mod exporter;
+mod fdleak;
+mod fdtable;
+mod guarded;
+mod history;
+mod kqueue;
+#[cfg(all(feature = "libproc", target_os = "macos"))]
+mod libproc_compat;
+mod listeners;
+mod listpidspath;
+mod mounts;
+mod openfds;
+#[cfg(feature = "parallel")]
+mod parallel;
+mod peers;
+mod poller;
+mod pretty;
+mod procargs;
+mod process;
+mod query;
+mod raw;
+mod regions;
+mod rusage;
+mod scan;
+mod search;
+mod sink;
+mod snapshot;
+mod socket;
+mod stats;
+mod threads;
+mod tree;
+#[cfg(feature = "users")]
+mod users;
+mod watcher;
+
+pub mod controls;
+#[cfg(feature = "mach")]
+pub mod mach;
+#[cfg(feature = "syscall")]
+pub mod syscall;
+pub mod sysctl;
+
+pub use ancestors::*;
+pub use arch::*;
+pub use besteffort::*;
+pub use capabilities::*;
+pub use channel::*;
+pub use coalition::*;
+pub use codesign::*;
+pub use diagnose::*;
+pub use environment::*;
+pub use exitreason::*;
+#[cfg(feature = "metrics")]
+pub use exporter::*;
+pub use fdleak::*;
+pub use fdtable::*;
+pub use guarded::*;
+pub use history::*;
+pub use kqueue::*;
+pub use listeners::*;
+pub use listpidspath::*;
+pub use mounts::*;
+pub use openfds::*;
+#[cfg(feature = "parallel")]
+pub use parallel::*;
+pub use peers::*;
+pub use poller::*;
+pub use pretty::*;
+pub use procargs::*;
+pub use process::*;
+pub use query::*;
+pub use raw::*;
+pub use regions::*;
+pub use rusage::*;
+pub use scan::*;
+pub use search::*;
+pub use sink::*;
+pub use snapshot::*;
+pub use socket::*;
+pub use stats::*;
+pub use threads::*;
+pub use tree::*;
+#[cfg(feature = "users")]
+pub use users::*;
+pub use watcher::*;
+
+mod ffi {
+    use libc::{c_char, c_int, c_void, dev_t, mode_t};
+
+    // The libproc calls are declared here rather than taken from `libc`, which only declares
+    // them for macOS, although libproc is part of libSystem on every Apple platform.
+    extern "C" {
+        pub fn devname_r(dev: dev_t, r#type: mode_t, buf: *mut c_char, len: c_int) -> *mut c_char;
+        pub fn fileport_makefd(port: u32) -> c_int;
+        pub fn proc_listpids(
+            r#type: u32,
+            typeinfo: u32,
+            buffer: *mut c_void,
+            buffersize: c_int,
+        ) -> c_int;
+        pub fn proc_pidinfo(
+            pid: c_int,
+            flavor: c_int,
+            arg: u64,
+            buffer: *mut c_void,
+            buffersize: c_int,
+        ) -> c_int;
+        pub fn proc_pidfdinfo(
+            pid: c_int,
+            fd: c_int,
+            flavor: c_int,
+            buffer: *mut c_void,
+            buffersize: c_int,
+        ) -> c_int;
+        pub fn proc_pidfileportinfo(
+            pid: c_int,
+            fileport: u32,
+            flavor: c_int,
+            buffer: *mut c_void,
+            buffersize: c_int,
+        ) -> c_int;
+        pub fn proc_pidpath(pid: c_int, buffer: *mut c_void, buffersize: u32) -> c_int;
+        pub fn proc_regionfilename(
+            pid: c_int,
+            address: u64,
+            buffer: *mut c_void,
+            buffersize: u32,
+        ) -> c_int;
+        pub fn proc_kmsgbuf(buffer: *mut c_void, buffersize: u32) -> c_int;
+        pub fn proc_libversion(major: *mut c_int, minor: *mut c_int) -> c_int;
+        pub fn proc_pid_rusage(pid: c_int, flavor: c_int, buffer: *mut *mut c_void) -> c_int;
+    }
+}
+
+/// Returns true when running on an embedded Apple device (iOS, tvOS, watchOS or visionOS
+/// hardware). Simulators and Mac Catalyst run on the macOS kernel and return false.
+///
+/// On embedded devices, the sandbox restricts almost every query to the current process.
+/// Queries that the sandbox rejects fail with [`std::io::ErrorKind::Unsupported`] rather than
+/// a bare `EPERM`; the original error is available through [`std::io::Error::get_ref`].
+pub const fn is_embedded_device() -> bool {
+    cfg!(all(
+        any(
+            target_os = "ios",
+            target_os = "tvos",
+            target_os = "watchos",
+            target_os = "visionos"
+        ),
+        not(target_abi = "sim"),
+        not(target_abi = "macabi")
+    ))
+}
+
+/// The error for the last failed libproc call, adjusted for the current platform. See
+/// [`is_embedded_device`].
+fn last_os_error() -> std::io::Error {
+    platform_error(std::io::Error::last_os_error(), is_embedded_device())
+}
+
+/// Make a libproc call that returns a byte count, and check its result.
+///
+/// The `proc_*` wrappers in libproc return 0 both when the call fails, with `errno` set, and
+/// when the kernel has nothing to report, eg: an empty list. `errno` is cleared before the call
+/// to tell the two apart.
+fn libproc_call(call: impl FnOnce() -> c_int) -> Result<usize, std::io::Error> {
+    // SAFETY: Clearing errno has no memory safety requirements.
+    unsafe { *libc::__error() = 0 };
+    let res = call();
+    if res > 0 {
+        return Ok(res as usize);
+    }
+    let err = std::io::Error::last_os_error();
+    if res == 0 && err.raw_os_error() == Some(0) {
+        return Ok(0);
+    }
+    Err(platform_error(err, is_embedded_device()))
+}
+
+fn platform_error(err: std::io::Error, embedded: bool) -> std::io::Error {
+    if embedded && err.raw_os_error() == Some(libc::EPERM) {
+        std::io::Error::new(std::io::ErrorKind::Unsupported, err)
+    } else {
+        err
+    }
+}
+
 /// A wrapper around a process ID.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[repr(transparent)]
 pub struct Pid(pub u32);
 
@@ -14,8 +216,96 @@ pub fn getpid() -> Pid {
     Pid(pid as _)
 }
 
+/// Convenience methods that call the `proc_*` functions for this process.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let pid = getpid();
+/// let info = pid.bsd_short_info().unwrap().unwrap();
+/// println!("{}", info.comm().unwrap());
+/// for fd in pid.fds().unwrap() {
+///     if fd.fd_type() == ProcFDType::VNODE {
+///         if let Some(vnode) = pid.fd_info::<VnodeFdInfoWithPath>(fd.proc_fd).unwrap() {
+///             println!("{:?}", vnode.path().unwrap());
+///         }
+///     }
+/// }
+/// ```
+#[allow(private_bounds)]
+impl Pid {
+    /// Get any info struct for this process. See [`proc_pidinfo`].
+    pub fn info<T: HasFlavor>(self) -> Result<Option<T>, std::io::Error> {
+        proc_pidinfo(self)
+    }
+
+    /// Get the [`ProcBSDInfo`] for this process.
+    pub fn bsd_info(self) -> Result<Option<ProcBSDInfo>, std::io::Error> {
+        proc_pidinfo(self)
+    }
+
+    /// Get the [`ProcBSDShortInfo`] for this process.
+    pub fn bsd_short_info(self) -> Result<Option<ProcBSDShortInfo>, std::io::Error> {
+        proc_pidinfo(self)
+    }
+
+    /// Get the [`ProcTaskInfo`] for this process.
+    pub fn task_info(self) -> Result<Option<ProcTaskInfo>, std::io::Error> {
+        proc_pidinfo(self)
+    }
+
+    /// Get the [`ProcTaskAllInfo`] for this process.
+    pub fn task_all_info(self) -> Result<Option<ProcTaskAllInfo>, std::io::Error> {
+        proc_pidinfo(self)
+    }
+
+    /// Get the [`ProcUniqueIdentifierInfo`] for this process.
+    pub fn unique_identifier_info(
+        self,
+    ) -> Result<Option<ProcUniqueIdentifierInfo>, std::io::Error> {
+        proc_pidinfo(self)
+    }
+
+    /// List the open file descriptors of this process. See [`proc_pidinfo_list`].
+    pub fn fds(self) -> Result<Vec<ProcFDInfo>, std::io::Error> {
+        proc_pidinfo_list(self)
+    }
+
+    /// List the fileports of this process. See [`proc_pidinfo_list`].
+    pub fn fileports(self) -> Result<Vec<ProcFilePortInfo>, std::io::Error> {
+        proc_pidinfo_list(self)
+    }
+
+    /// Get an info struct for one of this process's file descriptors. See [`proc_pidfdinfo`].
+    pub fn fd_info<T: HasFdFlavor>(self, fd: Fd) -> Result<Option<T>, std::io::Error> {
+        proc_pidfdinfo(self, fd)
+    }
+
+    /// Get an info struct for one of this process's fileports. See [`proc_pidfileportinfo`].
+    pub fn fileport_info<T: HasFdFlavor>(
+        self,
+        fileport: FilePort,
+    ) -> Result<Option<T>, std::io::Error> {
+        proc_pidfileportinfo(self, fileport)
+    }
+
+    /// See [`ThreadHandle`].
+    pub fn threads(self) -> Result<Vec<ThreadHandle>, std::io::Error> {
+        proc_pidinfo_list(self)
+    }
+
+    /// See [`proc_pidthreadinfo`].
+    pub fn thread_info(
+        self,
+        thread: ThreadHandle,
+    ) -> Result<Option<ProcThreadInfo>, std::io::Error> {
+        proc_pidthreadinfo(self, thread)
+    }
+}
+
 /// A wrapper around a file descriptor.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[repr(transparent)]
 pub struct Fd(pub c_int);
 
@@ -24,6 +314,54 @@ pub struct Fd(pub c_int);
 #[repr(transparent)]
 pub struct FilePort(pub u32);
 
+impl FilePort {
+    /// Create a new file descriptor in the current process for the file this fileport refers
+    /// to, like receiving a descriptor over a unix domain socket. The fileport itself is left
+    /// alone.
+    ///
+    /// Fileport names are only meaningful in the process that holds them, so this only works
+    /// for the fileports of the current process, eg: from `getpid().fileports()`. A name from
+    /// another process fails, or refers to an unrelated port.
+    ///
+    /// ```
+    /// use proc_pidinfo::*;
+    ///
+    /// for port in getpid().fileports().unwrap() {
+    ///     let fd = port.proc_fileport.make_fd().unwrap();
+    ///     println!("{:?}: {:?}", port.fd_type(), fd);
+    /// }
+    /// ```
+    pub fn make_fd(self) -> Result<std::os::fd::OwnedFd, std::io::Error> {
+        // SAFETY: The kernel validates the port name and returns a new descriptor or -1.
+        let fd = unsafe { ffi::fileport_makefd(self.0) };
+        if fd < 0 {
+            return Err(last_os_error());
+        }
+        // SAFETY: The descriptor was just created, and nothing else owns it.
+        Ok(unsafe { std::os::fd::FromRawFd::from_raw_fd(fd) })
+    }
+}
+
+/// A kernel audit token (`audit_token_t`), which identifies a process along with the version
+/// of its pid, so that it can't be confused with a later process that reuses the pid.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AuditToken {
+    pub val: [u32; 8],
+}
+
+impl AuditToken {
+    /// The process ID.
+    pub fn pid(&self) -> Pid {
+        Pid(self.val[5])
+    }
+
+    /// The version of the pid, which changes when the pid is reused.
+    pub fn pidversion(&self) -> u32 {
+        self.val[7]
+    }
+}
+
 /// An error that occurs when an unexpected value is encountered.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ValueError {
@@ -40,6 +378,14 @@ fn libc_str_to_str(array: &[c_char]) -> Result<&str, ValueError> {
         .map_err(|_| ValueError::InvalidString)
 }
 
+/// Convert a C string to an owned Rust string, replacing invalid UTF-8.
+fn libc_str_to_string_lossy(array: &[c_char]) -> String {
+    let nul_index = array.iter().position(|&c| c == 0).unwrap_or(array.len());
+    // SAFETY: We know this is actually u8.
+    let bytes = unsafe { std::mem::transmute::<&[c_char], &[u8]>(&array[..nul_index]) };
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
 /// Convert a C string to a Rust path.
 fn libc_str_to_path(array: &[c_char]) -> Result<&Path, ValueError> {
     // Find the first NUL, otherwise use the full array
@@ -52,15 +398,68 @@ fn libc_str_to_path(array: &[c_char]) -> Result<&Path, ValueError> {
     }))
 }
 
+/// Decoded access to the name carried by a struct as a fixed-size C string, such as the
+/// command name of a process or the name of a thread.
+///
+/// Implemented by [`ProcBSDInfo`], [`ProcBSDShortInfo`], [`ProcTaskAllInfo`] and
+/// [`ProcThreadInfo`].
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// fn print_name(info: &impl ProcNameExt) {
+///     println!("{}", info.name_lossy());
+/// }
+///
+/// print_name(&getpid().bsd_info().unwrap().unwrap());
+/// print_name(&getpid().bsd_short_info().unwrap().unwrap());
+/// ```
+pub trait ProcNameExt {
+    /// The raw name: a NUL-terminated C string, or the whole array if the name fills it.
+    fn raw_name(&self) -> &[c_char];
+
+    /// The name, failing if it isn't valid UTF-8.
+    fn name(&self) -> Result<&str, ValueError> {
+        libc_str_to_str(self.raw_name())
+    }
+
+    /// An owned copy of [`ProcNameExt::name`].
+    fn name_string(&self) -> Result<String, ValueError> {
+        self.name().map(str::to_owned)
+    }
+
+    /// The name, with invalid UTF-8 replaced.
+    fn name_lossy(&self) -> String {
+        libc_str_to_string_lossy(self.raw_name())
+    }
+}
+
 /// A trait for types that have a flavor.
-trait HasFlavor {
+trait HasFlavor: Sized {
     const FLAVOR: ProcPidInfoFlavor;
+    /// The smallest reply accepted from the kernel. For structs that grew in newer OS
+    /// versions, this is the size on older versions, and the fields they lack are zero.
+    const MIN_SIZE: usize = std::mem::size_of::<Self>();
 }
 
 trait HasFlavorList {
     const FLAVOR: ProcPidInfoFlavor;
 }
 
+/// A trait for types whose flavor requires an argument, such as a thread or an address. See
+/// [`proc_pidinfo_with_arg`].
+///
+/// This trait is sealed: it is implemented by this crate's types only.
+#[allow(private_bounds)]
+pub trait HasFlavorArg: ArgFlavor {
+    /// The argument, which is passed to the kernel as a `u64`.
+    type Arg: Into<u64>;
+}
+
+trait ArgFlavor {
+    const FLAVOR: ProcPidInfoFlavor;
+}
+
 /// For `proc_pidinfo`.
 #[allow(non_camel_case_types, unused)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -81,6 +480,44 @@ enum ProcPidInfoFlavor {
     PROC_PIDLISTFILEPORTS = 14,
     PROC_PIDTHREADID64INFO = 15,
     PROC_PID_RUSAGE = 16,
+    PROC_PIDUNIQIDENTIFIERINFO = 17,
+    PROC_PIDARCHINFO = 19,
+    PROC_PIDCOALITIONINFO = 20,
+    PROC_PIDEXITREASONBASICINFO = 25,
+    PROC_PIDLISTDYNKQUEUES = 27,
+    PROC_PIDLISTTHREADIDS = 28,
+    PROC_PIDTHREADCOUNTS = 34,
+}
+
+impl ProcPidInfoFlavor {
+    /// The kernel's name for this flavor.
+    fn name(self) -> &'static str {
+        match self {
+            ProcPidInfoFlavor::PROC_PIDLISTFDS => "PROC_PIDLISTFDS",
+            ProcPidInfoFlavor::PROC_PIDTASKALLINFO => "PROC_PIDTASKALLINFO",
+            ProcPidInfoFlavor::PROC_PIDTBSDINFO => "PROC_PIDTBSDINFO",
+            ProcPidInfoFlavor::PROC_PIDTASKINFO => "PROC_PIDTASKINFO",
+            ProcPidInfoFlavor::PROC_PIDTHREADINFO => "PROC_PIDTHREADINFO",
+            ProcPidInfoFlavor::PROC_PIDLISTTHREADS => "PROC_PIDLISTTHREADS",
+            ProcPidInfoFlavor::PROC_PIDREGIONINFO => "PROC_PIDREGIONINFO",
+            ProcPidInfoFlavor::PROC_PIDREGIONPATHINFO => "PROC_PIDREGIONPATHINFO",
+            ProcPidInfoFlavor::PROC_PIDVNODEPATHINFO => "PROC_PIDVNODEPATHINFO",
+            ProcPidInfoFlavor::PROC_PIDTHREADPATHINFO => "PROC_PIDTHREADPATHINFO",
+            ProcPidInfoFlavor::PROC_PIDPATHINFO => "PROC_PIDPATHINFO",
+            ProcPidInfoFlavor::PROC_PIDWORKQUEUEINFO => "PROC_PIDWORKQUEUEINFO",
+            ProcPidInfoFlavor::PROC_PIDT_SHORTBSDINFO => "PROC_PIDT_SHORTBSDINFO",
+            ProcPidInfoFlavor::PROC_PIDLISTFILEPORTS => "PROC_PIDLISTFILEPORTS",
+            ProcPidInfoFlavor::PROC_PIDTHREADID64INFO => "PROC_PIDTHREADID64INFO",
+            ProcPidInfoFlavor::PROC_PID_RUSAGE => "PROC_PID_RUSAGE",
+            ProcPidInfoFlavor::PROC_PIDUNIQIDENTIFIERINFO => "PROC_PIDUNIQIDENTIFIERINFO",
+            ProcPidInfoFlavor::PROC_PIDARCHINFO => "PROC_PIDARCHINFO",
+            ProcPidInfoFlavor::PROC_PIDCOALITIONINFO => "PROC_PIDCOALITIONINFO",
+            ProcPidInfoFlavor::PROC_PIDEXITREASONBASICINFO => "PROC_PIDEXITREASONBASICINFO",
+            ProcPidInfoFlavor::PROC_PIDLISTDYNKQUEUES => "PROC_PIDLISTDYNKQUEUES",
+            ProcPidInfoFlavor::PROC_PIDLISTTHREADIDS => "PROC_PIDLISTTHREADIDS",
+            ProcPidInfoFlavor::PROC_PIDTHREADCOUNTS => "PROC_PIDTHREADCOUNTS",
+        }
+    }
 }
 
 trait HasFdFlavor {
@@ -100,51 +537,100 @@ enum ProcPidFdInfoFlavor {
     PROC_PIDFDPIPEINFO = 6,
     PROC_PIDFDKQUEUEINFO = 7,
     PROC_PIDFDATALKINFO = 8,
+    PROC_PIDFDKQUEUE_EXTINFO = 9,
     PROC_PIDFDCHANNELINFO = 10,
 }
 
+impl ProcPidFdInfoFlavor {
+    /// The kernel's name for this flavor.
+    fn name(self) -> &'static str {
+        match self {
+            ProcPidFdInfoFlavor::PROC_PIDFDVNODEINFO => "PROC_PIDFDVNODEINFO",
+            ProcPidFdInfoFlavor::PROC_PIDFDVNODEPATHINFO => "PROC_PIDFDVNODEPATHINFO",
+            ProcPidFdInfoFlavor::PROC_PIDFDSOCKETINFO => "PROC_PIDFDSOCKETINFO",
+            ProcPidFdInfoFlavor::PROC_PIDFDPSEMINFO => "PROC_PIDFDPSEMINFO",
+            ProcPidFdInfoFlavor::PROC_PIDFDPSHMINFO => "PROC_PIDFDPSHMINFO",
+            ProcPidFdInfoFlavor::PROC_PIDFDPIPEINFO => "PROC_PIDFDPIPEINFO",
+            ProcPidFdInfoFlavor::PROC_PIDFDKQUEUEINFO => "PROC_PIDFDKQUEUEINFO",
+            ProcPidFdInfoFlavor::PROC_PIDFDATALKINFO => "PROC_PIDFDATALKINFO",
+            ProcPidFdInfoFlavor::PROC_PIDFDKQUEUE_EXTINFO => "PROC_PIDFDKQUEUE_EXTINFO",
+            ProcPidFdInfoFlavor::PROC_PIDFDCHANNELINFO => "PROC_PIDFDCHANNELINFO",
+        }
+    }
+}
+
 /// A type for the file descriptor.
+///
+/// New macOS releases add types, which are reported as [`ProcFDType::Unknown`] until this crate
+/// knows about them.
 #[allow(non_camel_case_types)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-#[repr(u32)]
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ProcFDType {
-    ATALK = 0,
-    VNODE = 1,
-    SOCKET = 2,
-    PSHM = 3,
-    PSEM = 4,
-    KQUEUE = 5,
-    PIPE = 6,
-    FSEVENTS = 7,
-    NETPOLICY = 9,
-    CHANNEL = 10,
-    NEXUS = 11,
+    ATALK,
+    VNODE,
+    SOCKET,
+    PSHM,
+    PSEM,
+    KQUEUE,
+    PIPE,
+    FSEVENTS,
+    NETPOLICY,
+    CHANNEL,
+    NEXUS,
+    /// A type this crate doesn't know about yet, with its raw value.
+    Unknown(u32),
+}
+
+impl ProcFDType {
+    fn from_raw(value: u32) -> Self {
+        match value {
+            0 => ProcFDType::ATALK,
+            1 => ProcFDType::VNODE,
+            2 => ProcFDType::SOCKET,
+            3 => ProcFDType::PSHM,
+            4 => ProcFDType::PSEM,
+            5 => ProcFDType::KQUEUE,
+            6 => ProcFDType::PIPE,
+            7 => ProcFDType::FSEVENTS,
+            9 => ProcFDType::NETPOLICY,
+            10 => ProcFDType::CHANNEL,
+            11 => ProcFDType::NEXUS,
+            value => ProcFDType::Unknown(value),
+        }
+    }
+
+    /// The kernel's `PROX_FDTYPE_*` value.
+    pub fn as_raw(self) -> u32 {
+        match self {
+            ProcFDType::ATALK => 0,
+            ProcFDType::VNODE => 1,
+            ProcFDType::SOCKET => 2,
+            ProcFDType::PSHM => 3,
+            ProcFDType::PSEM => 4,
+            ProcFDType::KQUEUE => 5,
+            ProcFDType::PIPE => 6,
+            ProcFDType::FSEVENTS => 7,
+            ProcFDType::NETPOLICY => 9,
+            ProcFDType::CHANNEL => 10,
+            ProcFDType::NEXUS => 11,
+            ProcFDType::Unknown(value) => value,
+        }
+    }
 }
 
 /// Information about file descriptors. Usable with [`proc_pidinfo_list`].
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ProcFDInfo {
     pub proc_fd: Fd,
     pub proc_fdtype: u32,
 }
 
 impl ProcFDInfo {
-    pub fn fd_type(&self) -> Result<ProcFDType, ValueError> {
-        match self.proc_fdtype {
-            0 => Ok(ProcFDType::ATALK),
-            1 => Ok(ProcFDType::VNODE),
-            2 => Ok(ProcFDType::SOCKET),
-            3 => Ok(ProcFDType::PSHM),
-            4 => Ok(ProcFDType::PSEM),
-            5 => Ok(ProcFDType::KQUEUE),
-            6 => Ok(ProcFDType::PIPE),
-            7 => Ok(ProcFDType::FSEVENTS),
-            9 => Ok(ProcFDType::NETPOLICY),
-            10 => Ok(ProcFDType::CHANNEL),
-            11 => Ok(ProcFDType::NEXUS),
-            _ => Err(ValueError::UnexpectedEnumValue),
-        }
+    pub fn fd_type(&self) -> ProcFDType {
+        ProcFDType::from_raw(self.proc_fdtype)
     }
 }
 
@@ -165,21 +651,8 @@ impl HasFlavorList for ProcFilePortInfo {
 }
 
 impl ProcFilePortInfo {
-    pub fn fd_type(&self) -> Result<ProcFDType, ValueError> {
-        match self.proc_fdtype {
-            0 => Ok(ProcFDType::ATALK),
-            1 => Ok(ProcFDType::VNODE),
-            2 => Ok(ProcFDType::SOCKET),
-            3 => Ok(ProcFDType::PSHM),
-            4 => Ok(ProcFDType::PSEM),
-            5 => Ok(ProcFDType::KQUEUE),
-            6 => Ok(ProcFDType::PIPE),
-            7 => Ok(ProcFDType::FSEVENTS),
-            9 => Ok(ProcFDType::NETPOLICY),
-            10 => Ok(ProcFDType::CHANNEL),
-            11 => Ok(ProcFDType::NEXUS),
-            _ => Err(ValueError::UnexpectedEnumValue),
-        }
+    pub fn fd_type(&self) -> ProcFDType {
+        ProcFDType::from_raw(self.proc_fdtype)
     }
 }
 
@@ -207,11 +680,71 @@ pub struct ProcTaskInfo {
     pub pti_priority: i32,
 }
 
+impl ProcTaskInfo {
+    /// CPU time spent in user mode by every thread, including threads that have exited.
+    ///
+    /// ```
+    /// use proc_pidinfo::*;
+    ///
+    /// let info = proc_pidinfo_self::<ProcTaskInfo>().unwrap().unwrap();
+    /// println!("{:?} user, {:?} system", info.total_user(), info.total_system());
+    /// ```
+    pub fn total_user(&self) -> std::time::Duration {
+        mach_ticks_to_duration(self.pti_total_user)
+    }
+
+    /// CPU time spent in the kernel by every thread, including threads that have exited.
+    pub fn total_system(&self) -> std::time::Duration {
+        mach_ticks_to_duration(self.pti_total_system)
+    }
+
+    /// CPU time spent in user mode by the threads that are still running.
+    pub fn threads_user(&self) -> std::time::Duration {
+        mach_ticks_to_duration(self.pti_threads_user)
+    }
+
+    /// CPU time spent in the kernel by the threads that are still running.
+    pub fn threads_system(&self) -> std::time::Duration {
+        mach_ticks_to_duration(self.pti_threads_system)
+    }
+}
+
+/// The scheduling state of a process, from `pbi_status` or `pbsi_status`.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[repr(u32)]
+pub enum ProcStatus {
+    /// Being created by `fork`.
+    SIDL = 1,
+    /// Runnable.
+    SRUN = 2,
+    /// Sleeping on an address.
+    SSLEEP = 3,
+    /// Stopped, eg: by `SIGSTOP` or a debugger.
+    SSTOP = 4,
+    /// Exited, but not yet reaped by its parent.
+    SZOMB = 5,
+}
+
+impl ProcStatus {
+    fn from_raw(status: u32) -> Result<Self, ValueError> {
+        match status {
+            1 => Ok(ProcStatus::SIDL),
+            2 => Ok(ProcStatus::SRUN),
+            3 => Ok(ProcStatus::SSLEEP),
+            4 => Ok(ProcStatus::SSTOP),
+            5 => Ok(ProcStatus::SZOMB),
+            _ => Err(ValueError::UnexpectedEnumValue),
+        }
+    }
+}
+
 /// BSD-style information about a process. Usable with [`proc_pidinfo`].
 ///
 /// In some cases, [`ProcBSDInfo`] may not be available, while [`ProcBSDShortInfo`] is.
 #[repr(C)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Clone, Copy)]
 pub struct ProcBSDInfo {
     pub pbi_flags: u32,
     pub pbi_status: u32,
@@ -237,13 +770,174 @@ pub struct ProcBSDInfo {
     pub pbi_start_tvusec: u64,
 }
 
+impl ProcBSDInfo {
+    pub fn status(&self) -> Result<ProcStatus, ValueError> {
+        ProcStatus::from_raw(self.pbi_status)
+    }
+
+    /// The command name, truncated to 16 bytes.
+    pub fn comm(&self) -> Result<&str, ValueError> {
+        libc_str_to_str(&self.pbi_comm)
+    }
+
+    /// An owned copy of [`ProcBSDInfo::comm`].
+    pub fn comm_string(&self) -> Result<String, ValueError> {
+        self.comm().map(str::to_owned)
+    }
+
+    /// The process name, which is longer than [`ProcBSDInfo::comm`], falling back to the
+    /// command name if the process has none.
+    pub fn name(&self) -> Result<&str, ValueError> {
+        libc_str_to_str(self.raw_name())
+    }
+
+    /// An owned copy of [`ProcBSDInfo::name`].
+    pub fn name_string(&self) -> Result<String, ValueError> {
+        self.name().map(str::to_owned)
+    }
+
+    /// The path of the controlling terminal, eg: `/dev/ttys003`, or `None` if the process
+    /// doesn't have one.
+    ///
+    /// ```
+    /// use proc_pidinfo::*;
+    ///
+    /// let info = getpid().bsd_info().unwrap().unwrap();
+    /// println!("{:?}", info.tty());
+    /// ```
+    pub fn tty(&self) -> Option<PathBuf> {
+        tty_path(self.e_tdev)
+    }
+
+    /// Decode into a [`ProcBSDInfoOwned`].
+    pub fn to_owned_info(&self) -> ProcBSDInfoOwned {
+        ProcBSDInfoOwned::from(self)
+    }
+}
+
+/// A decoded copy of [`ProcBSDInfo`], with owned strings in place of the raw name arrays.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let info = getpid().bsd_info().unwrap().unwrap().to_owned_info();
+/// std::thread::spawn(move || println!("{} {}", info.pid.0, info.comm))
+///     .join()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ProcBSDInfoOwned {
+    pub pid: Pid,
+    pub ppid: Pid,
+    /// The scheduling state, or `None` if the kernel reported an unknown state.
+    pub status: Option<ProcStatus>,
+    pub flags: u32,
+    pub xstatus: u32,
+    pub uid: libc::uid_t,
+    pub gid: libc::gid_t,
+    pub ruid: libc::uid_t,
+    pub rgid: libc::gid_t,
+    pub svuid: libc::uid_t,
+    pub svgid: libc::gid_t,
+    /// The short command name. Invalid UTF-8 is replaced with `U+FFFD`.
+    pub comm: String,
+    /// The longer process name. Invalid UTF-8 is replaced with `U+FFFD`.
+    pub name: String,
+    pub nfiles: u32,
+    pub pgid: u32,
+    pub pjobc: u32,
+    pub tdev: u32,
+    pub tpgid: u32,
+    pub nice: i32,
+    pub start_time: std::time::SystemTime,
+}
+
+impl ProcBSDInfoOwned {
+    /// The path of the controlling terminal. See [`ProcBSDInfo::tty`].
+    pub fn tty(&self) -> Option<PathBuf> {
+        tty_path(self.tdev)
+    }
+}
+
+/// The `e_tdev` of a process without a controlling terminal.
+const NODEV: u32 = u32::MAX;
+
+/// Resolve the device number of a terminal to its path in `/dev`.
+fn tty_path(dev: u32) -> Option<PathBuf> {
+    if dev == NODEV {
+        return None;
+    }
+    let mut buf = [0 as c_char; 256];
+    // SAFETY: The buffer is large enough for any name in /dev, and devname_r NUL-terminates
+    // the name it writes.
+    let name = unsafe {
+        let name = ffi::devname_r(
+            dev as libc::dev_t,
+            libc::S_IFCHR,
+            buf.as_mut_ptr(),
+            buf.len() as c_int,
+        );
+        if name.is_null() {
+            return None;
+        }
+        std::ffi::CStr::from_ptr(name)
+    };
+    Some(Path::new("/dev").join(OsStr::from_bytes(name.to_bytes())))
+}
+
+impl From<&ProcBSDInfo> for ProcBSDInfoOwned {
+    fn from(info: &ProcBSDInfo) -> Self {
+        Self {
+            pid: info.pbi_pid,
+            ppid: info.pbi_ppid,
+            status: info.status().ok(),
+            flags: info.pbi_flags,
+            xstatus: info.pbi_xstatus,
+            uid: info.pbi_uid,
+            gid: info.pbi_gid,
+            ruid: info.pbi_ruid,
+            rgid: info.pbi_rgid,
+            svuid: info.pbi_svuid,
+            svgid: info.pbi_svgid,
+            comm: libc_str_to_string_lossy(&info.pbi_comm),
+            name: libc_str_to_string_lossy(&info.pbi_name),
+            nfiles: info.pbi_nfiles,
+            pgid: info.pbi_pgid,
+            pjobc: info.pbi_pjobc,
+            tdev: info.e_tdev,
+            tpgid: info.e_tpgid,
+            nice: info.pbi_nice,
+            start_time: std::time::UNIX_EPOCH
+                + std::time::Duration::from_secs(info.pbi_start_tvsec)
+                + std::time::Duration::from_micros(info.pbi_start_tvusec),
+        }
+    }
+}
+
+impl From<ProcBSDInfo> for ProcBSDInfoOwned {
+    fn from(info: ProcBSDInfo) -> Self {
+        Self::from(&info)
+    }
+}
+
+impl ProcNameExt for ProcBSDInfo {
+    fn raw_name(&self) -> &[c_char] {
+        if self.pbi_name[0] == 0 {
+            &self.pbi_comm
+        } else {
+            &self.pbi_name
+        }
+    }
+}
+
 impl HasFlavor for ProcBSDInfo {
     const FLAVOR: ProcPidInfoFlavor = ProcPidInfoFlavor::PROC_PIDTBSDINFO;
 }
 
 /// A short version of [`ProcBSDInfo`]. Usable with [`proc_pidinfo`].
 #[repr(C)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Clone, Copy)]
 pub struct ProcBSDShortInfo {
     pub pbsi_pid: Pid,
     pub pbsi_ppid: Pid,
@@ -264,6 +958,22 @@ impl ProcBSDShortInfo {
     pub fn comm(&self) -> Result<&str, ValueError> {
         libc_str_to_str(&self.pbsi_comm)
     }
+
+    /// An owned copy of [`ProcBSDShortInfo::comm`].
+    pub fn comm_string(&self) -> Result<String, ValueError> {
+        self.comm().map(str::to_owned)
+    }
+
+    pub fn status(&self) -> Result<ProcStatus, ValueError> {
+        ProcStatus::from_raw(self.pbsi_status)
+    }
+}
+
+/// The name is the command name, as [`ProcBSDShortInfo::comm`].
+impl ProcNameExt for ProcBSDShortInfo {
+    fn raw_name(&self) -> &[c_char] {
+        &self.pbsi_comm
+    }
 }
 
 impl HasFlavor for ProcBSDShortInfo {
@@ -284,10 +994,50 @@ pub struct ProcTaskAllInfo {
     pub ptinfo: ProcTaskInfo,
 }
 
+/// The name is that of [`ProcTaskAllInfo::pbsd`].
+impl ProcNameExt for ProcTaskAllInfo {
+    fn raw_name(&self) -> &[c_char] {
+        self.pbsd.raw_name()
+    }
+}
+
 impl HasFlavor for ProcTaskAllInfo {
     const FLAVOR: ProcPidInfoFlavor = ProcPidInfoFlavor::PROC_PIDTASKALLINFO;
 }
 
+/// Identifiers that stay unique across pid reuse. Usable with [`proc_pidinfo`].
+///
+/// `p_uniqueid` is never reused while the system is up, so `(pid, p_uniqueid)` identifies a
+/// process instance even after its pid has been recycled.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let info = proc_pidinfo_self::<ProcUniqueIdentifierInfo>().unwrap().unwrap();
+/// println!("{:x?} {}", info.p_uuid, info.p_uniqueid);
+/// ```
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct ProcUniqueIdentifierInfo {
+    /// The UUID of the main executable (`LC_UUID`).
+    pub p_uuid: [u8; 16],
+    /// A unique id for this process, never reused until reboot.
+    pub p_uniqueid: u64,
+    /// The unique id of the parent process.
+    pub p_puniqueid: u64,
+    /// The version of the pid, which changes when the pid is reused.
+    pub p_idversion: i32,
+    /// The version of the original parent's pid.
+    pub p_orig_ppidversion: i32,
+    pub p_reserve2: u32,
+    pub p_reserve3: u64,
+}
+
+impl HasFlavor for ProcUniqueIdentifierInfo {
+    const FLAVOR: ProcPidInfoFlavor = ProcPidInfoFlavor::PROC_PIDUNIQIDENTIFIERINFO;
+    const MIN_SIZE: usize = std::mem::offset_of!(Self, p_reserve2);
+}
+
 /// Get an info struct for a given process.
 ///
 /// Supports:
@@ -296,6 +1046,12 @@ impl HasFlavor for ProcTaskAllInfo {
 /// - [`ProcTaskAllInfo`]
 /// - [`ProcBSDInfo`]
 /// - [`ProcBSDShortInfo`]
+/// - [`ProcUniqueIdentifierInfo`]
+/// - [`ProcCoalitionInfo`]
+/// - [`ProcArchInfo`]
+///
+/// Fails with `ESRCH` if the process doesn't exist, and with `EPERM` if the caller isn't
+/// allowed to read this flavor for it. Returns `None` if the kernel returned nothing.
 ///
 /// ```
 /// use proc_pidinfo::*;
@@ -305,120 +1061,676 @@ impl HasFlavor for ProcTaskAllInfo {
 /// println!("{:?}", info);
 /// ```
 #[allow(private_bounds)]
-pub fn proc_pidinfo<T: HasFlavor>(pid: Pid) -> Result<Option<T>, std::io::Error> {
-    // SAFETY: We check the size of the return value to ensure it's valid.
-    unsafe {
-        let mut value = std::mem::MaybeUninit::<T>::uninit();
-        let buffersize = std::mem::size_of::<T>() as c_int;
-        let res = libc::proc_pidinfo(
-            pid.0 as _,
-            T::FLAVOR as c_int,
-            0,
-            value.as_mut_ptr() as *mut c_void,
-            buffersize,
-        );
-        if res < 0 {
-            return Err(std::io::Error::from_raw_os_error(res));
-        }
-        if res == 0 {
-            return Ok(None);
+pub fn proc_pidinfo<T: HasFlavor>(pid: Pid) -> Result<Option<T>, std::io::Error> {
+    Ok(proc_pidinfo_with_size(pid)?.map(|(info, _)| info))
+}
+
+/// Like [`proc_pidinfo`], but also returns the number of bytes the kernel returned.
+///
+/// Some structs grew in newer OS versions. Older versions return the shorter struct, which is
+/// accepted with the newer fields left zero, so this returns less than `size_of::<T>()`.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let (info, size) = proc_pidinfo_with_size::<ProcUniqueIdentifierInfo>(getpid())
+///     .unwrap()
+///     .unwrap();
+/// if size < std::mem::size_of::<ProcUniqueIdentifierInfo>() {
+///     println!("this OS returned a shorter struct, without the reserved fields");
+/// }
+/// println!("{:?}", info);
+/// ```
+#[allow(private_bounds)]
+pub fn proc_pidinfo_with_size<T: HasFlavor>(
+    pid: Pid,
+) -> Result<Option<(T, usize)>, std::io::Error> {
+    // SAFETY: The flavor is declared by the type, and the fields past its minimum size are
+    // plain integers.
+    unsafe { proc_pidinfo_sized(pid, T::FLAVOR as c_int, 0, T::MIN_SIZE) }
+}
+
+/// Call `proc_pidinfo` with an explicit flavor and argument.
+///
+/// # Safety
+///
+/// `T` must be the struct that the kernel returns for `flavor`.
+unsafe fn proc_pidinfo_arg<T>(
+    pid: Pid,
+    flavor: ProcPidInfoFlavor,
+    arg: u64,
+) -> Result<Option<T>, std::io::Error> {
+    // SAFETY: Forwarded to the caller.
+    unsafe { proc_pidinfo_as(pid, flavor as c_int, arg) }
+}
+
+/// Get an info struct for a given process, for flavors that require an argument.
+///
+/// Supports:
+///
+/// - [`ProcThreadInfo`], for a [`ThreadHandle`]
+/// - [`ProcThreadWithPathInfo`], for a [`ThreadHandle`]
+/// - [`ProcRegionInfo`], for an address
+/// - [`ProcRegionWithPathInfo`], for an address
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// # let pid = getpid();
+/// for thread in proc_pidinfo_list::<ThreadHandle>(pid).unwrap() {
+///     if let Some(info) = proc_pidinfo_with_arg::<ProcThreadInfo>(pid, thread).unwrap() {
+///         println!("{:?}", info.name());
+///     }
+/// }
+/// let region = proc_pidinfo_with_arg::<ProcRegionInfo>(pid, 0).unwrap();
+/// println!("{:?}", region);
+/// ```
+pub fn proc_pidinfo_with_arg<T: HasFlavorArg>(
+    pid: Pid,
+    arg: T::Arg,
+) -> Result<Option<T>, std::io::Error> {
+    // SAFETY: The flavor is declared by the type.
+    unsafe { proc_pidinfo_arg(pid, T::FLAVOR, arg.into()) }
+}
+
+/// Get an info struct for the current process. A convenience function that calls
+/// [`proc_pidinfo`] with the current process ID.
+#[allow(private_bounds)]
+pub fn proc_pidinfo_self<T: HasFlavor>() -> Result<Option<T>, std::io::Error> {
+    proc_pidinfo(getpid())
+}
+
+/// The largest buffer, in bytes, that [`proc_pidinfo_list`] will allocate for a single list.
+///
+/// This comfortably covers several million file descriptors or fileports, far beyond any
+/// realistic `kern.maxfilesperproc` setting.
+pub const PROC_PIDINFO_LIST_MAX_BYTES: usize = 64 * 1024 * 1024;
+
+/// Get a list-type info struct for a given process.
+///
+/// Supports:
+///
+/// - [`ProcFDInfo`]
+/// - [`ProcFilePortInfo`]
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// # let pid = getpid();
+/// for fd in proc_pidinfo_list::<ProcFDInfo>(pid).unwrap() {
+///     println!("{:?}", fd);
+///     if fd.fd_type() == ProcFDType::VNODE {
+///         let vnode = proc_pidfdinfo::<VnodeFdInfo>(pid, fd.proc_fd).unwrap().unwrap();
+///         println!("Vnode: {:?}", vnode);
+///         if let Some(vnode) = proc_pidfdinfo::<VnodeFdInfoWithPath>(pid, fd.proc_fd).unwrap() {
+///             println!("Path: {:?}", vnode.path().unwrap());
+///         }
+///     } else if fd.fd_type() == ProcFDType::PIPE {
+///         let pipe = proc_pidfdinfo::<PipeFdInfo>(pid, fd.proc_fd).unwrap().unwrap();
+///         println!("Pipe: {:?}", pipe);
+///     } else {
+///         println!("Other fd type: {:?}", fd.fd_type());
+///     }
+/// }
+/// ```
+///
+/// # Sizing
+///
+/// The kernel has no way to return a list in chunks: each call copies as many entries as fit
+/// in the buffer and reports how many bytes it wrote. This function asks the kernel for a size
+/// estimate, and allocates that plus some headroom. If the list grew in the meantime and the
+/// buffer comes back full, the estimate is refreshed and the call retried a few times.
+///
+/// Growth stops at [`PROC_PIDINFO_LIST_MAX_BYTES`], so a process with an enormous (or
+/// endlessly growing) table can neither exhaust memory nor keep this function spinning. If
+/// the list still doesn't fit, an error is returned rather than a silently truncated list: of
+/// kind [`std::io::ErrorKind::OutOfMemory`] if the limit was hit, or
+/// [`std::io::ErrorKind::Other`] if the list kept growing. Use [`proc_pidinfo_list_bounded`]
+/// to accept a partial list instead.
+///
+/// If you already know roughly how long the list is, [`proc_pidinfo_list_with_hint`] skips
+/// the size estimate.
+#[allow(private_bounds)]
+pub fn proc_pidinfo_list<T: HasFlavorList>(pid: Pid) -> Result<Vec<T>, std::io::Error> {
+    let mut buffer = Vec::new();
+    proc_pidinfo_list_into(pid, &mut buffer)?;
+    Ok(buffer)
+}
+
+/// Get a list-type info struct for a given process, starting with room for `hint` entries.
+///
+/// This skips the call that asks the kernel for a size estimate, which saves a system call
+/// when the size of the list is predictable (eg: from a previous call). If the hint is too
+/// small, this falls back to the behaviour of [`proc_pidinfo_list`].
+#[allow(private_bounds)]
+pub fn proc_pidinfo_list_with_hint<T: HasFlavorList>(
+    pid: Pid,
+    hint: usize,
+) -> Result<Vec<T>, std::io::Error> {
+    let max_entries = PROC_PIDINFO_LIST_MAX_BYTES / std::mem::size_of::<T>();
+    // Leave room for the extra entry used to detect a full list.
+    let mut buffer = Vec::with_capacity(hint.min(max_entries) + 1);
+    proc_pidinfo_list_into(pid, &mut buffer)?;
+    Ok(buffer)
+}
+
+/// Get a list-type info struct for a given process, reusing the allocation of `buffer`.
+///
+/// The buffer is cleared and filled with the list, growing it if needed. When polling many
+/// processes, reusing one buffer avoids allocating a fresh [`Vec`] on every call. See
+/// [`proc_pidinfo_list`] for details on how the list is sized; the existing capacity of the
+/// buffer is used as a size hint.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let mut fds = Vec::new();
+/// for pid in [getpid(), Pid(1)] {
+///     if proc_pidinfo_list_into::<ProcFDInfo>(pid, &mut fds).is_ok() {
+///         println!("{} has {} fds", pid.0, fds.len());
+///     }
+/// }
+/// ```
+#[allow(private_bounds)]
+pub fn proc_pidinfo_list_into<T: HasFlavorList>(
+    pid: Pid,
+    buffer: &mut Vec<T>,
+) -> Result<(), std::io::Error> {
+    let max_entries = PROC_PIDINFO_LIST_MAX_BYTES / std::mem::size_of::<T>();
+    if list_into_vec(pid, buffer, max_entries)?.truncated {
+        let err = list_truncated_error(buffer.len(), max_entries);
+        buffer.clear();
+        return Err(err);
+    }
+    Ok(())
+}
+
+/// Get a list-type info struct for a given process into an uninitialized buffer, without
+/// allocating.
+///
+/// Returns the initialized prefix of `buffer`. The kernel only copies as many entries as fit,
+/// so if the returned slice fills the whole buffer, the list may have been truncated.
+///
+/// ```
+/// use proc_pidinfo::*;
+/// use std::mem::MaybeUninit;
+///
+/// let mut buffer = [MaybeUninit::<ProcFDInfo>::uninit(); 256];
+/// let fds = proc_pidinfo_list_into_uninit(getpid(), &mut buffer).unwrap();
+/// assert!(!fds.is_empty());
+/// ```
+#[allow(private_bounds)]
+pub fn proc_pidinfo_list_into_uninit<T: HasFlavorList>(
+    pid: Pid,
+    buffer: &mut [std::mem::MaybeUninit<T>],
+) -> Result<&mut [T], std::io::Error> {
+    let entry_size = std::mem::size_of::<T>();
+    let capacity = buffer.len().min(c_int::MAX as usize / entry_size);
+    // SAFETY: The kernel writes at most `buffersize` bytes, and we check that it wrote whole
+    // entries before exposing them.
+    unsafe {
+        let len = libproc_call(|| {
+            ffi::proc_pidinfo(
+                pid.0 as _,
+                T::FLAVOR as c_int,
+                0,
+                buffer.as_mut_ptr() as *mut c_void,
+                (capacity * entry_size) as c_int,
+            )
+        })?;
+        if !len.is_multiple_of(entry_size) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "Unexpected buffer size",
+            ));
+        }
+        Ok(std::slice::from_raw_parts_mut(
+            buffer.as_mut_ptr() as *mut T,
+            len / entry_size,
+        ))
+    }
+}
+
+/// A possibly-incomplete list returned by [`proc_pidinfo_list_bounded`].
+#[derive(Debug, Clone)]
+pub struct ProcList<T> {
+    /// The entries that were retrieved.
+    pub items: Vec<T>,
+    /// True if the process had more entries than were retrieved.
+    pub truncated: bool,
+    /// The kernel's estimate of the total number of entries, if it provided one. This is
+    /// usually an over-estimate, as the kernel leaves some headroom for new entries.
+    pub estimated_total: Option<usize>,
+}
+
+impl<T> ProcList<T> {
+    /// Returns true if every entry was retrieved.
+    pub fn is_complete(&self) -> bool {
+        !self.truncated
+    }
+}
+
+/// Get at most `max_entries` entries of a list-type info struct for a given process.
+///
+/// Unlike [`proc_pidinfo_list`], a list that doesn't fit is not an error: the first
+/// `max_entries` entries are returned and [`ProcList::truncated`] is set.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// # let pid = getpid();
+/// let fds = proc_pidinfo_list_bounded::<ProcFDInfo>(pid, 1024).unwrap();
+/// if fds.truncated {
+///     println!("Showing {} of ~{:?} fds", fds.items.len(), fds.estimated_total);
+/// }
+/// ```
+#[allow(private_bounds)]
+pub fn proc_pidinfo_list_bounded<T: HasFlavorList>(
+    pid: Pid,
+    max_entries: usize,
+) -> Result<ProcList<T>, std::io::Error> {
+    let mut buffer = Vec::new();
+    let fill = list_into_vec(pid, &mut buffer, max_entries)?;
+    Ok(ProcList {
+        items: buffer,
+        truncated: fill.truncated,
+        estimated_total: fill.estimated_total,
+    })
+}
+
+/// How many times a list query is retried when the list grows between calls.
+const LIST_MAX_ATTEMPTS: usize = 4;
+
+/// The outcome of [`list_into_vec`].
+struct ListFill {
+    /// The list didn't fit, either because it has more than `max_entries` entries or because
+    /// it kept growing between attempts.
+    truncated: bool,
+    /// The kernel's most recent size estimate, if it was asked for one.
+    estimated_total: Option<usize>,
+}
+
+/// Fill `buffer` with at most `max_entries` entries of a list, reusing its allocation.
+///
+/// The existing capacity of `buffer` is used as a size hint for the first attempt. If there is
+/// no capacity, or the list turns out not to fit, the kernel is asked for a size estimate and
+/// the buffer is grown to that size plus some headroom. This is retried a bounded number of
+/// times in case the list keeps growing between calls.
+fn list_into_vec<T: HasFlavorList>(
+    pid: Pid,
+    buffer: &mut Vec<T>,
+    max_entries: usize,
+) -> Result<ListFill, std::io::Error> {
+    let entry_size = std::mem::size_of::<T>();
+    // Leave room for one extra entry so we can tell a full list from a truncated one.
+    let limit = max_entries
+        .saturating_add(1)
+        .min(c_int::MAX as usize / entry_size);
+
+    let mut estimated_total = None;
+    let mut full = false;
+    for _ in 0..LIST_MAX_ATTEMPTS {
+        // Any entries from a previous attempt are stale, and `reserve_exact` is relative to
+        // the length, not the capacity.
+        buffer.clear();
+        if buffer.capacity() == 0 || full {
+            // Call with NULL to get a suggested buffer size
+            // SAFETY: A NULL buffer is allowed, and nothing is written.
+            let res = libproc_call(|| unsafe {
+                ffi::proc_pidinfo(pid.0 as _, T::FLAVOR as c_int, 0, std::ptr::null_mut(), 0)
+            })?;
+            let estimate = res / entry_size;
+            estimated_total = Some(estimate);
+            // Always grow after a full buffer, even if the estimate hasn't caught up.
+            let wanted = (estimate + estimate / 8 + 16).max(buffer.capacity() * 2);
+            buffer.reserve_exact(wanted.min(limit));
         }
-        if res != buffersize {
+
+        let capacity = buffer.capacity().min(limit);
+        let buffersize = (capacity * entry_size) as c_int;
+        // SAFETY: The kernel writes at most `buffersize` bytes into our capacity, and we check
+        // that it wrote whole entries before exposing them.
+        let res = libproc_call(|| unsafe {
+            ffi::proc_pidinfo(
+                pid.0 as _,
+                T::FLAVOR as c_int,
+                0,
+                buffer.as_mut_ptr() as *mut c_void,
+                buffersize,
+            )
+        })?;
+        if !res.is_multiple_of(entry_size) {
             return Err(std::io::Error::new(
                 std::io::ErrorKind::InvalidData,
-                format!("Unexpected buffer size {res} != {buffersize}"),
+                "Unexpected buffer size",
             ));
         }
-        Ok(Some(value.assume_init()))
+        // SAFETY: The kernel initialized this many entries.
+        unsafe { buffer.set_len(res / entry_size) };
+
+        // A full buffer means there may be more entries than we had room for.
+        full = res == buffersize as usize;
+        if !full || capacity >= limit {
+            break;
+        }
+    }
+
+    let truncated = buffer.len() > max_entries || full;
+    buffer.truncate(max_entries);
+    Ok(ListFill {
+        truncated,
+        estimated_total,
+    })
+}
+
+/// The error returned when a list doesn't fit within the limits of [`proc_pidinfo_list`].
+fn list_truncated_error(len: usize, max_entries: usize) -> std::io::Error {
+    if len >= max_entries {
+        std::io::Error::new(
+            std::io::ErrorKind::OutOfMemory,
+            format!("List has more than {max_entries} entries"),
+        )
+    } else {
+        std::io::Error::other(format!(
+            "List kept growing after {LIST_MAX_ATTEMPTS} attempts ({len} entries)"
+        ))
     }
 }
 
 /// Get an info struct for the current process. A convenience function that calls
-/// [`proc_pidinfo`] with the current process ID.
+/// [`proc_pidinfo_list`] with the current process ID.
 #[allow(private_bounds)]
-pub fn proc_pidinfo_self<T: HasFlavor>() -> Result<Option<T>, std::io::Error> {
-    proc_pidinfo(getpid())
+pub fn proc_pidinfo_list_self<T: HasFlavorList>() -> Result<Vec<T>, std::io::Error> {
+    proc_pidinfo_list(getpid())
 }
 
-/// Get a list-type info struct for a given process.
-///
-/// Supports:
+/// List the IDs of all processes on the system.
 ///
-/// - [`ProcFDInfo`]
-/// - [`ProcFilePortInfo`]
+/// Processes start and exit at any time, so this is only a snapshot: some of the returned
+/// processes may be gone by the time they are queried.
 ///
 /// ```
 /// use proc_pidinfo::*;
 ///
-/// # let pid = getpid();
-/// for fd in proc_pidinfo_list::<ProcFDInfo>(pid).unwrap() {
-///     println!("{:?}", fd);
-///     if fd.fd_type() == Ok(ProcFDType::VNODE) {
-///         let vnode = proc_pidfdinfo::<VnodeFdInfo>(pid, fd.proc_fd).unwrap().unwrap();
-///         println!("Vnode: {:?}", vnode);
-///         if let Some(vnode) = proc_pidfdinfo::<VnodeFdInfoWithPath>(pid, fd.proc_fd).unwrap() {
-///             println!("Path: {:?}", vnode.path().unwrap());
-///         }
-///     } else if fd.fd_type() == Ok(ProcFDType::PIPE) {
-///         let pipe = proc_pidfdinfo::<PipeFdInfo>(pid, fd.proc_fd).unwrap().unwrap();
-///         println!("Pipe: {:?}", pipe);
-///     } else {
-///         println!("Unknown fd type: {:?}", fd.fd_type().unwrap_err());
-///     }
-/// }
+/// let pids = proc_listallpids().unwrap();
+/// assert!(pids.contains(&getpid()));
 /// ```
-#[allow(private_bounds)]
-pub fn proc_pidinfo_list<T: HasFlavorList>(pid: Pid) -> Result<Vec<T>, std::io::Error> {
+pub fn proc_listallpids() -> Result<Vec<Pid>, std::io::Error> {
+    let mut buffer = Vec::new();
+    proc_listallpids_into(&mut buffer)?;
+    Ok(buffer)
+}
+
+/// List the IDs of all processes on the system into an existing buffer, reusing its
+/// allocation. The buffer is cleared first.
+pub fn proc_listallpids_into(buffer: &mut Vec<Pid>) -> Result<(), std::io::Error> {
+    const PROC_ALL_PIDS: u32 = 1;
+    let entry_size = std::mem::size_of::<Pid>();
+    buffer.clear();
+
     // SAFETY: We check the size of the return value to ensure it's valid.
     unsafe {
         // First call with NULL to get a suggested buffer size
-        let res = libc::proc_pidinfo(pid.0 as _, T::FLAVOR as c_int, 0, std::ptr::null_mut(), 0);
-        if res < 0 {
-            return Err(std::io::Error::from_raw_os_error(res));
-        }
-        let initial_buffer = if res == 0 {
-            std::mem::size_of::<T>() * 16
-        } else {
-            res as usize
-        };
+        let res = libproc_call(|| ffi::proc_listpids(PROC_ALL_PIDS, 0, std::ptr::null_mut(), 0))?;
+        // Leave some room for processes started since the first call
+        let mut entries = res / entry_size + 64;
 
-        // Use the initial buffer size guess, then keep doubling until we get a result
-        let mut buffer = Vec::with_capacity(initial_buffer);
+        // The number of processes is bounded by kern.maxproc, so this terminates.
         loop {
-            let buffersize = buffer.capacity() as c_int;
-            let res = libc::proc_pidinfo(
-                pid.0 as _,
-                T::FLAVOR as c_int,
-                0,
-                buffer.as_mut_ptr() as *mut c_void,
-                buffersize,
-            );
-            // We don't know the expected count, so we keep trying until we get less bytes
-            // than the buffer size.
-            if res == buffersize {
-                buffer.reserve(buffer.capacity() * 2);
+            buffer.reserve_exact(entries);
+            let buffersize = (buffer.capacity() * entry_size) as c_int;
+            let res = libproc_call(|| {
+                ffi::proc_listpids(
+                    PROC_ALL_PIDS,
+                    0,
+                    buffer.as_mut_ptr() as *mut c_void,
+                    buffersize,
+                )
+            })?;
+            if res == buffersize as usize {
+                entries = buffer.capacity() * 2;
                 continue;
             }
-            if res < 0 {
-                return Err(std::io::Error::from_raw_os_error(res));
+            buffer.set_len(res / entry_size);
+            return Ok(());
+        }
+    }
+}
+
+/// Get the [`ProcBSDShortInfo`] of every process on the system, skipping processes that exit
+/// mid-scan. Any other failure to read a process is returned.
+///
+/// This is equivalent to calling [`proc_pidinfo`] for each pid from [`proc_listallpids`], but
+/// the kernel writes each struct straight into the returned vector. Use
+/// [`all_short_bsd_info_into`] to also reuse the allocations between scans.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let infos = all_short_bsd_info().unwrap();
+/// assert!(infos.iter().any(|info| info.pbsi_pid == getpid()));
+/// ```
+pub fn all_short_bsd_info() -> Result<Vec<ProcBSDShortInfo>, std::io::Error> {
+    let mut infos = Vec::new();
+    all_short_bsd_info_into(&mut Vec::new(), &mut infos)?;
+    Ok(infos)
+}
+
+/// Get the [`ProcBSDShortInfo`] of every process on the system into existing buffers, so
+/// that a periodic scan doesn't allocate once the buffers have grown to fit.
+///
+/// `pids` is scratch space for the pid list. Both buffers are cleared first.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let (mut pids, mut infos) = (Vec::new(), Vec::new());
+/// for _ in 0..3 {
+///     all_short_bsd_info_into(&mut pids, &mut infos).unwrap();
+///     println!("{} processes", infos.len());
+/// }
+/// ```
+pub fn all_short_bsd_info_into(
+    pids: &mut Vec<Pid>,
+    infos: &mut Vec<ProcBSDShortInfo>,
+) -> Result<(), std::io::Error> {
+    proc_listallpids_into(pids)?;
+    infos.clear();
+    infos.reserve(pids.len());
+    let buffersize = std::mem::size_of::<ProcBSDShortInfo>() as c_int;
+    for pid in pids.iter() {
+        // SAFETY: There is room for at least one more entry, and we only extend the vector
+        // over it once the kernel has filled in the whole struct.
+        unsafe {
+            let res = match libproc_call(|| {
+                ffi::proc_pidinfo(
+                    pid.0 as _,
+                    ProcPidInfoFlavor::PROC_PIDT_SHORTBSDINFO as c_int,
+                    0,
+                    infos.as_mut_ptr().add(infos.len()) as *mut c_void,
+                    buffersize,
+                )
+            }) {
+                Ok(res) => res as c_int,
+                Err(err) if err.raw_os_error() == Some(libc::ESRCH) => continue,
+                Err(err) => return Err(err),
+            };
+            if res == 0 {
+                continue;
             }
-            if res as usize % std::mem::size_of::<T>() != 0 {
+            if res != buffersize {
                 return Err(std::io::Error::new(
                     std::io::ErrorKind::InvalidData,
-                    "Unexpected buffer size",
+                    format!("Unexpected buffer size {res} != {buffersize}"),
                 ));
             }
-            buffer.set_len(res as usize / std::mem::size_of::<T>());
-            return Ok(buffer);
+            infos.set_len(infos.len() + 1);
         }
     }
+    Ok(())
 }
 
-/// Get an info struct for the current process. A convenience function that calls
-/// [`proc_pidinfo_list`] with the current process ID.
-#[allow(private_bounds)]
-pub fn proc_pidinfo_list_self<T: HasFlavorList>() -> Result<Vec<T>, std::io::Error> {
-    proc_pidinfo_list(getpid())
+/// Get the version of the libproc API as `(major, minor)`.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let (major, minor) = proc_libversion().unwrap();
+/// println!("libproc {major}.{minor}");
+/// ```
+pub fn proc_libversion() -> Result<(c_int, c_int), std::io::Error> {
+    let (mut major, mut minor) = (0, 0);
+    // SAFETY: Both pointers are valid for a c_int.
+    let res = unsafe { ffi::proc_libversion(&mut major, &mut minor) };
+    if res != 0 {
+        return Err(last_os_error());
+    }
+    Ok((major, minor))
+}
+
+/// Get the process that is responsible for a given process, as shown by Activity Monitor and
+/// used for privacy (TCC) prompts. Helper processes such as XPC services and WebKit content
+/// processes are attributed to the app that launched them; most processes are responsible for
+/// themselves.
+///
+/// This uses `responsibility_get_pid_responsible_for_pid`, which isn't in the public SDK, so it
+/// is looked up at runtime. Fails with [`std::io::ErrorKind::Unsupported`] if it's missing.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let responsible = responsible_pid(getpid()).unwrap();
+/// println!("{:?} is responsible for us", responsible);
+/// ```
+pub fn responsible_pid(pid: Pid) -> Result<Pid, std::io::Error> {
+    type ResponsibleFn = unsafe extern "C" fn(libc::pid_t) -> libc::pid_t;
+    static RESPONSIBLE_FN: std::sync::OnceLock<Option<ResponsibleFn>> = std::sync::OnceLock::new();
+
+    let responsible_fn = RESPONSIBLE_FN.get_or_init(|| {
+        // SAFETY: dlsym with a valid C string. A non-null result is the function, which has
+        // had this signature since it was introduced.
+        unsafe {
+            let sym = libc::dlsym(
+                libc::RTLD_DEFAULT,
+                c"responsibility_get_pid_responsible_for_pid".as_ptr(),
+            );
+            (!sym.is_null()).then(|| std::mem::transmute::<*mut c_void, ResponsibleFn>(sym))
+        }
+    });
+    let Some(responsible_fn) = responsible_fn else {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "responsibility_get_pid_responsible_for_pid is not available",
+        ));
+    };
+    // SAFETY: The function takes and returns a pid.
+    let res = unsafe { responsible_fn(pid.0 as _) };
+    if res <= 0 {
+        return Err(last_os_error());
+    }
+    Ok(Pid(res as _))
+}
+
+/// Get the path of the executable of a given process.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let path = proc_pidpath(getpid()).unwrap();
+/// assert_eq!(path, std::env::current_exe().unwrap().canonicalize().unwrap());
+/// ```
+pub fn proc_pidpath(pid: Pid) -> Result<PathBuf, std::io::Error> {
+    const PROC_PIDPATHINFO_MAXSIZE: usize = 4 * libc::MAXPATHLEN as usize;
+    let mut buffer = vec![0_u8; PROC_PIDPATHINFO_MAXSIZE];
+    // SAFETY: The buffer is as large as the kernel will ever write.
+    let res = unsafe {
+        ffi::proc_pidpath(
+            pid.0 as _,
+            buffer.as_mut_ptr() as *mut c_void,
+            buffer.len() as u32,
+        )
+    };
+    if res <= 0 {
+        return Err(last_os_error());
+    }
+    buffer.truncate(res as usize);
+    Ok(PathBuf::from(OsString::from_vec(buffer)))
+}
+
+/// Get the path of the file mapped at a given address in a process, or `None` if the address
+/// is unmapped or the memory there isn't backed by a file.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let address = getpid as fn() -> Pid as usize as u64;
+/// let path = proc_regionfilename(getpid(), address).unwrap();
+/// assert_eq!(path, Some(proc_pidpath(getpid()).unwrap()));
+/// ```
+pub fn proc_regionfilename(pid: Pid, address: u64) -> Result<Option<PathBuf>, std::io::Error> {
+    let mut buffer = vec![0_u8; libc::MAXPATHLEN as usize];
+    // SAFETY: The buffer is MAXPATHLEN bytes, as libproc requires.
+    let res = unsafe {
+        ffi::proc_regionfilename(
+            pid.0 as _,
+            address,
+            buffer.as_mut_ptr() as *mut c_void,
+            buffer.len() as u32,
+        )
+    };
+    if res <= 0 {
+        let err = last_os_error();
+        // The kernel reports both unmapped and anonymous regions as EINVAL.
+        if err.raw_os_error() == Some(libc::EINVAL) {
+            return Ok(None);
+        }
+        return Err(err);
+    }
+    buffer.truncate(res as usize);
+    Ok(Some(PathBuf::from(OsString::from_vec(buffer))))
+}
+
+/// Read the kernel message buffer, as shown by `dmesg`. Requires root, and fails with
+/// [`std::io::ErrorKind::PermissionDenied`] otherwise.
+///
+/// ```no_run
+/// use proc_pidinfo::*;
+///
+/// let messages = proc_kmsgbuf().unwrap();
+/// println!("{}", String::from_utf8_lossy(&messages));
+/// ```
+pub fn proc_kmsgbuf() -> Result<Vec<u8>, std::io::Error> {
+    // The default size, used if the kernel doesn't report it.
+    let mut size: c_int = 128 * 1024;
+    let mut len = std::mem::size_of::<c_int>();
+    // SAFETY: The output is a single c_int with its size. On failure, the default is kept.
+    unsafe {
+        libc::sysctlbyname(
+            c"kern.msgbuf".as_ptr(),
+            &mut size as *mut c_int as *mut c_void,
+            &mut len,
+            std::ptr::null_mut(),
+            0,
+        );
+    }
+    let mut buffer = vec![0_u8; size as usize + 1];
+    // SAFETY: The kernel writes at most buffersize bytes.
+    let res = unsafe { ffi::proc_kmsgbuf(buffer.as_mut_ptr() as *mut c_void, buffer.len() as u32) };
+    if res <= 0 {
+        return Err(last_os_error());
+    }
+    buffer.truncate(res as usize);
+    while buffer.last() == Some(&0) {
+        buffer.pop();
+    }
+    Ok(buffer)
+}
+
+/// Read the kernel message buffer as text, replacing invalid UTF-8. See [`proc_kmsgbuf`].
+pub fn proc_kmsgbuf_string() -> Result<String, std::io::Error> {
+    let buffer = proc_kmsgbuf()?;
+    Ok(String::from_utf8(buffer)
+        .unwrap_or_else(|err| String::from_utf8_lossy(err.as_bytes()).into_owned()))
 }
 
 /// General information about a file descriptor. See [`VnodeFdInfo`]
@@ -474,7 +1786,7 @@ pub struct VnodeInfo {
 
 /// Path information about a vnode. See [`VnodeFdInfoWithPath`] for more specific information.
 #[repr(C)]
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct VnodeInfoPath {
     pub vip_vi: VnodeInfo,
     pub vip_path: [c_char; libc::MAXPATHLEN as usize],
@@ -484,6 +1796,11 @@ impl VnodeInfoPath {
     pub fn path(&self) -> Result<&Path, ValueError> {
         libc_str_to_path(&self.vip_path)
     }
+
+    /// An owned copy of [`VnodeInfoPath::path`].
+    pub fn to_path_buf(&self) -> Result<PathBuf, ValueError> {
+        self.path().map(Path::to_path_buf)
+    }
 }
 
 /// Information about [`ProcFDType::VNODE`] file descriptors.
@@ -510,6 +1827,11 @@ impl VnodeFdInfoWithPath {
     pub fn path(&self) -> Result<&Path, ValueError> {
         self.pvip.path()
     }
+
+    /// An owned copy of [`VnodeFdInfoWithPath::path`].
+    pub fn to_path_buf(&self) -> Result<PathBuf, ValueError> {
+        self.pvip.to_path_buf()
+    }
 }
 
 impl HasFdFlavor for VnodeFdInfoWithPath {
@@ -541,23 +1863,26 @@ impl HasFdFlavor for PipeFdInfo {
 
 /// Get an info struct for a given process and file descriptor.
 ///
+/// Returns `None` if the descriptor is of a type the flavor doesn't describe, eg: a pipe for
+/// [`VnodeFdInfo`]. Fails with `EBADF` if the descriptor isn't open.
+///
 /// ```
 /// use proc_pidinfo::*;
 ///
 /// # let pid = getpid();
 /// for fd in proc_pidinfo_list::<ProcFDInfo>(pid).unwrap() {
 ///     println!("{:?}", fd);
-///     if fd.fd_type() == Ok(ProcFDType::VNODE) {
+///     if fd.fd_type() == ProcFDType::VNODE {
 ///         let vnode = proc_pidfdinfo::<VnodeFdInfo>(pid, fd.proc_fd).unwrap().unwrap();
 ///         println!("Vnode: {:?}", vnode);
 ///         if let Some(vnode) = proc_pidfdinfo::<VnodeFdInfoWithPath>(pid, fd.proc_fd).unwrap() {
 ///             println!("Path: {:?}", vnode.path().unwrap());
 ///         }
-///     } else if fd.fd_type() == Ok(ProcFDType::PIPE) {
+///     } else if fd.fd_type() == ProcFDType::PIPE {
 ///         let pipe = proc_pidfdinfo::<PipeFdInfo>(pid, fd.proc_fd).unwrap().unwrap();
 ///         println!("Pipe: {:?}", pipe);
 ///     } else {
-///         println!("Unknown fd type: {:?}", fd.fd_type().unwrap_err());
+///         println!("Other fd type: {:?}", fd.fd_type());
 ///     }
 /// }
 /// ```
@@ -566,16 +1891,19 @@ pub fn proc_pidfdinfo<T: HasFdFlavor>(pid: Pid, fd: Fd) -> Result<Option<T>, std
     unsafe {
         let mut value = std::mem::MaybeUninit::<T>::uninit();
         let buffersize = std::mem::size_of::<T>() as c_int;
-        let res = libc::proc_pidfdinfo(
-            pid.0 as _,
-            fd.0,
-            T::FLAVOR as c_int,
-            value.as_mut_ptr() as *mut c_void,
-            buffersize,
-        );
-        if res < 0 {
-            return Err(std::io::Error::from_raw_os_error(res));
-        }
+        let res = match libproc_call(|| {
+            ffi::proc_pidfdinfo(
+                pid.0 as _,
+                fd.0,
+                T::FLAVOR as c_int,
+                value.as_mut_ptr() as *mut c_void,
+                buffersize,
+            )
+        }) {
+            Ok(res) => res as c_int,
+            Err(err) if is_wrong_fd_type(&err) => return Ok(None),
+            Err(err) => return Err(err),
+        };
         if res == 0 {
             return Ok(None);
         }
@@ -589,6 +1917,15 @@ pub fn proc_pidfdinfo<T: HasFdFlavor>(pid: Pid, fd: Fd) -> Result<Option<T>, std
     }
 }
 
+/// The kernel rejects a flavor for a different type of descriptor, eg: [`VnodeFdInfo`] for a
+/// pipe, with `ENOTSUP` or `EOPNOTSUPP`.
+fn is_wrong_fd_type(err: &std::io::Error) -> bool {
+    matches!(
+        err.raw_os_error(),
+        Some(libc::ENOTSUP) | Some(libc::EOPNOTSUPP)
+    )
+}
+
 /// Get an info struct for the current process.
 ///
 /// This is a convenience function that calls [`proc_pidfdinfo`] with the current process ID.
@@ -599,23 +1936,25 @@ pub fn proc_pidfdinfo_self<T: HasFdFlavor>(fd: Fd) -> Result<Option<T>, std::io:
 
 /// Get an info struct for a given process and fileport.
 ///
+/// Returns `None` if the fileport is of a type the flavor doesn't describe.
+///
 /// ```
 /// use proc_pidinfo::*;
 ///
 /// # let pid = getpid();
 /// for port in proc_pidinfo_list::<ProcFilePortInfo>(pid).unwrap() {
 ///     println!("{:?}", port);
-///     if port.fd_type() == Ok(ProcFDType::VNODE) {
+///     if port.fd_type() == ProcFDType::VNODE {
 ///         let vnode = proc_pidfileportinfo::<VnodeFdInfo>(pid, port.proc_fileport).unwrap().unwrap();
 ///         println!("Vnode: {:?}", vnode);
 ///         if let Some(vnode) = proc_pidfileportinfo::<VnodeFdInfoWithPath>(pid, port.proc_fileport).unwrap() {
 ///             println!("Path: {:?}", vnode.path().unwrap());
 ///         }
-///     } else if port.fd_type() == Ok(ProcFDType::PIPE) {
+///     } else if port.fd_type() == ProcFDType::PIPE {
 ///         let pipe = proc_pidfileportinfo::<PipeFdInfo>(pid, port.proc_fileport).unwrap().unwrap();
 ///         println!("Pipe: {:?}", pipe);
 ///     } else {
-///         println!("Unknown fd type: {:?}", port.fd_type().unwrap_err());
+///         println!("Other fd type: {:?}", port.fd_type());
 ///     }
 /// }
 /// ```
@@ -627,16 +1966,19 @@ pub fn proc_pidfileportinfo<T: HasFdFlavor>(
     unsafe {
         let mut value = std::mem::MaybeUninit::<T>::uninit();
         let buffersize = std::mem::size_of::<T>() as c_int;
-        let res = libc::proc_pidfileportinfo(
-            pid.0 as _,
-            fileport.0,
-            T::FLAVOR as c_int,
-            value.as_mut_ptr() as *mut c_void,
-            buffersize,
-        );
-        if res < 0 {
-            return Err(std::io::Error::from_raw_os_error(res));
-        }
+        let res = match libproc_call(|| {
+            ffi::proc_pidfileportinfo(
+                pid.0 as _,
+                fileport.0,
+                T::FLAVOR as c_int,
+                value.as_mut_ptr() as *mut c_void,
+                buffersize,
+            )
+        }) {
+            Ok(res) => res as c_int,
+            Err(err) if is_wrong_fd_type(&err) => return Ok(None),
+            Err(err) => return Err(err),
+        };
         if res == 0 {
             return Ok(None);
         }
@@ -663,6 +2005,7 @@ pub fn proc_pidfileportinfo_self<T: HasFdFlavor>(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testutil::TestChild;
 
     #[test]
     fn test_proc_pidinfo_pid_zero() {
@@ -682,7 +2025,7 @@ mod tests {
         let result = proc_pidinfo_list::<ProcFilePortInfo>(Pid(1)).unwrap();
         for port in result {
             println!("{:?}", port);
-            if port.fd_type() == Ok(ProcFDType::VNODE) {
+            if port.fd_type() == ProcFDType::VNODE {
                 let vnode = proc_pidfileportinfo::<VnodeFdInfo>(Pid(1), port.proc_fileport)
                     .unwrap()
                     .unwrap();
@@ -691,11 +2034,29 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_proc_pidinfo_errors() {
+        let missing = Pid(99_999_999);
+        let err = proc_pidinfo::<ProcBSDShortInfo>(missing).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::ESRCH));
+        let err = proc_pidinfo_list::<ProcFDInfo>(missing).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::ESRCH));
+        let err = proc_pidfdinfo::<VnodeFdInfo>(getpid(), Fd(i32::MAX)).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::EBADF));
+        // SAFETY: geteuid never fails.
+        if unsafe { libc::geteuid() } != 0 {
+            let err = proc_pidinfo::<ProcTaskInfo>(Pid(1)).unwrap_err();
+            assert_eq!(err.raw_os_error(), Some(libc::EPERM));
+            let err = proc_pidinfo_list::<ProcFDInfo>(Pid(1)).unwrap_err();
+            assert_eq!(err.raw_os_error(), Some(libc::EPERM));
+        }
+    }
+
     #[test]
     fn test_proc_pidinfo_self() {
         let result = proc_pidinfo_list_self::<ProcFDInfo>().unwrap();
         for fd in result {
-            if fd.fd_type() == Ok(ProcFDType::VNODE) {
+            if fd.fd_type() == ProcFDType::VNODE {
                 let vnode = proc_pidfdinfo_self::<VnodeFdInfo>(fd.proc_fd)
                     .unwrap()
                     .unwrap();
@@ -715,7 +2076,7 @@ mod tests {
     fn test_proc_pidinfo_fileport_self() {
         let result = proc_pidinfo_list_self::<ProcFilePortInfo>().unwrap();
         for port in result {
-            if port.fd_type() == Ok(ProcFDType::VNODE) {
+            if port.fd_type() == ProcFDType::VNODE {
                 let vnode = proc_pidfileportinfo_self::<VnodeFdInfo>(port.proc_fileport)
                     .unwrap()
                     .unwrap();
@@ -732,6 +2093,87 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_proc_pidinfo_list_bounded() {
+        let fds = proc_pidinfo_list_self::<ProcFDInfo>().unwrap();
+        assert!(fds.len() > 1);
+
+        let list = proc_pidinfo_list_bounded::<ProcFDInfo>(getpid(), 1).unwrap();
+        assert!(list.truncated);
+        assert_eq!(list.items.len(), 1);
+        assert!(list.estimated_total.unwrap() > 1);
+
+        let list = proc_pidinfo_list_bounded::<ProcFDInfo>(getpid(), 1_000_000).unwrap();
+        assert!(list.is_complete());
+    }
+
+    #[test]
+    fn test_proc_pidinfo_list_into() {
+        let mut buffer = Vec::with_capacity(1);
+        proc_pidinfo_list_into::<ProcFDInfo>(getpid(), &mut buffer).unwrap();
+        assert!(buffer.len() > 1);
+        let capacity = buffer.capacity();
+        proc_pidinfo_list_into::<ProcFDInfo>(getpid(), &mut buffer).unwrap();
+        assert!(buffer.len() > 1);
+        assert!(buffer.capacity() >= capacity);
+
+        let mut buffer = [std::mem::MaybeUninit::<ProcFDInfo>::uninit(); 1];
+        let fds = proc_pidinfo_list_into_uninit(getpid(), &mut buffer).unwrap();
+        assert_eq!(fds.len(), 1);
+        let fds = proc_pidinfo_list_into_uninit::<ProcFDInfo>(getpid(), &mut []).unwrap();
+        assert!(fds.is_empty());
+    }
+
+    #[test]
+    fn test_proc_pidinfo_list_with_hint() {
+        let fds = proc_pidinfo_list_with_hint::<ProcFDInfo>(getpid(), 1).unwrap();
+        assert!(fds.len() > 1);
+        let fds = proc_pidinfo_list_with_hint::<ProcFDInfo>(getpid(), 4096).unwrap();
+        assert!(fds.len() > 1);
+    }
+
+    #[test]
+    fn test_proc_pidinfo_with_arg() {
+        let threads = proc_pidinfo_list_self::<ThreadHandle>().unwrap();
+        assert!(!threads.is_empty());
+        let info = proc_pidinfo_with_arg::<ProcThreadInfo>(getpid(), threads[0])
+            .unwrap()
+            .unwrap();
+        assert!(info.pth_user_time > 0 || info.pth_system_time > 0);
+
+        let value = Box::new(0_u64);
+        let address = &*value as *const u64 as u64;
+        let region = proc_pidinfo_with_arg::<ProcRegionInfo>(getpid(), address)
+            .unwrap()
+            .unwrap();
+        assert!(region.pri_address <= address && address < region.end());
+    }
+
+    #[test]
+    fn test_fd_type_unknown() {
+        let fd = ProcFDInfo {
+            proc_fd: Fd(0),
+            proc_fdtype: 1,
+        };
+        assert_eq!(fd.fd_type(), ProcFDType::VNODE);
+        let fd = ProcFDInfo {
+            proc_fd: Fd(0),
+            proc_fdtype: 8,
+        };
+        assert_eq!(fd.fd_type(), ProcFDType::Unknown(8));
+        assert_eq!(fd.fd_type().as_raw(), 8);
+        assert_eq!(ProcFDType::NEXUS.as_raw(), 11);
+    }
+
+    #[test]
+    fn test_tty_path() {
+        use std::os::unix::fs::MetadataExt;
+
+        assert_eq!(tty_path(NODEV), None);
+        let dev = std::fs::metadata("/dev/null").unwrap().rdev();
+        assert_eq!(tty_path(dev as u32), Some(PathBuf::from("/dev/null")));
+    }
+
     #[test]
     fn test_proc_task_info_self() {
         let result = proc_pidinfo_self::<ProcTaskAllInfo>().unwrap().unwrap();
@@ -739,6 +2181,22 @@ mod tests {
         println!("{:?}", result);
     }
 
+    #[test]
+    fn test_proc_task_info_durations() {
+        let start = std::time::Instant::now();
+        while start.elapsed() < std::time::Duration::from_millis(20) {
+            std::hint::black_box(start.elapsed());
+        }
+        let info = proc_pidinfo_self::<ProcTaskInfo>().unwrap().unwrap();
+        assert!(info.total_user() + info.total_system() >= std::time::Duration::from_millis(10));
+        assert!(info.total_user() >= info.threads_user());
+        assert!(info.total_system() >= info.threads_system());
+        assert_eq!(
+            info.total_user(),
+            mach_ticks_to_duration(info.pti_total_user)
+        );
+    }
+
     #[test]
     fn test_proc_task_info_short_self() {
         let result = proc_pidinfo_self::<ProcBSDShortInfo>().unwrap().unwrap();
@@ -746,6 +2204,134 @@ mod tests {
         println!("{:?}", result);
     }
 
+    #[test]
+    fn test_proc_names() {
+        let info = getpid().bsd_info().unwrap().unwrap();
+        let short = getpid().bsd_short_info().unwrap().unwrap();
+        assert_eq!(info.comm(), short.comm());
+        assert!(info.name().unwrap().starts_with(info.comm().unwrap()));
+        assert_eq!(ProcNameExt::name(&info), info.name());
+        assert_eq!(short.name_lossy(), short.comm_string().unwrap());
+        let all = getpid().task_all_info().unwrap().unwrap();
+        assert_eq!(all.name_string(), info.name_string());
+
+        let mut empty = info;
+        empty.pbi_name = [0; 2 * libc::MAXCOMLEN];
+        assert_eq!(empty.name(), info.comm());
+    }
+
+    #[test]
+    fn test_all_short_bsd_info() {
+        let infos = all_short_bsd_info().unwrap();
+        let own = infos.iter().find(|info| info.pbsi_pid == getpid()).unwrap();
+        assert_eq!(
+            own.comm().unwrap(),
+            getpid().bsd_short_info().unwrap().unwrap().comm().unwrap()
+        );
+
+        let (mut pids, mut infos) = (Vec::new(), Vec::new());
+        for _ in 0..2 {
+            all_short_bsd_info_into(&mut pids, &mut infos).unwrap();
+            assert!(infos.len() <= pids.len());
+            assert!(infos.iter().any(|info| info.pbsi_pid == Pid(1)));
+        }
+    }
+
+    #[test]
+    fn test_proc_unique_identifier_info() {
+        assert_eq!(std::mem::size_of::<ProcUniqueIdentifierInfo>(), 56);
+        let info = getpid().unique_identifier_info().unwrap().unwrap();
+        assert_ne!(info.p_uniqueid, 0);
+        assert_ne!(info.p_uuid, [0; 16]);
+
+        // Each child gets a fresh unique id, and its parent's is ours.
+        let child = TestChild::sleep();
+        let child_info = child.pid().unique_identifier_info().unwrap().unwrap();
+        assert_ne!(child_info.p_uniqueid, info.p_uniqueid);
+        assert_eq!(child_info.p_puniqueid, info.p_uniqueid);
+    }
+
+    #[test]
+    fn test_owned_variants() {
+        let info = getpid().bsd_info().unwrap().unwrap();
+        let owned = std::thread::spawn(move || ProcBSDInfoOwned::from(info))
+            .join()
+            .unwrap();
+        assert_eq!(owned.pid, getpid());
+        assert_eq!(owned.status, Some(ProcStatus::SRUN));
+        let short = getpid().bsd_short_info().unwrap().unwrap();
+        assert_eq!(owned.comm, short.comm_string().unwrap());
+        assert!(owned.start_time <= std::time::SystemTime::now());
+
+        let file = std::fs::File::open("/dev/null").unwrap();
+        let fd = Fd(std::os::fd::AsRawFd::as_raw_fd(&file));
+        let vnode = proc_pidfdinfo_self::<VnodeFdInfoWithPath>(fd)
+            .unwrap()
+            .unwrap();
+        assert_eq!(vnode.to_path_buf().unwrap(), PathBuf::from("/dev/null"));
+    }
+
+    #[test]
+    fn test_platform_error() {
+        assert!(!is_embedded_device());
+        let err = platform_error(std::io::Error::from_raw_os_error(libc::EPERM), false);
+        assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+        let err = platform_error(std::io::Error::from_raw_os_error(libc::EPERM), true);
+        assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+        let err = platform_error(std::io::Error::from_raw_os_error(libc::ESRCH), true);
+        assert_eq!(err.raw_os_error(), Some(libc::ESRCH));
+    }
+
+    #[test]
+    fn test_pid_methods() {
+        let pid = getpid();
+        assert_eq!(pid.bsd_info().unwrap().unwrap().pbi_pid, pid);
+        assert_eq!(pid.task_all_info().unwrap().unwrap().pbsd.pbi_pid, pid);
+        assert!(pid.task_info().unwrap().unwrap().pti_threadnum > 0);
+        for fd in pid.fds().unwrap() {
+            if fd.fd_type() == ProcFDType::VNODE {
+                assert!(pid.fd_info::<VnodeFdInfo>(fd.proc_fd).unwrap().is_some());
+            }
+        }
+    }
+
+    #[test]
+    fn test_proc_regionfilename() {
+        let stack = 0_u8;
+        let address = &stack as *const u8 as u64;
+        assert_eq!(proc_regionfilename(getpid(), address).unwrap(), None);
+        assert_eq!(proc_regionfilename(getpid(), 0).unwrap(), None);
+        let address = test_proc_regionfilename as fn() as usize as u64;
+        let path = proc_regionfilename(getpid(), address).unwrap().unwrap();
+        assert_eq!(path, proc_pidpath(getpid()).unwrap());
+    }
+
+    #[test]
+    fn test_proc_kmsgbuf() {
+        // SAFETY: geteuid never fails.
+        if unsafe { libc::geteuid() } == 0 {
+            assert!(!proc_kmsgbuf_string().unwrap().is_empty());
+        } else {
+            let err = proc_kmsgbuf().unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+        }
+    }
+
+    #[test]
+    fn test_proc_libversion() {
+        let (major, _minor) = proc_libversion().unwrap();
+        assert!(major >= 1);
+    }
+
+    #[test]
+    fn test_responsible_pid() {
+        // Children are attributed to whoever is responsible for the parent.
+        let child = TestChild::sleep();
+        let responsible = responsible_pid(getpid()).unwrap();
+        assert_eq!(responsible_pid(child.pid()).unwrap(), responsible);
+        assert_eq!(responsible_pid(Pid(1)).unwrap(), Pid(1));
+    }
+
     #[test]
     fn test_proc_task_info_short_zero() {
         let result = proc_pidinfo::<ProcBSDShortInfo>(Pid(0)).unwrap().unwrap();
@@ -753,4 +2339,34 @@ mod tests {
         println!("{:?}", result);
         println!("{}", result.comm().unwrap());
     }
+
+    #[test]
+    fn test_fileport_make_fd() {
+        use std::os::fd::AsRawFd;
+
+        extern "C" {
+            fn fileport_makeport(fd: c_int, port: *mut u32) -> c_int;
+        }
+
+        let file = std::fs::File::open("/dev/null").unwrap();
+        let mut port = 0;
+        // SAFETY: The output is a single port name.
+        let res = unsafe { fileport_makeport(file.as_raw_fd(), &mut port) };
+        assert_eq!(res, 0);
+        drop(file);
+
+        let fileports = getpid().fileports().unwrap();
+        let info = fileports
+            .iter()
+            .find(|info| info.proc_fileport == FilePort(port))
+            .unwrap();
+        assert_eq!(info.fd_type(), ProcFDType::VNODE);
+        let fd = info.proc_fileport.make_fd().unwrap();
+        let vnode = proc_pidfdinfo::<VnodeFdInfoWithPath>(getpid(), Fd(fd.as_raw_fd()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(vnode.path().unwrap(), Path::new("/dev/null"));
+
+        assert!(FilePort(0).make_fd().is_err());
+    }
 }
@@ -0,0 +1,141 @@
+use std::collections::HashSet;
+use std::time::{Duration, SystemTime};
+
+use super::{proc_pidinfo, Pid, ProcBSDInfo, ProcBSDShortInfo};
+
+/// The start time of a process, if it can be read.
+fn start_time(pid: Pid) -> Option<SystemTime> {
+    let info = proc_pidinfo::<ProcBSDInfo>(pid).ok()??;
+    Some(
+        SystemTime::UNIX_EPOCH
+            + Duration::from_secs(info.pbi_start_tvsec)
+            + Duration::from_micros(info.pbi_start_tvusec),
+    )
+}
+
+/// Read a process's short info, or `None` if it has exited, which fails with `ESRCH`.
+fn short_info(pid: Pid) -> Result<Option<ProcBSDShortInfo>, std::io::Error> {
+    match proc_pidinfo::<ProcBSDShortInfo>(pid) {
+        Ok(Some(info)) => Ok(Some(info)),
+        // Not an exit: the short info is available for every live process.
+        Ok(None) => Err(std::io::Error::other("The kernel returned no data")),
+        Err(err) if err.raw_os_error() == Some(libc::ESRCH) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// An iterator over the ancestors of a process, from its parent up to `launchd`. See
+/// [`ancestors`].
+#[derive(Debug, Clone)]
+pub struct Ancestors {
+    current: Option<(ProcBSDShortInfo, Option<SystemTime>)>,
+    seen: HashSet<Pid>,
+}
+
+impl Iterator for Ancestors {
+    type Item = Result<ProcBSDShortInfo, std::io::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (child, child_start) = self.current.take()?;
+        let ppid = child.pbsi_ppid;
+        // launchd's parent is kernel_task, which is its own parent.
+        if child.pbsi_pid.0 <= 1 || !self.seen.insert(ppid) {
+            return None;
+        }
+        let parent = match short_info(ppid) {
+            Ok(Some(parent)) => parent,
+            // The parent exited, and the child hasn't been reparented yet.
+            Ok(None) => return None,
+            Err(err) => return Some(Err(err)),
+        };
+        let parent_start = start_time(ppid);
+        if let (Some(child_start), Some(parent_start)) = (child_start, parent_start) {
+            // The parent exited and its pid was reused by a later process.
+            if parent_start > child_start {
+                return None;
+            }
+        }
+        self.current = Some((parent, parent_start));
+        Some(Ok(parent))
+    }
+}
+
+/// Walk up the ancestors of a process, from its parent up to and including `launchd`.
+///
+/// Each step reads the parent's [`ProcBSDShortInfo`]. A parent that exits during the walk
+/// ends it early, and so does a parent pid that was reused by a process started after the
+/// child, which is detected by comparing start times from [`ProcBSDInfo`] where they can be
+/// read. Fails if the process itself doesn't exist.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// for ancestor in ancestors(getpid()).unwrap() {
+///     let ancestor = ancestor.unwrap();
+///     println!("{} {}", ancestor.pbsi_pid.0, ancestor.comm().unwrap());
+/// }
+/// ```
+pub fn ancestors(pid: Pid) -> Result<Ancestors, std::io::Error> {
+    let info = short_info(pid)?.ok_or(std::io::Error::from_raw_os_error(libc::ESRCH))?;
+    Ok(Ancestors {
+        current: Some((info, start_time(pid))),
+        seen: HashSet::from([pid]),
+    })
+}
+
+/// The parent of a process, or `None` for `launchd` and `kernel_task`, or if the parent has
+/// exited. See [`ancestors`].
+pub fn parent(pid: Pid) -> Result<Option<ProcBSDShortInfo>, std::io::Error> {
+    ancestors(pid)?.next().transpose()
+}
+
+/// Returns true if `pid` is a descendant of `ancestor`, ie: `ancestor` appears in its
+/// [`ancestors`]. A process is not its own descendant.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// assert!(is_descendant_of(getpid(), Pid(1)).unwrap());
+/// ```
+pub fn is_descendant_of(pid: Pid, ancestor: Pid) -> Result<bool, std::io::Error> {
+    for info in ancestors(pid)? {
+        if info?.pbsi_pid == ancestor {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::darwin::getpid;
+    use crate::testutil::TestChild;
+
+    #[test]
+    fn test_ancestors() {
+        let child = TestChild::sleep();
+        let pid = child.pid();
+        let chain = ancestors(pid).map(|chain| chain.collect::<Result<Vec<_>, _>>());
+        let is_descendant = is_descendant_of(pid, getpid());
+        let is_ancestor = is_descendant_of(getpid(), pid);
+        let child_parent = parent(pid);
+
+        let chain = chain.unwrap().unwrap();
+        assert_eq!(chain[0].pbsi_pid, getpid());
+        assert_eq!(chain.last().unwrap().pbsi_pid, Pid(1));
+        assert!(is_descendant.unwrap());
+        assert!(!is_ancestor.unwrap());
+        assert_eq!(child_parent.unwrap().unwrap().pbsi_pid, getpid());
+    }
+
+    #[test]
+    fn test_ancestors_roots() {
+        assert!(parent(Pid(1)).unwrap().is_none());
+        assert!(parent(Pid(0)).unwrap().is_none());
+        assert!(!is_descendant_of(Pid(1), Pid(0)).unwrap());
+        let err = ancestors(Pid(99_999_999)).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::ESRCH));
+        assert!(short_info(Pid(99_999_999)).unwrap().is_none());
+    }
+}
@@ -0,0 +1,226 @@
+use libc::{c_int, c_void};
+
+use super::sysctl::{kinfo_proc, P_TRANSLATED};
+use super::{last_os_error, proc_pidinfo, HasFlavor, Pid, ProcPidInfoFlavor};
+
+const CPU_ARCH_ABI64: i32 = 0x0100_0000;
+const CPU_TYPE_X86: i32 = 7;
+const CPU_TYPE_ARM: i32 = 12;
+const CPU_TYPE_X86_64: i32 = CPU_TYPE_X86 | CPU_ARCH_ABI64;
+const CPU_TYPE_ARM64: i32 = CPU_TYPE_ARM | CPU_ARCH_ABI64;
+/// The high byte of a subtype holds capability bits, eg: the pointer authentication ABI.
+const CPU_SUBTYPE_MASK: i32 = 0xff00_0000_u32 as i32;
+const CPU_SUBTYPE_ARM64E: i32 = 2;
+
+/// The most components in a sysctl name.
+const CTL_MAXNAME: usize = 12;
+
+/// The CPU type of a process's executable. Usable with [`proc_pidinfo`].
+///
+/// This is a private flavor, which needs the same privileges as [`super::ProcTaskInfo`]. See
+/// [`arch`], which falls back to a sysctl for other users' processes.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProcArchInfo {
+    /// The `CPU_TYPE_*` from `<mach/machine.h>`.
+    pub p_cputype: i32,
+    /// The `CPU_SUBTYPE_*` from `<mach/machine.h>`, including capability bits.
+    pub p_cpusubtype: i32,
+}
+
+impl HasFlavor for ProcArchInfo {
+    const FLAVOR: ProcPidInfoFlavor = ProcPidInfoFlavor::PROC_PIDARCHINFO;
+}
+
+/// The architecture of a process's executable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub enum ProcessArch {
+    Arm64,
+    /// `arm64` with pointer authentication, used by system executables.
+    Arm64e,
+    X86_64,
+    /// A CPU type this crate doesn't name.
+    Unknown {
+        cputype: i32,
+        cpusubtype: i32,
+    },
+}
+
+impl ProcessArch {
+    /// Decode a CPU type and subtype, eg: from [`ProcArchInfo`].
+    pub fn from_raw(cputype: i32, cpusubtype: i32) -> Self {
+        match (cputype, cpusubtype & !CPU_SUBTYPE_MASK) {
+            (CPU_TYPE_ARM64, CPU_SUBTYPE_ARM64E) => Self::Arm64e,
+            (CPU_TYPE_ARM64, _) => Self::Arm64,
+            (CPU_TYPE_X86_64, _) => Self::X86_64,
+            _ => Self::Unknown {
+                cputype,
+                cpusubtype,
+            },
+        }
+    }
+
+    /// The name used by `lipo` and `arch`, eg: `arm64e`.
+    pub fn name(self) -> Option<&'static str> {
+        match self {
+            Self::Arm64 => Some("arm64"),
+            Self::Arm64e => Some("arm64e"),
+            Self::X86_64 => Some("x86_64"),
+            Self::Unknown { .. } => None,
+        }
+    }
+}
+
+/// The architecture of a process, and whether it is translated. See [`arch`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ArchInfo {
+    pub arch: ProcessArch,
+    /// Whether the process runs under Rosetta 2, ie: an `x86_64` process on Apple silicon.
+    pub translated: bool,
+}
+
+impl ArchInfo {
+    /// The "Kind" shown by Activity Monitor: `Apple` for Apple silicon code, and `Intel` for
+    /// Intel code, whether translated or not.
+    pub fn kind(&self) -> &'static str {
+        match self.arch {
+            ProcessArch::Arm64 | ProcessArch::Arm64e => "Apple",
+            ProcessArch::X86_64 => "Intel",
+            ProcessArch::Unknown { .. } => "Unknown",
+        }
+    }
+}
+
+/// Get the architecture of a process, and whether it runs under Rosetta 2.
+///
+/// The CPU subtype, which tells `arm64e` from `arm64`, is read with [`ProcArchInfo`], which
+/// needs the same privileges as [`super::ProcTaskInfo`]. For other users' processes, the
+/// type is read from the `sysctl.proc_cputype` sysctl instead, and `arm64e` processes are
+/// reported as [`ProcessArch::Arm64`].
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let info = arch(getpid()).unwrap();
+/// println!("{} {:?}, translated: {}", info.kind(), info.arch.name(), info.translated);
+/// ```
+pub fn arch(pid: Pid) -> Result<ArchInfo, std::io::Error> {
+    let translated = kinfo_proc(pid)?
+        .ok_or(std::io::Error::from_raw_os_error(libc::ESRCH))?
+        .flags()
+        & P_TRANSLATED
+        != 0;
+    let arch = match proc_pidinfo::<ProcArchInfo>(pid) {
+        Ok(Some(info)) => ProcessArch::from_raw(info.p_cputype, info.p_cpusubtype),
+        _ => ProcessArch::from_raw(sysctl_cputype(pid)?, 0),
+    };
+    Ok(ArchInfo { arch, translated })
+}
+
+/// Returns true if the current process runs under Rosetta 2, from the `sysctl.proc_translated`
+/// sysctl. See [`arch`] for other processes.
+pub fn is_translated() -> bool {
+    let mut translated: c_int = 0;
+    let mut len = std::mem::size_of::<c_int>();
+    // SAFETY: The output is a single c_int with its size.
+    let res = unsafe {
+        libc::sysctlbyname(
+            c"sysctl.proc_translated".as_ptr(),
+            &mut translated as *mut c_int as *mut c_void,
+            &mut len,
+            std::ptr::null_mut(),
+            0,
+        )
+    };
+    // The sysctl doesn't exist on Intel Macs, where nothing is translated.
+    res == 0 && translated == 1
+}
+
+/// Read the CPU type of a process from the `sysctl.proc_cputype` sysctl, which takes the pid
+/// as an extra name component.
+fn sysctl_cputype(pid: Pid) -> Result<i32, std::io::Error> {
+    let mut mib = [0 as c_int; CTL_MAXNAME];
+    let mut mib_len = mib.len() - 1;
+    // SAFETY: The name is NUL-terminated, and the output is an array with its length.
+    let res = unsafe {
+        libc::sysctlnametomib(
+            c"sysctl.proc_cputype".as_ptr(),
+            mib.as_mut_ptr(),
+            &mut mib_len,
+        )
+    };
+    if res != 0 {
+        return Err(last_os_error());
+    }
+    mib[mib_len] = pid.0 as c_int;
+
+    let mut cputype: c_int = 0;
+    let mut len = std::mem::size_of::<c_int>();
+    // SAFETY: The output is a single c_int with its size.
+    let res = unsafe {
+        libc::sysctl(
+            mib.as_mut_ptr(),
+            mib_len as u32 + 1,
+            &mut cputype as *mut c_int as *mut c_void,
+            &mut len,
+            std::ptr::null_mut(),
+            0,
+        )
+    };
+    if res != 0 {
+        return Err(last_os_error());
+    }
+    Ok(cputype)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::darwin::getpid;
+
+    #[test]
+    fn test_arch_self() {
+        let info = arch(getpid()).unwrap();
+        assert_eq!(info.translated, is_translated());
+        if !info.translated {
+            let expected = match std::env::consts::ARCH {
+                "aarch64" => ProcessArch::Arm64,
+                _ => ProcessArch::X86_64,
+            };
+            assert_eq!(info.arch, expected);
+        }
+        assert_eq!(
+            ProcessArch::from_raw(sysctl_cputype(getpid()).unwrap(), 0),
+            info.arch
+        );
+    }
+
+    #[test]
+    fn test_arch_other() {
+        // launchd is a system executable, which is arm64e on Apple silicon.
+        let info = arch(Pid(1)).unwrap();
+        assert!(!info.translated);
+        assert_ne!(info.kind(), "Unknown");
+        assert!(arch(Pid(99_999_999)).is_err());
+    }
+
+    #[test]
+    fn test_from_raw() {
+        assert_eq!(
+            ProcessArch::from_raw(CPU_TYPE_ARM64, 0x8000_0002_u32 as i32),
+            ProcessArch::Arm64e
+        );
+        assert_eq!(ProcessArch::from_raw(CPU_TYPE_ARM64, 0), ProcessArch::Arm64);
+        assert_eq!(
+            ProcessArch::from_raw(CPU_TYPE_X86_64, 3),
+            ProcessArch::X86_64
+        );
+        assert_eq!(
+            ProcessArch::from_raw(CPU_TYPE_X86_64, 3).name(),
+            Some("x86_64")
+        );
+    }
+}
@@ -0,0 +1,171 @@
+use std::time::{Duration, SystemTime};
+
+use super::sysctl::{kinfo_proc, KinfoProc};
+use super::{proc_pidinfo, Pid, ProcBSDInfo, ProcBSDShortInfo, ProcTaskAllInfo, ProcTaskInfo};
+
+/// How much detail [`proc_pidinfo_best_effort`] could read about a process, from least to most.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum InfoLevel {
+    /// Only the `kern.proc.pid` sysctl, eg: for a zombie.
+    Sysctl,
+    /// [`ProcBSDShortInfo`], which is available for every live process.
+    Short,
+    /// [`ProcBSDInfo`].
+    Bsd,
+    /// [`ProcTaskAllInfo`], which needs the same user as the process, or root.
+    TaskAll,
+}
+
+/// Whatever could be read about a process by [`proc_pidinfo_best_effort`].
+#[derive(Debug, Clone, Copy)]
+pub struct BestEffortInfo {
+    /// The most detailed source that could be read.
+    pub level: InfoLevel,
+    /// The short info, which is always present. At [`InfoLevel::Sysctl`], it is filled in from
+    /// the sysctl, and `pbsi_flags` is zero.
+    pub short_info: ProcBSDShortInfo,
+    /// Present from [`InfoLevel::Bsd`].
+    pub bsd_info: Option<ProcBSDInfo>,
+    /// Present at [`InfoLevel::TaskAll`].
+    pub task_info: Option<ProcTaskInfo>,
+    /// When the process started. Available at every level except [`InfoLevel::Short`].
+    pub start_time: Option<SystemTime>,
+}
+
+impl BestEffortInfo {
+    fn from_bsd(level: InfoLevel, info: ProcBSDInfo, task_info: Option<ProcTaskInfo>) -> Self {
+        Self {
+            level,
+            short_info: ProcBSDShortInfo {
+                pbsi_pid: info.pbi_pid,
+                pbsi_ppid: info.pbi_ppid,
+                pbsi_pgid: info.pbi_pgid,
+                pbsi_status: info.pbi_status,
+                pbsi_comm: info.pbi_comm,
+                pbsi_flags: info.pbi_flags,
+                pbsi_uid: info.pbi_uid,
+                pbsi_gid: info.pbi_gid,
+                pbsi_ruid: info.pbi_ruid,
+                pbsi_rgid: info.pbi_rgid,
+                pbsi_svuid: info.pbi_svuid,
+                pbsi_svgid: info.pbi_svgid,
+                pbsi_rfu: 0,
+            },
+            bsd_info: Some(info),
+            task_info,
+            start_time: Some(
+                SystemTime::UNIX_EPOCH
+                    + Duration::from_secs(info.pbi_start_tvsec)
+                    + Duration::from_micros(info.pbi_start_tvusec),
+            ),
+        }
+    }
+
+    fn from_kinfo(info: &KinfoProc) -> Self {
+        Self {
+            level: InfoLevel::Sysctl,
+            short_info: ProcBSDShortInfo {
+                pbsi_pid: info.pid(),
+                pbsi_ppid: info.ppid(),
+                pbsi_pgid: info.pgid(),
+                // p_stat uses the same SIDL..SZOMB values.
+                pbsi_status: info.status().map_or(0, |status| status as u32),
+                pbsi_comm: info.short_comm(),
+                pbsi_flags: 0,
+                pbsi_uid: info.uid(),
+                pbsi_gid: info.gid(),
+                pbsi_ruid: info.ruid(),
+                pbsi_rgid: info.rgid(),
+                pbsi_svuid: info.svuid(),
+                pbsi_svgid: info.svgid(),
+                pbsi_rfu: 0,
+            },
+            bsd_info: None,
+            task_info: None,
+            start_time: Some(info.start_time()),
+        }
+    }
+}
+
+/// Read as much as the caller's privileges allow about a process, trying [`ProcTaskAllInfo`],
+/// then [`ProcBSDInfo`], then [`ProcBSDShortInfo`], and finally [`super::sysctl::kinfo_proc`].
+///
+/// Without root, other users' processes usually stop at [`InfoLevel::Short`] and zombies at
+/// [`InfoLevel::Sysctl`]. Returns `None` if there is no such process, and fails only if the
+/// sysctl fails.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// for pid in proc_listallpids().unwrap() {
+///     if let Some(info) = proc_pidinfo_best_effort(pid).unwrap() {
+///         println!("{} {:?} {:?}", pid.0, info.short_info.comm(), info.level);
+///     }
+/// }
+/// ```
+pub fn proc_pidinfo_best_effort(pid: Pid) -> Result<Option<BestEffortInfo>, std::io::Error> {
+    if let Ok(Some(info)) = proc_pidinfo::<ProcTaskAllInfo>(pid) {
+        return Ok(Some(BestEffortInfo::from_bsd(
+            InfoLevel::TaskAll,
+            info.pbsd,
+            Some(info.ptinfo),
+        )));
+    }
+    if let Ok(Some(info)) = proc_pidinfo::<ProcBSDInfo>(pid) {
+        return Ok(Some(BestEffortInfo::from_bsd(InfoLevel::Bsd, info, None)));
+    }
+    if let Ok(Some(short_info)) = proc_pidinfo::<ProcBSDShortInfo>(pid) {
+        return Ok(Some(BestEffortInfo {
+            level: InfoLevel::Short,
+            short_info,
+            bsd_info: None,
+            task_info: None,
+            start_time: None,
+        }));
+    }
+    Ok(kinfo_proc(pid)?.map(|info| BestEffortInfo::from_kinfo(&info)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::darwin::{getpid, ProcStatus};
+    use crate::testutil::TestChild;
+
+    #[test]
+    fn test_best_effort_self() {
+        let info = proc_pidinfo_best_effort(getpid()).unwrap().unwrap();
+        assert_eq!(info.level, InfoLevel::TaskAll);
+        assert!(info.task_info.is_some());
+        assert_eq!(
+            info.short_info.comm(),
+            getpid().bsd_short_info().unwrap().unwrap().comm()
+        );
+
+        let launchd = proc_pidinfo_best_effort(Pid(1)).unwrap().unwrap();
+        assert!(launchd.level >= InfoLevel::Short);
+        assert_eq!(launchd.short_info.pbsi_uid, 0);
+        assert!(proc_pidinfo_best_effort(Pid(99_999_999)).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_best_effort_zombie() {
+        let mut child = TestChild::sleep();
+        let pid = child.pid();
+        let live = proc_pidinfo_best_effort(pid).unwrap().unwrap();
+        // SAFETY: Sends a signal to our own child, without reaping it.
+        unsafe { libc::kill(pid.0 as _, libc::SIGKILL) };
+        std::thread::sleep(Duration::from_millis(100));
+        let zombie = proc_pidinfo_best_effort(pid).unwrap().unwrap();
+        child.wait().unwrap();
+
+        assert_eq!(zombie.short_info.status(), Ok(ProcStatus::SZOMB));
+        assert_eq!(zombie.short_info.pbsi_ppid, getpid());
+        assert_eq!(zombie.short_info.comm(), Ok("sleep"));
+        if zombie.level == InfoLevel::Sysctl {
+            let (zombie, live) = (zombie.start_time.unwrap(), live.start_time.unwrap());
+            assert!(zombie.duration_since(live).unwrap_or_default() < Duration::from_secs(1));
+        }
+    }
+}
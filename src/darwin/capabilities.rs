@@ -0,0 +1,181 @@
+use std::ffi::CStr;
+use std::sync::OnceLock;
+
+use libc::{c_int, c_void};
+
+use super::sysctl::kinfo_proc;
+use super::{getpid, Pid, ScanPolicy, Scanner};
+
+/// The newest `rusage_info` version to probe for.
+const RUSAGE_INFO_MAX_VERSION: c_int = 6;
+
+/// Whether a flavor could be queried, as reported by [`capabilities`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlavorSupport {
+    /// The kernel's name for the flavor, eg: `PROC_PIDTASKINFO`.
+    pub name: &'static str,
+    /// The flavor works for the current process.
+    pub own_process: bool,
+    /// The flavor works for a process owned by another user (`launchd`, pid 1). Always false
+    /// for file descriptor flavors, which aren't probed against other processes.
+    pub other_processes: bool,
+}
+
+/// A description of what the current process can query, as probed by [`capabilities`].
+#[derive(Debug, Clone)]
+pub struct CapabilityReport {
+    /// The OS product version (eg: `14.4.1`), if it could be read.
+    pub os_version: Option<String>,
+    /// The effective user is root.
+    pub is_root: bool,
+    /// The process is running inside the App Sandbox.
+    pub is_sandboxed: bool,
+    /// Support for each [`proc_pidinfo`], [`super::proc_pidinfo_list`] and
+    /// [`proc_pidfdinfo`] flavor known to this crate.
+    pub flavors: Vec<FlavorSupport>,
+    /// The `rusage_info` versions that `proc_pid_rusage` accepts for the current process.
+    pub rusage_versions: Vec<u32>,
+    /// [`super::sysctl::kinfo_proc`] works for `launchd`, so every process can at least be
+    /// listed with its status and parent, eg: by [`super::proc_pidinfo_best_effort`].
+    pub kinfo_proc: bool,
+}
+
+impl CapabilityReport {
+    /// Look up a flavor by its kernel name, eg: `PROC_PIDTASKINFO`.
+    pub fn flavor(&self, name: &str) -> Option<&FlavorSupport> {
+        self.flavors.iter().find(|flavor| flavor.name == name)
+    }
+
+    /// Returns true if the flavor can be queried for processes owned by other users.
+    pub fn can_inspect_other_processes(&self, name: &str) -> bool {
+        self.flavor(name)
+            .is_some_and(|flavor| flavor.other_processes)
+    }
+
+    /// Returns true if the current process's fileports can be listed. This is false if the
+    /// listing failed, eg: with `EPERM`, not if the process just has no fileports.
+    pub fn can_list_fileports(&self) -> bool {
+        self.flavor("PROC_PIDLISTFILEPORTS")
+            .is_some_and(|flavor| flavor.own_process)
+    }
+
+    /// The newest `rusage_info` version available, if any.
+    pub fn max_rusage_version(&self) -> Option<u32> {
+        self.rusage_versions.iter().copied().max()
+    }
+}
+
+/// Probe which queries work in the current context, once, and return the same report on
+/// every later call.
+///
+/// Each flavor known to this crate is tried against the current process and against
+/// `launchd` (pid 1), which is owned by root, with [`Scanner::smoke_test`]. A flavor that fails
+/// is reported as unsupported, eg: one that needs more privileges, or any query about `launchd`
+/// on an embedded device, where the sandbox restricts queries to the current process. Callers
+/// can plan their collection around the result rather than discovering failures for each
+/// process. See [`probe_capabilities`] to probe again, eg: after changing user.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let report = capabilities();
+/// if !report.can_inspect_other_processes("PROC_PIDTASKINFO") {
+///     println!("Task info is only available for our own processes");
+/// }
+/// assert!(std::ptr::eq(report, capabilities()));
+/// ```
+pub fn capabilities() -> &'static CapabilityReport {
+    static CAPABILITIES: OnceLock<CapabilityReport> = OnceLock::new();
+    CAPABILITIES.get_or_init(probe_capabilities)
+}
+
+/// Probe which queries work in the current context, without caching the result. This makes a
+/// handful of system calls. See [`capabilities`].
+pub fn probe_capabilities() -> CapabilityReport {
+    CapabilityReport {
+        os_version: sysctl_string(c"kern.osproductversion"),
+        // SAFETY: geteuid never fails.
+        is_root: unsafe { libc::geteuid() } == 0,
+        is_sandboxed: std::env::var_os("APP_SANDBOX_CONTAINER_ID").is_some(),
+        flavors: Scanner::new(ScanPolicy::smoke_test()).smoke_test(),
+        rusage_versions: (0..=RUSAGE_INFO_MAX_VERSION)
+            .filter(|&version| probe_rusage(getpid(), version))
+            .map(|version| version as u32)
+            .collect(),
+        kinfo_proc: matches!(kinfo_proc(Pid(1)), Ok(Some(_))),
+    }
+}
+
+fn probe_rusage(pid: Pid, version: c_int) -> bool {
+    // Comfortably larger than any rusage_info version.
+    let mut buffer = [0_u64; 128];
+    // SAFETY: The buffer is larger than every rusage_info struct.
+    let res =
+        unsafe { super::ffi::proc_pid_rusage(pid.0 as _, version, buffer.as_mut_ptr() as *mut _) };
+    res == 0
+}
+
+fn sysctl_string(name: &CStr) -> Option<String> {
+    let mut buffer = [0_u8; 256];
+    let mut len = buffer.len();
+    // SAFETY: The buffer and its length are valid.
+    let res = unsafe {
+        libc::sysctlbyname(
+            name.as_ptr(),
+            buffer.as_mut_ptr() as *mut c_void,
+            &mut len,
+            std::ptr::null_mut(),
+            0,
+        )
+    };
+    if res != 0 {
+        return None;
+    }
+    let value = CStr::from_bytes_until_nul(&buffer[..len]).ok()?;
+    Some(value.to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::darwin::{proc_pidinfo_list, ProcFilePortInfo};
+
+    #[test]
+    fn test_capabilities() {
+        let report = capabilities();
+        println!("{:#?}", report);
+        assert!(report.os_version.is_some());
+        assert!(report.flavor("PROC_PIDT_SHORTBSDINFO").unwrap().own_process);
+        assert!(
+            report
+                .flavor("PROC_PIDFDVNODEPATHINFO")
+                .unwrap()
+                .own_process
+        );
+        assert!(report.rusage_versions.contains(&0));
+        assert!(report.kinfo_proc);
+        assert!(std::ptr::eq(report, capabilities()));
+        assert_eq!(probe_capabilities().is_root, report.is_root);
+        if report.is_root {
+            assert!(report.can_list_fileports());
+            assert!(report.can_inspect_other_processes("PROC_PIDTASKINFO"));
+        }
+    }
+
+    #[test]
+    fn test_capabilities_fileports() {
+        let report = capabilities();
+        assert_eq!(
+            report.can_list_fileports(),
+            proc_pidinfo_list::<ProcFilePortInfo>(getpid()).is_ok()
+        );
+        // Listing launchd's fileports is denied without root, and must be reported as such.
+        assert_eq!(
+            report.can_inspect_other_processes("PROC_PIDLISTFILEPORTS"),
+            proc_pidinfo_list::<ProcFilePortInfo>(Pid(1)).is_ok()
+        );
+        if !report.is_root {
+            assert!(!report.can_inspect_other_processes("PROC_PIDLISTFILEPORTS"));
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use super::{
+    proc_pidfdinfo_raw, HasFdFlavor, Pid, ProcFDInfo, ProcFDType, ProcFileInfo,
+    ProcPidFdInfoFlavor, ValueError,
+};
+
+/// The kind of nexus a Skywalk channel is connected to, from `chi_type`.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum ChannelType {
+    /// A user pipe or kernel pipe nexus.
+    PROC_CHANNEL_TYPE_USER_PIPE_KERNEL_PIPE = 0,
+    /// A network interface nexus.
+    PROC_CHANNEL_TYPE_NET_IF = 1,
+    /// A flow switch nexus, used by the user-space networking stack.
+    PROC_CHANNEL_TYPE_FLOW_SWITCH = 2,
+}
+
+impl ChannelType {
+    fn from_raw(value: u32) -> Result<Self, ValueError> {
+        Ok(match value {
+            0 => Self::PROC_CHANNEL_TYPE_USER_PIPE_KERNEL_PIPE,
+            1 => Self::PROC_CHANNEL_TYPE_NET_IF,
+            2 => Self::PROC_CHANNEL_TYPE_FLOW_SWITCH,
+            _ => return Err(ValueError::UnexpectedEnumValue),
+        })
+    }
+}
+
+/// Information about a Skywalk channel (`proc_channel_info`).
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct ChannelInfo {
+    /// The UUID of the nexus instance the channel is open on.
+    pub chi_instance: [u8; 16],
+    /// The port of the nexus the channel is bound to.
+    pub chi_port: u32,
+    /// See [`ChannelInfo::channel_type`].
+    pub chi_type: u32,
+    /// The `PROC_CHANNEL_FLAGS_*` bits.
+    pub chi_flags: u32,
+    pub rfu_1: u32,
+}
+
+impl ChannelInfo {
+    pub fn channel_type(&self) -> Result<ChannelType, ValueError> {
+        ChannelType::from_raw(self.chi_type)
+    }
+}
+
+/// Information about [`ProcFDType::CHANNEL`] file descriptors, which are Skywalk channels to
+/// a nexus, eg: opened by the user-space networking stack.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct ChannelFdInfo {
+    pub pfi: ProcFileInfo,
+    pub channelinfo: ChannelInfo,
+}
+
+impl HasFdFlavor for ChannelFdInfo {
+    const FLAVOR: ProcPidFdInfoFlavor = ProcPidFdInfoFlavor::PROC_PIDFDCHANNELINFO;
+}
+
+impl ProcFDType {
+    /// The `proc_pidfdinfo` flavor that describes descriptors of this type, for use with
+    /// [`proc_pidfdinfo_raw`]. Returns `None` for types the kernel has no flavor for:
+    /// [`ProcFDType::NETPOLICY`], [`ProcFDType::NEXUS`], [`ProcFDType::FSEVENTS`],
+    /// [`ProcFDType::ATALK`] and unknown types, about which only the [`ProcFDInfo`] is
+    /// available.
+    pub fn detail_flavor(self) -> Option<i32> {
+        let flavor = match self {
+            ProcFDType::VNODE => ProcPidFdInfoFlavor::PROC_PIDFDVNODEPATHINFO,
+            ProcFDType::SOCKET => ProcPidFdInfoFlavor::PROC_PIDFDSOCKETINFO,
+            ProcFDType::PSHM => ProcPidFdInfoFlavor::PROC_PIDFDPSHMINFO,
+            ProcFDType::PSEM => ProcPidFdInfoFlavor::PROC_PIDFDPSEMINFO,
+            ProcFDType::KQUEUE => ProcPidFdInfoFlavor::PROC_PIDFDKQUEUEINFO,
+            ProcFDType::PIPE => ProcPidFdInfoFlavor::PROC_PIDFDPIPEINFO,
+            ProcFDType::CHANNEL => ProcPidFdInfoFlavor::PROC_PIDFDCHANNELINFO,
+            _ => return None,
+        };
+        Some(flavor as i32)
+    }
+}
+
+/// The largest reply of any `proc_pidfdinfo` flavor, rounded up.
+const MAX_DETAIL_SIZE: usize = 4096;
+
+/// Read the details of any file descriptor as raw bytes, using the flavor for its type. See
+/// [`ProcFDType::detail_flavor`].
+///
+/// This covers types this crate has no struct for yet. Returns `None` for types without a
+/// flavor, such as [`ProcFDType::NETPOLICY`] and [`ProcFDType::NEXUS`].
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// # let pid = getpid();
+/// for fd in proc_pidinfo_list::<ProcFDInfo>(pid).unwrap() {
+///     match proc_pidfddetail_raw(pid, &fd) {
+///         Ok(Some(bytes)) => println!("{:?}: {} bytes", fd.fd_type(), bytes.len()),
+///         Ok(None) => println!("{:?}: no details", fd.fd_type()),
+///         Err(err) => println!("{:?}: {err}", fd.fd_type()),
+///     }
+/// }
+/// ```
+pub fn proc_pidfddetail_raw(pid: Pid, fd: &ProcFDInfo) -> Result<Option<Vec<u8>>, std::io::Error> {
+    let Some(flavor) = fd.fd_type().detail_flavor() else {
+        return Ok(None);
+    };
+    let mut buf = vec![0; MAX_DETAIL_SIZE];
+    let len = proc_pidfdinfo_raw(pid, fd.proc_fd, flavor, &mut buf)?;
+    buf.truncate(len);
+    Ok(Some(buf))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::fd::AsRawFd;
+
+    use super::*;
+    use crate::darwin::{getpid, proc_pidinfo_list, PipeFdInfo, VnodeFdInfoWithPath};
+
+    #[test]
+    fn test_channel_fd_info_size() {
+        assert_eq!(std::mem::size_of::<ChannelFdInfo>(), 56);
+    }
+
+    #[test]
+    fn test_proc_pidfddetail_raw() {
+        let file = std::fs::File::open("/dev/null").unwrap();
+        let (read, _write) = std::io::pipe().unwrap();
+        let fds = proc_pidinfo_list::<ProcFDInfo>(getpid()).unwrap();
+        let find = |raw| fds.iter().find(|fd| fd.proc_fd.0 == raw).unwrap();
+
+        let detail = proc_pidfddetail_raw(getpid(), find(file.as_raw_fd()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(detail.len(), std::mem::size_of::<VnodeFdInfoWithPath>());
+        let detail = proc_pidfddetail_raw(getpid(), find(read.as_raw_fd()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(detail.len(), std::mem::size_of::<PipeFdInfo>());
+
+        assert_eq!(ProcFDType::NETPOLICY.detail_flavor(), None);
+        assert_eq!(ProcFDType::NEXUS.detail_flavor(), None);
+        assert_eq!(ProcFDType::CHANNEL.detail_flavor(), Some(10));
+    }
+}
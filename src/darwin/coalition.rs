@@ -0,0 +1,212 @@
+use std::collections::BTreeMap;
+
+use libc::{c_int, c_void};
+
+use super::{last_os_error, proc_pidinfo, HasFlavor, Pid, ProcPidInfoFlavor, ValueError};
+
+/// The number of coalition types (`COALITION_NUM_TYPES`).
+const COALITION_NUM_TYPES: usize = 2;
+
+/// The kind of a coalition. Every process belongs to one coalition of each type.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u32)]
+pub enum CoalitionType {
+    /// Groups an app with its helpers for resource (energy, CPU, I/O) accounting.
+    COALITION_TYPE_RESOURCE = 0,
+    /// Groups processes that jetsam treats as a unit under memory pressure.
+    COALITION_TYPE_JETSAM = 1,
+}
+
+/// The role of a process within a coalition (`COALITION_TASKROLE_*`).
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(i32)]
+pub enum CoalitionRole {
+    /// Not in a coalition of this type.
+    COALITION_TASKROLE_NONE = -1,
+    /// No particular role.
+    COALITION_TASKROLE_UNDEF = 0,
+    /// The process the coalition is attributed to, eg: the app.
+    COALITION_TASKROLE_LEADER = 1,
+    /// An XPC service launched on behalf of the leader.
+    COALITION_TASKROLE_XPC = 2,
+    /// An extension launched on behalf of the leader.
+    COALITION_TASKROLE_EXT = 3,
+}
+
+impl CoalitionRole {
+    fn from_raw(role: c_int) -> Result<Self, ValueError> {
+        match role {
+            -1 => Ok(CoalitionRole::COALITION_TASKROLE_NONE),
+            0 => Ok(CoalitionRole::COALITION_TASKROLE_UNDEF),
+            1 => Ok(CoalitionRole::COALITION_TASKROLE_LEADER),
+            2 => Ok(CoalitionRole::COALITION_TASKROLE_XPC),
+            3 => Ok(CoalitionRole::COALITION_TASKROLE_EXT),
+            _ => Err(ValueError::UnexpectedEnumValue),
+        }
+    }
+}
+
+/// The coalitions a process belongs to. Usable with [`super::proc_pidinfo`].
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let info = proc_pidinfo_self::<ProcCoalitionInfo>().unwrap().unwrap();
+/// println!("{}", info.id(CoalitionType::COALITION_TYPE_RESOURCE));
+/// ```
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct ProcCoalitionInfo {
+    /// The coalition IDs, indexed by [`CoalitionType`].
+    pub coalition_id: [u64; COALITION_NUM_TYPES],
+    pub reserved1: u64,
+    pub reserved2: u64,
+    pub reserved3: u64,
+}
+
+impl ProcCoalitionInfo {
+    /// The ID of the coalition of the given type. 0 if the process isn't in one.
+    pub fn id(&self, kind: CoalitionType) -> u64 {
+        self.coalition_id[kind as usize]
+    }
+}
+
+impl HasFlavor for ProcCoalitionInfo {
+    const FLAVOR: ProcPidInfoFlavor = ProcPidInfoFlavor::PROC_PIDCOALITIONINFO;
+    const MIN_SIZE: usize = std::mem::offset_of!(Self, reserved1);
+}
+
+/// Get the roles of a process in its coalitions, indexed by [`CoalitionType`]. The role is
+/// [`CoalitionRole::COALITION_TASKROLE_NONE`] for a type the process has no coalition of.
+///
+/// Roles aren't part of [`ProcCoalitionInfo`], so these are read from the
+/// `kern.coalition_roles` sysctl instead.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let roles = coalition_roles(getpid()).unwrap();
+/// println!("{:?}", roles[CoalitionType::COALITION_TYPE_RESOURCE as usize]);
+/// ```
+pub fn coalition_roles(pid: Pid) -> Result<[CoalitionRole; COALITION_NUM_TYPES], std::io::Error> {
+    let mut pid = pid.0 as c_int;
+    let mut roles = [0 as c_int; COALITION_NUM_TYPES];
+    let mut len = std::mem::size_of_val(&roles);
+    // SAFETY: The output is an array of c_int with its size, and the input is a single pid.
+    let res = unsafe {
+        libc::sysctlbyname(
+            c"kern.coalition_roles".as_ptr(),
+            roles.as_mut_ptr() as *mut c_void,
+            &mut len,
+            &mut pid as *mut c_int as *mut c_void,
+            std::mem::size_of::<c_int>(),
+        )
+    };
+    if res != 0 {
+        return Err(last_os_error());
+    }
+    let decode = |role| {
+        CoalitionRole::from_raw(role)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{err:?}")))
+    };
+    Ok([decode(roles[0])?, decode(roles[1])?])
+}
+
+/// The members of one coalition, as grouped by [`group_by_coalition`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoalitionGroup {
+    pub id: u64,
+    /// The member with the [`CoalitionRole::COALITION_TASKROLE_LEADER`] role, if it was among
+    /// the processes grouped and its role could be read.
+    pub leader: Option<Pid>,
+    /// The members, in the order they were given.
+    pub members: Vec<Pid>,
+}
+
+/// Group processes by their coalition of the given type, eg: to attribute the energy use of
+/// helpers to their app.
+///
+/// Processes that have exited, can't be queried, or aren't in a coalition of that type are
+/// skipped. Groups are ordered by coalition ID.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let pids = proc_listallpids().unwrap();
+/// for group in group_by_coalition(pids, CoalitionType::COALITION_TYPE_RESOURCE) {
+///     println!("{} ({:?}): {} processes", group.id, group.leader, group.members.len());
+/// }
+/// ```
+pub fn group_by_coalition(
+    pids: impl IntoIterator<Item = Pid>,
+    kind: CoalitionType,
+) -> Vec<CoalitionGroup> {
+    let mut groups = BTreeMap::<u64, CoalitionGroup>::new();
+    for pid in pids {
+        let Ok(Some(info)) = proc_pidinfo::<ProcCoalitionInfo>(pid) else {
+            continue;
+        };
+        let id = info.id(kind);
+        if id == 0 {
+            continue;
+        }
+        let group = groups.entry(id).or_insert_with(|| CoalitionGroup {
+            id,
+            leader: None,
+            members: vec![],
+        });
+        group.members.push(pid);
+        if group.leader.is_none()
+            && coalition_roles(pid)
+                .is_ok_and(|roles| roles[kind as usize] == CoalitionRole::COALITION_TASKROLE_LEADER)
+        {
+            group.leader = Some(pid);
+        }
+    }
+    groups.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::darwin::getpid;
+    use crate::testutil::TestChild;
+
+    #[test]
+    fn test_coalition_info_self() {
+        assert_eq!(std::mem::size_of::<ProcCoalitionInfo>(), 40);
+        let info = proc_pidinfo::<ProcCoalitionInfo>(getpid())
+            .unwrap()
+            .unwrap();
+        assert_ne!(info.id(CoalitionType::COALITION_TYPE_RESOURCE), 0);
+        println!("{:?} {:?}", info, coalition_roles(getpid()));
+    }
+
+    #[test]
+    fn test_coalition_role_from_raw() {
+        assert_eq!(
+            CoalitionRole::from_raw(-1),
+            Ok(CoalitionRole::COALITION_TASKROLE_NONE)
+        );
+        assert_eq!(
+            CoalitionRole::from_raw(1),
+            Ok(CoalitionRole::COALITION_TASKROLE_LEADER)
+        );
+        assert!(CoalitionRole::from_raw(4).is_err());
+    }
+
+    #[test]
+    fn test_group_by_coalition() {
+        // Children inherit the coalitions of their parent.
+        let child = TestChild::sleep();
+        let child_pid = child.pid();
+        let groups = group_by_coalition(
+            [getpid(), child_pid],
+            CoalitionType::COALITION_TYPE_RESOURCE,
+        );
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].members, [getpid(), child_pid]);
+    }
+}
@@ -0,0 +1,260 @@
+use libc::{c_uint, c_void, size_t};
+
+use super::{last_os_error, AuditToken, Pid};
+
+mod ffi {
+    use libc::{c_int, c_uint, c_void, size_t};
+
+    use super::AuditToken;
+
+    extern "C" {
+        pub fn csops(
+            pid: libc::pid_t,
+            ops: c_uint,
+            useraddr: *mut c_void,
+            usersize: size_t,
+        ) -> c_int;
+        pub fn csops_audittoken(
+            pid: libc::pid_t,
+            ops: c_uint,
+            useraddr: *mut c_void,
+            usersize: size_t,
+            token: *const AuditToken,
+        ) -> c_int;
+    }
+}
+
+const CS_OPS_STATUS: c_uint = 0;
+const CS_OPS_CDHASH: c_uint = 5;
+const CS_OPS_IDENTITY: c_uint = 11;
+const CS_OPS_TEAMID: c_uint = 14;
+
+/// The length of a code directory hash.
+pub const CS_CDHASH_LEN: usize = 20;
+
+/// The code-signing status flags of a running process (`CS_*` in `<kern/cs_blobs.h>`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CodeSignFlags(u32);
+
+impl CodeSignFlags {
+    /// The signature is valid. Cleared if the process loads invalid pages.
+    pub const VALID: Self = Self(0x0000_0001);
+    /// Ad-hoc signed, with no signing identity.
+    pub const ADHOC: Self = Self(0x0000_0002);
+    /// Has the `get-task-allow` entitlement, allowing debuggers to attach.
+    pub const GET_TASK_ALLOW: Self = Self(0x0000_0004);
+    /// Has installer entitlements.
+    pub const INSTALLER: Self = Self(0x0000_0008);
+    /// Library validation is enforced.
+    pub const FORCED_LV: Self = Self(0x0000_0010);
+    /// Invalid pages are allowed.
+    pub const INVALID_ALLOWED: Self = Self(0x0000_0020);
+    /// Invalid pages are refused.
+    pub const HARD: Self = Self(0x0000_0100);
+    /// The process is killed if it becomes invalid.
+    pub const KILL: Self = Self(0x0000_0200);
+    /// The process is restricted (no `DYLD_*` environment, no debugging).
+    pub const RESTRICT: Self = Self(0x0000_0800);
+    /// Code signing is enforced.
+    pub const ENFORCEMENT: Self = Self(0x0000_1000);
+    /// Library validation is required.
+    pub const REQUIRE_LV: Self = Self(0x0000_2000);
+    /// The entitlements were validated.
+    pub const ENTITLEMENTS_VALIDATED: Self = Self(0x0000_4000);
+    /// Hardened runtime.
+    pub const RUNTIME: Self = Self(0x0001_0000);
+    /// Automatically signed by the linker.
+    pub const LINKER_SIGNED: Self = Self(0x0002_0000);
+    /// The process has been killed for an invalid signature.
+    pub const KILLED: Self = Self(0x0100_0000);
+    /// A platform binary, signed by Apple.
+    pub const PLATFORM_BINARY: Self = Self(0x0400_0000);
+    /// The process is, or was, being debugged.
+    pub const DEBUGGED: Self = Self(0x1000_0000);
+    /// The process has a signature, even if invalid.
+    pub const SIGNED: Self = Self(0x2000_0000);
+
+    /// Wrap raw flags from the kernel.
+    pub const fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+
+    /// The raw flags.
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Returns true if all flags in `other` are also set.
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl std::ops::BitOr for CodeSignFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// The code-signing information of a running process. See [`codesign_info`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeSignInfo {
+    pub flags: CodeSignFlags,
+    /// The signing identifier, eg: `com.apple.Safari`. `None` for unsigned processes.
+    pub identifier: Option<String>,
+    /// The team identifier. `None` for unsigned, ad-hoc and Apple platform processes.
+    pub team_id: Option<String>,
+    /// The hash of the code directory. `None` for unsigned processes.
+    pub cdhash: Option<[u8; CS_CDHASH_LEN]>,
+}
+
+/// Get the code-signing status flags of a process.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let flags = codesign_status(Pid(1)).unwrap();
+/// assert!(flags.contains(CodeSignFlags::PLATFORM_BINARY));
+/// ```
+pub fn codesign_status(pid: Pid) -> Result<CodeSignFlags, std::io::Error> {
+    Csops::Pid(pid).status()
+}
+
+/// Get the code-signing flags, identifiers and CDHash of a process.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let info = codesign_info(Pid(1)).unwrap();
+/// assert_eq!(info.identifier.as_deref(), Some("com.apple.xpc.launchd"));
+/// ```
+pub fn codesign_info(pid: Pid) -> Result<CodeSignInfo, std::io::Error> {
+    Csops::Pid(pid).info()
+}
+
+/// Get the code-signing information of the process identified by an audit token.
+///
+/// Unlike [`codesign_info`], this fails with `ESRCH` if the pid has been reused by another
+/// process since the token was issued.
+pub fn codesign_info_audittoken(token: &AuditToken) -> Result<CodeSignInfo, std::io::Error> {
+    Csops::AuditToken(token).info()
+}
+
+/// The target of a `csops` call.
+#[derive(Clone, Copy)]
+enum Csops<'a> {
+    Pid(Pid),
+    AuditToken(&'a AuditToken),
+}
+
+impl Csops<'_> {
+    fn call(self, ops: c_uint, buffer: &mut [u8]) -> Result<(), std::io::Error> {
+        let (addr, size) = (buffer.as_mut_ptr() as *mut c_void, buffer.len() as size_t);
+        // SAFETY: The buffer and its size are valid, and the token outlives the call.
+        let res = unsafe {
+            match self {
+                Csops::Pid(pid) => ffi::csops(pid.0 as _, ops, addr, size),
+                Csops::AuditToken(token) => {
+                    ffi::csops_audittoken(token.pid().0 as _, ops, addr, size, token)
+                }
+            }
+        };
+        if res != 0 {
+            return Err(last_os_error());
+        }
+        Ok(())
+    }
+
+    fn status(self) -> Result<CodeSignFlags, std::io::Error> {
+        let mut flags = [0_u8; 4];
+        self.call(CS_OPS_STATUS, &mut flags)?;
+        Ok(CodeSignFlags(u32::from_ne_bytes(flags)))
+    }
+
+    fn cdhash(self) -> Result<Option<[u8; CS_CDHASH_LEN]>, std::io::Error> {
+        let mut cdhash = [0_u8; CS_CDHASH_LEN];
+        missing_as_none(self.call(CS_OPS_CDHASH, &mut cdhash).map(|_| cdhash))
+    }
+
+    /// Fetch a string wrapped in a blob: a big-endian magic and length, then the string.
+    fn blob_string(self, ops: c_uint) -> Result<Option<String>, std::io::Error> {
+        const HEADER: usize = 8;
+        let mut buffer = vec![0_u8; HEADER + 256];
+        loop {
+            match self.call(ops, &mut buffer) {
+                Ok(()) => break,
+                // The kernel fills in the header with the required length.
+                Err(err) if err.raw_os_error() == Some(libc::ERANGE) => {
+                    let len = u32::from_be_bytes(buffer[4..8].try_into().unwrap()) as usize;
+                    if len <= buffer.len() {
+                        return Err(err);
+                    }
+                    buffer.resize(len, 0);
+                }
+                Err(err) => return missing_as_none(Err(err)),
+            }
+        }
+        let len = (u32::from_be_bytes(buffer[4..8].try_into().unwrap()) as usize)
+            .clamp(HEADER, buffer.len());
+        let data = &buffer[HEADER..len];
+        let data = &data[..data.iter().position(|&b| b == 0).unwrap_or(data.len())];
+        if data.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(String::from_utf8_lossy(data).into_owned()))
+    }
+
+    fn info(self) -> Result<CodeSignInfo, std::io::Error> {
+        Ok(CodeSignInfo {
+            flags: self.status()?,
+            identifier: self.blob_string(CS_OPS_IDENTITY)?,
+            team_id: self.blob_string(CS_OPS_TEAMID)?,
+            cdhash: self.cdhash()?,
+        })
+    }
+}
+
+/// The kernel reports a missing signature or identifier as `EINVAL` or `ENOENT`.
+fn missing_as_none<T>(result: Result<T, std::io::Error>) -> Result<Option<T>, std::io::Error> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(err) if matches!(err.raw_os_error(), Some(libc::EINVAL | libc::ENOENT)) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::darwin::getpid;
+
+    #[test]
+    fn test_codesign_self() {
+        let info = codesign_info(getpid()).unwrap();
+        println!("{:?}", info);
+        if info.flags.contains(CodeSignFlags::ADHOC) {
+            assert!(info.team_id.is_none());
+        }
+    }
+
+    #[test]
+    fn test_codesign_launchd() {
+        let info = codesign_info(Pid(1)).unwrap();
+        assert!(info
+            .flags
+            .contains(CodeSignFlags::VALID | CodeSignFlags::PLATFORM_BINARY));
+        assert_eq!(info.identifier.as_deref(), Some("com.apple.xpc.launchd"));
+        assert!(info.cdhash.is_some());
+    }
+
+    #[test]
+    fn test_codesign_stale_audit_token() {
+        let mut token = AuditToken { val: [0; 8] };
+        token.val[5] = getpid().0;
+        token.val[7] = u32::MAX;
+        let err = codesign_info_audittoken(&token).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::ESRCH));
+    }
+}
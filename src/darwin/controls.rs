@@ -0,0 +1,590 @@
+//! Calls that change the behaviour of processes, rather than inspecting them.
+
+use std::time::Duration;
+
+use libc::{c_int, c_void};
+
+use super::{last_os_error, Pid};
+
+mod ffi {
+    use libc::c_int;
+
+    extern "C" {
+        pub fn proc_setpcontrol(control: c_int) -> c_int;
+        pub fn proc_terminate(pid: libc::pid_t, sig: *mut c_int) -> c_int;
+        pub fn proc_track_dirty(pid: libc::pid_t, flags: u32) -> c_int;
+        pub fn proc_set_dirty(pid: libc::pid_t, dirty: bool) -> c_int;
+        pub fn proc_get_dirty(pid: libc::pid_t, flags: *mut u32) -> c_int;
+        pub fn proc_clear_dirty(pid: libc::pid_t, flags: u32) -> c_int;
+        pub fn proc_get_cpumon_params(
+            pid: libc::pid_t,
+            percentage: *mut c_int,
+            interval: *mut c_int,
+        ) -> c_int;
+        pub fn proc_set_cpumon_params(
+            pid: libc::pid_t,
+            percentage: c_int,
+            interval: c_int,
+        ) -> c_int;
+        pub fn proc_disable_cpumon(pid: libc::pid_t) -> c_int;
+        pub fn proc_get_wakemon_params(
+            pid: libc::pid_t,
+            rate_hz: *mut c_int,
+            flags: *mut c_int,
+        ) -> c_int;
+        pub fn proc_set_wakemon_params(pid: libc::pid_t, rate_hz: c_int, flags: c_int) -> c_int;
+        pub fn proc_disable_wakemon(pid: libc::pid_t) -> c_int;
+        pub fn proc_set_no_smt() -> c_int;
+        pub fn proc_setthread_no_smt() -> c_int;
+        pub fn proc_set_csm(flags: u32) -> c_int;
+        pub fn proc_setthread_csm(flags: u32) -> c_int;
+    }
+}
+
+/// What the kernel does to the current process when the system runs low on swap. See
+/// [`proc_setpcontrol`].
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(i32)]
+pub enum ProcPcontrol {
+    /// No action.
+    NONE = 0,
+    /// Throttle the process.
+    THROTTLEMEM = 1,
+    /// Suspend the process.
+    SUSPEND = 2,
+    /// Terminate the process.
+    TERMINATE = 3,
+}
+
+/// Set the action the kernel takes against the current process under memory pressure.
+///
+/// ```
+/// use proc_pidinfo::controls::*;
+///
+/// proc_setpcontrol(ProcPcontrol::NONE).unwrap();
+/// ```
+pub fn proc_setpcontrol(control: ProcPcontrol) -> Result<(), std::io::Error> {
+    // SAFETY: No memory is passed, and the control is always in range.
+    let res = unsafe { ffi::proc_setpcontrol(control as c_int) };
+    // Unlike most libproc calls, this returns the error directly.
+    errno_result(res)
+}
+
+/// The signal that [`proc_terminate`] sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TerminationSignal {
+    /// `SIGTERM`, which the process may handle.
+    Term,
+    /// `SIGKILL`, sent to processes that opted into idle exit and are clean.
+    Kill,
+    /// Any other signal.
+    Other(c_int),
+}
+
+impl TerminationSignal {
+    fn from_raw(sig: c_int) -> Self {
+        match sig {
+            libc::SIGTERM => TerminationSignal::Term,
+            libc::SIGKILL => TerminationSignal::Kill,
+            sig => TerminationSignal::Other(sig),
+        }
+    }
+
+    /// The raw signal number.
+    pub fn signal(self) -> c_int {
+        match self {
+            TerminationSignal::Term => libc::SIGTERM,
+            TerminationSignal::Kill => libc::SIGKILL,
+            TerminationSignal::Other(sig) => sig,
+        }
+    }
+}
+
+/// Ask the kernel to terminate a process, in the same way `launchd` does.
+///
+/// The kernel picks the signal: processes that use dirty tracking and are currently clean are
+/// sent `SIGKILL`, and everything else is sent `SIGTERM`. Fails with `ESRCH` if the process
+/// doesn't exist, or `EPERM` if the caller isn't allowed to signal it.
+///
+/// ```
+/// use proc_pidinfo::*;
+/// use proc_pidinfo::controls::*;
+///
+/// let mut child = std::process::Command::new("/bin/sleep").arg("10").spawn().unwrap();
+/// let signal = proc_terminate(Pid(child.id())).unwrap();
+/// assert_eq!(signal, TerminationSignal::Term);
+/// child.wait().unwrap();
+/// ```
+pub fn proc_terminate(pid: Pid) -> Result<TerminationSignal, std::io::Error> {
+    let mut sig = 0;
+    // SAFETY: The signal pointer is valid for a c_int.
+    let res = unsafe { ffi::proc_terminate(pid.0 as _, &mut sig) };
+    // Like proc_setpcontrol, this returns the error directly.
+    errno_result(res)?;
+    Ok(TerminationSignal::from_raw(sig))
+}
+
+/// Options for [`proc_track_dirty`] and [`proc_clear_dirty`] (`PROC_DIRTY_*` in `<libproc.h>`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DirtyTrackingFlags(u32);
+
+impl DirtyTrackingFlags {
+    /// Opt into dirty tracking.
+    pub const TRACK: Self = Self(0x01);
+    /// Allow the system to kill the process with `SIGKILL` while it's clean.
+    pub const ALLOW_IDLE_EXIT: Self = Self(0x02);
+    /// Stay dirty for a short time after launch.
+    pub const DEFER: Self = Self(0x04);
+    /// The process is still launching. Cleared with [`proc_clear_dirty`].
+    pub const LAUNCH_IN_PROGRESS: Self = Self(0x08);
+    /// Stay dirty for a short time after every transition to clean.
+    pub const DEFER_ALWAYS: Self = Self(0x10);
+
+    /// The raw flags.
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Returns true if all flags in `other` are also set.
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl std::ops::BitOr for DirtyTrackingFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// The dirty-tracking state of a process, as returned by [`proc_get_dirty`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DirtyStatus(u32);
+
+impl DirtyStatus {
+    /// The process has opted into dirty tracking.
+    pub const TRACKED: Self = Self(0x01);
+    /// The process may be killed while clean.
+    pub const ALLOWS_IDLE_EXIT: Self = Self(0x02);
+    /// The process is dirty, so it won't be killed for idle exit.
+    pub const IS_DIRTY: Self = Self(0x04);
+    /// The process is still launching.
+    pub const LAUNCH_IS_IN_PROGRESS: Self = Self(0x08);
+
+    /// The raw flags.
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Returns true if all flags in `other` are also set.
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl std::ops::BitOr for DirtyStatus {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// Opt a process into dirty tracking, which is how daemons support idle exit: while clean,
+/// the system may kill them with `SIGKILL` rather than asking them to exit.
+///
+/// ```no_run
+/// use proc_pidinfo::*;
+/// use proc_pidinfo::controls::*;
+///
+/// let pid = getpid();
+/// proc_track_dirty(pid, DirtyTrackingFlags::TRACK | DirtyTrackingFlags::ALLOW_IDLE_EXIT)
+///     .unwrap();
+/// // Mark the process dirty while it has work in flight.
+/// proc_set_dirty(pid, true).unwrap();
+/// proc_set_dirty(pid, false).unwrap();
+/// ```
+pub fn proc_track_dirty(pid: Pid, flags: DirtyTrackingFlags) -> Result<(), std::io::Error> {
+    // SAFETY: No memory is passed.
+    let res = unsafe { ffi::proc_track_dirty(pid.0 as _, flags.0) };
+    errno_result(res)
+}
+
+/// Mark a process that uses dirty tracking as dirty or clean. Fails with `EINVAL` if the
+/// process hasn't opted in with [`proc_track_dirty`].
+pub fn proc_set_dirty(pid: Pid, dirty: bool) -> Result<(), std::io::Error> {
+    // SAFETY: No memory is passed.
+    let res = unsafe { ffi::proc_set_dirty(pid.0 as _, dirty) };
+    errno_result(res)
+}
+
+/// Get the dirty-tracking state of a process.
+pub fn proc_get_dirty(pid: Pid) -> Result<DirtyStatus, std::io::Error> {
+    let mut flags = 0;
+    // SAFETY: The flags pointer is valid for a u32.
+    let res = unsafe { ffi::proc_get_dirty(pid.0 as _, &mut flags) };
+    errno_result(res)?;
+    Ok(DirtyStatus(flags))
+}
+
+/// Clear dirty-tracking flags of a process. Only [`DirtyTrackingFlags::LAUNCH_IN_PROGRESS`]
+/// can be cleared.
+pub fn proc_clear_dirty(pid: Pid, flags: DirtyTrackingFlags) -> Result<(), std::io::Error> {
+    // SAFETY: No memory is passed.
+    let res = unsafe { ffi::proc_clear_dirty(pid.0 as _, flags.0) };
+    errno_result(res)
+}
+
+/// The limits of the CPU usage monitor, which reports a process that uses more than
+/// `percentage` of a CPU averaged over `interval`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MonitorParams {
+    pub percentage: u32,
+    /// Rounded down to whole seconds by the kernel.
+    pub interval: Duration,
+}
+
+/// Get the CPU usage monitor limits of a process, or `None` if the monitor is disabled.
+///
+/// ```
+/// use proc_pidinfo::*;
+/// use proc_pidinfo::controls::*;
+///
+/// if let Some(params) = proc_get_cpumon_params(getpid()).unwrap() {
+///     println!("{}% over {:?}", params.percentage, params.interval);
+/// }
+/// ```
+pub fn proc_get_cpumon_params(pid: Pid) -> Result<Option<MonitorParams>, std::io::Error> {
+    let (mut percentage, mut interval) = (0, 0);
+    // SAFETY: Both pointers are valid for a c_int.
+    let res = unsafe { ffi::proc_get_cpumon_params(pid.0 as _, &mut percentage, &mut interval) };
+    if res != 0 {
+        return Err(last_os_error());
+    }
+    Ok((percentage > 0).then(|| MonitorParams {
+        percentage: percentage as u32,
+        interval: Duration::from_secs(interval.max(0) as u64),
+    }))
+}
+
+/// Enable the CPU usage monitor for a process with the given limits.
+pub fn proc_set_cpumon_params(pid: Pid, params: MonitorParams) -> Result<(), std::io::Error> {
+    let percentage = c_int::try_from(params.percentage).unwrap_or(c_int::MAX);
+    let interval = c_int::try_from(params.interval.as_secs()).unwrap_or(c_int::MAX);
+    // SAFETY: No memory is passed.
+    let res = unsafe { ffi::proc_set_cpumon_params(pid.0 as _, percentage, interval) };
+    if res != 0 {
+        return Err(last_os_error());
+    }
+    Ok(())
+}
+
+/// Disable the CPU usage monitor for a process.
+pub fn proc_disable_cpumon(pid: Pid) -> Result<(), std::io::Error> {
+    // SAFETY: No memory is passed.
+    let res = unsafe { ffi::proc_disable_cpumon(pid.0 as _) };
+    if res != 0 {
+        return Err(last_os_error());
+    }
+    Ok(())
+}
+
+/// Options for the wakeups monitor (`WAKEMON_*` in `<libproc.h>`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WakemonFlags(c_int);
+
+impl WakemonFlags {
+    pub const ENABLE: Self = Self(0x01);
+    pub const DISABLE: Self = Self(0x02);
+    pub const GET_PARAMS: Self = Self(0x04);
+    /// Reset the rate to the system default.
+    pub const SET_DEFAULTS: Self = Self(0x08);
+    /// Kill the process, rather than reporting it, when it exceeds the rate.
+    pub const MAKE_FATAL: Self = Self(0x10);
+
+    /// The raw flags.
+    pub const fn bits(self) -> c_int {
+        self.0
+    }
+
+    /// Returns true if all flags in `other` are also set.
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl std::ops::BitOr for WakemonFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// The state of the wakeups monitor, which reports a process that wakes the CPU more than
+/// `rate_hz` times a second. See [`proc_get_wakemon_params`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WakemonParams {
+    pub rate_hz: c_int,
+    /// [`WakemonFlags::ENABLE`] if the monitor is active, along with
+    /// [`WakemonFlags::MAKE_FATAL`] if exceeding the rate kills the process.
+    pub flags: WakemonFlags,
+}
+
+/// Get the wakeups monitor state of a process.
+///
+/// ```
+/// use proc_pidinfo::*;
+/// use proc_pidinfo::controls::*;
+///
+/// let params = proc_get_wakemon_params(getpid()).unwrap();
+/// if params.flags.contains(WakemonFlags::ENABLE) {
+///     println!("Limited to {} wakeups/s", params.rate_hz);
+/// }
+/// ```
+pub fn proc_get_wakemon_params(pid: Pid) -> Result<WakemonParams, std::io::Error> {
+    let (mut rate_hz, mut flags) = (0, 0);
+    // SAFETY: Both pointers are valid for a c_int.
+    let res = unsafe { ffi::proc_get_wakemon_params(pid.0 as _, &mut rate_hz, &mut flags) };
+    if res != 0 {
+        return Err(last_os_error());
+    }
+    Ok(WakemonParams {
+        rate_hz,
+        flags: WakemonFlags(flags),
+    })
+}
+
+/// Configure the wakeups monitor of a process. Pass [`WakemonFlags::ENABLE`] to turn it on.
+pub fn proc_set_wakemon_params(
+    pid: Pid,
+    rate_hz: c_int,
+    flags: WakemonFlags,
+) -> Result<(), std::io::Error> {
+    // SAFETY: No memory is passed.
+    let res = unsafe { ffi::proc_set_wakemon_params(pid.0 as _, rate_hz, flags.0) };
+    if res != 0 {
+        return Err(last_os_error());
+    }
+    Ok(())
+}
+
+/// Disable the wakeups monitor for a process.
+pub fn proc_disable_wakemon(pid: Pid) -> Result<(), std::io::Error> {
+    // SAFETY: No memory is passed.
+    let res = unsafe { ffi::proc_disable_wakemon(pid.0 as _) };
+    if res != 0 {
+        return Err(last_os_error());
+    }
+    Ok(())
+}
+
+/// Stop the current process from sharing a CPU core with other processes' threads through SMT
+/// (hyper-threading), which mitigates side channels between sibling threads. Has no effect on
+/// CPUs without SMT, such as Apple silicon.
+///
+/// This can't be undone, and the kernel provides no way to read it back. See
+/// [`smt_available`] to check whether it has any effect.
+pub fn proc_set_no_smt() -> Result<(), std::io::Error> {
+    // SAFETY: No memory is passed.
+    let res = unsafe { ffi::proc_set_no_smt() };
+    errno_result(res)
+}
+
+/// Like [`proc_set_no_smt`], but only for the calling thread.
+pub fn proc_setthread_no_smt() -> Result<(), std::io::Error> {
+    // SAFETY: No memory is passed.
+    let res = unsafe { ffi::proc_setthread_no_smt() };
+    errno_result(res)
+}
+
+/// CPU security mitigations for [`proc_set_csm`] (`PROC_CSM_*` in `<libproc.h>`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CsmFlags(u32);
+
+impl CsmFlags {
+    /// Every mitigation below.
+    pub const ALL: Self = Self(0x0001);
+    /// Disable SMT, as with [`proc_set_no_smt`].
+    pub const NOSMT: Self = Self(0x0002);
+    /// Clear CPU buffers (`VERW`) on every return to user mode, which mitigates microarchitectural
+    /// data sampling on Intel CPUs.
+    pub const TECS: Self = Self(0x0004);
+
+    /// The raw flags.
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Returns true if all flags in `other` are also set.
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl std::ops::BitOr for CsmFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// Enable CPU security mitigations for the current process. Like [`proc_set_no_smt`], these
+/// can't be undone or read back.
+///
+/// ```no_run
+/// use proc_pidinfo::controls::*;
+///
+/// proc_set_csm(CsmFlags::NOSMT | CsmFlags::TECS).unwrap();
+/// ```
+pub fn proc_set_csm(flags: CsmFlags) -> Result<(), std::io::Error> {
+    // SAFETY: No memory is passed.
+    let res = unsafe { ffi::proc_set_csm(flags.0) };
+    errno_result(res)
+}
+
+/// Like [`proc_set_csm`], but only for the calling thread.
+pub fn proc_setthread_csm(flags: CsmFlags) -> Result<(), std::io::Error> {
+    // SAFETY: No memory is passed.
+    let res = unsafe { ffi::proc_setthread_csm(flags.0) };
+    errno_result(res)
+}
+
+/// Returns true if the CPU runs more than one thread per core (`hw.logicalcpu` is greater
+/// than `hw.physicalcpu`), in which case [`CsmFlags::NOSMT`] has an effect.
+pub fn smt_available() -> Result<bool, std::io::Error> {
+    Ok(sysctl_int(c"hw.logicalcpu")? > sysctl_int(c"hw.physicalcpu")?)
+}
+
+fn sysctl_int(name: &std::ffi::CStr) -> Result<c_int, std::io::Error> {
+    let mut value: c_int = 0;
+    let mut len = std::mem::size_of::<c_int>();
+    // SAFETY: The output is a single c_int with its size.
+    let res = unsafe {
+        libc::sysctlbyname(
+            name.as_ptr(),
+            &mut value as *mut c_int as *mut c_void,
+            &mut len,
+            std::ptr::null_mut(),
+            0,
+        )
+    };
+    if res != 0 {
+        return Err(last_os_error());
+    }
+    Ok(value)
+}
+
+/// The `proc_setpcontrol`, `proc_terminate`, dirty-tracking and mitigation calls return the
+/// error directly, rather than setting `errno`.
+fn errno_result(res: c_int) -> Result<(), std::io::Error> {
+    if res != 0 {
+        return Err(std::io::Error::from_raw_os_error(res));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TestChild;
+    use std::os::unix::process::ExitStatusExt;
+
+    #[test]
+    fn test_proc_setpcontrol() {
+        proc_setpcontrol(ProcPcontrol::THROTTLEMEM).unwrap();
+        proc_setpcontrol(ProcPcontrol::NONE).unwrap();
+    }
+
+    #[test]
+    fn test_proc_terminate() {
+        let mut child = TestChild::sleep();
+        let signal = proc_terminate(child.pid()).unwrap();
+        assert_eq!(signal.signal(), libc::SIGTERM);
+        let status = child.wait().unwrap();
+        assert_eq!(status.signal(), Some(libc::SIGTERM));
+
+        let err = proc_terminate(Pid(i32::MAX as _)).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::ESRCH));
+    }
+
+    #[test]
+    fn test_dirty_tracking() {
+        let child = TestChild::sleep();
+        let pid = child.pid();
+        let status = proc_get_dirty(pid).unwrap();
+        assert!(!status.contains(DirtyStatus::TRACKED));
+        assert!(proc_set_dirty(pid, true).is_err());
+
+        // Tracking may be refused outside of launchd-managed jobs.
+        if proc_track_dirty(pid, DirtyTrackingFlags::TRACK).is_ok() {
+            proc_set_dirty(pid, true).unwrap();
+            let status = proc_get_dirty(pid).unwrap();
+            assert!(status.contains(DirtyStatus::TRACKED | DirtyStatus::IS_DIRTY));
+            proc_set_dirty(pid, false).unwrap();
+            assert!(!proc_get_dirty(pid).unwrap().contains(DirtyStatus::IS_DIRTY));
+        }
+    }
+
+    #[test]
+    fn test_cpumon_params() {
+        let child = TestChild::sleep();
+        let pid = child.pid();
+        let params = MonitorParams {
+            percentage: 90,
+            interval: Duration::from_secs(60),
+        };
+        proc_set_cpumon_params(pid, params).unwrap();
+        assert_eq!(proc_get_cpumon_params(pid).unwrap(), Some(params));
+        proc_disable_cpumon(pid).unwrap();
+        assert_eq!(proc_get_cpumon_params(pid).unwrap(), None);
+    }
+
+    #[test]
+    fn test_csm() {
+        let err = proc_set_csm(CsmFlags(0x8000)).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+
+        // Only change a short-lived thread, rather than the whole test process.
+        let smt = smt_available().unwrap();
+        let res = std::thread::spawn(proc_setthread_no_smt).join().unwrap();
+        match res {
+            Ok(()) => {}
+            Err(err) => assert!(!smt, "{err}"),
+        }
+        let res = std::thread::spawn(|| proc_setthread_csm(CsmFlags::NOSMT))
+            .join()
+            .unwrap();
+        assert_eq!(
+            res.is_ok(),
+            std::thread::spawn(proc_setthread_no_smt)
+                .join()
+                .unwrap()
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_wakemon_params() {
+        let child = TestChild::sleep();
+        let pid = child.pid();
+        let params = proc_get_wakemon_params(pid).unwrap();
+        assert!((WakemonFlags::ENABLE | WakemonFlags::MAKE_FATAL).contains(params.flags));
+
+        proc_set_wakemon_params(pid, 1000, WakemonFlags::ENABLE).unwrap();
+        assert_eq!(
+            proc_get_wakemon_params(pid).unwrap(),
+            WakemonParams {
+                rate_hz: 1000,
+                flags: WakemonFlags::ENABLE,
+            }
+        );
+        proc_disable_wakemon(pid).unwrap();
+        let params = proc_get_wakemon_params(pid).unwrap();
+        assert!(!params.flags.contains(WakemonFlags::ENABLE));
+    }
+}
@@ -0,0 +1,544 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use super::{
+    mach_ticks_to_duration, proc_pidargs, proc_pidfdinfo, proc_pidinfo, proc_pidinfo_list,
+    proc_pidpath, ByteSize, Fd, Pid, ProcBSDInfo, ProcBSDShortInfo, ProcFDInfo, ProcFDType,
+    ProcStatus, ProcTaskInfo, VnodeFdInfoWithPath,
+};
+
+/// The most file descriptors that [`diagnose`] describes individually.
+pub const DIAGNOSE_MAX_FDS: usize = 64;
+
+/// A one-shot report about a single process, produced by [`diagnose`].
+///
+/// Sections that couldn't be collected (usually for lack of privileges) are `None`, and the
+/// reason is recorded in [`DiagnoseReport::errors`]. The [`fmt::Display`] implementation renders
+/// a plain-text report suitable for pasting into a bug report.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DiagnoseReport {
+    pub pid: Pid,
+    /// When the report was collected.
+    pub taken_at: SystemTime,
+    pub identity: DiagnoseIdentity,
+    /// The process arguments, decoded lossily.
+    pub args: Option<Vec<String>>,
+    pub resources: Option<DiagnoseResources>,
+    pub fds: Option<DiagnoseFds>,
+    /// The sections that failed, and why.
+    pub errors: Vec<DiagnoseError>,
+}
+
+/// Who a process is, and where it came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DiagnoseIdentity {
+    pub comm: String,
+    pub path: Option<PathBuf>,
+    pub ppid: Pid,
+    pub pgid: u32,
+    pub uid: libc::uid_t,
+    pub gid: libc::gid_t,
+    pub ruid: libc::uid_t,
+    pub rgid: libc::gid_t,
+    pub status: Option<ProcStatus>,
+    /// Only available with [`ProcBSDInfo`].
+    pub start_time: Option<SystemTime>,
+    /// Only available with [`ProcBSDInfo`].
+    pub nice: Option<i32>,
+}
+
+/// CPU, memory and thread usage, from [`ProcTaskInfo`]. Counters are cumulative over the
+/// lifetime of the process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DiagnoseResources {
+    pub virtual_size: u64,
+    pub resident_size: u64,
+    /// User CPU time, in Mach absolute time units.
+    pub cpu_user: u64,
+    /// System CPU time, in Mach absolute time units.
+    pub cpu_system: u64,
+    pub threads: i32,
+    pub running_threads: i32,
+    pub priority: i32,
+    pub faults: i32,
+    pub pageins: i32,
+    pub cow_faults: i32,
+    pub context_switches: i32,
+    pub syscalls_unix: i32,
+    pub syscalls_mach: i32,
+    pub messages_sent: i32,
+    pub messages_received: i32,
+}
+
+/// A summary of a process's file descriptor table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DiagnoseFds {
+    /// The number of open file descriptors.
+    pub count: usize,
+    /// The number of open file descriptors of each type, keyed by [`ProcFDType`] name.
+    pub by_type: BTreeMap<String, usize>,
+    /// The lowest-numbered descriptors, up to [`DIAGNOSE_MAX_FDS`].
+    pub top: Vec<DiagnoseFd>,
+    /// Every socket descriptor.
+    pub sockets: Vec<Fd>,
+}
+
+/// A single file descriptor in a [`DiagnoseFds`] summary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DiagnoseFd {
+    pub fd: Fd,
+    /// The [`ProcFDType`] name.
+    pub fd_type: String,
+    /// The path, for vnodes.
+    pub path: Option<PathBuf>,
+}
+
+/// A section of a [`DiagnoseReport`] that couldn't be collected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DiagnoseError {
+    pub section: &'static str,
+    pub error: String,
+}
+
+/// Collect a [`DiagnoseReport`] for a process from the available flavors.
+///
+/// Fails with `ESRCH` if the process doesn't exist, and with the kernel's error if its short
+/// info can't be read. Every other failure, eg: `EPERM` for the task info of another user's
+/// process, is recorded in the report rather than failing the whole call.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let report = diagnose(getpid()).unwrap().unwrap();
+/// println!("{report}");
+/// ```
+pub fn diagnose(pid: Pid) -> Result<Option<DiagnoseReport>, std::io::Error> {
+    diagnose_with(pid, true)
+}
+
+/// [`diagnose`], optionally skipping the file descriptor summary.
+pub(super) fn diagnose_with(
+    pid: Pid,
+    with_fds: bool,
+) -> Result<Option<DiagnoseReport>, std::io::Error> {
+    let Some(short_info) = proc_pidinfo::<ProcBSDShortInfo>(pid)? else {
+        return Ok(None);
+    };
+    let mut errors = vec![];
+    let mut record = |section, err: std::io::Error| {
+        errors.push(DiagnoseError {
+            section,
+            error: err.to_string(),
+        })
+    };
+
+    let bsd_info = section(&mut record, "bsd_info", proc_pidinfo::<ProcBSDInfo>(pid));
+    let path = section(&mut record, "path", proc_pidpath(pid).map(Some));
+    let args = section(&mut record, "args", proc_pidargs(pid).map(Some)).map(|args| {
+        args.args
+            .iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    });
+    let resources = section(&mut record, "task_info", proc_pidinfo::<ProcTaskInfo>(pid))
+        .map(|info| resources(&info));
+    let fds = with_fds
+        .then(|| {
+            section(
+                &mut record,
+                "fds",
+                proc_pidinfo_list::<ProcFDInfo>(pid).map(Some),
+            )
+        })
+        .flatten()
+        .map(|fds| summarize_fds(pid, &fds));
+
+    let identity = DiagnoseIdentity {
+        comm: short_info.comm().unwrap_or_default().to_owned(),
+        path,
+        ppid: short_info.pbsi_ppid,
+        pgid: short_info.pbsi_pgid,
+        uid: short_info.pbsi_uid,
+        gid: short_info.pbsi_gid,
+        ruid: short_info.pbsi_ruid,
+        rgid: short_info.pbsi_rgid,
+        status: short_info.status().ok(),
+        start_time: bsd_info.map(|info| {
+            SystemTime::UNIX_EPOCH
+                + Duration::from_secs(info.pbi_start_tvsec)
+                + Duration::from_micros(info.pbi_start_tvusec)
+        }),
+        nice: bsd_info.map(|info| info.pbi_nice),
+    };
+
+    Ok(Some(DiagnoseReport {
+        pid,
+        taken_at: SystemTime::now(),
+        identity,
+        args,
+        resources,
+        fds,
+        errors,
+    }))
+}
+
+/// Unwrap a section's result, recording the error if it failed.
+fn section<T>(
+    record: &mut impl FnMut(&'static str, std::io::Error),
+    name: &'static str,
+    result: Result<Option<T>, std::io::Error>,
+) -> Option<T> {
+    result.unwrap_or_else(|err| {
+        record(name, err);
+        None
+    })
+}
+
+fn resources(info: &ProcTaskInfo) -> DiagnoseResources {
+    DiagnoseResources {
+        virtual_size: info.pti_virtual_size,
+        resident_size: info.pti_resident_size,
+        cpu_user: info.pti_total_user,
+        cpu_system: info.pti_total_system,
+        threads: info.pti_threadnum,
+        running_threads: info.pti_numrunning,
+        priority: info.pti_priority,
+        faults: info.pti_faults,
+        pageins: info.pti_pageins,
+        cow_faults: info.pti_cow_faults,
+        context_switches: info.pti_csw,
+        syscalls_unix: info.pti_syscalls_unix,
+        syscalls_mach: info.pti_syscalls_mach,
+        messages_sent: info.pti_messages_sent,
+        messages_received: info.pti_messages_received,
+    }
+}
+
+fn fd_type_name(fd: &ProcFDInfo) -> String {
+    match fd.fd_type() {
+        ProcFDType::Unknown(value) => format!("UNKNOWN({value})"),
+        fd_type => format!("{fd_type:?}"),
+    }
+}
+
+fn summarize_fds(pid: Pid, fds: &[ProcFDInfo]) -> DiagnoseFds {
+    let mut by_type = BTreeMap::new();
+    for fd in fds {
+        *by_type.entry(fd_type_name(fd)).or_default() += 1;
+    }
+    let top = fds
+        .iter()
+        .take(DIAGNOSE_MAX_FDS)
+        .map(|fd| DiagnoseFd {
+            fd: fd.proc_fd,
+            fd_type: fd_type_name(fd),
+            path: (fd.fd_type() == ProcFDType::VNODE)
+                .then(|| proc_pidfdinfo::<VnodeFdInfoWithPath>(pid, fd.proc_fd).ok())
+                .flatten()
+                .flatten()
+                .and_then(|info| info.path().ok().map(|path| path.to_owned())),
+        })
+        .collect();
+    DiagnoseFds {
+        count: fds.len(),
+        by_type,
+        top,
+        sockets: fds
+            .iter()
+            .filter(|fd| fd.fd_type() == ProcFDType::SOCKET)
+            .map(|fd| fd.proc_fd)
+            .collect(),
+    }
+}
+
+/// A numeric value that changed between two [`DiagnoseReport`]s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct MetricChange {
+    /// The [`DiagnoseResources`] field name, or `fds` for the descriptor count.
+    pub name: &'static str,
+    pub before: i64,
+    pub after: i64,
+}
+
+impl MetricChange {
+    pub fn delta(&self) -> i64 {
+        self.after - self.before
+    }
+}
+
+/// The difference between two [`DiagnoseReport`]s of the same process, produced by
+/// [`DiagnoseReport::diff`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DiagnoseDiff {
+    /// The time between the two reports, if the second was taken later.
+    pub elapsed: Option<Duration>,
+    /// Metrics that changed. Metrics missing from either report are skipped.
+    pub metrics: Vec<MetricChange>,
+    /// Descriptors that appear only in the second report. Only descriptors described in
+    /// [`DiagnoseFds::top`] are compared.
+    pub opened_fds: Vec<DiagnoseFd>,
+    /// Descriptors that appear only in the first report.
+    pub closed_fds: Vec<DiagnoseFd>,
+    /// Sockets that appear only in the second report.
+    pub new_sockets: Vec<Fd>,
+    /// Sockets that appear only in the first report.
+    pub closed_sockets: Vec<Fd>,
+}
+
+impl DiagnoseDiff {
+    /// Returns true if nothing changed, ignoring the elapsed time.
+    pub fn is_empty(&self) -> bool {
+        self.metrics.is_empty()
+            && self.opened_fds.is_empty()
+            && self.closed_fds.is_empty()
+            && self.new_sockets.is_empty()
+            && self.closed_sockets.is_empty()
+    }
+}
+
+impl DiagnoseReport {
+    /// Compare this report with a later report of the same process.
+    ///
+    /// ```
+    /// use proc_pidinfo::*;
+    ///
+    /// let before = diagnose(getpid()).unwrap().unwrap();
+    /// let _file = std::fs::File::open("/dev/null").unwrap();
+    /// let after = diagnose(getpid()).unwrap().unwrap();
+    /// let diff = before.diff(&after);
+    /// assert!(diff.metrics.iter().any(|metric| metric.name == "fds"));
+    /// ```
+    pub fn diff(&self, other: &Self) -> DiagnoseDiff {
+        let mut diff = DiagnoseDiff {
+            elapsed: other.taken_at.duration_since(self.taken_at).ok(),
+            ..Default::default()
+        };
+
+        if let (Some(before), Some(after)) = (&self.resources, &other.resources) {
+            for ((name, before), (_, after)) in before.metrics().into_iter().zip(after.metrics()) {
+                if before != after {
+                    diff.metrics.push(MetricChange {
+                        name,
+                        before,
+                        after,
+                    });
+                }
+            }
+        }
+
+        if let (Some(before), Some(after)) = (&self.fds, &other.fds) {
+            if before.count != after.count {
+                diff.metrics.push(MetricChange {
+                    name: "fds",
+                    before: before.count as i64,
+                    after: after.count as i64,
+                });
+            }
+            diff.opened_fds = missing_from(&after.top, &before.top);
+            diff.closed_fds = missing_from(&before.top, &after.top);
+            diff.new_sockets = missing_from(&after.sockets, &before.sockets);
+            diff.closed_sockets = missing_from(&before.sockets, &after.sockets);
+        }
+
+        diff
+    }
+}
+
+impl DiagnoseResources {
+    fn metrics(&self) -> [(&'static str, i64); 15] {
+        [
+            ("virtual_size", self.virtual_size as i64),
+            ("resident_size", self.resident_size as i64),
+            ("cpu_user", self.cpu_user as i64),
+            ("cpu_system", self.cpu_system as i64),
+            ("threads", self.threads as i64),
+            ("running_threads", self.running_threads as i64),
+            ("priority", self.priority as i64),
+            ("faults", self.faults as i64),
+            ("pageins", self.pageins as i64),
+            ("cow_faults", self.cow_faults as i64),
+            ("context_switches", self.context_switches as i64),
+            ("syscalls_unix", self.syscalls_unix as i64),
+            ("syscalls_mach", self.syscalls_mach as i64),
+            ("messages_sent", self.messages_sent as i64),
+            ("messages_received", self.messages_received as i64),
+        ]
+    }
+}
+
+/// The items in `items` that aren't in `other`.
+fn missing_from<T: Clone + PartialEq>(items: &[T], other: &[T]) -> Vec<T> {
+    items
+        .iter()
+        .filter(|item| !other.contains(item))
+        .cloned()
+        .collect()
+}
+
+impl fmt::Display for DiagnoseReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let identity = &self.identity;
+        writeln!(f, "Process {} ({})", self.pid.0, identity.comm)?;
+        if let Some(path) = &identity.path {
+            writeln!(f, "  path: {}", path.display())?;
+        }
+        if let Some(args) = &self.args {
+            writeln!(f, "  args: {}", args.join(" "))?;
+        }
+        writeln!(
+            f,
+            "  parent: {}, pgid: {}, uid: {} ({}), gid: {} ({})",
+            identity.ppid.0,
+            identity.pgid,
+            identity.uid,
+            identity.ruid,
+            identity.gid,
+            identity.rgid
+        )?;
+        if let Some(status) = identity.status {
+            writeln!(f, "  status: {status:?}")?;
+        }
+        if let Some(start_time) = identity.start_time {
+            if let Ok(age) = self.taken_at.duration_since(start_time) {
+                writeln!(f, "  running for: {}s", age.as_secs())?;
+            }
+        }
+        if let Some(resources) = &self.resources {
+            writeln!(
+                f,
+                "  memory: {} resident, {} virtual",
+                ByteSize(resources.resident_size),
+                ByteSize(resources.virtual_size)
+            )?;
+            writeln!(
+                f,
+                "  cpu: {:?} user, {:?} system",
+                mach_ticks_to_duration(resources.cpu_user),
+                mach_ticks_to_duration(resources.cpu_system)
+            )?;
+            writeln!(
+                f,
+                "  threads: {} ({} running), priority {}",
+                resources.threads, resources.running_threads, resources.priority
+            )?;
+            writeln!(
+                f,
+                "  faults: {} ({} pageins, {} copy-on-write)",
+                resources.faults, resources.pageins, resources.cow_faults
+            )?;
+        }
+        if let Some(fds) = &self.fds {
+            let by_type = fds
+                .by_type
+                .iter()
+                .map(|(fd_type, count)| format!("{fd_type}: {count}"))
+                .collect::<Vec<_>>();
+            writeln!(f, "  fds: {} ({})", fds.count, by_type.join(", "))?;
+            for fd in &fds.top {
+                match &fd.path {
+                    Some(path) => writeln!(f, "    {} {} {}", fd.fd.0, fd.fd_type, path.display())?,
+                    None => writeln!(f, "    {} {}", fd.fd.0, fd.fd_type)?,
+                }
+            }
+            if fds.count > fds.top.len() {
+                writeln!(f, "    ... {} more", fds.count - fds.top.len())?;
+            }
+        }
+        for error in &self.errors {
+            writeln!(f, "  {} unavailable: {}", error.section, error.error)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::darwin::getpid;
+
+    #[test]
+    fn test_diagnose_self() {
+        let (_read, _write) = std::io::pipe().unwrap();
+        let report = diagnose(getpid()).unwrap().unwrap();
+        println!("{report}");
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert_eq!(
+            report.identity.ppid,
+            Pid(std::os::unix::process::parent_id())
+        );
+        assert!(report.identity.start_time.unwrap() <= report.taken_at);
+        assert!(report.resources.unwrap().threads >= 1);
+        let fds = report.fds.unwrap();
+        assert!(fds.by_type["PIPE"] >= 2);
+        assert!(fds.top.len() <= DIAGNOSE_MAX_FDS);
+    }
+
+    fn fd(fd: i32, fd_type: &str) -> DiagnoseFd {
+        DiagnoseFd {
+            fd: Fd(fd),
+            fd_type: fd_type.to_owned(),
+            path: None,
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let before = diagnose(getpid()).unwrap().unwrap();
+        let mut after = before.clone();
+        assert!(before.diff(&after).is_empty());
+
+        after.taken_at += Duration::from_secs(1);
+        after.resources.as_mut().unwrap().resident_size += 4096;
+        let fds = after.fds.as_mut().unwrap();
+        fds.count += 1;
+        fds.top.push(fd(1000, "SOCKET"));
+        fds.sockets.push(Fd(1000));
+
+        let diff = before.diff(&after);
+        assert_eq!(diff.elapsed, Some(Duration::from_secs(1)));
+        assert_eq!(
+            diff.metrics
+                .iter()
+                .map(|m| (m.name, m.delta()))
+                .collect::<Vec<_>>(),
+            [("resident_size", 4096), ("fds", 1)]
+        );
+        assert_eq!(diff.opened_fds, [fd(1000, "SOCKET")]);
+        assert_eq!(diff.new_sockets, [Fd(1000)]);
+        assert!(diff.closed_fds.is_empty());
+
+        let reverse = after.diff(&before);
+        assert_eq!(reverse.elapsed, None);
+        assert_eq!(reverse.closed_sockets, [Fd(1000)]);
+    }
+
+    #[test]
+    fn test_diagnose_missing_process() {
+        let err = diagnose(Pid(i32::MAX as _)).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::ESRCH));
+    }
+
+    #[test]
+    fn test_diagnose_denied_sections() {
+        // SAFETY: geteuid never fails.
+        if unsafe { libc::geteuid() } == 0 {
+            return;
+        }
+        let report = diagnose(Pid(1)).unwrap().unwrap();
+        assert!(report.resources.is_none());
+        assert!(report
+            .errors
+            .iter()
+            .any(|error| error.section == "task_info"));
+    }
+}
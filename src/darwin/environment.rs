@@ -0,0 +1,192 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use super::{proc_listallpids, proc_pidpath, Pid};
+
+/// The kind of Apple environment the current process was built for and is running in.
+///
+/// Simulators and Mac Catalyst run on the macOS kernel, so every query works there, but the
+/// results describe the host Mac: the process list includes every macOS process, and paths
+/// carry host-specific prefixes. [`SimulatorInfo`] helps map these back to what the simulated
+/// device would see.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Environment {
+    /// A native macOS process.
+    MacOS,
+    /// An iOS app running on macOS through Mac Catalyst.
+    MacCatalyst,
+    /// A process running in an iOS, tvOS, watchOS or visionOS simulator.
+    Simulator,
+    /// A process running on an iOS, tvOS, watchOS or visionOS device. See
+    /// [`super::is_embedded_device`].
+    EmbeddedDevice,
+}
+
+impl Environment {
+    /// The environment of the current process.
+    pub const fn current() -> Self {
+        if cfg!(target_abi = "sim") {
+            Environment::Simulator
+        } else if cfg!(target_abi = "macabi") {
+            Environment::MacCatalyst
+        } else if cfg!(target_os = "macos") {
+            Environment::MacOS
+        } else {
+            Environment::EmbeddedDevice
+        }
+    }
+
+    /// Returns true if this environment runs on the macOS kernel.
+    pub const fn is_macos_kernel(self) -> bool {
+        !matches!(self, Environment::EmbeddedDevice)
+    }
+
+    /// Rewrite a host path into the path the process would see on a real device.
+    ///
+    /// In a simulator, this strips the simulator runtime root (eg: `.../RuntimeRoot/usr/lib`
+    /// becomes `/usr/lib`). Under Mac Catalyst, this strips the `/System/iOSSupport` prefix
+    /// from iOS frameworks. Other paths are returned unchanged.
+    pub fn device_path(self, path: &Path) -> PathBuf {
+        let root = match self {
+            Environment::Simulator => SimulatorInfo::current().map(|info| info.root),
+            Environment::MacCatalyst => Some(PathBuf::from(MAC_CATALYST_ROOT)),
+            _ => None,
+        };
+        match root {
+            Some(root) => strip_root(&root, path),
+            None => path.to_owned(),
+        }
+    }
+}
+
+const MAC_CATALYST_ROOT: &str = "/System/iOSSupport";
+
+/// Keeps the leading `/` when stripping `root` from `path`.
+fn strip_root(root: &Path, path: &Path) -> PathBuf {
+    match path.strip_prefix(root) {
+        Ok(relative) => Path::new("/").join(relative),
+        Err(_) => path.to_owned(),
+    }
+}
+
+/// Details about the simulated device the current process runs on, read from the
+/// `SIMULATOR_*` environment variables that the simulator sets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimulatorInfo {
+    /// The root of the simulator runtime (`SIMULATOR_ROOT`), containing the simulated
+    /// system's `/usr`, `/System`, etc.
+    pub root: PathBuf,
+    /// The simulated device's data directory, containing installed apps and their containers.
+    pub device_dir: Option<PathBuf>,
+    /// The simulated device's UDID (`SIMULATOR_UDID`).
+    pub udid: Option<String>,
+    /// The simulated device's name (`SIMULATOR_DEVICE_NAME`).
+    pub device_name: Option<String>,
+}
+
+impl SimulatorInfo {
+    /// Details about the current simulator, or `None` outside of a simulator.
+    pub fn current() -> Option<Self> {
+        if Environment::current() != Environment::Simulator {
+            return None;
+        }
+        Self::from_vars(|name| std::env::var_os(name))
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<OsString>) -> Option<Self> {
+        let root = PathBuf::from(var("SIMULATOR_ROOT")?);
+        let udid = var("SIMULATOR_UDID").and_then(|udid| udid.into_string().ok());
+        let device_dir = match (&udid, var("SIMULATOR_HOST_HOME")) {
+            (Some(udid), Some(home)) => Some(
+                Path::new(&home)
+                    .join("Library/Developer/CoreSimulator/Devices")
+                    .join(udid),
+            ),
+            _ => None,
+        };
+        Some(Self {
+            root,
+            device_dir,
+            udid,
+            device_name: var("SIMULATOR_DEVICE_NAME").and_then(|name| name.into_string().ok()),
+        })
+    }
+
+    /// Returns true if the path belongs to the simulated device, either as part of the
+    /// simulator runtime or the device's data directory.
+    pub fn contains(&self, path: &Path) -> bool {
+        path.starts_with(&self.root)
+            || self
+                .device_dir
+                .as_ref()
+                .is_some_and(|device_dir| path.starts_with(device_dir))
+    }
+
+    /// Rewrite a host path into the path the process would see on a real device. See
+    /// [`Environment::device_path`].
+    pub fn device_path(&self, path: &Path) -> PathBuf {
+        strip_root(&self.root, path)
+    }
+
+    /// List the processes that belong to the simulated device, rather than every process on
+    /// the host Mac.
+    ///
+    /// A process belongs to the device if its executable lives in the simulator runtime or
+    /// the device's data directory. Processes whose path can't be read are skipped.
+    pub fn pids(&self) -> Result<Vec<Pid>, std::io::Error> {
+        Ok(proc_listallpids()?
+            .into_iter()
+            .filter(|&pid| proc_pidpath(pid).is_ok_and(|path| self.contains(&path)))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn simulator() -> SimulatorInfo {
+        SimulatorInfo::from_vars(|name| match name {
+            "SIMULATOR_ROOT" => Some("/Runtimes/iOS.simruntime/RuntimeRoot".into()),
+            "SIMULATOR_UDID" => Some("ABCD".into()),
+            "SIMULATOR_HOST_HOME" => Some("/Users/me".into()),
+            _ => None,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_environment() {
+        assert_eq!(Environment::current(), Environment::MacOS);
+        assert!(Environment::current().is_macos_kernel());
+        assert!(SimulatorInfo::current().is_none());
+        let path = Path::new("/System/iOSSupport/System/Library/Frameworks/UIKit.framework");
+        assert_eq!(Environment::MacOS.device_path(path), path);
+        assert_eq!(
+            Environment::MacCatalyst.device_path(path),
+            Path::new("/System/Library/Frameworks/UIKit.framework")
+        );
+    }
+
+    #[test]
+    fn test_simulator_info() {
+        let info = simulator();
+        assert_eq!(
+            info.device_dir.as_deref(),
+            Some(Path::new(
+                "/Users/me/Library/Developer/CoreSimulator/Devices/ABCD"
+            ))
+        );
+        assert_eq!(
+            info.device_path(Path::new(
+                "/Runtimes/iOS.simruntime/RuntimeRoot/usr/lib/dyld"
+            )),
+            Path::new("/usr/lib/dyld")
+        );
+        assert!(info.contains(Path::new(
+            "/Users/me/Library/Developer/CoreSimulator/Devices/ABCD/data/Containers/App"
+        )));
+        assert!(!info.contains(Path::new("/usr/lib/dyld")));
+        assert!(info.pids().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,167 @@
+use super::{proc_pidinfo_arg, Pid, ProcPidInfoFlavor, ValueError};
+
+/// The subsystem that terminated a process (`OS_REASON_*` in `<sys/reason.h>`).
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[repr(u32)]
+pub enum ExitReasonNamespace {
+    INVALID = 0,
+    /// Killed by jetsam under memory pressure. The code is a `JETSAM_REASON_*` value.
+    JETSAM = 1,
+    /// Killed by a signal. The code is the signal number.
+    SIGNAL = 2,
+    /// Killed for invalid code signatures.
+    CODESIGNING = 3,
+    /// Killed by the hang tracer.
+    HANGTRACER = 4,
+    TEST = 5,
+    /// Failed to load, eg: a missing library.
+    DYLD = 6,
+    LIBXPC = 7,
+    OBJC = 8,
+    /// Failed during `exec`.
+    EXEC = 9,
+    SPRINGBOARD = 10,
+    TCC = 11,
+    REPORTCRASH = 12,
+    COREANIMATION = 13,
+    AGGREGATED = 14,
+    /// Killed by RunningBoard, which manages app lifecycles.
+    RUNNINGBOARD = 15,
+    SKYWALK = 16,
+    SETTINGS = 17,
+    LIBSYSTEM = 18,
+    FOUNDATION = 19,
+    /// Killed by the watchdog.
+    WATCHDOG = 20,
+    METAL = 21,
+    WATCHKIT = 22,
+    /// Killed for violating a guarded resource.
+    GUARD = 23,
+}
+
+impl ExitReasonNamespace {
+    fn from_raw(namespace: u32) -> Result<Self, ValueError> {
+        use ExitReasonNamespace::*;
+        Ok(match namespace {
+            0 => INVALID,
+            1 => JETSAM,
+            2 => SIGNAL,
+            3 => CODESIGNING,
+            4 => HANGTRACER,
+            5 => TEST,
+            6 => DYLD,
+            7 => LIBXPC,
+            8 => OBJC,
+            9 => EXEC,
+            10 => SPRINGBOARD,
+            11 => TCC,
+            12 => REPORTCRASH,
+            13 => COREANIMATION,
+            14 => AGGREGATED,
+            15 => RUNNINGBOARD,
+            16 => SKYWALK,
+            17 => SETTINGS,
+            18 => LIBSYSTEM,
+            19 => FOUNDATION,
+            20 => WATCHDOG,
+            21 => METAL,
+            22 => WATCHKIT,
+            23 => GUARD,
+            _ => return Err(ValueError::UnexpectedEnumValue),
+        })
+    }
+}
+
+/// Why a process exited. See [`proc_pidexitreason`].
+#[repr(C, packed)]
+#[derive(Debug, Clone, Copy)]
+pub struct ProcExitReasonBasicInfo {
+    pub beri_namespace: u32,
+    /// A namespace-specific code.
+    pub beri_code: u64,
+    /// `OS_REASON_FLAG_*` flags.
+    pub beri_flags: u64,
+    /// The size of the extended reason, which isn't returned by this flavor.
+    pub beri_reason_buf_size: u32,
+}
+
+impl ProcExitReasonBasicInfo {
+    /// The namespace, or an error for namespaces newer than this crate.
+    pub fn namespace(&self) -> Result<ExitReasonNamespace, ValueError> {
+        ExitReasonNamespace::from_raw(self.beri_namespace)
+    }
+
+    pub fn code(&self) -> u64 {
+        self.beri_code
+    }
+
+    pub fn flags(&self) -> u64 {
+        self.beri_flags
+    }
+}
+
+/// Get the exit reason of a process that has exited but not yet been reaped.
+///
+/// The kernel only records a reason for processes terminated by the system or a signal, and
+/// discards it when the parent reaps the process with `wait`, so a supervisor must query its
+/// child before reaping it. Returns `None` if there's no reason, including for running
+/// processes.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let mut child = std::process::Command::new("/bin/sleep").arg("10").spawn().unwrap();
+/// let pid = Pid(child.id());
+/// child.kill().unwrap();
+/// // Wait for the child to become a zombie, without reaping it.
+/// while pid.bsd_short_info().unwrap().unwrap().status() != Ok(ProcStatus::SZOMB) {
+///     std::thread::yield_now();
+/// }
+/// if let Some(reason) = proc_pidexitreason(pid).unwrap() {
+///     println!("{:?} {}", reason.namespace(), reason.code());
+/// }
+/// child.wait().unwrap();
+/// ```
+pub fn proc_pidexitreason(pid: Pid) -> Result<Option<ProcExitReasonBasicInfo>, std::io::Error> {
+    // SAFETY: This is the struct for the flavor.
+    let res = unsafe {
+        proc_pidinfo_arg::<ProcExitReasonBasicInfo>(
+            pid,
+            ProcPidInfoFlavor::PROC_PIDEXITREASONBASICINFO,
+            0,
+        )
+    };
+    match res {
+        Err(err) if err.raw_os_error() == Some(libc::ENOENT) => Ok(None),
+        res => res,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::darwin::{getpid, ProcStatus};
+    use crate::testutil::TestChild;
+
+    #[test]
+    fn test_exit_reason_running() {
+        assert_eq!(std::mem::size_of::<ProcExitReasonBasicInfo>(), 24);
+        assert!(proc_pidexitreason(getpid()).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_exit_reason_signal() {
+        let mut child = TestChild::sleep();
+        let pid = child.pid();
+        child.kill().unwrap();
+        while pid.bsd_short_info().unwrap().unwrap().status() != Ok(ProcStatus::SZOMB) {
+            std::thread::yield_now();
+        }
+        let reason = proc_pidexitreason(pid).unwrap().unwrap();
+        child.wait().unwrap();
+        assert_eq!(reason.namespace(), Ok(ExitReasonNamespace::SIGNAL));
+        assert_eq!(reason.code(), libc::SIGKILL as u64);
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use super::{Pid, PollEvent, ProcessPoller, ProcessStats};
+
+/// Publishes per-process gauges through the [`metrics`] facade. Requires the `metrics` feature.
+///
+/// Each [`MetricsExporter::publish`] samples the tracked processes with a [`ProcessPoller`] and
+/// sets these gauges, labelled with `pid` and `name`:
+///
+/// - `process_cpu_percent`: CPU usage since the previous sample, as shown by `top`.
+/// - `process_resident_bytes`: resident memory.
+/// - `process_footprint_bytes`: the memory attributed to the process by Activity Monitor.
+/// - `process_open_fds`: the number of open file descriptors.
+/// - `process_threads`: the number of threads.
+///
+/// Values that can't be read (see [`ProcessStats`]) aren't published. The facade has no way to
+/// remove a gauge, so the gauges of exited processes keep their last value; the exporter's
+/// recorder decides when they expire.
+///
+/// ```no_run
+/// use proc_pidinfo::*;
+/// use std::time::Duration;
+///
+/// // Install a recorder, eg: a Prometheus exporter, first.
+/// let exporter = MetricsExporter::all(Duration::from_secs(10))
+///     .filter(|stats| stats.name.starts_with("postgres"));
+/// std::thread::spawn(move || exporter.run());
+/// ```
+pub struct MetricsExporter {
+    poller: ProcessPoller,
+    filter: Box<dyn Fn(&ProcessStats) -> bool + Send>,
+}
+
+impl std::fmt::Debug for MetricsExporter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MetricsExporter")
+            .field("poller", &self.poller)
+            .finish_non_exhaustive()
+    }
+}
+
+impl MetricsExporter {
+    /// Track a fixed set of processes. See [`ProcessPoller::new`].
+    pub fn new(pids: impl IntoIterator<Item = Pid>, interval: Duration) -> Self {
+        Self::with_poller(ProcessPoller::new(pids, interval))
+    }
+
+    /// Track every process on the system. See [`ProcessPoller::all`].
+    pub fn all(interval: Duration) -> Self {
+        Self::with_poller(ProcessPoller::all(interval))
+    }
+
+    fn with_poller(poller: ProcessPoller) -> Self {
+        Self {
+            poller,
+            filter: Box::new(|_| true),
+        }
+    }
+
+    /// Only publish processes for which `filter` returns true, eg: those with a given name.
+    pub fn filter(mut self, filter: impl Fn(&ProcessStats) -> bool + Send + 'static) -> Self {
+        self.filter = Box::new(filter);
+        self
+    }
+
+    /// Sample the tracked processes now and set their gauges. CPU usage is published from the
+    /// second sample on. Fails only if the process list can't be read.
+    pub fn publish(&mut self) -> Result<(), std::io::Error> {
+        let cpu = self
+            .poller
+            .sample()?
+            .into_iter()
+            .filter_map(|event| match event {
+                PollEvent::Delta(delta) => Some((delta.pid, delta.cpu_percent?)),
+                _ => None,
+            })
+            .collect::<HashMap<_, _>>();
+        for stats in self.poller.latest().filter(|stats| (self.filter)(stats)) {
+            let labels = [
+                ("pid", stats.pid.0.to_string()),
+                ("name", stats.name.clone()),
+            ];
+            if let Some(cpu) = cpu.get(&stats.pid) {
+                metrics::gauge!("process_cpu_percent", &labels).set(*cpu);
+            }
+            if let Some(rss) = stats.rss_bytes {
+                metrics::gauge!("process_resident_bytes", &labels).set(rss as f64);
+            }
+            if let Some(footprint) = stats.footprint_bytes {
+                metrics::gauge!("process_footprint_bytes", &labels).set(footprint as f64);
+            }
+            if let Some(files) = stats.open_files {
+                metrics::gauge!("process_open_fds", &labels).set(files as f64);
+            }
+            if let Some(threads) = stats.thread_count {
+                metrics::gauge!("process_threads", &labels).set(threads);
+            }
+        }
+        Ok(())
+    }
+
+    /// Publish every interval until sampling fails.
+    pub fn run(mut self) -> Result<(), std::io::Error> {
+        loop {
+            self.publish()?;
+            std::thread::sleep(self.poller.interval());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+
+    use super::*;
+    use crate::darwin::getpid;
+
+    #[test]
+    fn test_publish() {
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        let mut exporter = MetricsExporter::new([getpid(), Pid(1)], Duration::from_millis(1))
+            .filter(|stats| stats.pid != Pid(1));
+        metrics::with_local_recorder(&recorder, || {
+            exporter.publish().unwrap();
+            exporter.publish().unwrap();
+        });
+
+        let gauges = snapshotter
+            .snapshot()
+            .into_vec()
+            .into_iter()
+            .map(|(key, _, _, value)| {
+                let key = key.key();
+                let pid = key
+                    .labels()
+                    .find(|label| label.key() == "pid")
+                    .unwrap()
+                    .value()
+                    .to_owned();
+                let DebugValue::Gauge(value) = value else {
+                    panic!("{value:?}");
+                };
+                ((key.name().to_owned(), pid), value.0)
+            })
+            .collect::<HashMap<_, _>>();
+        let pid = getpid().0.to_string();
+        assert!(gauges[&("process_resident_bytes".to_owned(), pid.clone())] > 0.0);
+        assert!(gauges[&("process_threads".to_owned(), pid.clone())] >= 1.0);
+        assert!(gauges.contains_key(&("process_cpu_percent".to_owned(), pid)));
+        assert!(gauges.keys().all(|(_, pid)| pid != "1"));
+    }
+}
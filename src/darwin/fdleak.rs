@@ -0,0 +1,214 @@
+use std::fmt;
+
+use super::{
+    getpid, proc_pidfdinfo, FdTableSnapshot, Pid, ProcFDInfo, ProcFDType, SocketFdInfo, SocketKind,
+    VnodeFdInfoWithPath,
+};
+
+/// A file descriptor opened after a [`FdLeakGuard`] was created and still open.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LeakedFd {
+    pub fd: ProcFDInfo,
+    /// What the descriptor refers to, eg: a path or socket addresses, read when the leak was
+    /// found.
+    pub description: String,
+}
+
+impl fmt::Display for LeakedFd {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.fd, self.description)
+    }
+}
+
+/// Detects file descriptors leaked by a block of code, eg: a test.
+///
+/// The guard snapshots the current process's descriptor table when created. Any descriptor
+/// open at [`FdLeakGuard::check`] or on drop that wasn't open before is reported as a leak, and
+/// dropping the guard panics if there are any, unless the thread is already panicking or the
+/// guard was [disarmed](FdLeakGuard::disarm).
+///
+/// The descriptor table is shared by every thread, so descriptors opened concurrently by other
+/// threads (eg: other tests run by the same harness) are reported too. Run leak-checked tests
+/// with `--test-threads=1`, or check only for the descriptors you expect to be closed.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let guard = FdLeakGuard::new().unwrap();
+/// let file = std::fs::File::open("/dev/null").unwrap();
+/// assert!(guard.leaks().unwrap().iter().any(|leak| leak.description.contains("/dev/null")));
+/// drop(file);
+/// guard.check().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct FdLeakGuard {
+    before: FdTableSnapshot,
+    armed: bool,
+}
+
+impl FdLeakGuard {
+    /// Snapshot the current process's descriptor table.
+    pub fn new() -> Result<Self, std::io::Error> {
+        Ok(Self {
+            before: FdTableSnapshot::capture(getpid())?,
+            armed: true,
+        })
+    }
+
+    /// List the descriptors opened since the guard was created that are still open, including
+    /// descriptor numbers that were closed and reused for a different type of descriptor.
+    pub fn leaks(&self) -> Result<Vec<LeakedFd>, std::io::Error> {
+        let pid = self.before.pid();
+        let after = FdTableSnapshot::capture(pid)?;
+        let diff = FdTableSnapshot::diff(&self.before, &after);
+        let mut leaks = diff
+            .opened
+            .into_iter()
+            .chain(diff.changed.into_iter().map(|change| change.after))
+            .map(|fd| LeakedFd {
+                fd,
+                description: describe(pid, &fd),
+            })
+            .collect::<Vec<_>>();
+        leaks.sort_by_key(|leak| leak.fd.proc_fd.0);
+        Ok(leaks)
+    }
+
+    /// Fail with an error listing every leaked descriptor, if there are any.
+    pub fn check(&self) -> Result<(), std::io::Error> {
+        let leaks = self.leaks()?;
+        if leaks.is_empty() {
+            return Ok(());
+        }
+        let leaks = leaks
+            .iter()
+            .map(|leak| leak.to_string())
+            .collect::<Vec<_>>();
+        Err(std::io::Error::other(format!(
+            "{} file descriptors leaked: {}",
+            leaks.len(),
+            leaks.join(", ")
+        )))
+    }
+
+    /// Don't check for leaks when the guard is dropped.
+    pub fn disarm(&mut self) {
+        self.armed = false;
+    }
+}
+
+impl Drop for FdLeakGuard {
+    fn drop(&mut self) {
+        if !self.armed || std::thread::panicking() {
+            return;
+        }
+        if let Err(err) = self.check() {
+            panic!("{err}");
+        }
+    }
+}
+
+/// Describe a descriptor from its details, falling back to its type if they can't be read.
+fn describe(pid: Pid, fd: &ProcFDInfo) -> String {
+    let detail = match fd.fd_type() {
+        ProcFDType::VNODE => proc_pidfdinfo::<VnodeFdInfoWithPath>(pid, fd.proc_fd)
+            .ok()
+            .flatten()
+            .map(|info| info.to_string()),
+        ProcFDType::SOCKET => proc_pidfdinfo::<SocketFdInfo>(pid, fd.proc_fd)
+            .ok()
+            .flatten()
+            .and_then(|info| describe_socket(&info)),
+        _ => None,
+    };
+    detail.unwrap_or_else(|| match fd.fd_type() {
+        ProcFDType::Unknown(value) => format!("type {value}"),
+        fd_type => format!("{fd_type:?}"),
+    })
+}
+
+fn describe_socket(info: &SocketFdInfo) -> Option<String> {
+    let protocol = match info.psi.soi_type {
+        libc::SOCK_STREAM => "stream",
+        libc::SOCK_DGRAM => "datagram",
+        _ => "socket",
+    };
+    if let Some(inet) = info.psi.inet() {
+        return Some(format!(
+            "{protocol} {} -> {}",
+            inet.local_addr(),
+            inet.foreign_addr()
+        ));
+    }
+    match info.psi.kind() {
+        SocketKind::Unix(unix) => {
+            let path = unix.path().or(unix.peer_path());
+            Some(match path {
+                Some(path) => format!("unix {protocol} {}", path.display()),
+                None => format!("unix {protocol}"),
+            })
+        }
+        SocketKind::Route => Some("route socket".to_owned()),
+        SocketKind::Ndrv(ndrv) => Some(format!(
+            "ndrv {}{}",
+            ndrv.if_name().unwrap_or_default(),
+            ndrv.ndrvsi_if_unit
+        )),
+        SocketKind::KernEvent(_) => Some("kernel event socket".to_owned()),
+        SocketKind::KernCtl(ctl) => Some(format!(
+            "kernel control {} unit {}",
+            ctl.name().unwrap_or_default(),
+            ctl.kcsi_unit
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+    use std::os::fd::AsRawFd;
+
+    use super::*;
+    use crate::darwin::Fd;
+
+    #[test]
+    fn test_leaks() {
+        let mut guard = FdLeakGuard::new().unwrap();
+        let file = std::fs::File::open("/dev/null").unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let leaks = guard.leaks().unwrap();
+        let find = |fd: Fd| leaks.iter().find(|leak| leak.fd.proc_fd == fd).unwrap();
+
+        assert!(find(Fd(file.as_raw_fd()))
+            .description
+            .starts_with("/dev/null"));
+        let port = listener.local_addr().unwrap().port();
+        assert!(find(Fd(listener.as_raw_fd()))
+            .description
+            .contains(&format!("127.0.0.1:{port}")));
+
+        let fds = [file.as_raw_fd(), listener.as_raw_fd()];
+        drop((file, listener));
+        assert!(!guard
+            .leaks()
+            .unwrap()
+            .iter()
+            .any(|leak| fds.contains(&leak.fd.proc_fd.0)));
+        guard.disarm();
+    }
+
+    #[test]
+    fn test_drop_panics() {
+        let err = std::panic::catch_unwind(|| {
+            let _guard = FdLeakGuard::new().unwrap();
+            // The pipe outlives the guard, which is dropped first.
+            std::io::pipe().unwrap()
+        })
+        .unwrap_err();
+        let message = err.downcast::<String>().unwrap();
+        assert!(message.contains("leaked"));
+        assert!(message.contains("PIPE"));
+    }
+}
@@ -0,0 +1,253 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use libc::c_int;
+
+use super::{proc_pidinfo_list, Fd, Pid, ProcFDInfo, Sink};
+
+/// A point-in-time copy of a process's file descriptor table.
+///
+/// Compare two snapshots with [`FdTableSnapshot::diff`], or use [`FdTableSnapshot::poll`] to
+/// watch a process for changes.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let before = FdTableSnapshot::capture(getpid()).unwrap();
+/// let _file = std::fs::File::open("/dev/null").unwrap();
+/// let after = FdTableSnapshot::capture(getpid()).unwrap();
+/// let diff = FdTableSnapshot::diff(&before, &after);
+/// assert_eq!(diff.opened.len(), 1);
+/// ```
+#[derive(Debug, Clone)]
+pub struct FdTableSnapshot {
+    pid: Pid,
+    taken_at: Instant,
+    fds: BTreeMap<c_int, ProcFDInfo>,
+}
+
+impl FdTableSnapshot {
+    /// Capture the file descriptor table of a process.
+    pub fn capture(pid: Pid) -> Result<Self, std::io::Error> {
+        let fds = proc_pidinfo_list::<ProcFDInfo>(pid)?;
+        Ok(Self::from_fds(pid, fds))
+    }
+
+    /// Build a snapshot from an already-fetched list of file descriptors.
+    pub fn from_fds(pid: Pid, fds: impl IntoIterator<Item = ProcFDInfo>) -> Self {
+        Self {
+            pid,
+            taken_at: Instant::now(),
+            fds: fds.into_iter().map(|fd| (fd.proc_fd.0, fd)).collect(),
+        }
+    }
+
+    /// The process this snapshot was taken from.
+    pub fn pid(&self) -> Pid {
+        self.pid
+    }
+
+    /// When this snapshot was taken.
+    pub fn taken_at(&self) -> Instant {
+        self.taken_at
+    }
+
+    /// The number of open file descriptors.
+    pub fn len(&self) -> usize {
+        self.fds.len()
+    }
+
+    /// Returns true if the process had no open file descriptors.
+    pub fn is_empty(&self) -> bool {
+        self.fds.is_empty()
+    }
+
+    /// Look up a single file descriptor.
+    pub fn get(&self, fd: Fd) -> Option<&ProcFDInfo> {
+        self.fds.get(&fd.0)
+    }
+
+    /// All file descriptors, in ascending order.
+    pub fn fds(&self) -> impl Iterator<Item = &ProcFDInfo> {
+        self.fds.values()
+    }
+
+    /// Compare two snapshots of the same process.
+    ///
+    /// A descriptor number that was closed and reused for a different type of descriptor is
+    /// reported in [`FdTableDiff::changed`]. A descriptor that was closed and reopened with the
+    /// same type between the two snapshots can't be detected from the table alone.
+    pub fn diff(older: &Self, newer: &Self) -> FdTableDiff {
+        let mut diff = FdTableDiff::default();
+        for (fd, before) in &older.fds {
+            match newer.fds.get(fd) {
+                None => diff.closed.push(*before),
+                Some(after) if after.proc_fdtype != before.proc_fdtype => {
+                    diff.changed.push(FdChange {
+                        before: *before,
+                        after: *after,
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+        for (fd, after) in &newer.fds {
+            if !older.fds.contains_key(fd) {
+                diff.opened.push(*after);
+            }
+        }
+        diff
+    }
+
+    /// Poll a process's file descriptor table at a fixed interval.
+    ///
+    /// The returned iterator blocks for `interval` between samples and yields the difference
+    /// from the previous successful sample. It never ends on its own; an error (for example,
+    /// because the process exited) is yielded and polling continues on the next call.
+    pub fn poll(pid: Pid, interval: Duration) -> Result<FdTablePoller, std::io::Error> {
+        Ok(FdTablePoller {
+            interval,
+            last: Self::capture(pid)?,
+        })
+    }
+}
+
+/// A file descriptor whose type changed between two snapshots.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FdChange {
+    pub before: ProcFDInfo,
+    pub after: ProcFDInfo,
+}
+
+/// The difference between two [`FdTableSnapshot`]s.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FdTableDiff {
+    /// Descriptors present only in the newer snapshot.
+    pub opened: Vec<ProcFDInfo>,
+    /// Descriptors present only in the older snapshot.
+    pub closed: Vec<ProcFDInfo>,
+    /// Descriptors present in both snapshots, but with a different type.
+    pub changed: Vec<FdChange>,
+}
+
+impl FdTableDiff {
+    /// Returns true if nothing changed.
+    pub fn is_empty(&self) -> bool {
+        self.opened.is_empty() && self.closed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// An iterator that periodically samples a process's file descriptor table. See
+/// [`FdTableSnapshot::poll`].
+#[derive(Debug)]
+pub struct FdTablePoller {
+    interval: Duration,
+    last: FdTableSnapshot,
+}
+
+impl FdTablePoller {
+    /// The most recent successful snapshot.
+    pub fn latest(&self) -> &FdTableSnapshot {
+        &self.last
+    }
+
+    /// Push non-empty diffs into a [`Sink`] until a sample or the sink fails.
+    pub fn forward(&mut self, sink: &mut impl Sink<FdTableDiff>) -> Result<(), std::io::Error> {
+        for diff in self {
+            let diff = diff?;
+            if !diff.is_empty() {
+                sink.send(diff)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for FdTablePoller {
+    type Item = Result<FdTableDiff, std::io::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        std::thread::sleep(self.interval);
+        let next = match FdTableSnapshot::capture(self.last.pid) {
+            Ok(next) => next,
+            Err(err) => return Some(Err(err)),
+        };
+        let diff = FdTableSnapshot::diff(&self.last, &next);
+        self.last = next;
+        Some(Ok(diff))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::darwin::getpid;
+
+    fn fd(fd: c_int, fdtype: u32) -> ProcFDInfo {
+        ProcFDInfo {
+            proc_fd: Fd(fd),
+            proc_fdtype: fdtype,
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let older = FdTableSnapshot::from_fds(Pid(1), [fd(0, 1), fd(1, 1), fd(2, 6)]);
+        let newer = FdTableSnapshot::from_fds(Pid(1), [fd(0, 1), fd(2, 2), fd(3, 1)]);
+        let diff = FdTableSnapshot::diff(&older, &newer);
+        assert_eq!(
+            diff.opened.iter().map(|fd| fd.proc_fd).collect::<Vec<_>>(),
+            [Fd(3)]
+        );
+        assert_eq!(
+            diff.closed.iter().map(|fd| fd.proc_fd).collect::<Vec<_>>(),
+            [Fd(1)]
+        );
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].before.proc_fdtype, 6);
+        assert_eq!(diff.changed[0].after.proc_fdtype, 2);
+        assert!(FdTableSnapshot::diff(&older, &older).is_empty());
+    }
+
+    #[test]
+    fn test_capture_self() {
+        let before = FdTableSnapshot::capture(getpid()).unwrap();
+        let (read, write) = std::io::pipe().unwrap();
+        let after = FdTableSnapshot::capture(getpid()).unwrap();
+        let diff = FdTableSnapshot::diff(&before, &after);
+        drop((read, write));
+
+        for fd in &diff.opened {
+            println!("{:?}", fd);
+        }
+        assert!(diff.opened.len() >= 2);
+    }
+
+    #[test]
+    fn test_poll() {
+        let mut poller = FdTableSnapshot::poll(getpid(), Duration::from_millis(1)).unwrap();
+        poller.next().unwrap().unwrap();
+        assert_eq!(poller.latest().pid(), getpid());
+    }
+
+    #[test]
+    fn test_forward() {
+        let mut poller = FdTableSnapshot::poll(getpid(), Duration::from_millis(1)).unwrap();
+        let mut pipes = vec![std::io::pipe().unwrap()];
+        let err = poller
+            .forward(&mut |diff: FdTableDiff| {
+                if pipes.len() < 3 {
+                    pipes.push(std::io::pipe().unwrap());
+                    return Ok(());
+                }
+                Err(std::io::Error::other(format!(
+                    "{} opened",
+                    diff.opened.len()
+                )))
+            })
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::Other);
+    }
+}
@@ -0,0 +1,197 @@
+use super::{proc_pidfddetail_raw, proc_pidinfo_list, Fd, Pid, ProcFDInfo, ProcFileInfo};
+
+/// `PROC_FP_GUARDED` in `fi_status`: the descriptor has a guard.
+const PROC_FP_GUARDED: u32 = 0x4;
+
+/// The operations a guarded file descriptor is protected against, from
+/// [`ProcFileInfo::fi_guardflags`] (`PROC_FI_GUARD_*` in `<sys/proc_info.h>`).
+///
+/// Guards are set with `guarded_open_np` or `change_fdguard_np`, and the guarded operations
+/// fail with `EPERM` (and usually crash the process with a `GUARD` exception) unless they're
+/// made with the matching guard, eg: by `guarded_close_np`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct GuardFlags(u32);
+
+impl GuardFlags {
+    /// The descriptor can't be closed.
+    pub const CLOSE: Self = Self(0x1);
+    /// The descriptor can't be duplicated, and is closed on exec rather than inherited.
+    pub const DUP: Self = Self(0x2);
+    /// The descriptor can't be sent to another process over a unix domain socket.
+    pub const SOCKET_IPC: Self = Self(0x4);
+    /// The descriptor can't be made into a fileport.
+    pub const FILEPORT: Self = Self(0x8);
+
+    const NAMES: [(Self, &'static str); 4] = [
+        (Self::CLOSE, "close"),
+        (Self::DUP, "dup"),
+        (Self::SOCKET_IPC, "socket-ipc"),
+        (Self::FILEPORT, "fileport"),
+    ];
+
+    /// Wrap raw `PROC_FI_GUARD_*` bits.
+    pub const fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+
+    /// The raw flags.
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Returns true if no flags are set.
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns true if all flags in `other` are also set.
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// The names of the set flags, eg: `["close", "dup"]`. Unknown bits are left out.
+    pub fn names(self) -> Vec<&'static str> {
+        Self::NAMES
+            .iter()
+            .filter(|(flag, _)| self.contains(*flag))
+            .map(|(_, name)| *name)
+            .collect()
+    }
+}
+
+impl std::ops::BitOr for GuardFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl ProcFileInfo {
+    /// Returns true if the descriptor has a guard. See [`ProcFileInfo::guard_flags`].
+    pub fn is_guarded(&self) -> bool {
+        self.fi_status & PROC_FP_GUARDED != 0
+    }
+
+    /// The operations the descriptor is guarded against, which is empty if it isn't guarded.
+    pub fn guard_flags(&self) -> GuardFlags {
+        GuardFlags(self.fi_guardflags)
+    }
+}
+
+/// Read the [`ProcFileInfo`] of a descriptor of any type, from the details for its type. See
+/// [`proc_pidfddetail_raw`].
+///
+/// Returns `None` for types the kernel has no details for, such as
+/// [`ProcFDType::NEXUS`](super::ProcFDType::NEXUS).
+pub fn proc_pidfdfileinfo(
+    pid: Pid,
+    fd: &ProcFDInfo,
+) -> Result<Option<ProcFileInfo>, std::io::Error> {
+    let Some(detail) = proc_pidfddetail_raw(pid, fd)? else {
+        return Ok(None);
+    };
+    if detail.len() < std::mem::size_of::<ProcFileInfo>() {
+        return Ok(None);
+    }
+    // SAFETY: Every proc_pidfdinfo reply starts with a proc_fileinfo, and the length was
+    // checked. The buffer is not aligned for it.
+    Ok(Some(unsafe {
+        std::ptr::read_unaligned(detail.as_ptr() as *const ProcFileInfo)
+    }))
+}
+
+/// List the guarded file descriptors of a process, with their guards.
+///
+/// Descriptors closed while listing, and those of types without details, are skipped.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// for (fd, guards) in guarded_fds(getpid()).unwrap() {
+///     println!("fd {} guarded against {}", fd.0, guards.names().join(", "));
+/// }
+/// ```
+pub fn guarded_fds(pid: Pid) -> Result<Vec<(Fd, GuardFlags)>, std::io::Error> {
+    let mut guarded = vec![];
+    for fd in proc_pidinfo_list::<ProcFDInfo>(pid)? {
+        match proc_pidfdfileinfo(pid, &fd) {
+            Ok(Some(info)) if info.is_guarded() => guarded.push((fd.proc_fd, info.guard_flags())),
+            Ok(_) => {}
+            Err(err) if err.raw_os_error() == Some(libc::EBADF) => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(guarded)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::fd::AsRawFd;
+
+    use super::*;
+    use crate::darwin::getpid;
+
+    mod ffi {
+        use libc::{c_int, c_uint};
+
+        extern "C" {
+            pub fn change_fdguard_np(
+                fd: c_int,
+                guard: *const u64,
+                guardflags: c_uint,
+                nguard: *const u64,
+                nguardflags: c_uint,
+                fdflagsp: *mut c_int,
+            ) -> c_int;
+        }
+    }
+
+    #[test]
+    fn test_guarded_fds() {
+        let file = std::fs::File::open("/dev/null").unwrap();
+        let fd = Fd(file.as_raw_fd());
+        assert!(!guarded_fds(getpid())
+            .unwrap()
+            .iter()
+            .any(|(found, _)| *found == fd));
+
+        let guard = 0x1234_u64;
+        let flags = GuardFlags::CLOSE | GuardFlags::DUP;
+        let mut fdflags = 0;
+        // SAFETY: Guards a descriptor this test owns, and removes the guard before closing it.
+        let res = unsafe {
+            ffi::change_fdguard_np(
+                fd.0,
+                std::ptr::null(),
+                0,
+                &guard,
+                flags.bits(),
+                &mut fdflags,
+            )
+        };
+        assert_eq!(res, 0);
+        let guarded = guarded_fds(getpid());
+        // SAFETY: As above.
+        let res = unsafe {
+            ffi::change_fdguard_np(
+                fd.0,
+                &guard,
+                flags.bits(),
+                std::ptr::null(),
+                0,
+                &mut fdflags,
+            )
+        };
+        assert_eq!(res, 0);
+
+        let (_, found) = guarded
+            .unwrap()
+            .into_iter()
+            .find(|(found, _)| *found == fd)
+            .unwrap();
+        assert_eq!(found, flags);
+        assert_eq!(found.names(), ["close", "dup"]);
+    }
+}
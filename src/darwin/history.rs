@@ -0,0 +1,314 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use super::{all_short_bsd_info, proc_pidinfo, proc_pidpath, Pid, ProcUniqueIdentifierInfo};
+
+/// A rolling history of which processes were running, used to spot processes that repeatedly
+/// exit and restart.
+///
+/// Each snapshot records the unique id ([`ProcUniqueIdentifierInfo::p_uniqueid`]), pid and name
+/// (`comm`) of every process that passes the filter. A process instance is identified by its
+/// unique id, which, unlike the pid, is never reused, so a restarted daemon shows up as the same
+/// name with a new unique id even if it got its old pid back. Snapshots older than the window
+/// are discarded.
+///
+/// ```
+/// use proc_pidinfo::*;
+/// use std::time::Duration;
+///
+/// let mut history = ProcessHistory::new(Duration::from_secs(60))
+///     .filter(|entry| entry.name.starts_with("com.example."));
+/// // Call periodically, eg: every few seconds.
+/// history.capture().unwrap();
+/// for candidate in history.crash_loop_candidates(2) {
+///     println!("{} restarted {} times", candidate.name, candidate.restarts);
+/// }
+/// ```
+#[derive(Clone)]
+pub struct ProcessHistory {
+    window: Duration,
+    filter: Arc<dyn Fn(&HistoryEntry) -> bool + Send + Sync>,
+    snapshots: VecDeque<HistorySnapshot>,
+}
+
+impl std::fmt::Debug for ProcessHistory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProcessHistory")
+            .field("window", &self.window)
+            .field("snapshots", &self.snapshots)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct HistorySnapshot {
+    taken_at: Instant,
+    processes: HashMap<u64, HistoryEntry>,
+}
+
+/// A process in a [`ProcessHistory`] snapshot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryEntry {
+    /// [`ProcUniqueIdentifierInfo::p_uniqueid`], which identifies the process instance.
+    pub unique_id: u64,
+    pub pid: Pid,
+    pub name: String,
+    /// The executable path, if it could be read.
+    pub path: Option<PathBuf>,
+}
+
+/// A process name that repeatedly exited and restarted within a [`ProcessHistory`] window.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrashLoopCandidate {
+    pub name: String,
+    /// The number of times a process with this name exited and another one started.
+    pub restarts: usize,
+    /// The pid of every process seen with this name in the window, oldest first. A reused pid
+    /// appears once per process.
+    pub pids: Vec<Pid>,
+    /// Whether a process with this name was running in the latest snapshot.
+    pub running: bool,
+}
+
+impl ProcessHistory {
+    /// Create an empty history that keeps snapshots for `window`.
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            filter: Arc::new(|_| true),
+            snapshots: VecDeque::new(),
+        }
+    }
+
+    /// Only track processes for which `filter` returns true, eg: those with a given name or
+    /// executable path.
+    pub fn filter(
+        mut self,
+        filter: impl Fn(&HistoryEntry) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.filter = Arc::new(filter);
+        self
+    }
+
+    /// The number of snapshots currently in the window.
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    /// Returns true if no snapshots have been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    /// Snapshot every process on the system, skipping processes that exit mid-scan.
+    pub fn capture(&mut self) -> Result<(), std::io::Error> {
+        let processes = all_short_bsd_info()?.into_iter().filter_map(|info| {
+            let pid = info.pbsi_pid;
+            let unique_id = proc_pidinfo::<ProcUniqueIdentifierInfo>(pid)
+                .ok()??
+                .p_uniqueid;
+            Some(HistoryEntry {
+                unique_id,
+                pid,
+                name: info.comm().ok()?.to_owned(),
+                path: proc_pidpath(pid).ok(),
+            })
+        });
+        self.record(Instant::now(), processes);
+        Ok(())
+    }
+
+    /// Record a snapshot collected elsewhere, eg: by a [`super::Scanner`]. Processes that don't
+    /// pass the filter are skipped.
+    pub fn record(&mut self, taken_at: Instant, processes: impl IntoIterator<Item = HistoryEntry>) {
+        self.snapshots.push_back(HistorySnapshot {
+            taken_at,
+            processes: processes
+                .into_iter()
+                .filter(|entry| (self.filter)(entry))
+                .map(|entry| (entry.unique_id, entry))
+                .collect(),
+        });
+        while let Some(oldest) = self.snapshots.front() {
+            if taken_at.saturating_duration_since(oldest.taken_at) <= self.window {
+                break;
+            }
+            self.snapshots.pop_front();
+        }
+    }
+
+    /// Find process names that exited and were replaced by a new process with the same name at
+    /// least `min_restarts` times within the window, most restarts first.
+    pub fn crash_loop_candidates(&self, min_restarts: usize) -> Vec<CrashLoopCandidate> {
+        #[derive(Default)]
+        struct Counts {
+            exits: usize,
+            starts: usize,
+            pids: Vec<Pid>,
+        }
+
+        let mut names = BTreeMap::<&str, Counts>::new();
+        let mut snapshots = self.snapshots.iter();
+        let Some(mut previous) = snapshots.next() else {
+            return vec![];
+        };
+        for entry in previous.processes.values() {
+            names.entry(&entry.name).or_default().pids.push(entry.pid);
+        }
+        for next in snapshots {
+            for (unique_id, entry) in &previous.processes {
+                if !next.processes.contains_key(unique_id) {
+                    names.entry(&entry.name).or_default().exits += 1;
+                }
+            }
+            for (unique_id, entry) in &next.processes {
+                if !previous.processes.contains_key(unique_id) {
+                    let counts = names.entry(&entry.name).or_default();
+                    counts.starts += 1;
+                    counts.pids.push(entry.pid);
+                }
+            }
+            previous = next;
+        }
+
+        let mut candidates = names
+            .into_iter()
+            .map(|(name, counts)| CrashLoopCandidate {
+                name: name.to_owned(),
+                restarts: counts.exits.min(counts.starts),
+                pids: counts.pids,
+                running: previous
+                    .processes
+                    .values()
+                    .any(|running| running.name == name),
+            })
+            .filter(|candidate| candidate.restarts > 0 && candidate.restarts >= min_restarts)
+            .collect::<Vec<_>>();
+        candidates.sort_by_key(|candidate| std::cmp::Reverse(candidate.restarts));
+        candidates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TestChild;
+
+    /// A snapshot of processes whose unique id is their pid.
+    fn snapshot(processes: &[(u32, &str)]) -> Vec<HistoryEntry> {
+        processes
+            .iter()
+            .map(|&(pid, name)| entry(pid as u64, pid, name))
+            .collect()
+    }
+
+    fn entry(unique_id: u64, pid: u32, name: &str) -> HistoryEntry {
+        HistoryEntry {
+            unique_id,
+            pid: Pid(pid),
+            name: name.to_owned(),
+            path: None,
+        }
+    }
+
+    #[test]
+    fn test_crash_loop_candidates() {
+        let start = Instant::now();
+        let mut history = ProcessHistory::new(Duration::from_secs(60));
+        let snapshots = [
+            snapshot(&[(1, "launchd"), (10, "flappy"), (20, "stable")]),
+            snapshot(&[(1, "launchd"), (20, "stable")]),
+            snapshot(&[(1, "launchd"), (11, "flappy"), (20, "stable")]),
+            snapshot(&[(1, "launchd"), (12, "flappy"), (20, "stable")]),
+            snapshot(&[(1, "launchd"), (20, "stable"), (30, "oneshot")]),
+        ];
+        for (i, processes) in snapshots.into_iter().enumerate() {
+            history.record(start + Duration::from_secs(i as u64), processes);
+        }
+
+        let candidates = history.crash_loop_candidates(2);
+        assert_eq!(
+            candidates,
+            [CrashLoopCandidate {
+                name: "flappy".to_owned(),
+                restarts: 2,
+                pids: vec![Pid(10), Pid(11), Pid(12)],
+                running: false,
+            }]
+        );
+        assert_eq!(history.crash_loop_candidates(3), []);
+    }
+
+    #[test]
+    fn test_window() {
+        let start = Instant::now();
+        let mut history = ProcessHistory::new(Duration::from_secs(10));
+        for i in 0..5 {
+            let processes = snapshot(&[(100 + i, "flappy")]);
+            history.record(start + Duration::from_secs(i as u64 * 5), processes);
+        }
+        assert_eq!(history.len(), 3);
+        assert_eq!(history.crash_loop_candidates(1)[0].restarts, 2);
+    }
+
+    #[test]
+    fn test_pid_reuse() {
+        let start = Instant::now();
+        let mut history = ProcessHistory::new(Duration::from_secs(60));
+        for unique_id in 0..3 {
+            let processes = [
+                entry(100 + unique_id, 500, "flappy"),
+                entry(1, 1, "launchd"),
+            ];
+            history.record(start + Duration::from_secs(unique_id), processes);
+        }
+        let candidates = history.crash_loop_candidates(1);
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].restarts, 2);
+        assert_eq!(candidates[0].pids, [Pid(500); 3]);
+        assert!(candidates[0].running);
+    }
+
+    #[test]
+    fn test_filter() {
+        let start = Instant::now();
+        let mut history = ProcessHistory::new(Duration::from_secs(60))
+            .filter(|entry| entry.name.starts_with("flappy"));
+        let snapshots = [
+            snapshot(&[(10, "flappy"), (20, "other")]),
+            snapshot(&[(11, "flappy"), (21, "other")]),
+            snapshot(&[(12, "flappy"), (22, "other")]),
+        ];
+        for (i, processes) in snapshots.into_iter().enumerate() {
+            history.record(start + Duration::from_secs(i as u64), processes);
+        }
+        let names = history
+            .crash_loop_candidates(1)
+            .into_iter()
+            .map(|candidate| candidate.name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["flappy"]);
+    }
+
+    #[test]
+    fn test_capture() {
+        let mut history = ProcessHistory::new(Duration::from_secs(60)).filter(|entry| {
+            entry
+                .path
+                .as_ref()
+                .is_some_and(|path| path.ends_with("sleep"))
+        });
+        for _ in 0..3 {
+            let mut child = TestChild::sleep();
+            history.capture().unwrap();
+            child.kill().unwrap();
+            child.wait().unwrap();
+            history.capture().unwrap();
+        }
+        let candidates = history.crash_loop_candidates(2);
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].name, "sleep");
+    }
+}
@@ -0,0 +1,380 @@
+use std::path::PathBuf;
+
+use libc::{c_int, c_void};
+
+use super::{
+    last_os_error, proc_pidfdinfo, proc_pidinfo_list, Fd, Pid, ProcFDInfo, ProcFDType,
+    ProcPidFdInfoFlavor, ProcPidInfoFlavor, VInfoStat, VnodeFdInfoWithPath,
+};
+
+mod ffi {
+    use libc::{c_int, c_void};
+
+    extern "C" {
+        pub fn proc_piddynkqueueinfo(
+            pid: c_int,
+            flavor: c_int,
+            kq_id: u64,
+            buffer: *mut c_void,
+            buffersize: c_int,
+        ) -> c_int;
+    }
+}
+
+/// The kernel's limit on the number of knotes reported for a single kqueue.
+const PROC_PIDFDKQUEUE_KNOTES_MAX: usize = 1024 * 128;
+
+/// The kernel's limit on the number of dynamic kqueues reported for a process.
+const PROC_PIDDYNKQUEUES_MAX: usize = 1024 * 128;
+
+/// Flavors for `proc_piddynkqueueinfo`.
+const PROC_PIDDYNKQUEUE_INFO: c_int = 0;
+const PROC_PIDDYNKQUEUE_EXTINFO: c_int = 1;
+
+/// A kevent as stored by the kernel (`kevent_qos_s`).
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct KeventQos {
+    pub ident: u64,
+    pub filter: i16,
+    pub flags: u16,
+    pub qos: i32,
+    pub fflags: u32,
+    pub xflags: u32,
+    pub data: i64,
+    pub udata: u64,
+    pub ext: [u64; 4],
+}
+
+/// A kevent registered on a kqueue, with its kernel state (`kevent_extinfo`). See
+/// [`proc_pidfdkqueue_extinfo`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct KeventExtInfo {
+    pub kqext_kev: KeventQos,
+    pub kqext_sdata: u64,
+    pub kqext_status: c_int,
+    pub kqext_sfflags: c_int,
+    pub kqext_reserved: [u64; 2],
+}
+
+/// List the kevents registered on a kqueue file descriptor of a process.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// for fd in proc_pidinfo_list_self::<ProcFDInfo>().unwrap() {
+///     if fd.fd_type() == ProcFDType::KQUEUE {
+///         for kevent in proc_pidfdkqueue_extinfo(getpid(), fd.proc_fd).unwrap() {
+///             println!("{:?}", kevent.kqext_kev);
+///         }
+///     }
+/// }
+/// ```
+pub fn proc_pidfdkqueue_extinfo(pid: Pid, fd: Fd) -> Result<Vec<KeventExtInfo>, std::io::Error> {
+    counted_list(PROC_PIDFDKQUEUE_KNOTES_MAX, |buffer, buffersize| {
+        // SAFETY: The kernel writes at most `buffersize` bytes.
+        unsafe {
+            super::ffi::proc_pidfdinfo(
+                pid.0 as _,
+                fd.0,
+                ProcPidFdInfoFlavor::PROC_PIDFDKQUEUE_EXTINFO as c_int,
+                buffer,
+                buffersize,
+            )
+        }
+    })
+}
+
+/// The ID of a dynamically-allocated kqueue (`kqueue_id_t`), such as the kqueue backing a
+/// dispatch workloop. These have no file descriptor. See [`proc_pidlistdynkqueues`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct DynKqueueId(pub u64);
+
+/// General information about a kqueue (`kqueue_info`).
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct KqueueInfo {
+    pub kq_stat: VInfoStat,
+    /// `PROC_KQUEUE_*` flags, eg: [`KqueueInfo::PROC_KQUEUE_WORKLOOP`].
+    pub kq_state: u32,
+    pub rfu_1: u32,
+}
+
+impl KqueueInfo {
+    pub const PROC_KQUEUE_SELECT: u32 = 0x01;
+    pub const PROC_KQUEUE_SLEEP: u32 = 0x02;
+    pub const PROC_KQUEUE_32: u32 = 0x08;
+    pub const PROC_KQUEUE_64: u32 = 0x10;
+    pub const PROC_KQUEUE_QOS: u32 = 0x20;
+    /// A kqueue serviced by the workqueue, eg: the process's default dispatch kqueue.
+    pub const PROC_KQUEUE_WORKQ: u32 = 0x40;
+    /// A dispatch workloop.
+    pub const PROC_KQUEUE_WORKLOOP: u32 = 0x80;
+}
+
+/// Information about a dynamically-allocated kqueue (`kqueue_dyninfo`). See
+/// [`proc_piddynkqueueinfo`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct KqueueDynInfo {
+    pub kqdi_info: KqueueInfo,
+    /// The thread currently servicing the workloop, if any.
+    pub kqdi_servicer: u64,
+    /// The thread that owns the workloop, if any.
+    pub kqdi_owner: u64,
+    pub kqdi_sync_waiters: u32,
+    pub kqdi_sync_waiter_qos: u8,
+    pub kqdi_async_qos: u8,
+    pub kqdi_request_state: u16,
+    pub kqdi_events_qos: u8,
+    pub kqdi_pri: u8,
+    pub kqdi_pol: u8,
+    pub kqdi_cpupercent: u8,
+    pub _kqdi_reserved0: [u8; 4],
+    pub _kqdi_reserved1: [u64; 4],
+}
+
+/// List the dynamically-allocated kqueues of a process, such as dispatch workloops.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// for id in proc_pidlistdynkqueues(getpid()).unwrap() {
+///     let info = proc_piddynkqueueinfo(getpid(), id).unwrap();
+///     let kevents = proc_piddynkqueue_extinfo(getpid(), id).unwrap();
+///     println!("{:?}: {:?} with {} kevents", id, info, kevents.len());
+/// }
+/// ```
+pub fn proc_pidlistdynkqueues(pid: Pid) -> Result<Vec<DynKqueueId>, std::io::Error> {
+    counted_list(PROC_PIDDYNKQUEUES_MAX, |buffer, buffersize| {
+        // SAFETY: The kernel writes at most `buffersize` bytes.
+        unsafe {
+            super::ffi::proc_pidinfo(
+                pid.0 as _,
+                ProcPidInfoFlavor::PROC_PIDLISTDYNKQUEUES as c_int,
+                0,
+                buffer,
+                buffersize,
+            )
+        }
+    })
+}
+
+/// Get information about a dynamically-allocated kqueue of a process. Returns `None` if the
+/// kernel returned no data.
+pub fn proc_piddynkqueueinfo(
+    pid: Pid,
+    id: DynKqueueId,
+) -> Result<Option<KqueueDynInfo>, std::io::Error> {
+    let mut value = std::mem::MaybeUninit::<KqueueDynInfo>::uninit();
+    let buffersize = std::mem::size_of::<KqueueDynInfo>() as c_int;
+    // SAFETY: We check the size of the return value to ensure it's valid.
+    unsafe {
+        let res = super::libproc_call(|| {
+            ffi::proc_piddynkqueueinfo(
+                pid.0 as _,
+                PROC_PIDDYNKQUEUE_INFO,
+                id.0,
+                value.as_mut_ptr() as *mut c_void,
+                buffersize,
+            )
+        })? as c_int;
+        if res == 0 {
+            return Ok(None);
+        }
+        if res != buffersize {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Unexpected buffer size {res} != {buffersize}"),
+            ));
+        }
+        Ok(Some(value.assume_init()))
+    }
+}
+
+/// List the kevents registered on a dynamically-allocated kqueue of a process.
+pub fn proc_piddynkqueue_extinfo(
+    pid: Pid,
+    id: DynKqueueId,
+) -> Result<Vec<KeventExtInfo>, std::io::Error> {
+    counted_list(PROC_PIDFDKQUEUE_KNOTES_MAX, |buffer, buffersize| {
+        // SAFETY: The kernel writes at most `buffersize` bytes.
+        unsafe {
+            ffi::proc_piddynkqueueinfo(
+                pid.0 as _,
+                PROC_PIDDYNKQUEUE_EXTINFO,
+                id.0,
+                buffer,
+                buffersize,
+            )
+        }
+    })
+}
+
+/// Read a list from a call that returns the total number of entries, which may exceed the
+/// buffer, rather than the number of bytes written.
+fn counted_list<T>(
+    max_entries: usize,
+    mut call: impl FnMut(*mut c_void, c_int) -> c_int,
+) -> Result<Vec<T>, std::io::Error> {
+    let mut buffer = Vec::<T>::with_capacity(16);
+    for _ in 0..4 {
+        let capacity = buffer.capacity();
+        // libproc returns 0 both for an empty list and on failure, so clear errno to tell
+        // them apart.
+        // SAFETY: Clearing errno has no memory safety requirements.
+        unsafe { *libc::__error() = 0 };
+        let res = call(
+            buffer.as_mut_ptr() as *mut c_void,
+            (capacity * std::mem::size_of::<T>()) as c_int,
+        );
+        if res <= 0 {
+            let err = last_os_error();
+            if err.raw_os_error() == Some(0) {
+                return Ok(vec![]);
+            }
+            return Err(err);
+        }
+        let count = res as usize;
+        if count <= capacity || capacity >= max_entries {
+            // SAFETY: The kernel initialized this many entries.
+            unsafe { buffer.set_len(count.min(capacity)) };
+            return Ok(buffer);
+        }
+        buffer.reserve_exact((count + count / 8 + 16).min(max_entries));
+    }
+    Err(std::io::Error::other("List kept growing"))
+}
+
+/// A file or directory that a process is watching with an `EVFILT_VNODE` kevent. See
+/// [`watched_vnodes`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchedVnode {
+    /// The kqueue the watch is registered on.
+    pub kqueue: Fd,
+    /// The watched file descriptor.
+    pub fd: Fd,
+    /// The path of the watched file, if it could be resolved.
+    pub path: Option<PathBuf>,
+    /// The `NOTE_*` events being watched for, eg: [`libc::NOTE_WRITE`].
+    pub fflags: u32,
+}
+
+/// List the files and directories that a process is watching for changes through kqueues.
+///
+/// This covers `EVFILT_VNODE` registrations on kqueue file descriptors, which is how
+/// `FSEvents`-free file watchers (editors, sync clients, build tools) notice changes. Watches on
+/// dispatch workloops are not included.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// for watch in watched_vnodes(getpid()).unwrap() {
+///     println!("{:?} is watching {:?}", watch.kqueue, watch.path);
+/// }
+/// ```
+pub fn watched_vnodes(pid: Pid) -> Result<Vec<WatchedVnode>, std::io::Error> {
+    let mut watches = vec![];
+    for kqueue in proc_pidinfo_list::<ProcFDInfo>(pid)? {
+        if kqueue.fd_type() != ProcFDType::KQUEUE {
+            continue;
+        }
+        // The kqueue may have been closed since the fd list was read.
+        let Ok(kevents) = proc_pidfdkqueue_extinfo(pid, kqueue.proc_fd) else {
+            continue;
+        };
+        for kevent in kevents {
+            let kev = kevent.kqext_kev;
+            if kev.filter != libc::EVFILT_VNODE {
+                continue;
+            }
+            let fd = Fd(kev.ident as c_int);
+            let path = proc_pidfdinfo::<VnodeFdInfoWithPath>(pid, fd)
+                .ok()
+                .flatten()
+                .and_then(|info| info.path().ok().map(|path| path.to_owned()));
+            watches.push(WatchedVnode {
+                kqueue: kqueue.proc_fd,
+                fd,
+                path,
+                fflags: kev.fflags,
+            });
+        }
+    }
+    Ok(watches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::darwin::getpid;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+
+    #[test]
+    fn test_kevent_sizes() {
+        assert_eq!(std::mem::size_of::<KeventQos>(), 72);
+        assert_eq!(std::mem::size_of::<KeventExtInfo>(), 104);
+        assert_eq!(std::mem::size_of::<KqueueDynInfo>(), 208);
+    }
+
+    #[test]
+    fn test_dyn_kqueues_self() {
+        for id in proc_pidlistdynkqueues(getpid()).unwrap() {
+            // Workloops can be torn down between the list and the query.
+            if let Ok(Some(info)) = proc_piddynkqueueinfo(getpid(), id) {
+                assert_ne!(
+                    info.kqdi_info.kq_state & KqueueInfo::PROC_KQUEUE_WORKLOOP,
+                    0
+                );
+            }
+        }
+        assert!(proc_piddynkqueueinfo(getpid(), DynKqueueId(u64::MAX)).is_err());
+    }
+
+    #[test]
+    fn test_watched_vnodes_self() {
+        let path = std::env::temp_dir().join(format!("proc_pidinfo_kqueue_{}", getpid().0));
+        let file = std::fs::File::create(&path).unwrap();
+        // SAFETY: kqueue has no memory safety requirements, and we take ownership of the fd.
+        let kq = unsafe { OwnedFd::from_raw_fd(libc::kqueue()) };
+        let change = libc::kevent {
+            ident: file.as_raw_fd() as _,
+            filter: libc::EVFILT_VNODE,
+            flags: libc::EV_ADD | libc::EV_CLEAR,
+            fflags: libc::NOTE_WRITE | libc::NOTE_DELETE,
+            data: 0,
+            udata: std::ptr::null_mut(),
+        };
+        // SAFETY: We pass one valid change and no output buffer.
+        let res = unsafe {
+            libc::kevent(
+                kq.as_raw_fd(),
+                &change,
+                1,
+                std::ptr::null_mut(),
+                0,
+                std::ptr::null(),
+            )
+        };
+        assert_eq!(res, 0);
+
+        // SAFETY: kqueue has no memory safety requirements, and we take ownership of the fd.
+        let empty = unsafe { OwnedFd::from_raw_fd(libc::kqueue()) };
+        let kevents = proc_pidfdkqueue_extinfo(getpid(), Fd(empty.as_raw_fd())).unwrap();
+        assert!(kevents.is_empty());
+        let kevents = proc_pidfdkqueue_extinfo(getpid(), Fd(kq.as_raw_fd())).unwrap();
+        assert_eq!(kevents.len(), 1);
+
+        let watches = watched_vnodes(getpid()).unwrap();
+        let watch = watches
+            .iter()
+            .find(|watch| watch.kqueue == Fd(kq.as_raw_fd()))
+            .unwrap();
+        assert_eq!(watch.fd, Fd(file.as_raw_fd()));
+        assert_eq!(watch.path, Some(path.canonicalize().unwrap()));
+        assert_eq!(watch.fflags, libc::NOTE_WRITE | libc::NOTE_DELETE);
+        std::fs::remove_file(path).unwrap();
+    }
+}
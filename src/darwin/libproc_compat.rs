@@ -0,0 +1,99 @@
+// Conversions to and from the types of the `libproc` crate, for code moving between the two
+// crates one call at a time. Both crates declare the same `<sys/proc_info.h>` structs with
+// `#[repr(C)]`, so each conversion is a copy of the same bytes.
+
+use super::{
+    Pid, ProcBSDInfo, ProcFDInfo, ProcFileInfo, ProcTaskAllInfo, ProcTaskInfo, ProcThreadInfo,
+    SocketFdInfo,
+};
+
+/// Implement `From` in both directions between a type of this crate and its `libproc`
+/// equivalent.
+macro_rules! same_layout {
+    ($ours:ty, $theirs:ty) => {
+        impl From<$theirs> for $ours {
+            fn from(value: $theirs) -> Self {
+                // SAFETY: Both are `#[repr(C)]` declarations of the same kernel struct, made up of
+                // integers for which every bit pattern is valid. `transmute` checks the sizes.
+                unsafe { std::mem::transmute::<$theirs, $ours>(value) }
+            }
+        }
+
+        impl From<$ours> for $theirs {
+            fn from(value: $ours) -> Self {
+                // SAFETY: As above.
+                unsafe { std::mem::transmute::<$ours, $theirs>(value) }
+            }
+        }
+    };
+}
+
+same_layout!(ProcBSDInfo, libproc::bsd_info::BSDInfo);
+same_layout!(ProcTaskInfo, libproc::task_info::TaskInfo);
+same_layout!(ProcTaskAllInfo, libproc::task_info::TaskAllInfo);
+same_layout!(ProcThreadInfo, libproc::thread_info::ThreadInfo);
+same_layout!(ProcFDInfo, libproc::file_info::ProcFDInfo);
+same_layout!(ProcFileInfo, libproc::net_info::ProcFileInfo);
+same_layout!(SocketFdInfo, libproc::net_info::SocketFDInfo);
+
+/// `libproc` takes pids as `i32`.
+impl From<Pid> for i32 {
+    fn from(pid: Pid) -> Self {
+        // Pids are at most `PID_MAX` (99999).
+        pid.0 as i32
+    }
+}
+
+/// Fails for negative pids.
+impl TryFrom<i32> for Pid {
+    type Error = std::num::TryFromIntError;
+
+    fn try_from(pid: i32) -> Result<Self, Self::Error> {
+        u32::try_from(pid).map(Pid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::darwin::{getpid, proc_pidinfo, proc_pidinfo_list};
+
+    #[test]
+    fn test_round_trip() {
+        let pid = getpid();
+        let theirs =
+            libproc::proc_pid::pidinfo::<libproc::bsd_info::BSDInfo>(pid.into(), 0).unwrap();
+        let info = ProcBSDInfo::from(theirs);
+        assert_eq!(info.pbi_pid, pid);
+        assert_eq!(
+            info.pbi_ppid,
+            proc_pidinfo::<ProcBSDInfo>(pid).unwrap().unwrap().pbi_ppid
+        );
+        let theirs = libproc::bsd_info::BSDInfo::from(info);
+        assert_eq!(theirs.pbi_pid, pid.0);
+
+        let task = proc_pidinfo::<ProcTaskInfo>(pid).unwrap().unwrap();
+        let theirs = libproc::task_info::TaskInfo::from(task);
+        assert_eq!(theirs.pti_virtual_size, task.pti_virtual_size);
+
+        let fds = libproc::proc_pid::listpidinfo::<libproc::file_info::ListFDs>(pid.into(), 1024)
+            .unwrap()
+            .into_iter()
+            .map(ProcFDInfo::from)
+            .map(|fd| fd.proc_fd)
+            .collect::<Vec<_>>();
+        let ours = proc_pidinfo_list::<ProcFDInfo>(pid)
+            .unwrap()
+            .into_iter()
+            .map(|fd| fd.proc_fd)
+            .collect::<Vec<_>>();
+        assert_eq!(fds, ours);
+    }
+
+    #[test]
+    fn test_pid() {
+        assert_eq!(i32::from(Pid(1)), 1);
+        assert_eq!(Pid::try_from(42), Ok(Pid(42)));
+        assert!(Pid::try_from(-1).is_err());
+    }
+}
@@ -0,0 +1,148 @@
+use std::net::SocketAddr;
+
+use super::{
+    proc_listallpids, proc_pidfdinfo, proc_pidinfo, proc_pidinfo_list, FdEndpoint, Pid,
+    ProcBSDShortInfo, ProcFDInfo, ProcFDType, SocketFdInfo, SocketInfo, TcpState,
+};
+
+/// Find the socket descriptors of every process that match a predicate, skipping processes
+/// that can't be read or exit mid-scan.
+fn find_sockets(
+    mut matches: impl FnMut(&SocketInfo) -> bool,
+) -> Result<Vec<FdEndpoint>, std::io::Error> {
+    let mut found = vec![];
+    for pid in proc_listallpids()? {
+        let Ok(fds) = proc_pidinfo_list::<ProcFDInfo>(pid) else {
+            continue;
+        };
+        let mut name = None;
+        for fd in fds {
+            if fd.fd_type() != ProcFDType::SOCKET {
+                continue;
+            }
+            let Ok(Some(info)) = proc_pidfdinfo::<SocketFdInfo>(pid, fd.proc_fd) else {
+                continue;
+            };
+            if matches(&info.psi) {
+                let name = name.get_or_insert_with(|| process_name(pid));
+                found.push(FdEndpoint {
+                    pid,
+                    fd: fd.proc_fd,
+                    name: name.clone(),
+                });
+            }
+        }
+    }
+    Ok(found)
+}
+
+fn process_name(pid: Pid) -> String {
+    match proc_pidinfo::<ProcBSDShortInfo>(pid) {
+        Ok(Some(info)) => info.comm().unwrap_or_default().to_owned(),
+        _ => String::new(),
+    }
+}
+
+/// Find the processes listening on a local port: TCP sockets in the `LISTEN` state, and UDP
+/// sockets bound to the port. This is the equivalent of `lsof -i :<port>`, without the
+/// connected sockets.
+///
+/// Processes the current process isn't allowed to inspect (without root, those of other
+/// users) are skipped.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// for listener in find_listeners(8080).unwrap() {
+///     println!("{} ({}) fd {}", listener.pid.0, listener.name, listener.fd.0);
+/// }
+/// ```
+pub fn find_listeners(port: u16) -> Result<Vec<FdEndpoint>, std::io::Error> {
+    find_sockets(|info| {
+        let Some(inet) = info.inet() else {
+            return false;
+        };
+        if inet.local_port() != port {
+            return false;
+        }
+        match info.tcp() {
+            Some(tcp) => tcp.state() == Ok(TcpState::LISTEN),
+            None => info.soi_protocol == libc::IPPROTO_UDP,
+        }
+    })
+}
+
+/// Find the processes with a TCP or UDP socket using an address as either end: sockets bound
+/// to it (including sockets bound to every address on the same port), and sockets connected
+/// to it.
+///
+/// Processes the current process isn't allowed to inspect (without root, those of other
+/// users) are skipped.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let addr = "127.0.0.1:8080".parse().unwrap();
+/// for socket in find_by_socket(addr).unwrap() {
+///     println!("{} ({}) fd {}", socket.pid.0, socket.name, socket.fd.0);
+/// }
+/// ```
+pub fn find_by_socket(addr: SocketAddr) -> Result<Vec<FdEndpoint>, std::io::Error> {
+    find_sockets(|info| {
+        let Some(inet) = info.inet() else {
+            return false;
+        };
+        let local = inet.local_addr();
+        inet.foreign_addr() == addr
+            || local == addr
+            || (local.port() == addr.port()
+                && local.ip().is_unspecified()
+                && local.is_ipv4() == addr.is_ipv4())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{TcpListener, TcpStream, UdpSocket};
+    use std::os::fd::AsRawFd;
+
+    use super::*;
+    use crate::darwin::{getpid, Fd};
+
+    fn contains(found: &[FdEndpoint], fd: &impl AsRawFd) -> bool {
+        found
+            .iter()
+            .any(|endpoint| endpoint.pid == getpid() && endpoint.fd == Fd(fd.as_raw_fd()))
+    }
+
+    #[test]
+    fn test_find_listeners() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let client = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        let found = find_listeners(port).unwrap();
+        assert!(contains(&found, &listener));
+        assert!(!contains(&found, &client));
+
+        let udp = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let found = find_listeners(udp.local_addr().unwrap().port()).unwrap();
+        assert!(contains(&found, &udp));
+    }
+
+    #[test]
+    fn test_find_by_socket() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+
+        let found = find_by_socket(listener.local_addr().unwrap()).unwrap();
+        assert!(contains(&found, &listener));
+        assert!(contains(&found, &client));
+        assert!(contains(&found, &server));
+
+        let found = find_by_socket(client.local_addr().unwrap()).unwrap();
+        assert!(contains(&found, &client));
+        assert!(contains(&found, &server));
+        assert!(!contains(&found, &listener));
+    }
+}
@@ -0,0 +1,137 @@
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+use libc::{c_int, c_void};
+
+use super::{last_os_error, Pid};
+
+mod ffi {
+    use libc::{c_char, c_int, c_void};
+
+    extern "C" {
+        pub fn proc_listpidspath(
+            r#type: u32,
+            typeinfo: u32,
+            path: *const c_char,
+            pathflags: u32,
+            buffer: *mut c_void,
+            buffersize: c_int,
+        ) -> c_int;
+    }
+}
+
+/// Options for [`proc_listpidspath`] (`PROC_LISTPIDSPATH_*` in `<libproc.h>`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ListPidsPathFlags(u32);
+
+impl ListPidsPathFlags {
+    /// Treat the path as a volume, and find processes using any file on it, eg: to find what
+    /// is preventing an unmount.
+    pub const PATH_IS_VOLUME: Self = Self(0x0001);
+    /// Ignore files opened with `O_EVTONLY`, which only watch for changes and don't prevent
+    /// an unmount.
+    pub const EXCLUDE_EVTONLY: Self = Self(0x0002);
+
+    /// The raw flags.
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Returns true if all flags in `other` are also set.
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl std::ops::BitOr for ListPidsPathFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// List the processes that are using a file or volume: with it open, as their current or root
+/// directory, or mapped into memory.
+///
+/// The kernel does the matching, which is much cheaper than reading the descriptors of every
+/// process. Processes the current process isn't allowed to inspect (without root, those of
+/// other users) are skipped.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let pids = proc_listpidspath("/", ListPidsPathFlags::PATH_IS_VOLUME).unwrap();
+/// println!("{} processes are using the root volume", pids.len());
+///
+/// let exe = std::env::current_exe().unwrap();
+/// let pids = proc_listpidspath(&exe, ListPidsPathFlags::EXCLUDE_EVTONLY).unwrap();
+/// println!("{:?}", pids);
+/// ```
+pub fn proc_listpidspath(
+    path: impl AsRef<Path>,
+    flags: ListPidsPathFlags,
+) -> Result<Vec<Pid>, std::io::Error> {
+    const PROC_ALL_PIDS: u32 = 1;
+    let path = CString::new(path.as_ref().as_os_str().as_bytes())
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))?;
+    let entry_size = std::mem::size_of::<Pid>();
+    let mut buffer = Vec::<Pid>::new();
+
+    // SAFETY: We check the size of the return value to ensure it's valid.
+    unsafe {
+        // The number of processes bounds the number of matches.
+        let res = super::ffi::proc_listpids(PROC_ALL_PIDS, 0, std::ptr::null_mut(), 0);
+        if res < 0 {
+            return Err(last_os_error());
+        }
+        let mut entries = res as usize / entry_size + 64;
+
+        loop {
+            buffer.reserve_exact(entries);
+            let buffersize = (buffer.capacity() * entry_size) as c_int;
+            let res = ffi::proc_listpidspath(
+                PROC_ALL_PIDS,
+                0,
+                path.as_ptr(),
+                flags.0,
+                buffer.as_mut_ptr() as *mut c_void,
+                buffersize,
+            );
+            if res < 0 {
+                return Err(last_os_error());
+            }
+            if res == buffersize {
+                entries = buffer.capacity() * 2;
+                continue;
+            }
+            buffer.set_len(res as usize / entry_size);
+            return Ok(buffer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::darwin::getpid;
+
+    #[test]
+    fn test_proc_listpidspath() {
+        let path = std::env::temp_dir().join(format!("proc_pidinfo-{}.txt", getpid().0));
+        let file = std::fs::File::create(&path).unwrap();
+        let pids = proc_listpidspath(&path, ListPidsPathFlags::default()).unwrap();
+        assert_eq!(pids, vec![getpid()]);
+
+        let flags = ListPidsPathFlags::PATH_IS_VOLUME | ListPidsPathFlags::EXCLUDE_EVTONLY;
+        assert!(flags.contains(ListPidsPathFlags::PATH_IS_VOLUME));
+        let pids = proc_listpidspath(&path, flags).unwrap();
+        assert!(pids.contains(&getpid()));
+
+        drop(file);
+        let pids = proc_listpidspath(&path, ListPidsPathFlags::default()).unwrap();
+        assert!(!pids.contains(&getpid()));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
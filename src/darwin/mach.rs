@@ -0,0 +1,936 @@
+//! Mach `task_info` and `thread_info` queries, which report statistics that `proc_pidinfo`
+//! doesn't, such as the physical footprint shown by Activity Monitor. Requires the `mach`
+//! feature.
+//!
+//! These need a port for the target task. The current process always has one, but other
+//! processes usually need root (and, with SIP, an entitlement), so callers should be prepared
+//! for [`std::io::ErrorKind::PermissionDenied`].
+
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use libc::{kern_return_t, mach_msg_type_number_t, mach_port_t};
+
+use super::threads::{task_threads, thread_identifier};
+use super::{
+    getpid, proc_pidinfo_list, proc_pidregionpathinfo, proc_pidthreadinfo, FilePort, Pid,
+    ProcFilePortInfo, ThreadHandle, ValueError,
+};
+
+mod ffi {
+    use libc::{kern_return_t, mach_port_t};
+
+    extern "C" {
+        pub static mach_task_self_: mach_port_t;
+        pub fn task_name_for_pid(
+            target_tport: mach_port_t,
+            pid: libc::pid_t,
+            tn: *mut mach_port_t,
+        ) -> kern_return_t;
+        pub fn task_read_for_pid(
+            target_tport: mach_port_t,
+            pid: libc::pid_t,
+            t: *mut mach_port_t,
+        ) -> kern_return_t;
+        pub fn mach_port_deallocate(task: mach_port_t, name: mach_port_t) -> kern_return_t;
+        pub fn mach_port_space_info(
+            space: mach_port_t,
+            space_info: *mut super::IpcInfoSpace,
+            table_info: *mut *mut super::IpcInfoName,
+            table_info_count: *mut libc::mach_msg_type_number_t,
+            tree_info: *mut *mut u8,
+            tree_info_count: *mut libc::mach_msg_type_number_t,
+        ) -> kern_return_t;
+        pub fn mach_vm_read_overwrite(
+            target_task: mach_port_t,
+            address: u64,
+            size: u64,
+            data: u64,
+            outsize: *mut u64,
+        ) -> kern_return_t;
+    }
+}
+
+const TASK_VM_INFO: libc::task_flavor_t = 22;
+const TASK_DYLD_INFO: libc::task_flavor_t = 17;
+const TASK_DYLD_ALL_IMAGE_INFO_64: i32 = 1;
+
+/// `struct task_dyld_info`, which locates dyld's image list in the task.
+#[repr(C, packed(4))]
+#[derive(Default)]
+struct TaskDyldInfo {
+    all_image_info_addr: u64,
+    all_image_info_size: u64,
+    all_image_info_format: i32,
+}
+
+/// The size of `struct dyld_image_info` in a 64-bit process.
+const DYLD_IMAGE_INFO_SIZE: usize = 24;
+
+/// `struct ipc_info_space`.
+#[repr(C)]
+#[derive(Default)]
+struct IpcInfoSpace {
+    iis_genno_mask: u32,
+    iis_table_size: u32,
+    iis_table_next: u32,
+    iis_tree_size: u32,
+    iis_tree_small: u32,
+    iis_tree_hash: u32,
+}
+
+/// `struct ipc_info_name`, one entry in a task's port name table.
+#[repr(C)]
+struct IpcInfoName {
+    iin_name: u32,
+    iin_collision: i32,
+    iin_type: u32,
+    iin_urefs: u32,
+    iin_object: u32,
+    iin_next: u32,
+    iin_hash: u32,
+}
+
+/// The size of `struct ipc_info_tree_name`, which the kernel no longer fills in.
+const IPC_INFO_TREE_NAME_SIZE: usize = 36;
+
+/// The port of the current task.
+fn mach_task_self() -> mach_port_t {
+    // SAFETY: The task port is set up before any Rust code runs, and never changes.
+    unsafe { ffi::mach_task_self_ }
+}
+
+/// Convert a Mach error to an I/O error.
+fn kern_error(kr: kern_return_t) -> std::io::Error {
+    let kind = match kr {
+        libc::KERN_PROTECTION_FAILURE | libc::KERN_NO_ACCESS | libc::KERN_FAILURE => {
+            std::io::ErrorKind::PermissionDenied
+        }
+        libc::KERN_INVALID_ARGUMENT => std::io::ErrorKind::InvalidInput,
+        _ => std::io::ErrorKind::Other,
+    };
+    std::io::Error::new(kind, format!("Mach error {kr}"))
+}
+
+/// A Mach port for a task, released on drop.
+#[derive(Debug)]
+pub struct TaskPort {
+    port: mach_port_t,
+    pid: Pid,
+    /// A name port only supports a few `task_info` flavors.
+    is_name: bool,
+}
+
+impl TaskPort {
+    /// The port of the current process.
+    pub fn current() -> Self {
+        Self {
+            port: mach_task_self(),
+            pid: getpid(),
+            is_name: false,
+        }
+    }
+
+    /// Get a port for a process with `task_for_pid`, falling back to a less capable name
+    /// port from `task_name_for_pid`. Fails with [`std::io::ErrorKind::PermissionDenied`] if
+    /// neither is available.
+    pub fn for_pid(pid: Pid) -> Result<Self, std::io::Error> {
+        if pid == getpid() {
+            return Ok(Self::current());
+        }
+        let mut port = 0;
+        // SAFETY: The output is a single port.
+        let kr = unsafe { libc::task_for_pid(mach_task_self(), pid.0 as _, &mut port) };
+        if kr == libc::KERN_SUCCESS {
+            return Ok(Self {
+                port,
+                pid,
+                is_name: false,
+            });
+        }
+        // SAFETY: The output is a single port.
+        let kr = unsafe { ffi::task_name_for_pid(mach_task_self(), pid.0 as _, &mut port) };
+        if kr == libc::KERN_SUCCESS {
+            return Ok(Self {
+                port,
+                pid,
+                is_name: true,
+            });
+        }
+        Err(kern_error(kr))
+    }
+
+    /// Returns true if this is a name port, which can't be used to inspect the task.
+    pub fn is_name_port(&self) -> bool {
+        self.is_name
+    }
+
+    /// The process this port belongs to.
+    pub fn pid(&self) -> Pid {
+        self.pid
+    }
+
+    /// The raw port, which remains owned by this struct.
+    pub fn as_raw(&self) -> mach_port_t {
+        self.port
+    }
+
+    /// Get the VM statistics of the task.
+    pub fn vm_info(&self) -> Result<TaskVmInfo, std::io::Error> {
+        // SAFETY: TaskVmInfo is plain integers.
+        let mut info = unsafe { std::mem::zeroed::<TaskVmInfo>() };
+        let mut count = (std::mem::size_of::<TaskVmInfo>() / std::mem::size_of::<libc::integer_t>())
+            as mach_msg_type_number_t;
+        // SAFETY: The count is the size of the output in integers. The kernel fills in as many
+        // revisions as it supports and updates the count.
+        let kr = unsafe {
+            libc::task_info(
+                self.port,
+                TASK_VM_INFO,
+                &mut info as *mut TaskVmInfo as libc::task_info_t,
+                &mut count,
+            )
+        };
+        if kr != libc::KERN_SUCCESS {
+            return Err(kern_error(kr));
+        }
+        Ok(info)
+    }
+
+    /// Copy memory from the task into `buffer`. Fails with
+    /// [`std::io::ErrorKind::InvalidInput`] if any of the range isn't mapped.
+    pub fn read_memory(&self, address: u64, buffer: &mut [u8]) -> Result<(), std::io::Error> {
+        let mut size = 0;
+        // SAFETY: The kernel writes at most `buffer.len()` bytes into the buffer.
+        let kr = unsafe {
+            ffi::mach_vm_read_overwrite(
+                self.port,
+                address,
+                buffer.len() as u64,
+                buffer.as_mut_ptr() as u64,
+                &mut size,
+            )
+        };
+        if kr != libc::KERN_SUCCESS {
+            return Err(kern_error(kr));
+        }
+        if size != buffer.len() as u64 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                format!("Short read of {size} bytes at {address:#x}"),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Copy a NUL-terminated string of at most `MAXPATHLEN` bytes from the task.
+    fn read_c_string(&self, mut address: u64) -> Result<Vec<u8>, std::io::Error> {
+        // Read a page at a time, as the string may end just before an unmapped page.
+        const CHUNK: u64 = 4096;
+        let mut string = vec![];
+        while string.len() < libc::MAXPATHLEN as usize {
+            let mut chunk = vec![0; (CHUNK - address % CHUNK) as usize];
+            self.read_memory(address, &mut chunk)?;
+            if let Some(end) = chunk.iter().position(|&b| b == 0) {
+                string.extend_from_slice(&chunk[..end]);
+                return Ok(string);
+            }
+            string.extend_from_slice(&chunk);
+            address += chunk.len() as u64;
+        }
+        Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "Unterminated string",
+        ))
+    }
+
+    /// List the images that dyld has loaded into the task, from its `dyld_all_image_infos`.
+    /// The main executable comes first. dyld itself isn't included.
+    ///
+    /// Only 64-bit tasks are supported. Name ports can't read memory, and fail with
+    /// [`std::io::ErrorKind::PermissionDenied`].
+    pub fn loaded_images(&self) -> Result<Vec<LoadedImage>, std::io::Error> {
+        if self.is_name {
+            return Err(kern_error(libc::KERN_PROTECTION_FAILURE));
+        }
+        let mut dyld = TaskDyldInfo::default();
+        let mut count = (std::mem::size_of::<TaskDyldInfo>()
+            / std::mem::size_of::<libc::integer_t>())
+            as mach_msg_type_number_t;
+        // SAFETY: The count is the size of the output in integers.
+        let kr = unsafe {
+            libc::task_info(
+                self.port,
+                TASK_DYLD_INFO,
+                &mut dyld as *mut TaskDyldInfo as libc::task_info_t,
+                &mut count,
+            )
+        };
+        if kr != libc::KERN_SUCCESS {
+            return Err(kern_error(kr));
+        }
+        if dyld.all_image_info_format != TASK_DYLD_ALL_IMAGE_INFO_64 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "Only 64-bit tasks are supported",
+            ));
+        }
+
+        // struct dyld_all_image_infos { uint32_t version; uint32_t infoArrayCount;
+        // const struct dyld_image_info *infoArray; ... }
+        let mut header = [0_u8; 16];
+        let mut array = 0;
+        let mut image_count = 0;
+        // dyld clears the array pointer while it updates the list.
+        for _ in 0..100 {
+            self.read_memory(dyld.all_image_info_addr, &mut header)?;
+            image_count = u32::from_ne_bytes(header[4..8].try_into().unwrap()) as usize;
+            array = u64::from_ne_bytes(header[8..16].try_into().unwrap());
+            if array != 0 {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        if array == 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::WouldBlock,
+                "dyld is updating its image list",
+            ));
+        }
+
+        // struct dyld_image_info { const struct mach_header *imageLoadAddress;
+        // const char *imageFilePath; uintptr_t imageFileModDate; }
+        let mut infos = vec![0_u8; image_count * DYLD_IMAGE_INFO_SIZE];
+        self.read_memory(array, &mut infos)?;
+        let mut images = Vec::with_capacity(image_count);
+        for info in infos.chunks_exact(DYLD_IMAGE_INFO_SIZE) {
+            let load_address = u64::from_ne_bytes(info[0..8].try_into().unwrap());
+            let path = u64::from_ne_bytes(info[8..16].try_into().unwrap());
+            let path = self.read_c_string(path)?;
+            images.push(LoadedImage {
+                load_address,
+                path: PathBuf::from(OsStr::from_bytes(&path)),
+            });
+        }
+        Ok(images)
+    }
+
+    /// Get the scheduler statistics of every thread in the task, named with
+    /// [`super::proc_pidthreadinfo`]. Threads that exit mid-scan are skipped.
+    ///
+    /// Name ports can't list threads, and fail with [`std::io::ErrorKind::PermissionDenied`].
+    pub fn threads(&self) -> Result<Vec<MachThreadInfo>, std::io::Error> {
+        if self.is_name {
+            return Err(kern_error(libc::KERN_PROTECTION_FAILURE));
+        }
+        // SAFETY: The port is a task port, valid until we're dropped.
+        unsafe { task_threads(self.port, |thread| self.thread_info(thread)) }.map_err(kern_error)
+    }
+
+    /// List the port names in the task's IPC space with `mach_port_space_info`, and match send
+    /// rights to the task's fileports.
+    ///
+    /// Name ports can't read the IPC space, and fail with
+    /// [`std::io::ErrorKind::PermissionDenied`].
+    pub fn ports(&self) -> Result<Vec<MachPortInfo>, std::io::Error> {
+        if self.is_name {
+            return Err(kern_error(libc::KERN_PROTECTION_FAILURE));
+        }
+        let mut space = IpcInfoSpace::default();
+        let mut table: *mut IpcInfoName = std::ptr::null_mut();
+        let mut table_count: mach_msg_type_number_t = 0;
+        let mut tree: *mut u8 = std::ptr::null_mut();
+        let mut tree_count: mach_msg_type_number_t = 0;
+        // SAFETY: The outputs are valid, and the kernel allocates the arrays in our address
+        // space, which are freed below.
+        let kr = unsafe {
+            ffi::mach_port_space_info(
+                self.port,
+                &mut space,
+                &mut table,
+                &mut table_count,
+                &mut tree,
+                &mut tree_count,
+            )
+        };
+        if kr != libc::KERN_SUCCESS {
+            return Err(kern_error(kr));
+        }
+        // SAFETY: The kernel returned an array of `table_count` entries.
+        let names = unsafe { std::slice::from_raw_parts(table, table_count as usize) };
+        let fileports = proc_pidinfo_list::<ProcFilePortInfo>(self.pid).unwrap_or_default();
+        let ports = names
+            .iter()
+            .filter(|name| name.iin_type & MachPortRights::ALL.bits() != 0)
+            .map(|name| {
+                let rights = MachPortRights(name.iin_type & MachPortRights::ALL.bits());
+                MachPortInfo {
+                    name: name.iin_name,
+                    rights,
+                    urefs: name.iin_urefs,
+                    object: name.iin_object,
+                    fileport: fileports
+                        .iter()
+                        .find(|port| {
+                            rights.contains(MachPortRights::SEND)
+                                && port.proc_fileport == FilePort(name.iin_name)
+                        })
+                        .copied(),
+                }
+            })
+            .collect();
+        // SAFETY: The arrays were allocated by the kernel for us, with these sizes.
+        unsafe {
+            libc::vm_deallocate(
+                mach_task_self(),
+                table as usize,
+                table_count as usize * std::mem::size_of::<IpcInfoName>(),
+            );
+            libc::vm_deallocate(
+                mach_task_self(),
+                tree as usize,
+                tree_count as usize * IPC_INFO_TREE_NAME_SIZE,
+            );
+        }
+        Ok(ports)
+    }
+
+    /// Read the statistics of one thread, or `None` if it has exited.
+    fn thread_info(&self, thread: libc::thread_act_t) -> Option<MachThreadInfo> {
+        let identifier = thread_identifier(thread)?;
+        // SAFETY: thread_basic_info is plain integers.
+        let mut basic = unsafe { std::mem::zeroed::<libc::thread_basic_info>() };
+        let mut count = libc::THREAD_BASIC_INFO_COUNT;
+        // SAFETY: The output struct and its size in integers are valid.
+        let kr = unsafe {
+            libc::thread_info(
+                thread,
+                libc::THREAD_BASIC_INFO as _,
+                &mut basic as *mut _ as libc::thread_info_t,
+                &mut count,
+            )
+        };
+        if kr != libc::KERN_SUCCESS {
+            return None;
+        }
+
+        let handle = ThreadHandle(identifier.thread_handle);
+        let name = proc_pidthreadinfo(self.pid, handle)
+            .ok()
+            .flatten()
+            .and_then(|info| info.name_string().ok())
+            .filter(|name| !name.is_empty());
+        Some(MachThreadInfo {
+            thread: handle,
+            thread_id: identifier.thread_id,
+            name,
+            user_time: time_value(basic.user_time),
+            system_time: time_value(basic.system_time),
+            cpu_usage: basic.cpu_usage,
+            run_state: basic.run_state,
+            flags: basic.flags,
+            suspend_count: basic.suspend_count,
+            sleep_time: Duration::from_secs(basic.sleep_time.max(0) as u64),
+        })
+    }
+}
+
+fn time_value(time: libc::time_value_t) -> Duration {
+    Duration::from_secs(time.seconds.max(0) as u64)
+        + Duration::from_micros(time.microseconds.max(0) as u64)
+}
+
+impl Drop for TaskPort {
+    fn drop(&mut self) {
+        if self.port != mach_task_self() {
+            // SAFETY: We own a send right to the port.
+            unsafe { ffi::mach_port_deallocate(mach_task_self(), self.port) };
+        }
+    }
+}
+
+/// An image (executable, dylib or bundle) mapped into a process. See [`loaded_images`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadedImage {
+    /// The address of the image's Mach-O header.
+    pub load_address: u64,
+    pub path: PathBuf,
+}
+
+/// The scheduling state of a thread (`TH_STATE_*`).
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(i32)]
+pub enum ThreadRunState {
+    TH_STATE_RUNNING = 1,
+    TH_STATE_STOPPED = 2,
+    TH_STATE_WAITING = 3,
+    /// Waiting without the possibility of being interrupted, eg: on disk I/O.
+    TH_STATE_UNINTERRUPTIBLE = 4,
+    TH_STATE_HALTED = 5,
+}
+
+impl ThreadRunState {
+    fn from_raw(state: i32) -> Result<Self, ValueError> {
+        match state {
+            1 => Ok(ThreadRunState::TH_STATE_RUNNING),
+            2 => Ok(ThreadRunState::TH_STATE_STOPPED),
+            3 => Ok(ThreadRunState::TH_STATE_WAITING),
+            4 => Ok(ThreadRunState::TH_STATE_UNINTERRUPTIBLE),
+            5 => Ok(ThreadRunState::TH_STATE_HALTED),
+            _ => Err(ValueError::UnexpectedEnumValue),
+        }
+    }
+}
+
+/// The thread is swapped out.
+pub const TH_FLAGS_SWAPPED: i32 = 0x1;
+/// The thread is an idle thread.
+pub const TH_FLAGS_IDLE: i32 = 0x2;
+/// The thread is forced idle by the scheduler.
+pub const TH_FLAGS_GLOBAL_FORCED_IDLE: i32 = 0x4;
+
+/// The `cpu_usage` that represents 100% of one CPU.
+pub const TH_USAGE_SCALE: i32 = 1000;
+
+/// Scheduler statistics for a thread (`thread_basic_info`), with its name from
+/// `proc_pidinfo`. See [`TaskPort::threads`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MachThreadInfo {
+    /// The handle to pass to [`super::proc_pidthreadinfo`].
+    pub thread: ThreadHandle,
+    /// The system-wide thread id, as returned by `pthread_threadid_np`.
+    pub thread_id: u64,
+    /// The thread name, or `None` if it's unnamed.
+    pub name: Option<String>,
+    pub user_time: Duration,
+    pub system_time: Duration,
+    /// Scaled CPU usage, where [`TH_USAGE_SCALE`] is 100% of one CPU. The kernel decays this
+    /// over a few seconds.
+    pub cpu_usage: i32,
+    /// The raw `TH_STATE_*` value. See [`MachThreadInfo::run_state`].
+    pub run_state: i32,
+    /// `TH_FLAGS_*` bits.
+    pub flags: i32,
+    pub suspend_count: i32,
+    /// How long the thread has been sleeping.
+    pub sleep_time: Duration,
+}
+
+impl MachThreadInfo {
+    pub fn run_state(&self) -> Result<ThreadRunState, ValueError> {
+        ThreadRunState::from_raw(self.run_state)
+    }
+
+    /// The CPU usage as a percentage of one CPU.
+    pub fn cpu_percent(&self) -> f64 {
+        self.cpu_usage as f64 * 100.0 / TH_USAGE_SCALE as f64
+    }
+
+    /// Returns true if this is a kernel idle thread.
+    pub fn is_idle(&self) -> bool {
+        self.flags & TH_FLAGS_IDLE != 0
+    }
+}
+
+/// The rights a task holds for a port name (`MACH_PORT_TYPE_*` in `<mach/port.h>`). See
+/// [`MachPortInfo`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MachPortRights(u32);
+
+impl MachPortRights {
+    pub const SEND: Self = Self(1 << 16);
+    pub const RECEIVE: Self = Self(1 << 17);
+    pub const SEND_ONCE: Self = Self(1 << 18);
+    pub const PORT_SET: Self = Self(1 << 19);
+    /// A send right whose port was destroyed.
+    pub const DEAD_NAME: Self = Self(1 << 20);
+    /// Every right above.
+    pub const ALL: Self = Self(0x1f << 16);
+
+    const NAMES: [(Self, &'static str); 5] = [
+        (Self::SEND, "send"),
+        (Self::RECEIVE, "receive"),
+        (Self::SEND_ONCE, "send-once"),
+        (Self::PORT_SET, "port-set"),
+        (Self::DEAD_NAME, "dead-name"),
+    ];
+
+    /// The raw flags.
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Returns true if all flags in `other` are also set.
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// The names of the set rights, eg: `["send", "receive"]`.
+    pub fn names(self) -> Vec<&'static str> {
+        Self::NAMES
+            .iter()
+            .filter(|(right, _)| self.contains(*right))
+            .map(|(_, name)| *name)
+            .collect()
+    }
+}
+
+impl std::ops::BitOr for MachPortRights {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// A port name in a task's IPC space. See [`mach_ports`].
+#[derive(Debug, Clone, Copy)]
+pub struct MachPortInfo {
+    /// The name of the port in the task, which is meaningless in other tasks.
+    pub name: u32,
+    pub rights: MachPortRights,
+    /// The number of user references to the send or dead-name right.
+    pub urefs: u32,
+    /// An obfuscated address of the port, which is the same for every name of the port across
+    /// tasks, so it can be used to find the tasks holding rights to the same port.
+    pub object: u32,
+    /// The fileport this send right is, if any.
+    pub fileport: Option<ProcFilePortInfo>,
+}
+
+/// VM statistics of a task (`task_vm_info`). Sizes are in bytes.
+///
+/// The kernel fills in as many revisions of the struct as it supports, and leaves the rest
+/// zeroed.
+#[repr(C, packed(4))]
+#[derive(Debug, Clone, Copy)]
+pub struct TaskVmInfo {
+    pub virtual_size: u64,
+    pub region_count: i32,
+    pub page_size: i32,
+    pub resident_size: u64,
+    pub resident_size_peak: u64,
+    pub device: u64,
+    pub device_peak: u64,
+    pub internal: u64,
+    pub internal_peak: u64,
+    pub external: u64,
+    pub external_peak: u64,
+    pub reusable: u64,
+    pub reusable_peak: u64,
+    pub purgeable_volatile_pmap: u64,
+    pub purgeable_volatile_resident: u64,
+    pub purgeable_volatile_virtual: u64,
+    /// Memory held in the compressor.
+    pub compressed: u64,
+    pub compressed_peak: u64,
+    pub compressed_lifetime: u64,
+    /// The memory attributed to the task, as shown in Activity Monitor's "Memory" column.
+    pub phys_footprint: u64,
+    pub min_address: u64,
+    pub max_address: u64,
+    pub ledger_phys_footprint_peak: i64,
+    pub ledger_purgeable_nonvolatile: i64,
+    pub ledger_purgeable_novolatile_compressed: i64,
+    pub ledger_purgeable_volatile: i64,
+    pub ledger_purgeable_volatile_compressed: i64,
+    pub ledger_tag_network_nonvolatile: i64,
+    pub ledger_tag_network_nonvolatile_compressed: i64,
+    pub ledger_tag_network_volatile: i64,
+    pub ledger_tag_network_volatile_compressed: i64,
+    pub ledger_tag_media_footprint: i64,
+    pub ledger_tag_media_footprint_compressed: i64,
+    pub ledger_tag_media_nofootprint: i64,
+    pub ledger_tag_media_nofootprint_compressed: i64,
+    pub ledger_tag_graphics_footprint: i64,
+    pub ledger_tag_graphics_footprint_compressed: i64,
+    pub ledger_tag_graphics_nofootprint: i64,
+    pub ledger_tag_graphics_nofootprint_compressed: i64,
+    pub ledger_tag_neural_footprint: i64,
+    pub ledger_tag_neural_footprint_compressed: i64,
+    pub ledger_tag_neural_nofootprint: i64,
+    pub ledger_tag_neural_nofootprint_compressed: i64,
+    /// How far the task is below its memory limit.
+    pub limit_bytes_remaining: u64,
+    pub decompressions: i32,
+}
+
+/// Get the VM statistics of a process. See [`TaskPort::for_pid`] for the permissions needed.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let info = mach::task_vm_info(getpid()).unwrap();
+/// let footprint = info.phys_footprint;
+/// println!("{footprint} bytes");
+/// ```
+pub fn task_vm_info(pid: Pid) -> Result<TaskVmInfo, std::io::Error> {
+    TaskPort::for_pid(pid)?.vm_info()
+}
+
+/// Get the physical footprint of a process, as shown in Activity Monitor.
+///
+/// Uses [`task_vm_info`] where the task port is available, and otherwise falls back to
+/// `proc_pid_rusage`, which reports the same number for the current user's processes.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// println!("{} bytes", mach::phys_footprint(getpid()).unwrap());
+/// ```
+pub fn phys_footprint(pid: Pid) -> Result<u64, std::io::Error> {
+    match task_vm_info(pid) {
+        Ok(info) => Ok(info.phys_footprint),
+        Err(err) => match super::proc_pid_rusage_v6(pid) {
+            Ok(usage) => Ok(usage.ri_phys_footprint),
+            Err(_) => Err(err),
+        },
+    }
+}
+
+/// Get the scheduler statistics of every thread in a process. See [`TaskPort::for_pid`] for
+/// the permissions needed.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let mut threads = mach::thread_usage(getpid()).unwrap();
+/// threads.sort_by_key(|thread| std::cmp::Reverse(thread.cpu_usage));
+/// for thread in threads {
+///     println!(
+///         "{:>6.1}% {:?} {}",
+///         thread.cpu_percent(),
+///         thread.run_state(),
+///         thread.name.as_deref().unwrap_or("<unnamed>"),
+///     );
+/// }
+/// ```
+pub fn thread_usage(pid: Pid) -> Result<Vec<MachThreadInfo>, std::io::Error> {
+    TaskPort::for_pid(pid)?.threads()
+}
+
+/// List the port names of a process, with the rights held for each, and the fileports they
+/// carry.
+///
+/// This needs a task port (see [`TaskPort::for_pid`]) or a task read port, from
+/// `task_read_for_pid`, which is tried when only a name port is available. Both usually need
+/// root for other processes.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let ports = mach::mach_ports(getpid()).unwrap();
+/// let receive = ports
+///     .iter()
+///     .filter(|port| port.rights.contains(mach::MachPortRights::RECEIVE))
+///     .count();
+/// println!("{} ports, {receive} receive rights", ports.len());
+/// ```
+pub fn mach_ports(pid: Pid) -> Result<Vec<MachPortInfo>, std::io::Error> {
+    let port = TaskPort::for_pid(pid)?;
+    if !port.is_name_port() {
+        return port.ports();
+    }
+    let mut read_port = 0;
+    // SAFETY: The output is a single port.
+    let kr = unsafe { ffi::task_read_for_pid(mach_task_self(), pid.0 as _, &mut read_port) };
+    if kr != libc::KERN_SUCCESS {
+        return port.ports();
+    }
+    TaskPort {
+        port: read_port,
+        pid,
+        is_name: false,
+    }
+    .ports()
+}
+
+/// List the images mapped into a process, with their load addresses and paths.
+///
+/// Where the task port is available (see [`TaskPort::for_pid`]), this reads dyld's image list
+/// with [`TaskPort::loaded_images`]. Otherwise, it falls back to walking the memory regions of
+/// the process with [`super::proc_pidregionpathinfo`], which works for the current user's
+/// processes, and reports each file mapped from its start. The fallback can't see inside the
+/// dyld shared cache, so system libraries show up as a single cache file. Fails if neither
+/// works, eg: for another user's process without root.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// for image in mach::loaded_images(getpid()).unwrap() {
+///     println!("{:#x} {}", image.load_address, image.path.display());
+/// }
+/// ```
+pub fn loaded_images(pid: Pid) -> Result<Vec<LoadedImage>, std::io::Error> {
+    match TaskPort::for_pid(pid) {
+        Ok(port) if !port.is_name_port() => match port.loaded_images() {
+            Ok(images) => Ok(images),
+            Err(err) => mapped_images(pid).map_err(|_| err),
+        },
+        Ok(_) => mapped_images(pid),
+        Err(err) => mapped_images(pid).map_err(|_| err),
+    }
+}
+
+/// Find the files mapped into a process from their start, in address order. Fails, rather than
+/// returning an empty list, if no region could be read, eg: with `EPERM` for another user's
+/// process.
+fn mapped_images(pid: Pid) -> Result<Vec<LoadedImage>, std::io::Error> {
+    let mut images: Vec<LoadedImage> = vec![];
+    let mut address = 0;
+    loop {
+        let region = match proc_pidregionpathinfo(pid, address) {
+            Ok(Some(region)) => region,
+            Ok(None) => break,
+            // EINVAL marks the end of the address space.
+            Err(err) if err.raw_os_error() == Some(libc::EINVAL) && address != 0 => break,
+            Err(err) => return Err(err),
+        };
+        address = region.prp_prinfo.end();
+        if region.prp_prinfo.pri_offset != 0 {
+            continue;
+        }
+        let Ok(path) = region.to_path_buf() else {
+            continue;
+        };
+        if path.as_os_str().is_empty() || images.iter().any(|image| image.path == path) {
+            continue;
+        }
+        images.push(LoadedImage {
+            load_address: region.prp_prinfo.pri_address,
+            path,
+        });
+    }
+    // Every process maps at least its executable and dyld.
+    if images.is_empty() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "No mapped images found",
+        ));
+    }
+    Ok(images)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_vm_info_self() {
+        assert_eq!(std::mem::size_of::<TaskVmInfo>(), 348);
+        let info = task_vm_info(getpid()).unwrap();
+        let (footprint, resident, page_size) =
+            (info.phys_footprint, info.resident_size, info.page_size);
+        assert!(footprint > 0);
+        assert!(resident > 0);
+        assert!(page_size == 4096 || page_size == 16384);
+    }
+
+    #[test]
+    fn test_task_port_other() {
+        // launchd's task port needs root and an entitlement.
+        match TaskPort::for_pid(Pid(1)) {
+            Ok(port) if port.is_name_port() => assert!(port.vm_info().is_err()),
+            Ok(port) => assert!(port.vm_info().is_ok()),
+            Err(err) => assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied),
+        }
+        assert!(phys_footprint(getpid()).unwrap() > 0);
+    }
+
+    #[test]
+    fn test_loaded_images_self() {
+        let exe = std::env::current_exe().unwrap();
+        let images = TaskPort::current().loaded_images().unwrap();
+        assert_eq!(images[0].path.file_name(), exe.file_name());
+        assert!(images
+            .iter()
+            .any(|image| image.path.ends_with("libSystem.B.dylib")));
+        assert_eq!(loaded_images(getpid()).unwrap(), images);
+
+        let mapped = mapped_images(getpid()).unwrap();
+        let main = mapped
+            .iter()
+            .find(|image| image.path.file_name() == exe.file_name())
+            .unwrap();
+        assert_eq!(main.load_address, images[0].load_address);
+    }
+
+    #[test]
+    fn test_loaded_images_denied() {
+        // SAFETY: geteuid never fails.
+        if unsafe { libc::geteuid() } == 0 {
+            return;
+        }
+        let err = mapped_images(Pid(1)).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::EPERM));
+        assert!(loaded_images(Pid(1)).is_err());
+    }
+
+    #[test]
+    fn test_thread_usage_self() {
+        let name = "proc-pidinfo-mach-thread";
+        let (tx, rx) = std::sync::mpsc::channel::<()>();
+        let (id_tx, id_rx) = std::sync::mpsc::channel();
+        let thread = std::thread::Builder::new()
+            .name(name.to_owned())
+            .spawn(move || {
+                let mut id = 0;
+                // SAFETY: Only reads the current thread's id.
+                unsafe { libc::pthread_threadid_np(0 as libc::pthread_t, &mut id) };
+                id_tx.send(id).unwrap();
+                rx.recv()
+            })
+            .unwrap();
+        let id = id_rx.recv().unwrap();
+
+        let threads = thread_usage(getpid()).unwrap();
+        assert!(threads.len() >= 2);
+        let info = threads.iter().find(|info| info.thread_id == id).unwrap();
+        assert_eq!(info.name.as_deref(), Some(name));
+        assert_eq!(info.run_state(), Ok(ThreadRunState::TH_STATE_WAITING));
+        assert!(!info.is_idle());
+        assert_eq!(info.suspend_count, 0);
+        // The calling thread is running.
+        assert!(threads
+            .iter()
+            .any(|info| info.run_state() == Ok(ThreadRunState::TH_STATE_RUNNING)));
+
+        drop(tx);
+        thread.join().unwrap().unwrap_err();
+    }
+
+    #[test]
+    fn test_mach_ports_self() {
+        use std::os::fd::AsRawFd;
+
+        extern "C" {
+            fn fileport_makeport(fd: libc::c_int, port: *mut mach_port_t) -> libc::c_int;
+        }
+
+        let file = std::fs::File::open("/dev/null").unwrap();
+        let mut fileport = 0;
+        // SAFETY: The output is a single port name.
+        let res = unsafe { fileport_makeport(file.as_raw_fd(), &mut fileport) };
+        assert_eq!(res, 0);
+
+        let ports = mach_ports(getpid()).unwrap();
+        // SAFETY: Releases the fileport created above.
+        unsafe { ffi::mach_port_deallocate(mach_task_self(), fileport) };
+
+        let task = ports
+            .iter()
+            .find(|port| port.name == mach_task_self())
+            .unwrap();
+        assert!(task.rights.contains(MachPortRights::SEND));
+        assert!(task.fileport.is_none());
+        let found = ports.iter().find(|port| port.name == fileport).unwrap();
+        assert_eq!(found.rights.names(), ["send"]);
+        assert_eq!(
+            found.fileport.unwrap().fd_type(),
+            crate::darwin::ProcFDType::VNODE
+        );
+    }
+}
@@ -0,0 +1,198 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use super::{last_os_error, libc_str_to_path, libc_str_to_string_lossy, VInfoStat, VnodeInfo};
+
+/// A mounted filesystem, from `getfsstat`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MountInfo {
+    /// The filesystem id. The first value is the device number, as in [`VInfoStat::vst_dev`].
+    pub fsid: [i32; 2],
+    /// Where the filesystem is mounted, eg: `/Volumes/Data`.
+    pub mount_point: PathBuf,
+    /// What is mounted, eg: `/dev/disk3s5`.
+    pub source: String,
+    /// The filesystem type, eg: `apfs`.
+    pub fs_type: String,
+    /// The `MNT_*` mount flags.
+    pub flags: u32,
+}
+
+impl MountInfo {
+    /// The device number of the filesystem, as in [`VInfoStat::vst_dev`].
+    pub fn dev(&self) -> u32 {
+        self.fsid[0] as u32
+    }
+}
+
+/// A snapshot of the mounted filesystems, for finding the filesystem a vnode is on.
+///
+/// [`VInfoStat::mount`] and [`VnodeInfo::mount`] share a cached table, which is refreshed
+/// when a lookup misses, eg: after a new volume is mounted.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let mounts = MountTable::snapshot().unwrap();
+/// for mount in mounts.iter() {
+///     println!("{} on {} ({})", mount.source, mount.mount_point.display(), mount.fs_type);
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MountTable {
+    mounts: Vec<MountInfo>,
+}
+
+static MOUNTS: OnceLock<Mutex<Option<Arc<MountTable>>>> = OnceLock::new();
+
+impl MountTable {
+    /// Read the current mount table.
+    pub fn snapshot() -> Result<Self, std::io::Error> {
+        let entry_size = std::mem::size_of::<libc::statfs>();
+        let mut buffer = Vec::<libc::statfs>::new();
+
+        // SAFETY: We check the size of the return value to ensure it's valid.
+        unsafe {
+            loop {
+                let res = libc::getfsstat(std::ptr::null_mut(), 0, libc::MNT_NOWAIT);
+                if res < 0 {
+                    return Err(last_os_error());
+                }
+                // Leave room for volumes mounted between the two calls.
+                buffer.reserve_exact(res as usize + 4);
+                let bufsize = (buffer.capacity() * entry_size) as libc::c_int;
+                let res = libc::getfsstat(buffer.as_mut_ptr(), bufsize, libc::MNT_NOWAIT);
+                if res < 0 {
+                    return Err(last_os_error());
+                }
+                if res as usize == buffer.capacity() {
+                    continue;
+                }
+                buffer.set_len(res as usize);
+                break;
+            }
+        }
+
+        let mounts = buffer
+            .iter()
+            .map(|fs| MountInfo {
+                // SAFETY: fsid_t is two i32s, but libc keeps them private.
+                fsid: unsafe { std::mem::transmute::<libc::fsid_t, [i32; 2]>(fs.f_fsid) },
+                mount_point: libc_str_to_path(&fs.f_mntonname)
+                    .map(Path::to_path_buf)
+                    .unwrap_or_default(),
+                source: libc_str_to_string_lossy(&fs.f_mntfromname),
+                fs_type: libc_str_to_string_lossy(&fs.f_fstypename),
+                flags: fs.f_flags,
+            })
+            .collect();
+        Ok(Self { mounts })
+    }
+
+    /// The cached mount table, read on first use.
+    pub fn cached() -> Result<Arc<Self>, std::io::Error> {
+        let cache = MOUNTS.get_or_init(Default::default);
+        let mut cache = cache.lock().unwrap();
+        if let Some(table) = &*cache {
+            return Ok(table.clone());
+        }
+        let table = Arc::new(Self::snapshot()?);
+        *cache = Some(table.clone());
+        Ok(table)
+    }
+
+    /// Re-read the cached mount table, returning the new table.
+    pub fn refresh() -> Result<Arc<Self>, std::io::Error> {
+        let table = Arc::new(Self::snapshot()?);
+        *MOUNTS.get_or_init(Default::default).lock().unwrap() = Some(table.clone());
+        Ok(table)
+    }
+
+    /// Every mounted filesystem, in mount order.
+    pub fn iter(&self) -> impl Iterator<Item = &MountInfo> {
+        self.mounts.iter()
+    }
+
+    /// The filesystem with a device number, eg: [`VInfoStat::vst_dev`].
+    pub fn by_dev(&self, dev: u32) -> Option<&MountInfo> {
+        self.mounts.iter().find(|mount| mount.dev() == dev)
+    }
+
+    /// The filesystem with an id, eg: [`VnodeInfo::vi_fsid`].
+    pub fn by_fsid(&self, fsid: [i32; 2]) -> Option<&MountInfo> {
+        self.mounts.iter().find(|mount| mount.fsid == fsid)
+    }
+}
+
+/// Look up a filesystem in the cached table, refreshing it once on a miss.
+fn find_mount(
+    find: impl Fn(&MountTable) -> Option<&MountInfo>,
+) -> Result<Option<MountInfo>, std::io::Error> {
+    if let Some(mount) = find(&*MountTable::cached()?) {
+        return Ok(Some(mount.clone()));
+    }
+    Ok(find(&*MountTable::refresh()?).cloned())
+}
+
+impl VInfoStat {
+    /// The filesystem this vnode is on, from the cached [`MountTable`]. Returns `None` for
+    /// vnodes that aren't on a mounted filesystem, eg: pipes.
+    ///
+    /// ```
+    /// use proc_pidinfo::*;
+    /// use std::os::fd::AsRawFd;
+    ///
+    /// let file = std::fs::File::open("/dev/null").unwrap();
+    /// let info = proc_pidfdinfo::<VnodeFdInfo>(getpid(), Fd(file.as_raw_fd())).unwrap().unwrap();
+    /// let mount = info.pvi.vi_stat.mount().unwrap().unwrap();
+    /// assert_eq!(mount.mount_point, std::path::Path::new("/dev"));
+    /// ```
+    pub fn mount(&self) -> Result<Option<MountInfo>, std::io::Error> {
+        find_mount(|table| table.by_dev(self.vst_dev))
+    }
+}
+
+impl VnodeInfo {
+    /// The filesystem this vnode is on, from the cached [`MountTable`].
+    pub fn mount(&self) -> Result<Option<MountInfo>, std::io::Error> {
+        find_mount(|table| table.by_fsid(self.vi_fsid))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::fd::AsRawFd;
+
+    use super::*;
+    use crate::darwin::{getpid, proc_pidfdinfo, Fd, VnodeFdInfo};
+
+    #[test]
+    fn test_mount_table() {
+        let table = MountTable::snapshot().unwrap();
+        let root = table
+            .iter()
+            .find(|mount| mount.mount_point == Path::new("/"))
+            .unwrap();
+        assert!(!root.fs_type.is_empty());
+        assert_eq!(table.by_fsid(root.fsid), Some(root));
+    }
+
+    #[test]
+    fn test_vnode_mount() {
+        let exe = std::fs::File::open(std::env::current_exe().unwrap()).unwrap();
+        let info = proc_pidfdinfo::<VnodeFdInfo>(getpid(), Fd(exe.as_raw_fd()))
+            .unwrap()
+            .unwrap();
+        let by_dev = info.pvi.vi_stat.mount().unwrap().unwrap();
+        let by_fsid = info.pvi.mount().unwrap().unwrap();
+        assert_eq!(by_dev, by_fsid);
+        assert_eq!(by_dev.dev(), info.pvi.vi_stat.vst_dev);
+
+        let devnull = std::fs::File::open("/dev/null").unwrap();
+        let info = proc_pidfdinfo::<VnodeFdInfo>(getpid(), Fd(devnull.as_raw_fd()))
+            .unwrap()
+            .unwrap();
+        let mount = info.pvi.vi_stat.mount().unwrap().unwrap();
+        assert_eq!(mount.fs_type, "devfs");
+    }
+}
@@ -0,0 +1,89 @@
+use super::{
+    proc_pidfdinfo, proc_pidinfo_list, Fd, HasFdFlavor, Pid, ProcFDInfo, ProcFDType, SocketFdInfo,
+    VnodeFdInfoWithPath,
+};
+
+/// List the descriptors of one type, and read the details of each. Descriptors closed between
+/// the two calls are skipped.
+fn open_fds<T: HasFdFlavor>(pid: Pid, fd_type: ProcFDType) -> Result<Vec<(Fd, T)>, std::io::Error> {
+    let mut found = vec![];
+    for fd in proc_pidinfo_list::<ProcFDInfo>(pid)? {
+        if fd.fd_type() != fd_type {
+            continue;
+        }
+        match proc_pidfdinfo::<T>(pid, fd.proc_fd) {
+            Ok(Some(info)) => found.push((fd.proc_fd, info)),
+            Ok(None) => {}
+            Err(err) if err.raw_os_error() == Some(libc::EBADF) => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(found)
+}
+
+/// List the files, directories and devices a process has open, with their paths.
+///
+/// Descriptors closed while listing are skipped. Fails if the process can't be read, eg: it
+/// has exited or, without root, belongs to another user.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// for (fd, info) in open_vnodes(getpid()).unwrap() {
+///     println!("{} {:?}", fd.0, info.path());
+/// }
+/// ```
+pub fn open_vnodes(pid: Pid) -> Result<Vec<(Fd, VnodeFdInfoWithPath)>, std::io::Error> {
+    open_fds(pid, ProcFDType::VNODE)
+}
+
+/// List the sockets a process has open. See [`open_vnodes`].
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// for (fd, info) in open_sockets(getpid()).unwrap() {
+///     println!("{} {:?}", fd.0, info.psi.inet().map(|inet| inet.local_addr()));
+/// }
+/// ```
+pub fn open_sockets(pid: Pid) -> Result<Vec<(Fd, SocketFdInfo)>, std::io::Error> {
+    open_fds(pid, ProcFDType::SOCKET)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+    use std::os::fd::AsRawFd;
+
+    use super::*;
+    use crate::darwin::getpid;
+
+    #[test]
+    fn test_open_vnodes() {
+        let file = std::fs::File::open("/dev/null").unwrap();
+        let fd = Fd(file.as_raw_fd());
+        let vnodes = open_vnodes(getpid()).unwrap();
+        let (_, info) = vnodes.iter().find(|(found, _)| *found == fd).unwrap();
+        assert_eq!(info.path().unwrap(), std::path::Path::new("/dev/null"));
+
+        drop(file);
+        let vnodes = open_vnodes(getpid()).unwrap();
+        assert!(!vnodes.iter().any(|(found, info)| *found == fd
+            && info.path().unwrap() == std::path::Path::new("/dev/null")));
+    }
+
+    #[test]
+    fn test_open_sockets() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let sockets = open_sockets(getpid()).unwrap();
+        let (_, info) = sockets
+            .iter()
+            .find(|(fd, _)| *fd == Fd(listener.as_raw_fd()))
+            .unwrap();
+        assert_eq!(
+            info.psi.inet().unwrap().local_port(),
+            listener.local_addr().unwrap().port()
+        );
+        assert!(open_sockets(Pid(99_999_999)).is_err());
+    }
+}
@@ -0,0 +1,120 @@
+use rayon::prelude::*;
+
+use super::{
+    proc_listallpids, proc_pidfdinfo, proc_pidinfo_list, Fd, FdTableSnapshot, HasFdFlavor, Pid,
+    ProcFDInfo, ProcFDType, ProcessScan, Scanner,
+};
+
+/// The number of processes handled by each rayon task. Each query is a single syscall, so
+/// handing out one process at a time costs more in scheduling than it saves.
+const PIDS_PER_TASK: usize = 16;
+
+/// Run `f` on every process in parallel, skipping processes for which it returns `None`, and
+/// keeping the results in the order of [`proc_listallpids`].
+fn par_map_pids<T: Send>(f: impl Fn(Pid) -> Option<T> + Sync) -> Result<Vec<T>, std::io::Error> {
+    Ok(proc_listallpids()?
+        .par_chunks(PIDS_PER_TASK)
+        .flat_map_iter(|pids| pids.iter().filter_map(|&pid| f(pid)))
+        .collect())
+}
+
+impl Scanner {
+    /// Scan every process on the system in parallel, skipping processes that exit mid-scan.
+    /// See [`Scanner::scan_all`].
+    pub fn scan_all_par(&self) -> Result<Vec<ProcessScan>, std::io::Error> {
+        par_map_pids(|pid| self.scan(pid).ok().flatten())
+    }
+}
+
+/// Scan every process on the system in parallel with [`Scanner::detect`], skipping processes
+/// that exit mid-scan.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// for process in processes_par().unwrap() {
+///     println!("{} {:?}", process.pid.0, process.fds.map(|fds| fds.len()));
+/// }
+/// ```
+pub fn processes_par() -> Result<Vec<ProcessScan>, std::io::Error> {
+    Scanner::detect().scan_all_par()
+}
+
+/// Capture the file descriptor table of every process in parallel, skipping processes that
+/// can't be read (without root, those of other users) or exit mid-scan.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let tables = fd_tables_par().unwrap();
+/// let total = tables.iter().map(|table| table.len()).sum::<usize>();
+/// println!("{} fds in {} processes", total, tables.len());
+/// ```
+pub fn fd_tables_par() -> Result<Vec<FdTableSnapshot>, std::io::Error> {
+    par_map_pids(|pid| FdTableSnapshot::capture(pid).ok())
+}
+
+/// Read the details of every descriptor of a type, in every process, in parallel. Processes
+/// and descriptors that can't be read, or go away mid-scan, are skipped.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// for (pid, fd, info) in fd_info_par::<SocketFdInfo>(ProcFDType::SOCKET).unwrap() {
+///     println!("{} {} {:?}", pid.0, fd.0, info.psi.inet().map(|inet| inet.local_port()));
+/// }
+/// ```
+#[allow(private_bounds)]
+pub fn fd_info_par<T: HasFdFlavor + Send>(
+    fd_type: ProcFDType,
+) -> Result<Vec<(Pid, Fd, T)>, std::io::Error> {
+    Ok(par_map_pids(|pid| {
+        let fds = proc_pidinfo_list::<ProcFDInfo>(pid).ok()?;
+        let infos = fds
+            .into_iter()
+            .filter(|fd| fd.fd_type() == fd_type)
+            .filter_map(|fd| {
+                let info = proc_pidfdinfo::<T>(pid, fd.proc_fd).ok().flatten()?;
+                Some((pid, fd.proc_fd, info))
+            })
+            .collect::<Vec<_>>();
+        Some(infos)
+    })?
+    .into_iter()
+    .flatten()
+    .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::fd::AsRawFd;
+
+    use super::*;
+    use crate::darwin::{getpid, VnodeFdInfoWithPath};
+
+    #[test]
+    fn test_processes_par() {
+        let serial = Scanner::detect().scan_all().unwrap();
+        let parallel = processes_par().unwrap();
+        assert!(parallel.iter().any(|scan| scan.pid == getpid()));
+        // Processes come and go, but not many in the time between the two scans.
+        assert!(parallel.len().abs_diff(serial.len()) < serial.len() / 2);
+    }
+
+    #[test]
+    fn test_fd_tables_par() {
+        let file = std::fs::File::open("/dev/null").unwrap();
+        let fd = Fd(file.as_raw_fd());
+
+        let tables = fd_tables_par().unwrap();
+        let own = tables.iter().find(|table| table.pid() == getpid()).unwrap();
+        assert!(own.get(fd).is_some());
+
+        let infos = fd_info_par::<VnodeFdInfoWithPath>(ProcFDType::VNODE).unwrap();
+        let (_, _, info) = infos
+            .iter()
+            .find(|(pid, found, _)| *pid == getpid() && *found == fd)
+            .unwrap();
+        assert_eq!(info.path().unwrap(), std::path::Path::new("/dev/null"));
+    }
+}
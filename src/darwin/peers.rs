@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+
+use super::{
+    proc_listallpids, proc_pidfdinfo, proc_pidinfo, proc_pidinfo_list, Fd, Pid, PipeFdInfo,
+    ProcBSDShortInfo, ProcFDInfo, ProcFDType, SocketFdInfo,
+};
+
+/// A descriptor held open by a process, eg: one end of a pipe. See [`PeerMap`] and
+/// [`find_listeners`](super::find_listeners).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FdEndpoint {
+    pub pid: Pid,
+    pub fd: Fd,
+    /// The name of the process, from [`ProcBSDShortInfo::comm`].
+    pub name: String,
+}
+
+/// The kernel object behind a descriptor. Handles are opaque, but consistent across
+/// processes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Handle {
+    Pipe(u64),
+    UnixSocket(u64),
+}
+
+/// The handles of a descriptor's own end and of its peer, or `None` if the descriptor isn't
+/// a pipe or a connected unix domain socket.
+fn handles(pid: Pid, fd: &ProcFDInfo) -> Option<(Handle, Handle)> {
+    match fd.fd_type() {
+        ProcFDType::PIPE => {
+            let info = proc_pidfdinfo::<PipeFdInfo>(pid, fd.proc_fd)
+                .ok()??
+                .pipe_info;
+            (info.pipe_peerhandle != 0).then_some((
+                Handle::Pipe(info.pipe_handle),
+                Handle::Pipe(info.pipe_peerhandle),
+            ))
+        }
+        ProcFDType::SOCKET => {
+            let info = proc_pidfdinfo::<SocketFdInfo>(pid, fd.proc_fd).ok()??.psi;
+            let unix = info.unix()?;
+            unix.is_connected().then_some((
+                Handle::UnixSocket(info.soi_so),
+                Handle::UnixSocket(unix.unsi_conn_so),
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// An index of the pipes and connected unix domain sockets held by every process, for
+/// finding which process is on the other end of one.
+///
+/// Building the map reads the descriptors of every process, so build it once and query it
+/// for many descriptors. Processes the current process isn't allowed to inspect (without
+/// root, those of other users) are skipped, so their ends won't be found.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let peers = PeerMap::scan().unwrap();
+/// for fd in proc_pidinfo_list_self::<ProcFDInfo>().unwrap() {
+///     for peer in peers.peers(getpid(), fd.proc_fd).unwrap() {
+///         println!("fd {} -> {} ({}) fd {}", fd.proc_fd.0, peer.pid.0, peer.name, peer.fd.0);
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PeerMap {
+    endpoints: HashMap<Handle, Vec<FdEndpoint>>,
+}
+
+impl PeerMap {
+    /// Index every process on the system, skipping processes that can't be read or exit
+    /// mid-scan.
+    pub fn scan() -> Result<Self, std::io::Error> {
+        Ok(Self::scan_pids(proc_listallpids()?))
+    }
+
+    /// Index the given processes, skipping processes that can't be read or exit mid-scan.
+    pub fn scan_pids(pids: impl IntoIterator<Item = Pid>) -> Self {
+        let mut map = Self::default();
+        for pid in pids {
+            map.add(pid);
+        }
+        map
+    }
+
+    fn add(&mut self, pid: Pid) {
+        let Ok(fds) = proc_pidinfo_list::<ProcFDInfo>(pid) else {
+            return;
+        };
+        let name = match proc_pidinfo::<ProcBSDShortInfo>(pid) {
+            Ok(Some(info)) => info.comm().unwrap_or_default().to_owned(),
+            _ => return,
+        };
+        for fd in fds {
+            if let Some((own, _)) = handles(pid, &fd) {
+                self.endpoints.entry(own).or_default().push(FdEndpoint {
+                    pid,
+                    fd: fd.proc_fd,
+                    name: name.clone(),
+                });
+            }
+        }
+    }
+
+    /// The processes and descriptors on the other end of a pipe or unix domain socket.
+    ///
+    /// The descriptor is read live, so it may have been opened after the map was built. An
+    /// end can be held by several processes, eg: after `fork`. Returns an empty list if the
+    /// descriptor isn't a pipe or a connected unix domain socket, or if the peer is held only
+    /// by processes that weren't indexed.
+    ///
+    /// Fails with `EBADF` if the process doesn't have the descriptor open.
+    pub fn peers(&self, pid: Pid, fd: Fd) -> Result<Vec<FdEndpoint>, std::io::Error> {
+        let info = proc_pidinfo_list::<ProcFDInfo>(pid)?
+            .into_iter()
+            .find(|info| info.proc_fd == fd)
+            .ok_or_else(|| std::io::Error::from_raw_os_error(libc::EBADF))?;
+        Ok(handles(pid, &info)
+            .and_then(|(_, peer)| self.endpoints.get(&peer))
+            .cloned()
+            .unwrap_or_default())
+    }
+}
+
+/// Find the processes on the other end of a pipe or unix domain socket. This scans every
+/// process; use a [`PeerMap`] to look up many descriptors.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let (read, _write) = std::io::pipe().unwrap();
+/// # use std::os::fd::AsRawFd;
+/// let peers = fd_peers(getpid(), Fd(read.as_raw_fd())).unwrap();
+/// assert!(peers.iter().any(|peer| peer.pid == getpid()));
+/// ```
+pub fn fd_peers(pid: Pid, fd: Fd) -> Result<Vec<FdEndpoint>, std::io::Error> {
+    PeerMap::scan()?.peers(pid, fd)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::fd::AsRawFd;
+    use std::os::unix::net::UnixStream;
+    use std::process::{Command, Stdio};
+
+    use super::*;
+    use crate::darwin::getpid;
+    use crate::testutil::TestChild;
+
+    #[test]
+    fn test_pipe_peer_child() {
+        let child = TestChild::spawn(Command::new("/bin/sleep").arg("10").stdin(Stdio::piped()));
+        let child_pid = child.pid();
+        let stdin = child.stdin.as_ref().unwrap();
+
+        let peers = PeerMap::scan_pids([getpid(), child_pid]);
+        let found = peers.peers(getpid(), Fd(stdin.as_raw_fd())).unwrap();
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].pid, child_pid);
+        assert_eq!(found[0].fd, Fd(0));
+        assert_eq!(found[0].name, "sleep");
+    }
+
+    #[test]
+    fn test_unix_socket_peer() {
+        let (a, b) = UnixStream::pair().unwrap();
+        let peers = PeerMap::scan_pids([getpid()]);
+        let found = peers.peers(getpid(), Fd(a.as_raw_fd())).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].pid, getpid());
+        assert_eq!(found[0].fd, Fd(b.as_raw_fd()));
+    }
+
+    #[test]
+    fn test_not_a_peer() {
+        let file = std::fs::File::open("/dev/null").unwrap();
+        let peers = PeerMap::scan_pids([getpid()]);
+        assert!(peers
+            .peers(getpid(), Fd(file.as_raw_fd()))
+            .unwrap()
+            .is_empty());
+        let err = peers.peers(getpid(), Fd(i32::MAX)).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::EBADF));
+    }
+}
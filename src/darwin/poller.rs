@@ -0,0 +1,339 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use super::{proc_listallpids, Pid, ProcessStats, Sink};
+
+/// An event produced by a [`ProcessPoller`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum PollEvent {
+    /// A process was seen for the first time, including every process in the first sample.
+    Started(ProcessStats),
+    /// A process seen in the previous sample is gone. A process whose pid was reused between
+    /// two samples is reported as exited and then started.
+    Exited { pid: Pid, name: String },
+    /// The change in a process since the previous sample.
+    Delta(ProcessDelta),
+}
+
+impl PollEvent {
+    /// The process this event is about.
+    pub fn pid(&self) -> Pid {
+        match self {
+            PollEvent::Started(stats) => stats.pid,
+            PollEvent::Exited { pid, .. } => *pid,
+            PollEvent::Delta(delta) => delta.pid,
+        }
+    }
+}
+
+/// The change in a process between two samples. Values that couldn't be read in either sample
+/// (see [`ProcessStats`]) are `None`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ProcessDelta {
+    pub pid: Pid,
+    pub name: String,
+    /// The time between the two samples.
+    pub elapsed: Duration,
+    /// The CPU time used between the samples as a percentage of the elapsed time, as shown by
+    /// `top`. A process with several busy threads can exceed 100%.
+    pub cpu_percent: Option<f64>,
+    pub rss_bytes: Option<u64>,
+    pub rss_change: Option<i64>,
+    pub open_files: Option<usize>,
+    pub open_files_change: Option<i64>,
+    pub thread_count: Option<u32>,
+    pub thread_count_change: Option<i64>,
+}
+
+fn change<T: Into<i64>>(before: Option<T>, after: Option<T>) -> Option<i64> {
+    Some(after?.into() - before?.into())
+}
+
+impl ProcessDelta {
+    fn between(before: &ProcessStats, after: &ProcessStats, elapsed: Duration) -> Self {
+        let cpu_percent = match (before.cpu_time, after.cpu_time) {
+            (Some(before), Some(after)) if !elapsed.is_zero() => {
+                Some(after.saturating_sub(before).as_secs_f64() / elapsed.as_secs_f64() * 100.0)
+            }
+            _ => None,
+        };
+        Self {
+            pid: after.pid,
+            name: after.name.clone(),
+            elapsed,
+            cpu_percent,
+            rss_bytes: after.rss_bytes,
+            rss_change: change(
+                before.rss_bytes.map(|rss| rss as i64),
+                after.rss_bytes.map(|rss| rss as i64),
+            ),
+            open_files: after.open_files,
+            open_files_change: change(
+                before.open_files.map(|files| files as i64),
+                after.open_files.map(|files| files as i64),
+            ),
+            thread_count: after.thread_count,
+            thread_count_change: change(before.thread_count, after.thread_count),
+        }
+    }
+
+    /// Returns true if nothing but the elapsed time changed, ie: the process used no CPU and its
+    /// memory, descriptor and thread counts are unchanged.
+    pub fn is_idle(&self) -> bool {
+        self.cpu_percent.unwrap_or(0.0) == 0.0
+            && self.rss_change.unwrap_or(0) == 0
+            && self.open_files_change.unwrap_or(0) == 0
+            && self.thread_count_change.unwrap_or(0) == 0
+    }
+}
+
+/// Periodically samples a set of processes, or every process, and reports what changed.
+///
+/// Each sample gathers the [`ProcessStats`] of the watched processes, and produces a
+/// [`PollEvent::Delta`] for each process that was also in the previous sample. Processes that
+/// exit are reported once with [`PollEvent::Exited`]; a process that exits while it's being
+/// sampled is treated as gone.
+///
+/// Use [`ProcessPoller::sample`] to drive the poller from your own loop, or iterate over it
+/// (which sleeps for the interval between samples) or [forward](ProcessPoller::forward) its
+/// events into a [`Sink`], such as a channel.
+///
+/// ```
+/// use proc_pidinfo::*;
+/// use std::time::Duration;
+///
+/// let mut poller = ProcessPoller::new([getpid()], Duration::from_millis(10));
+/// for event in poller.by_ref().take(2) {
+///     if let PollEvent::Delta(delta) = event.unwrap() {
+///         println!("{} {:.1}% cpu", delta.name, delta.cpu_percent.unwrap());
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct ProcessPoller {
+    interval: Duration,
+    /// The processes to sample, or `None` for every process.
+    pids: Option<Vec<Pid>>,
+    last: HashMap<Pid, (Instant, ProcessStats)>,
+    pending: VecDeque<PollEvent>,
+    sampled: bool,
+}
+
+impl ProcessPoller {
+    /// Poll a fixed set of processes. Processes that exit are reported once and then ignored,
+    /// unless their pid is reused.
+    pub fn new(pids: impl IntoIterator<Item = Pid>, interval: Duration) -> Self {
+        Self::with_pids(Some(pids.into_iter().collect()), interval)
+    }
+
+    /// Poll every process on the system, including those started after the poller.
+    pub fn all(interval: Duration) -> Self {
+        Self::with_pids(None, interval)
+    }
+
+    fn with_pids(pids: Option<Vec<Pid>>, interval: Duration) -> Self {
+        Self {
+            interval,
+            pids,
+            last: HashMap::new(),
+            pending: VecDeque::new(),
+            sampled: false,
+        }
+    }
+
+    /// The interval the iterator sleeps for between samples.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// The latest stats of every process that was running at the last sample.
+    pub fn latest(&self) -> impl Iterator<Item = &ProcessStats> {
+        self.last.values().map(|(_, stats)| stats)
+    }
+
+    /// Take a sample now, without waiting for the interval, and return the events since the
+    /// previous sample. Fails only if the process list can't be read.
+    pub fn sample(&mut self) -> Result<Vec<PollEvent>, std::io::Error> {
+        let pids = match &self.pids {
+            Some(pids) => pids.clone(),
+            None => proc_listallpids()?,
+        };
+        let samples = pids.into_iter().filter_map(|pid| {
+            let stats = ProcessStats::for_pid(pid).ok().flatten()?;
+            Some((Instant::now(), stats))
+        });
+        Ok(self.record(samples))
+    }
+
+    /// Record a sample collected elsewhere, and return the events since the previous sample.
+    pub fn record(
+        &mut self,
+        samples: impl IntoIterator<Item = (Instant, ProcessStats)>,
+    ) -> Vec<PollEvent> {
+        self.sampled = true;
+        let mut previous = std::mem::take(&mut self.last);
+        let mut events = vec![];
+        for (taken_at, stats) in samples {
+            match previous.remove(&stats.pid) {
+                Some((before_at, before)) if before.unique_id == stats.unique_id => {
+                    let elapsed = taken_at.saturating_duration_since(before_at);
+                    events.push(PollEvent::Delta(ProcessDelta::between(
+                        &before, &stats, elapsed,
+                    )));
+                }
+                Some((_, before)) => {
+                    events.push(PollEvent::Exited {
+                        pid: before.pid,
+                        name: before.name,
+                    });
+                    events.push(PollEvent::Started(stats.clone()));
+                }
+                None => events.push(PollEvent::Started(stats.clone())),
+            }
+            self.last.insert(stats.pid, (taken_at, stats));
+        }
+        let mut exited = previous.into_values().collect::<Vec<_>>();
+        exited.sort_by_key(|(_, stats)| stats.pid);
+        events.extend(exited.into_iter().map(|(_, stats)| PollEvent::Exited {
+            pid: stats.pid,
+            name: stats.name,
+        }));
+        events
+    }
+
+    /// Push events into a [`Sink`] until sampling or the sink fails.
+    pub fn forward(&mut self, sink: &mut impl Sink<PollEvent>) -> Result<(), std::io::Error> {
+        for event in self {
+            sink.send(event?)?;
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for ProcessPoller {
+    type Item = Result<PollEvent, std::io::Error>;
+
+    /// Returns the next event, sampling (after sleeping for the interval, except for the first
+    /// sample) when none are pending. Never ends on its own.
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(Ok(event));
+            }
+            if self.sampled {
+                std::thread::sleep(self.interval);
+            }
+            match self.sample() {
+                Ok(events) => self.pending.extend(events),
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::darwin::getpid;
+    use crate::testutil::TestChild;
+
+    fn stats(pid: u32, unique_id: u64, cpu_ms: u64, rss: u64, files: usize) -> ProcessStats {
+        ProcessStats {
+            pid: Pid(pid),
+            ppid: Pid(1),
+            uid: 501,
+            name: format!("proc{pid}"),
+            path: None,
+            // Left unset, as it is for other users' processes.
+            start_time: None,
+            unique_id: Some(unique_id),
+            rss_bytes: Some(rss),
+            virtual_bytes: None,
+            footprint_bytes: None,
+            cpu_time: Some(Duration::from_millis(cpu_ms)),
+            user_time: None,
+            system_time: None,
+            thread_count: None,
+            open_files: Some(files),
+            disk_read_bytes: None,
+            disk_written_bytes: None,
+        }
+    }
+
+    #[test]
+    fn test_record() {
+        let start = Instant::now();
+        let later = start + Duration::from_secs(1);
+        let mut poller = ProcessPoller::all(Duration::from_secs(1));
+        let events = poller.record([
+            (start, stats(10, 100, 0, 1000, 3)),
+            (start, stats(20, 100, 0, 1000, 3)),
+            (start, stats(30, 100, 0, 1000, 3)),
+        ]);
+        assert!(events
+            .iter()
+            .all(|event| matches!(event, PollEvent::Started(_))));
+
+        let events = poller.record([
+            (later, stats(10, 100, 500, 1500, 2)),
+            (later, stats(30, 200, 0, 1000, 3)),
+        ]);
+        let PollEvent::Delta(delta) = &events[0] else {
+            panic!("{events:?}");
+        };
+        assert_eq!(delta.pid, Pid(10));
+        assert_eq!(delta.elapsed, Duration::from_secs(1));
+        assert_eq!(delta.cpu_percent, Some(50.0));
+        assert_eq!(delta.rss_change, Some(500));
+        assert_eq!(delta.open_files_change, Some(-1));
+        assert_eq!(delta.thread_count_change, None);
+        assert!(!delta.is_idle());
+
+        // pid 30 was reused, and pid 20 exited.
+        assert_eq!(
+            events[1],
+            PollEvent::Exited {
+                pid: Pid(30),
+                name: "proc30".to_owned()
+            }
+        );
+        assert!(matches!(&events[2], PollEvent::Started(stats) if stats.pid == Pid(30)));
+        assert_eq!(
+            events[3],
+            PollEvent::Exited {
+                pid: Pid(20),
+                name: "proc20".to_owned()
+            }
+        );
+        assert_eq!(events.len(), 4);
+        assert_eq!(poller.latest().count(), 2);
+    }
+
+    #[test]
+    fn test_poll_self_and_exit() {
+        let mut child = TestChild::sleep();
+        let pid = child.pid();
+        let mut poller = ProcessPoller::new([getpid(), pid], Duration::from_millis(10));
+        let events = poller.sample().unwrap();
+        assert_eq!(events.len(), 2);
+
+        child.kill().unwrap();
+        child.wait().unwrap();
+        let events = poller.sample().unwrap();
+        assert!(matches!(&events[0], PollEvent::Delta(delta) if delta.pid == getpid()));
+        assert_eq!(events[1].pid(), pid);
+        assert!(matches!(events[1], PollEvent::Exited { .. }));
+        assert_eq!(poller.sample().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_forward() {
+        let (mut tx, rx) = std::sync::mpsc::channel();
+        let mut poller = ProcessPoller::new([getpid()], Duration::from_millis(1));
+        std::thread::spawn(move || poller.forward(&mut tx));
+        assert!(matches!(rx.recv().unwrap(), PollEvent::Started(_)));
+        assert!(matches!(rx.recv().unwrap(), PollEvent::Delta(_)));
+    }
+}
@@ -0,0 +1,326 @@
+use std::fmt;
+use std::time::{Duration, UNIX_EPOCH};
+
+use super::{
+    libc_str_to_string_lossy, Fd, Pid, ProcBSDInfo, ProcBSDShortInfo, ProcFDInfo, ProcFDType,
+    ProcTaskInfo, ProcThreadInfo, VInfoStat, VnodeFdInfoWithPath, VnodeInfoPath,
+};
+
+/// A size in bytes, displayed in binary units, eg: `1.5 MiB`.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// assert_eq!(ByteSize(1536 * 1024).to_string(), "1.5 MiB");
+/// assert_eq!(ByteSize(512).to_string(), "512 B");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ByteSize(pub u64);
+
+impl fmt::Display for ByteSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const UNITS: [&str; 6] = ["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+        if self.0 < 1024 {
+            return write!(f, "{} B", self.0);
+        }
+        let mut size = self.0 as f64 / 1024.0;
+        let mut unit = 0;
+        while size >= 1024.0 && unit < UNITS.len() - 1 {
+            size /= 1024.0;
+            unit += 1;
+        }
+        write!(f, "{size:.1} {}", UNITS[unit])
+    }
+}
+
+/// File type and permission bits (`st_mode`), displayed like `ls -l`, eg: `-rwxr-xr-x`.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// assert_eq!(FileMode(0o100755).to_string(), "-rwxr-xr-x");
+/// assert_eq!(FileMode(0o041777).to_string(), "drwxrwxrwt");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileMode(pub u16);
+
+impl fmt::Display for FileMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mode = self.0 as libc::mode_t;
+        let kind = match mode & libc::S_IFMT {
+            libc::S_IFDIR => 'd',
+            libc::S_IFLNK => 'l',
+            libc::S_IFCHR => 'c',
+            libc::S_IFBLK => 'b',
+            libc::S_IFIFO => 'p',
+            libc::S_IFSOCK => 's',
+            _ => '-',
+        };
+        let bit = |mask: libc::mode_t, c: char| if mode & mask != 0 { c } else { '-' };
+        // The execute position shows setuid, setgid and sticky bits.
+        let exec = |x: libc::mode_t, special: libc::mode_t, set: char| match (
+            mode & x != 0,
+            mode & special != 0,
+        ) {
+            (true, true) => set,
+            (false, true) => set.to_ascii_uppercase(),
+            (true, false) => 'x',
+            (false, false) => '-',
+        };
+        let chars = [
+            kind,
+            bit(libc::S_IRUSR, 'r'),
+            bit(libc::S_IWUSR, 'w'),
+            exec(libc::S_IXUSR, libc::S_ISUID, 's'),
+            bit(libc::S_IRGRP, 'r'),
+            bit(libc::S_IWGRP, 'w'),
+            exec(libc::S_IXGRP, libc::S_ISGID, 's'),
+            bit(libc::S_IROTH, 'r'),
+            bit(libc::S_IWOTH, 'w'),
+            exec(libc::S_IXOTH, libc::S_ISVTX, 't'),
+        ];
+        chars.iter().try_for_each(|c| write!(f, "{c}"))
+    }
+}
+
+impl fmt::Display for Pid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Display for Fd {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Display for ProcBSDShortInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "pid {} ({}) ppid {} uid {}",
+            self.pbsi_pid,
+            self.comm().unwrap_or("?"),
+            self.pbsi_ppid,
+            self.pbsi_uid
+        )?;
+        if let Ok(status) = self.status() {
+            write!(f, " {status:?}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ProcBSDInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.to_owned_info().name;
+        write!(
+            f,
+            "pid {} ({name}) ppid {} uid {} gid {} nice {} files {}",
+            self.pbi_pid, self.pbi_ppid, self.pbi_uid, self.pbi_gid, self.pbi_nice, self.pbi_nfiles
+        )?;
+        if let Ok(status) = self.status() {
+            write!(f, " {status:?}")?;
+        }
+        let start = UNIX_EPOCH + Duration::from_secs(self.pbi_start_tvsec);
+        if let Ok(age) = start.elapsed() {
+            write!(f, " up {}s", age.as_secs())?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ProcTaskInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} resident, {} virtual, {:?} user, {:?} system, {} threads ({} running), \
+             {} faults, {} context switches",
+            ByteSize(self.pti_resident_size),
+            ByteSize(self.pti_virtual_size),
+            self.total_user(),
+            self.total_system(),
+            self.pti_threadnum,
+            self.pti_numrunning,
+            self.pti_faults,
+            self.pti_csw
+        )
+    }
+}
+
+impl fmt::Display for ProcThreadInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Ok(name) if !name.is_empty() => write!(f, "{name}: ")?,
+            _ => write!(f, "<unnamed>: ")?,
+        }
+        write!(
+            f,
+            "{:?} user, {:?} system, {:.1}% cpu, priority {}",
+            Duration::from_nanos(self.pth_user_time),
+            Duration::from_nanos(self.pth_system_time),
+            self.pth_cpu_usage as f64 / 10.0,
+            self.pth_curpri
+        )
+    }
+}
+
+impl fmt::Display for ProcFDInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.fd_type() {
+            ProcFDType::Unknown(value) => write!(f, "fd {} type {value}", self.proc_fd),
+            fd_type => write!(f, "fd {} {fd_type:?}", self.proc_fd),
+        }
+    }
+}
+
+impl fmt::Display for VInfoStat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} uid {} gid {} {} inode {}",
+            FileMode(self.vst_mode),
+            self.vst_nlink,
+            self.vst_uid,
+            self.vst_gid,
+            ByteSize(self.vst_size.max(0) as u64),
+            self.vst_ino
+        )
+    }
+}
+
+impl fmt::Display for VnodeFdInfoWithPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stat = &self.pvip.vip_vi.vi_stat;
+        match self.path() {
+            Ok(path) => write!(f, "{}", path.display())?,
+            Err(_) => write!(f, "?")?,
+        }
+        write!(
+            f,
+            " ({}, {}, offset {})",
+            FileMode(stat.vst_mode),
+            ByteSize(stat.vst_size.max(0) as u64),
+            self.pfi.fi_offset
+        )
+    }
+}
+
+// The C string arrays are shown decoded, rather than as hundreds of integers.
+
+impl fmt::Debug for ProcBSDInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProcBSDInfo")
+            .field("pbi_flags", &self.pbi_flags)
+            .field("pbi_status", &self.pbi_status)
+            .field("pbi_xstatus", &self.pbi_xstatus)
+            .field("pbi_pid", &self.pbi_pid)
+            .field("pbi_ppid", &self.pbi_ppid)
+            .field("pbi_uid", &self.pbi_uid)
+            .field("pbi_gid", &self.pbi_gid)
+            .field("pbi_ruid", &self.pbi_ruid)
+            .field("pbi_rgid", &self.pbi_rgid)
+            .field("pbi_svuid", &self.pbi_svuid)
+            .field("pbi_svgid", &self.pbi_svgid)
+            .field("pbi_comm", &libc_str_to_string_lossy(&self.pbi_comm))
+            .field("pbi_name", &libc_str_to_string_lossy(&self.pbi_name))
+            .field("pbi_nfiles", &self.pbi_nfiles)
+            .field("pbi_pgid", &self.pbi_pgid)
+            .field("pbi_pjobc", &self.pbi_pjobc)
+            .field("e_tdev", &self.e_tdev)
+            .field("e_tpgid", &self.e_tpgid)
+            .field("pbi_nice", &self.pbi_nice)
+            .field("pbi_start_tvsec", &self.pbi_start_tvsec)
+            .field("pbi_start_tvusec", &self.pbi_start_tvusec)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Debug for ProcBSDShortInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProcBSDShortInfo")
+            .field("pbsi_pid", &self.pbsi_pid)
+            .field("pbsi_ppid", &self.pbsi_ppid)
+            .field("pbsi_pgid", &self.pbsi_pgid)
+            .field("pbsi_status", &self.pbsi_status)
+            .field("pbsi_comm", &libc_str_to_string_lossy(&self.pbsi_comm))
+            .field("pbsi_flags", &self.pbsi_flags)
+            .field("pbsi_uid", &self.pbsi_uid)
+            .field("pbsi_gid", &self.pbsi_gid)
+            .field("pbsi_ruid", &self.pbsi_ruid)
+            .field("pbsi_rgid", &self.pbsi_rgid)
+            .field("pbsi_svuid", &self.pbsi_svuid)
+            .field("pbsi_svgid", &self.pbsi_svgid)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Debug for VnodeInfoPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VnodeInfoPath")
+            .field("vip_vi", &self.vip_vi)
+            .field("vip_path", &libc_str_to_string_lossy(&self.vip_path))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::darwin::{getpid, proc_pidfdinfo, proc_pidinfo};
+    use std::os::fd::AsRawFd;
+
+    #[test]
+    fn test_byte_size() {
+        assert_eq!(ByteSize(0).to_string(), "0 B");
+        assert_eq!(ByteSize(1023).to_string(), "1023 B");
+        assert_eq!(ByteSize(1024).to_string(), "1.0 KiB");
+        assert_eq!(ByteSize(5 * 1024 * 1024 * 1024).to_string(), "5.0 GiB");
+        assert_eq!(ByteSize(u64::MAX).to_string(), "16.0 EiB");
+    }
+
+    #[test]
+    fn test_file_mode() {
+        assert_eq!(FileMode(0o100644).to_string(), "-rw-r--r--");
+        assert_eq!(FileMode(0o104755).to_string(), "-rwsr-xr-x");
+        assert_eq!(FileMode(0o102644).to_string(), "-rw-r-Sr--");
+        assert_eq!(FileMode(0o120777).to_string(), "lrwxrwxrwx");
+    }
+
+    #[test]
+    fn test_display_self() {
+        let info = proc_pidinfo::<ProcBSDInfo>(getpid()).unwrap().unwrap();
+        let text = info.to_string();
+        assert!(text.starts_with(&format!("pid {} (", getpid())), "{text}");
+
+        let task = proc_pidinfo::<ProcTaskInfo>(getpid()).unwrap().unwrap();
+        assert!(task.to_string().contains(" resident, "));
+
+        let file = std::fs::File::open("/dev/null").unwrap();
+        let vnode = proc_pidfdinfo::<VnodeFdInfoWithPath>(getpid(), Fd(file.as_raw_fd()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(vnode.to_string(), "/dev/null (crw-rw-rw-, 0 B, offset 0)");
+    }
+
+    #[test]
+    fn test_debug_self() {
+        let info = proc_pidinfo::<ProcBSDInfo>(getpid()).unwrap().unwrap();
+        let comm = info.to_owned_info().comm;
+        let text = format!("{info:?}");
+        assert!(text.contains(&format!("pbi_comm: {comm:?}")), "{text}");
+        assert!(text.len() < 1000, "{text}");
+
+        let short = proc_pidinfo::<ProcBSDShortInfo>(getpid()).unwrap().unwrap();
+        assert!(format!("{short:?}").contains(&format!("pbsi_comm: {comm:?}")));
+
+        let file = std::fs::File::open("/dev/null").unwrap();
+        let vnode = proc_pidfdinfo::<VnodeFdInfoWithPath>(getpid(), Fd(file.as_raw_fd()))
+            .unwrap()
+            .unwrap();
+        let text = format!("{vnode:?}");
+        assert!(text.contains(r#"vip_path: "/dev/null""#), "{text}");
+        assert!(text.len() < 2000, "{text}");
+    }
+}
@@ -0,0 +1,241 @@
+use std::ffi::{OsStr, OsString};
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::path::PathBuf;
+
+use libc::{c_int, c_void};
+
+use super::{last_os_error, Pid};
+
+/// The arguments and environment of a process, as read from the `KERN_PROCARGS2` sysctl.
+///
+/// These are the values the process was started with. A process can overwrite its own argument
+/// and environment memory, so they are informational rather than authoritative.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcArgs {
+    /// The path passed to `exec`, which may be relative. See [`super::proc_pidpath`] for the
+    /// resolved path of the executable.
+    pub exec_path: PathBuf,
+    /// The arguments, including `argv[0]`.
+    pub args: Vec<OsString>,
+    /// The environment, as `KEY=value` entries.
+    pub env: Vec<OsString>,
+}
+
+impl ProcArgs {
+    /// Look up an environment variable.
+    pub fn env_var(&self, key: impl AsRef<OsStr>) -> Option<&OsStr> {
+        self.env
+            .iter()
+            .find_map(|entry| env_value(entry, key.as_ref()))
+    }
+}
+
+/// Split a `KEY=value` entry, returning the value if the key matches.
+fn env_value<'a>(entry: &'a OsStr, key: &OsStr) -> Option<&'a OsStr> {
+    let entry = entry.as_bytes();
+    let key = key.as_bytes();
+    if entry.len() > key.len() && entry.starts_with(key) && entry[key.len()] == b'=' {
+        Some(OsStr::from_bytes(&entry[key.len() + 1..]))
+    } else {
+        None
+    }
+}
+
+/// Get the arguments and environment of a process.
+///
+/// Only the current user's processes can be read without root. Fails with `EINVAL` for
+/// processes that can't be read, including zombies.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let args = proc_pidargs(getpid()).unwrap();
+/// assert_eq!(args.args, std::env::args_os().collect::<Vec<_>>());
+/// ```
+pub fn proc_pidargs(pid: Pid) -> Result<ProcArgs, std::io::Error> {
+    let buffer = procargs_buffer(pid)?;
+    parse_procargs(&buffer).ok_or_else(malformed)
+}
+
+/// Returns true if a process was started with the given environment variable, even if empty.
+///
+/// This scans the raw `KERN_PROCARGS2` buffer in place, without decoding the arguments or
+/// copying the environment, so it's cheap enough to run against every process. The same
+/// permission rules as [`proc_pidargs`] apply.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// for pid in proc_listallpids().unwrap() {
+///     if process_has_env(pid, "DYLD_INSERT_LIBRARIES").unwrap_or(false) {
+///         println!("{} has injected libraries", pid.0);
+///     }
+/// }
+/// ```
+pub fn process_has_env(pid: Pid, key: impl AsRef<OsStr>) -> Result<bool, std::io::Error> {
+    let buffer = procargs_buffer(pid)?;
+    let parts = ProcArgsParts::split(&buffer).ok_or_else(malformed)?;
+    let key = key.as_ref();
+    let found = parts
+        .env()
+        .any(|entry| env_value(OsStr::from_bytes(entry), key).is_some());
+    Ok(found)
+}
+
+fn malformed() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        "Malformed KERN_PROCARGS2 buffer",
+    )
+}
+
+/// Read the raw `KERN_PROCARGS2` buffer for a process.
+pub(crate) fn procargs_buffer(pid: Pid) -> Result<Vec<u8>, std::io::Error> {
+    let mut mib = [libc::CTL_KERN, libc::KERN_ARGMAX];
+    let mut argmax: c_int = 0;
+    let mut len = std::mem::size_of::<c_int>();
+    // SAFETY: The output is a single c_int with its size.
+    let res = unsafe {
+        libc::sysctl(
+            mib.as_mut_ptr(),
+            mib.len() as _,
+            &mut argmax as *mut c_int as *mut c_void,
+            &mut len,
+            std::ptr::null_mut(),
+            0,
+        )
+    };
+    if res != 0 {
+        return Err(last_os_error());
+    }
+
+    let mut mib = [libc::CTL_KERN, libc::KERN_PROCARGS2, pid.0 as c_int];
+    let mut buffer = vec![0_u8; argmax as usize];
+    let mut len = buffer.len();
+    // SAFETY: The buffer is KERN_ARGMAX bytes, the most the kernel will write.
+    let res = unsafe {
+        libc::sysctl(
+            mib.as_mut_ptr(),
+            mib.len() as _,
+            buffer.as_mut_ptr() as *mut c_void,
+            &mut len,
+            std::ptr::null_mut(),
+            0,
+        )
+    };
+    if res != 0 {
+        return Err(last_os_error());
+    }
+    buffer.truncate(len);
+    Ok(buffer)
+}
+
+/// A `KERN_PROCARGS2` buffer split into its NUL-terminated strings, without copying.
+///
+/// The layout is `argc`, the exec path, NUL padding, `argc` arguments, the environment, an empty
+/// string, and then the loader's `apple` strings, which are skipped.
+pub(crate) struct ProcArgsParts<'a> {
+    pub exec_path: &'a [u8],
+    pub args: Vec<&'a [u8]>,
+    env: &'a [u8],
+}
+
+impl<'a> ProcArgsParts<'a> {
+    pub fn split(buffer: &'a [u8]) -> Option<Self> {
+        let argc = c_int::from_ne_bytes(buffer.get(..4)?.try_into().ok()?).max(0) as usize;
+        let rest = &buffer[4..];
+        let exec_len = rest.iter().position(|&b| b == 0)?;
+        let (exec_path, mut rest) = rest.split_at(exec_len);
+        let padding = rest.iter().position(|&b| b != 0).unwrap_or(rest.len());
+        rest = &rest[padding..];
+
+        let mut args = Vec::with_capacity(argc);
+        while args.len() < argc {
+            let len = rest.iter().position(|&b| b == 0)?;
+            args.push(&rest[..len]);
+            rest = &rest[len + 1..];
+        }
+        Some(Self {
+            exec_path,
+            args,
+            env: rest,
+        })
+    }
+
+    /// The environment entries, stopping at the empty string before the `apple` strings.
+    pub fn env(&self) -> impl Iterator<Item = &'a [u8]> {
+        self.env.split(|&b| b == 0).take_while(|s| !s.is_empty())
+    }
+}
+
+fn parse_procargs(buffer: &[u8]) -> Option<ProcArgs> {
+    let parts = ProcArgsParts::split(buffer)?;
+    let owned = |s: &[u8]| OsString::from_vec(s.to_vec());
+    Some(ProcArgs {
+        exec_path: PathBuf::from(owned(parts.exec_path)),
+        args: parts.args.iter().copied().map(owned).collect(),
+        env: parts.env().map(owned).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::darwin::getpid;
+    use crate::testutil::TestChild;
+
+    fn buffer(argc: c_int, rest: &[u8]) -> Vec<u8> {
+        let mut buffer = argc.to_ne_bytes().to_vec();
+        buffer.extend_from_slice(rest);
+        buffer
+    }
+
+    #[test]
+    fn test_parse_procargs() {
+        let args = parse_procargs(&buffer(
+            2,
+            b"/bin/ls\0\0\0\0ls\0-l\0HOME=/Users/me\0A=\0\0executable_path=/bin/ls\0",
+        ))
+        .unwrap();
+        assert_eq!(args.exec_path, PathBuf::from("/bin/ls"));
+        assert_eq!(args.args, ["ls", "-l"]);
+        assert_eq!(args.env, ["HOME=/Users/me", "A="]);
+        assert_eq!(args.env_var("HOME"), Some(OsStr::new("/Users/me")));
+        assert_eq!(args.env_var("A"), Some(OsStr::new("")));
+        assert_eq!(args.env_var("HOM"), None);
+
+        assert!(parse_procargs(&buffer(3, b"/bin/ls\0ls\0")).is_none());
+        assert!(parse_procargs(b"\0\0").is_none());
+    }
+
+    #[test]
+    fn test_proc_pidargs_self() {
+        let args = proc_pidargs(getpid()).unwrap();
+        assert_eq!(args.args, std::env::args_os().collect::<Vec<_>>());
+        assert_eq!(args.env_var("PATH"), std::env::var_os("PATH").as_deref());
+    }
+
+    #[test]
+    fn test_process_has_env() {
+        let child = TestChild::spawn(
+            std::process::Command::new("/bin/sleep")
+                .arg("10")
+                .env("PROC_PIDINFO_TEST", ""),
+        );
+        let pid = child.pid();
+        // Wait for the exec to complete, so that the child's arguments are the new ones.
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while !proc_pidargs(pid)
+            .is_ok_and(|args| args.args.first().is_some_and(|arg| arg == "/bin/sleep"))
+        {
+            assert!(
+                std::time::Instant::now() < deadline,
+                "Child never exec'd /bin/sleep"
+            );
+            std::thread::yield_now();
+        }
+        assert!(process_has_env(pid, "PROC_PIDINFO_TEST").unwrap());
+        assert!(!process_has_env(pid, "PROC_PIDINFO_TEST_MISSING").unwrap());
+        assert!(!process_has_env(pid, "10").unwrap());
+    }
+}
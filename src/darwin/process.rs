@@ -0,0 +1,384 @@
+use std::ffi::OsString;
+use std::os::unix::ffi::OsStringExt;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use libc::c_void;
+
+use super::{
+    codesign_info, codesign_info_audittoken, last_os_error, proc_pidinfo, proc_pidinfo_list,
+    proc_pidpath, AuditToken, CodeSignInfo, HasFlavor, Pid, ProcBSDInfo, ProcFDInfo, ProcTaskInfo,
+    ProcUniqueIdentifierInfo,
+};
+
+mod ffi {
+    use libc::{c_int, c_void, mach_port_t};
+
+    use super::AuditToken;
+
+    extern "C" {
+        pub static mach_task_self_: mach_port_t;
+        pub fn proc_pidpath_audittoken(
+            token: *const AuditToken,
+            buffer: *mut c_void,
+            buffersize: u32,
+        ) -> c_int;
+    }
+}
+
+const TASK_AUDIT_TOKEN: libc::task_flavor_t = 15;
+
+impl AuditToken {
+    /// The audit token of the current process.
+    pub fn current() -> Result<Self, std::io::Error> {
+        let mut token = AuditToken { val: [0; 8] };
+        let mut count = token.val.len() as libc::mach_msg_type_number_t;
+        // SAFETY: The task port is set up before any Rust code runs. The count is the size of
+        // the token in integers.
+        let kr = unsafe {
+            libc::task_info(
+                ffi::mach_task_self_,
+                TASK_AUDIT_TOKEN,
+                token.val.as_mut_ptr() as libc::task_info_t,
+                &mut count,
+            )
+        };
+        if kr != libc::KERN_SUCCESS {
+            return Err(std::io::Error::other(format!("task_info failed: {kr}")));
+        }
+        Ok(token)
+    }
+}
+
+/// Get the path of the executable of the process identified by an audit token. Unlike
+/// [`proc_pidpath`], this fails with `ESRCH` if the pid has been reused.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let token = AuditToken::current().unwrap();
+/// assert_eq!(proc_pidpath_audittoken(&token).unwrap(), proc_pidpath(getpid()).unwrap());
+/// ```
+pub fn proc_pidpath_audittoken(token: &AuditToken) -> Result<PathBuf, std::io::Error> {
+    const PROC_PIDPATHINFO_MAXSIZE: usize = 4 * libc::MAXPATHLEN as usize;
+    let mut buffer = vec![0_u8; PROC_PIDPATHINFO_MAXSIZE];
+    // SAFETY: The buffer is as large as the kernel will ever write.
+    let res = unsafe {
+        ffi::proc_pidpath_audittoken(
+            token,
+            buffer.as_mut_ptr() as *mut c_void,
+            buffer.len() as u32,
+        )
+    };
+    if res <= 0 {
+        return Err(last_os_error());
+    }
+    buffer.truncate(res as usize);
+    Ok(PathBuf::from(OsString::from_vec(buffer)))
+}
+
+/// A process pinned to a single instance of its pid, so that queries fail with `ESRCH`
+/// rather than silently describing a later process that reused the pid.
+///
+/// Each query checks the version of the pid after reading, so a result is only returned if
+/// the process was still running when it was read. Servers that receive an [`AuditToken`]
+/// from Endpoint Security or XPC should use [`Process::from_audit_token`], which also lets
+/// the kernel check the token for the calls that accept one.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let process = Process::from_audit_token(AuditToken::current().unwrap()).unwrap();
+/// let info = process.bsd_info().unwrap().unwrap();
+/// println!("{} {:?}", info.pbi_pid.0, process.path().unwrap());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Process {
+    pid: Pid,
+    pidversion: u32,
+    token: Option<AuditToken>,
+}
+
+#[allow(private_bounds)]
+impl Process {
+    /// The current process.
+    pub fn current() -> Result<Self, std::io::Error> {
+        Self::from_audit_token(AuditToken::current()?)
+    }
+
+    /// The process identified by an audit token. Fails with `ESRCH` if it has exited.
+    pub fn from_audit_token(token: AuditToken) -> Result<Self, std::io::Error> {
+        let process = Self {
+            pid: token.pid(),
+            pidversion: token.pidversion(),
+            token: Some(token),
+        };
+        process.validate()?;
+        Ok(process)
+    }
+
+    /// The process currently running with a pid. This is only as reliable as the pid: if it
+    /// was reused before this call, this is the new process.
+    pub fn from_pid(pid: Pid) -> Result<Self, std::io::Error> {
+        Ok(Self {
+            pid,
+            pidversion: current_pidversion(pid)?,
+            token: None,
+        })
+    }
+
+    pub fn pid(&self) -> Pid {
+        self.pid
+    }
+
+    /// The version of the pid, which changes when the pid is reused.
+    pub fn pidversion(&self) -> u32 {
+        self.pidversion
+    }
+
+    /// The audit token this process was created from, if any.
+    pub fn audit_token(&self) -> Option<&AuditToken> {
+        self.token.as_ref()
+    }
+
+    /// Fails with `ESRCH` if the process has exited, even if its pid has been reused.
+    pub fn validate(&self) -> Result<(), std::io::Error> {
+        if current_pidversion(self.pid)? != self.pidversion {
+            return Err(std::io::Error::from_raw_os_error(libc::ESRCH));
+        }
+        Ok(())
+    }
+
+    /// Returns true if the process is still running.
+    pub fn is_running(&self) -> bool {
+        self.validate().is_ok()
+    }
+
+    /// Get any info struct for this process. See [`proc_pidinfo`].
+    pub fn info<T: HasFlavor>(&self) -> Result<Option<T>, std::io::Error> {
+        self.checked(proc_pidinfo(self.pid))
+    }
+
+    /// Get the [`ProcBSDInfo`] for this process.
+    pub fn bsd_info(&self) -> Result<Option<ProcBSDInfo>, std::io::Error> {
+        self.info()
+    }
+
+    /// Get the [`ProcTaskInfo`] for this process.
+    pub fn task_info(&self) -> Result<Option<ProcTaskInfo>, std::io::Error> {
+        self.info()
+    }
+
+    /// List the open file descriptors of this process.
+    pub fn fds(&self) -> Result<Vec<ProcFDInfo>, std::io::Error> {
+        self.checked(proc_pidinfo_list(self.pid))
+    }
+
+    /// The path of the executable, using [`proc_pidpath_audittoken`] where there's a token.
+    pub fn path(&self) -> Result<PathBuf, std::io::Error> {
+        match &self.token {
+            Some(token) => proc_pidpath_audittoken(token),
+            None => self.checked(proc_pidpath(self.pid)),
+        }
+    }
+
+    /// The code signature, using [`codesign_info_audittoken`] where there's a token.
+    pub fn codesign_info(&self) -> Result<CodeSignInfo, std::io::Error> {
+        match &self.token {
+            Some(token) => codesign_info_audittoken(token),
+            None => self.checked(codesign_info(self.pid)),
+        }
+    }
+
+    /// Discard a result read by pid if the process has since exited.
+    fn checked<T>(&self, result: Result<T, std::io::Error>) -> Result<T, std::io::Error> {
+        let value = result?;
+        self.validate()?;
+        Ok(value)
+    }
+}
+
+/// A pid together with the unique id and start time of the process that had it when the
+/// identity was captured, so that it can be stored and compared across a long-running tool's
+/// samples without confusing a process with a later one that reused its pid.
+///
+/// The unique id (`p_uniqueid` from [`ProcUniqueIdentifierInfo`]) is never reused until reboot,
+/// so two identities are equal only if they are the same process. The query methods read the
+/// data and then check the identity, and fail with `ESRCH` if the process has exited, even if
+/// its pid was reused in between. See [`Process`] to pin a process from an [`AuditToken`].
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let identity = ProcessIdentity::capture(getpid()).unwrap();
+/// assert!(identity.still_valid());
+/// let info = identity.bsd_info().unwrap().unwrap();
+/// println!("{} started at {:?}", info.pbi_pid.0, identity.start_time());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ProcessIdentity {
+    pid: Pid,
+    unique_id: u64,
+    start_time: Option<SystemTime>,
+}
+
+#[allow(private_bounds)]
+impl ProcessIdentity {
+    /// Capture the identity of the process currently running with a pid. Fails with `ESRCH`
+    /// if there is none.
+    pub fn capture(pid: Pid) -> Result<Self, std::io::Error> {
+        let unique_id = current_unique_id(pid)?;
+        let start_time = proc_pidinfo::<ProcBSDInfo>(pid).ok().flatten().map(|info| {
+            SystemTime::UNIX_EPOCH
+                + Duration::from_secs(info.pbi_start_tvsec)
+                + Duration::from_micros(info.pbi_start_tvusec)
+        });
+        let identity = Self {
+            pid,
+            unique_id,
+            start_time,
+        };
+        // The pid may have been reused between the two reads.
+        identity.validate()?;
+        Ok(identity)
+    }
+
+    pub fn pid(&self) -> Pid {
+        self.pid
+    }
+
+    /// The unique id of the process, which is never reused until reboot.
+    pub fn unique_id(&self) -> u64 {
+        self.unique_id
+    }
+
+    /// When the process started, or `None` if it couldn't be read when the identity was
+    /// captured.
+    pub fn start_time(&self) -> Option<SystemTime> {
+        self.start_time
+    }
+
+    /// Fails with `ESRCH` if the process has exited, even if its pid has been reused.
+    pub fn validate(&self) -> Result<(), std::io::Error> {
+        if current_unique_id(self.pid)? != self.unique_id {
+            return Err(std::io::Error::from_raw_os_error(libc::ESRCH));
+        }
+        Ok(())
+    }
+
+    /// Returns true if the process is still running.
+    pub fn still_valid(&self) -> bool {
+        self.validate().is_ok()
+    }
+
+    /// Get any info struct for this process. See [`proc_pidinfo`].
+    pub fn info<T: HasFlavor>(&self) -> Result<Option<T>, std::io::Error> {
+        self.checked(proc_pidinfo(self.pid))
+    }
+
+    /// Get the [`ProcBSDInfo`] for this process.
+    pub fn bsd_info(&self) -> Result<Option<ProcBSDInfo>, std::io::Error> {
+        self.info()
+    }
+
+    /// Get the [`ProcTaskInfo`] for this process.
+    pub fn task_info(&self) -> Result<Option<ProcTaskInfo>, std::io::Error> {
+        self.info()
+    }
+
+    /// List the open file descriptors of this process.
+    pub fn fds(&self) -> Result<Vec<ProcFDInfo>, std::io::Error> {
+        self.checked(proc_pidinfo_list(self.pid))
+    }
+
+    /// The path of the executable.
+    pub fn path(&self) -> Result<PathBuf, std::io::Error> {
+        self.checked(proc_pidpath(self.pid))
+    }
+
+    /// Discard a result read by pid if the process has since exited.
+    fn checked<T>(&self, result: Result<T, std::io::Error>) -> Result<T, std::io::Error> {
+        let value = result?;
+        self.validate()?;
+        Ok(value)
+    }
+}
+
+/// The unique id of the process that is currently running with a pid.
+fn current_unique_id(pid: Pid) -> Result<u64, std::io::Error> {
+    match proc_pidinfo::<ProcUniqueIdentifierInfo>(pid)? {
+        Some(info) => Ok(info.p_uniqueid),
+        None => Err(std::io::Error::from_raw_os_error(libc::ESRCH)),
+    }
+}
+
+/// The version of the pid that is currently running.
+fn current_pidversion(pid: Pid) -> Result<u32, std::io::Error> {
+    match proc_pidinfo::<ProcUniqueIdentifierInfo>(pid)? {
+        Some(info) => Ok(info.p_idversion as u32),
+        None => Err(std::io::Error::from_raw_os_error(libc::ESRCH)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::darwin::getpid;
+    use crate::testutil::TestChild;
+
+    #[test]
+    fn test_process_current() {
+        let token = AuditToken::current().unwrap();
+        assert_eq!(token.pid(), getpid());
+        let process = Process::current().unwrap();
+        assert_eq!(process, Process::from_audit_token(token).unwrap());
+        assert_eq!(
+            Process::from_pid(getpid()).unwrap().pidversion(),
+            token.pidversion()
+        );
+        assert!(process.is_running());
+        assert_eq!(process.bsd_info().unwrap().unwrap().pbi_pid, getpid());
+        assert_eq!(process.path().unwrap(), proc_pidpath(getpid()).unwrap());
+        assert!(!process.fds().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_process_stale() {
+        let mut token = AuditToken::current().unwrap();
+        token.val[7] = token.val[7].wrapping_add(1);
+        let err = Process::from_audit_token(token).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::ESRCH));
+        let err = proc_pidpath_audittoken(&token).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::ESRCH));
+
+        let mut child = TestChild::sleep();
+        let process = Process::from_pid(child.pid()).unwrap();
+        assert!(process.task_info().unwrap().is_some());
+        child.kill().unwrap();
+        child.wait().unwrap();
+        assert!(!process.is_running());
+        assert!(process.bsd_info().is_err());
+    }
+
+    #[test]
+    fn test_process_identity() {
+        let identity = ProcessIdentity::capture(getpid()).unwrap();
+        assert_eq!(identity, ProcessIdentity::capture(getpid()).unwrap());
+        assert!(identity.still_valid());
+        assert!(identity.start_time().unwrap() <= SystemTime::now());
+        assert_eq!(identity.path().unwrap(), proc_pidpath(getpid()).unwrap());
+
+        let mut child = TestChild::sleep();
+        let child_identity = ProcessIdentity::capture(child.pid()).unwrap();
+        assert_ne!(child_identity, identity);
+        assert!(child_identity.unique_id() > identity.unique_id());
+        assert!(child_identity.task_info().unwrap().is_some());
+        child.kill().unwrap();
+        child.wait().unwrap();
+        assert!(!child_identity.still_valid());
+        let err = child_identity.bsd_info().unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::ESRCH));
+        assert!(ProcessIdentity::capture(child.pid()).is_err());
+    }
+}
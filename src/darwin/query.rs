@@ -0,0 +1,276 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use super::{
+    proc_listallpids, proc_pidinfo, proc_pidinfo_list, proc_pidinfo_list_with_hint, proc_pidpath,
+    Pid, ProcBSDInfo, ProcFDInfo, ProcFDType, ProcTaskAllInfo, ProcTaskInfo,
+};
+
+/// A part of a [`ProcessQuery`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum QueryField {
+    TaskInfo,
+    BsdInfo,
+    ExePath,
+    FdSummary,
+}
+
+/// The number of open file descriptors of a process, by type.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FdSummary {
+    pub count: usize,
+    pub by_type: HashMap<ProcFDType, usize>,
+}
+
+impl FdSummary {
+    fn from_fds(fds: &[ProcFDInfo]) -> Self {
+        let mut by_type = HashMap::new();
+        for fd in fds {
+            *by_type.entry(fd.fd_type()).or_default() += 1;
+        }
+        Self {
+            count: fds.len(),
+            by_type,
+        }
+    }
+
+    /// The number of open file descriptors of one type.
+    pub fn count_of(&self, fd_type: ProcFDType) -> usize {
+        self.by_type.get(&fd_type).copied().unwrap_or_default()
+    }
+}
+
+/// What a [`ProcessQuery`] read about one process. Each requested field is either present or
+/// has an entry in [`ProcessQueryResult::errors`].
+#[derive(Debug)]
+pub struct ProcessQueryResult {
+    pub pid: Pid,
+    pub task_info: Option<ProcTaskInfo>,
+    pub bsd_info: Option<ProcBSDInfo>,
+    pub exe_path: Option<PathBuf>,
+    pub fd_summary: Option<FdSummary>,
+    /// The requested fields that couldn't be read, and why.
+    pub errors: Vec<(QueryField, std::io::Error)>,
+}
+
+impl ProcessQueryResult {
+    /// Why a field couldn't be read, if it was requested and failed.
+    pub fn error(&self, field: QueryField) -> Option<&std::io::Error> {
+        self.errors
+            .iter()
+            .find(|(failed, _)| *failed == field)
+            .map(|(_, err)| err)
+    }
+
+    /// Returns true if every requested field was read.
+    pub fn is_complete(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Returns true if every field failed with `ESRCH` because the process has exited. Fields
+    /// that failed for other reasons, eg: `EPERM`, mean the process is still there.
+    fn exited(&self) -> bool {
+        self.task_info.is_none()
+            && self.bsd_info.is_none()
+            && self.exe_path.is_none()
+            && self.fd_summary.is_none()
+            && self
+                .errors
+                .iter()
+                .all(|(_, err)| err.raw_os_error() == Some(libc::ESRCH))
+    }
+
+    fn record<T>(
+        &mut self,
+        field: QueryField,
+        result: Result<Option<T>, std::io::Error>,
+    ) -> Option<T> {
+        match result {
+            Ok(Some(value)) => Some(value),
+            // Not an exit: that fails with ESRCH.
+            Ok(None) => {
+                self.errors
+                    .push((field, std::io::Error::other("The kernel returned no data")));
+                None
+            }
+            Err(err) => {
+                self.errors.push((field, err));
+                None
+            }
+        }
+    }
+}
+
+/// A set of fields to read about a process, read with as few calls as possible.
+///
+/// Asking for both [`ProcessQuery::task_info`] and [`ProcessQuery::bsd_info`] reads them with
+/// one [`ProcTaskAllInfo`] call, falling back to separate calls if that fails, and
+/// [`ProcessQuery::fd_summary`] sizes its list from the BSD info's file count where one was
+/// read. A field that fails is recorded in [`ProcessQueryResult::errors`] without affecting the
+/// others.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let query = ProcessQuery::new().task_info().bsd_info().exe_path().fd_summary();
+/// for result in query.execute_all().unwrap() {
+///     match &result.exe_path {
+///         Some(path) => println!("{} {}", result.pid.0, path.display()),
+///         None => println!("{} {:?}", result.pid.0, result.error(QueryField::ExePath)),
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProcessQuery {
+    task_info: bool,
+    bsd_info: bool,
+    exe_path: bool,
+    fd_summary: bool,
+}
+
+impl ProcessQuery {
+    /// A query for nothing. Add fields with the builder methods.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read [`ProcTaskInfo`].
+    pub fn task_info(mut self) -> Self {
+        self.task_info = true;
+        self
+    }
+
+    /// Read [`ProcBSDInfo`].
+    pub fn bsd_info(mut self) -> Self {
+        self.bsd_info = true;
+        self
+    }
+
+    /// Read the executable path, with [`proc_pidpath`].
+    pub fn exe_path(mut self) -> Self {
+        self.exe_path = true;
+        self
+    }
+
+    /// Count the open file descriptors by type.
+    pub fn fd_summary(mut self) -> Self {
+        self.fd_summary = true;
+        self
+    }
+
+    /// Run the query for one process.
+    pub fn execute(&self, pid: Pid) -> ProcessQueryResult {
+        let mut result = ProcessQueryResult {
+            pid,
+            task_info: None,
+            bsd_info: None,
+            exe_path: None,
+            fd_summary: None,
+            errors: vec![],
+        };
+
+        if self.task_info && self.bsd_info {
+            if let Ok(Some(info)) = proc_pidinfo::<ProcTaskAllInfo>(pid) {
+                result.task_info = Some(info.ptinfo);
+                result.bsd_info = Some(info.pbsd);
+            }
+        }
+        if self.bsd_info && result.bsd_info.is_none() {
+            result.bsd_info = result.record(QueryField::BsdInfo, proc_pidinfo::<ProcBSDInfo>(pid));
+        }
+        if self.task_info && result.task_info.is_none() {
+            result.task_info =
+                result.record(QueryField::TaskInfo, proc_pidinfo::<ProcTaskInfo>(pid));
+        }
+        if self.fd_summary {
+            let fds = match result.bsd_info {
+                Some(info) => proc_pidinfo_list_with_hint::<ProcFDInfo>(pid, info.pbi_nfiles as _),
+                None => proc_pidinfo_list::<ProcFDInfo>(pid),
+            };
+            result.fd_summary = result.record(
+                QueryField::FdSummary,
+                fds.map(|fds| Some(FdSummary::from_fds(&fds))),
+            );
+        }
+        if self.exe_path {
+            result.exe_path = result.record(QueryField::ExePath, proc_pidpath(pid).map(Some));
+        }
+        result
+    }
+
+    /// Run the query for every process, skipping those that exit while it runs. Fails only if
+    /// the process list can't be read.
+    pub fn execute_all(&self) -> Result<Vec<ProcessQueryResult>, std::io::Error> {
+        Ok(proc_listallpids()?
+            .into_iter()
+            .map(|pid| self.execute(pid))
+            .filter(|result| !result.exited())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::darwin::getpid;
+
+    #[test]
+    fn test_query_self() {
+        let _file = std::fs::File::open("/dev/null").unwrap();
+        let result = ProcessQuery::new()
+            .task_info()
+            .bsd_info()
+            .exe_path()
+            .fd_summary()
+            .execute(getpid());
+        assert!(result.is_complete(), "{:?}", result.errors);
+        assert!(result.task_info.unwrap().pti_threadnum >= 1);
+        assert_eq!(result.bsd_info.unwrap().pbi_pid, getpid());
+        assert_eq!(
+            result.exe_path.unwrap(),
+            std::env::current_exe().unwrap().canonicalize().unwrap()
+        );
+        let fds = result.fd_summary.unwrap();
+        assert!(fds.count_of(ProcFDType::VNODE) >= 1);
+        assert_eq!(fds.count, fds.by_type.values().sum::<usize>());
+    }
+
+    #[test]
+    fn test_query_fields() {
+        let result = ProcessQuery::new().exe_path().execute(getpid());
+        assert!(result.exe_path.is_some());
+        assert!(result.task_info.is_none() && result.bsd_info.is_none());
+        assert!(result.is_complete());
+
+        let result = ProcessQuery::new().bsd_info().execute(Pid(99_999_999));
+        assert!(result.bsd_info.is_none());
+        assert!(result.error(QueryField::BsdInfo).is_some());
+        assert!(result.error(QueryField::TaskInfo).is_none());
+
+        let err = result.error(QueryField::BsdInfo).unwrap();
+        assert_eq!(err.raw_os_error(), Some(libc::ESRCH));
+
+        let all = ProcessQuery::new().bsd_info().execute_all().unwrap();
+        assert!(all.iter().any(|result| result.pid == getpid()));
+        assert!(all.iter().any(|result| result.pid == Pid(1)));
+    }
+
+    #[test]
+    fn test_query_denied() {
+        // SAFETY: geteuid never fails.
+        if unsafe { libc::geteuid() } == 0 {
+            return;
+        }
+        let query = ProcessQuery::new().task_info().fd_summary();
+        let result = query.execute(Pid(1));
+        assert!(result.task_info.is_none() && result.fd_summary.is_none());
+        for field in [QueryField::TaskInfo, QueryField::FdSummary] {
+            let err = result.error(field).unwrap();
+            assert_eq!(err.raw_os_error(), Some(libc::EPERM));
+        }
+        let all = query.execute_all().unwrap();
+        let launchd = all.iter().find(|result| result.pid == Pid(1)).unwrap();
+        assert!(!launchd.is_complete());
+    }
+}
@@ -0,0 +1,231 @@
+use libc::{c_int, c_void};
+
+use super::{libproc_call, Fd, FilePort, Pid};
+
+/// The buffer length to pass to libproc, which takes an `int`.
+fn buffer_size(buf: &[u8]) -> c_int {
+    buf.len().min(c_int::MAX as usize) as c_int
+}
+
+/// Call `proc_pidinfo` with a raw flavor, for flavors this crate doesn't have a type for.
+///
+/// Returns the number of bytes the kernel wrote into `buf`. Fails with the kernel's error,
+/// eg: `ENOMEM` if `buf` is too small for the flavor.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// // PROC_PIDT_SHORTBSDINFO
+/// let mut buf = [0; 64];
+/// let len = proc_pidinfo_raw(getpid(), 13, 0, &mut buf).unwrap();
+/// assert_eq!(len, std::mem::size_of::<ProcBSDShortInfo>());
+/// ```
+pub fn proc_pidinfo_raw(
+    pid: Pid,
+    flavor: i32,
+    arg: u64,
+    buf: &mut [u8],
+) -> Result<usize, std::io::Error> {
+    // SAFETY: The kernel writes at most `buffer_size(buf)` bytes.
+    libproc_call(|| unsafe {
+        super::ffi::proc_pidinfo(
+            pid.0 as _,
+            flavor,
+            arg,
+            buf.as_mut_ptr() as *mut c_void,
+            buffer_size(buf),
+        )
+    })
+}
+
+/// Call `proc_pidfdinfo` with a raw flavor. See [`proc_pidinfo_raw`].
+pub fn proc_pidfdinfo_raw(
+    pid: Pid,
+    fd: Fd,
+    flavor: i32,
+    buf: &mut [u8],
+) -> Result<usize, std::io::Error> {
+    // SAFETY: The kernel writes at most `buffer_size(buf)` bytes.
+    libproc_call(|| unsafe {
+        super::ffi::proc_pidfdinfo(
+            pid.0 as _,
+            fd.0,
+            flavor,
+            buf.as_mut_ptr() as *mut c_void,
+            buffer_size(buf),
+        )
+    })
+}
+
+/// Call `proc_pidfileportinfo` with a raw flavor. See [`proc_pidinfo_raw`].
+pub fn proc_pidfileportinfo_raw(
+    pid: Pid,
+    fileport: FilePort,
+    flavor: i32,
+    buf: &mut [u8],
+) -> Result<usize, std::io::Error> {
+    // SAFETY: The kernel writes at most `buffer_size(buf)` bytes.
+    libproc_call(|| unsafe {
+        super::ffi::proc_pidfileportinfo(
+            pid.0 as _,
+            fileport.0,
+            flavor,
+            buf.as_mut_ptr() as *mut c_void,
+            buffer_size(buf),
+        )
+    })
+}
+
+/// Call `proc_pidinfo` with a raw flavor and read the result as a `T`.
+///
+/// Like [`proc_pidinfo`](crate::proc_pidinfo), this returns `None` if the kernel returned
+/// nothing. It fails with `ENOMEM` if `T` is smaller than the kernel's struct for `flavor`,
+/// and with an error of kind [`std::io::ErrorKind::InvalidData`] if the kernel returned
+/// something other than `size_of::<T>()` bytes.
+///
+/// # Safety
+///
+/// `T` must be a `#[repr(C)]` struct matching the kernel's layout for `flavor`, and every
+/// bit pattern the kernel writes must be a valid `T`.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// // PROC_PIDT_SHORTBSDINFO
+/// let info = unsafe { proc_pidinfo_as::<ProcBSDShortInfo>(getpid(), 13, 0) }.unwrap().unwrap();
+/// assert_eq!(info.pbsi_pid, getpid());
+/// ```
+pub unsafe fn proc_pidinfo_as<T>(
+    pid: Pid,
+    flavor: i32,
+    arg: u64,
+) -> Result<Option<T>, std::io::Error> {
+    // SAFETY: Forwarded to the caller. Only full-size replies are accepted, so nothing is left
+    // zeroed.
+    let value = unsafe { proc_pidinfo_sized(pid, flavor, arg, std::mem::size_of::<T>())? };
+    Ok(value.map(|(value, _)| value))
+}
+
+/// Call `proc_pidinfo`, accepting any reply of at least `min_size` bytes. The rest of the
+/// struct is zeroed. Returns the struct and the number of bytes the kernel returned.
+///
+/// # Safety
+///
+/// As for [`proc_pidinfo_as`]. If `min_size` is less than `size_of::<T>()`, zero bytes must
+/// also be valid for the rest of `T`.
+pub(crate) unsafe fn proc_pidinfo_sized<T>(
+    pid: Pid,
+    flavor: i32,
+    arg: u64,
+    min_size: usize,
+) -> Result<Option<(T, usize)>, std::io::Error> {
+    // SAFETY: We check the size of the return value to ensure it's valid.
+    unsafe {
+        let mut value = std::mem::MaybeUninit::<T>::zeroed();
+        let buffersize = std::mem::size_of::<T>() as c_int;
+        let res = libproc_call(|| {
+            super::ffi::proc_pidinfo(
+                pid.0 as _,
+                flavor,
+                arg,
+                value.as_mut_ptr() as *mut c_void,
+                buffersize,
+            )
+        })? as c_int;
+        if res == 0 {
+            return Ok(None);
+        }
+        if (res as usize) < min_size || res > buffersize {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Unexpected buffer size {res}, expected {min_size}..={buffersize}"),
+            ));
+        }
+        Ok(Some((value.assume_init(), res as usize)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::darwin::{
+        getpid, proc_pidinfo_self, proc_pidinfo_with_size, ProcBSDInfo, ProcBSDShortInfo,
+        ProcPidFdInfoFlavor, ProcPidInfoFlavor, VnodeFdInfoWithPath,
+    };
+
+    #[test]
+    fn test_proc_pidinfo_raw() {
+        let mut buf = vec![0; std::mem::size_of::<ProcBSDInfo>()];
+        let len = proc_pidinfo_raw(
+            getpid(),
+            ProcPidInfoFlavor::PROC_PIDTBSDINFO as i32,
+            0,
+            &mut buf,
+        )
+        .unwrap();
+        assert_eq!(len, buf.len());
+        let err = proc_pidinfo_raw(
+            getpid(),
+            ProcPidInfoFlavor::PROC_PIDTBSDINFO as i32,
+            0,
+            &mut buf[..8],
+        )
+        .unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::ENOMEM));
+
+        let typed = proc_pidinfo_self::<ProcBSDInfo>().unwrap().unwrap();
+        // SAFETY: PROC_PIDTBSDINFO returns a ProcBSDInfo.
+        let raw = unsafe {
+            proc_pidinfo_as::<ProcBSDInfo>(getpid(), ProcPidInfoFlavor::PROC_PIDTBSDINFO as i32, 0)
+        }
+        .unwrap()
+        .unwrap();
+        assert_eq!(raw.pbi_pid, typed.pbi_pid);
+        assert_eq!(raw.pbi_start_tvsec, typed.pbi_start_tvsec);
+    }
+
+    #[test]
+    fn test_proc_pidinfo_as_wrong_size() {
+        // SAFETY: Any bit pattern is a valid u8; the size mismatch is reported as an error.
+        let err = unsafe {
+            proc_pidinfo_as::<[u8; 8]>(getpid(), ProcPidInfoFlavor::PROC_PIDTBSDINFO as i32, 0)
+        };
+        assert_eq!(err.unwrap_err().raw_os_error(), Some(libc::ENOMEM));
+    }
+
+    #[test]
+    fn test_proc_pidinfo_sized() {
+        const SIZE: usize = std::mem::size_of::<ProcBSDShortInfo>();
+        let flavor = ProcPidInfoFlavor::PROC_PIDT_SHORTBSDINFO as i32;
+        // SAFETY: Any bit pattern is a valid u8.
+        let (buf, len) = unsafe { proc_pidinfo_sized::<[u8; SIZE * 2]>(getpid(), flavor, 0, SIZE) }
+            .unwrap()
+            .unwrap();
+        assert_eq!(len, SIZE);
+        assert!(buf[SIZE..].iter().all(|&byte| byte == 0));
+
+        // SAFETY: As above; the short reply is reported as an error.
+        let err = unsafe { proc_pidinfo_sized::<[u8; SIZE * 2]>(getpid(), flavor, 0, SIZE + 1) };
+        assert!(err.is_err());
+
+        let (_, len) = proc_pidinfo_with_size::<ProcBSDShortInfo>(getpid())
+            .unwrap()
+            .unwrap();
+        assert_eq!(len, SIZE);
+    }
+
+    #[test]
+    fn test_proc_pidfdinfo_raw() {
+        let file = std::fs::File::open("/dev/null").unwrap();
+        let fd = Fd(std::os::fd::AsRawFd::as_raw_fd(&file));
+        let mut buf = vec![0; std::mem::size_of::<VnodeFdInfoWithPath>()];
+        let len = proc_pidfdinfo_raw(
+            getpid(),
+            fd,
+            ProcPidFdInfoFlavor::PROC_PIDFDVNODEPATHINFO as i32,
+            &mut buf,
+        )
+        .unwrap();
+        assert_eq!(len, buf.len());
+    }
+}
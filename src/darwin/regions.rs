@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::{
+    proc_pidinfo_with_arg, ArgFlavor, HasFlavorArg, Pid, ProcPidInfoFlavor, ValueError,
+    VnodeInfoPath,
+};
+
+/// A region of a process's address space (`proc_regioninfo`). See [`proc_pidregioninfo`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct ProcRegionInfo {
+    /// `VM_PROT_*` bits.
+    pub pri_protection: u32,
+    pub pri_max_protection: u32,
+    pub pri_inheritance: u32,
+    pub pri_flags: u32,
+    /// The offset of the region in the object it maps, eg: a file.
+    pub pri_offset: u64,
+    pub pri_behavior: u32,
+    pub pri_user_wired_count: u32,
+    /// The `VM_MEMORY_*` tag, eg: `VM_MEMORY_MALLOC`.
+    pub pri_user_tag: u32,
+    pub pri_pages_resident: u32,
+    pub pri_pages_shared_now_private: u32,
+    pub pri_pages_swapped_out: u32,
+    pub pri_pages_dirtied: u32,
+    pub pri_ref_count: u32,
+    pub pri_shadow_depth: u32,
+    pub pri_share_mode: u32,
+    pub pri_private_pages_resident: u32,
+    pub pri_shared_pages_resident: u32,
+    pub pri_obj_id: u32,
+    pub pri_depth: u32,
+    pub pri_address: u64,
+    pub pri_size: u64,
+}
+
+impl ArgFlavor for ProcRegionInfo {
+    const FLAVOR: ProcPidInfoFlavor = ProcPidInfoFlavor::PROC_PIDREGIONINFO;
+}
+
+impl HasFlavorArg for ProcRegionInfo {
+    type Arg = u64;
+}
+
+impl ProcRegionInfo {
+    /// The address just past the end of the region, where the next lookup should start.
+    pub fn end(&self) -> u64 {
+        self.pri_address.saturating_add(self.pri_size)
+    }
+}
+
+/// A region of a process's address space, with the file it maps
+/// (`proc_regionwithpathinfo`). See [`proc_pidregionpathinfo`].
+#[repr(C)]
+#[derive(Debug)]
+pub struct ProcRegionWithPathInfo {
+    pub prp_prinfo: ProcRegionInfo,
+    /// The mapped file. The path is empty for anonymous memory.
+    pub prp_vip: VnodeInfoPath,
+}
+
+impl ArgFlavor for ProcRegionWithPathInfo {
+    const FLAVOR: ProcPidInfoFlavor = ProcPidInfoFlavor::PROC_PIDREGIONPATHINFO;
+}
+
+impl HasFlavorArg for ProcRegionWithPathInfo {
+    type Arg = u64;
+}
+
+impl ProcRegionWithPathInfo {
+    pub fn path(&self) -> Result<&Path, ValueError> {
+        self.prp_vip.path()
+    }
+
+    /// An owned copy of [`ProcRegionWithPathInfo::path`].
+    pub fn to_path_buf(&self) -> Result<PathBuf, ValueError> {
+        self.prp_vip.to_path_buf()
+    }
+}
+
+/// Get the region of a process's address space that contains `address`, or the next region
+/// after it. Walk the address space by passing [`ProcRegionInfo::end`] to the next call.
+///
+/// Fails with `EINVAL` past the last region, and with `EPERM` for other users' processes
+/// unless running as root.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let mut address = 0;
+/// while let Ok(Some(region)) = proc_pidregioninfo(getpid(), address) {
+///     println!("{:#x} {} bytes", region.pri_address, region.pri_size);
+///     address = region.end();
+/// }
+/// ```
+pub fn proc_pidregioninfo(
+    pid: Pid,
+    address: u64,
+) -> Result<Option<ProcRegionInfo>, std::io::Error> {
+    proc_pidinfo_with_arg(pid, address)
+}
+
+/// Like [`proc_pidregioninfo`], and also returns the path of the file the region maps.
+pub fn proc_pidregionpathinfo(
+    pid: Pid,
+    address: u64,
+) -> Result<Option<ProcRegionWithPathInfo>, std::io::Error> {
+    proc_pidinfo_with_arg(pid, address)
+}
+
+/// A range of addresses a file is mapped at. See [`MappedFile`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MappedRange {
+    pub start: u64,
+    /// The address just past the end of the range.
+    pub end: u64,
+    /// The offset in the file that `start` maps.
+    pub offset: u64,
+    /// `VM_PROT_*` bits.
+    pub protection: u32,
+    pub max_protection: u32,
+}
+
+impl MappedRange {
+    /// The protections in `vmmap` form, eg: `r-x/rwx` for the current and maximum.
+    pub fn permissions(&self) -> String {
+        let prot = |prot: u32| {
+            [(1, 'r'), (2, 'w'), (4, 'x')]
+                .iter()
+                .map(|&(bit, c)| if prot & bit != 0 { c } else { '-' })
+                .collect::<String>()
+        };
+        format!("{}/{}", prot(self.protection), prot(self.max_protection))
+    }
+}
+
+/// A file mapped into a process's address space. See [`mapped_files`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MappedFile {
+    pub path: PathBuf,
+    /// The device and inode of the file, which identify it even if it was renamed.
+    pub dev: u32,
+    pub inode: u64,
+    /// Where the file is mapped, in address order. Contiguous regions mapping consecutive
+    /// parts of the file with the same protections are merged.
+    pub ranges: Vec<MappedRange>,
+}
+
+impl MappedFile {
+    /// The total number of bytes mapped.
+    pub fn mapped_size(&self) -> u64 {
+        self.ranges
+            .iter()
+            .map(|range| range.end - range.start)
+            .sum()
+    }
+}
+
+/// List the files mapped into a process's address space, like the file-backed regions shown by
+/// `vmmap`. Unlike the images listed by dyld, this includes data files mapped with `mmap`.
+///
+/// Files are returned in the order they are first mapped in the address space. Fails with
+/// `EPERM` for other users' processes unless running as root.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// for file in mapped_files(getpid()).unwrap() {
+///     for range in &file.ranges {
+///         println!("{:#x}-{:#x} {} {}", range.start, range.end, range.permissions(), file.path.display());
+///     }
+/// }
+/// ```
+pub fn mapped_files(pid: Pid) -> Result<Vec<MappedFile>, std::io::Error> {
+    let mut files = Vec::<MappedFile>::new();
+    let mut by_vnode = HashMap::new();
+    let mut address = 0;
+    loop {
+        let region = match proc_pidregionpathinfo(pid, address) {
+            Ok(Some(region)) => region,
+            Ok(None) => break,
+            // Past the last region.
+            Err(err) if err.raw_os_error() == Some(libc::EINVAL) && address > 0 => break,
+            Err(err) => return Err(err),
+        };
+        let info = &region.prp_prinfo;
+        if info.end() <= address {
+            break;
+        }
+        address = info.end();
+
+        let path = region.to_path_buf().unwrap_or_default();
+        if path.as_os_str().is_empty() {
+            continue;
+        }
+        let stat = &region.prp_vip.vip_vi.vi_stat;
+        let index = *by_vnode
+            .entry((stat.vst_dev, stat.vst_ino))
+            .or_insert_with(|| {
+                files.push(MappedFile {
+                    path,
+                    dev: stat.vst_dev,
+                    inode: stat.vst_ino,
+                    ranges: vec![],
+                });
+                files.len() - 1
+            });
+        let range = MappedRange {
+            start: info.pri_address,
+            end: info.end(),
+            offset: info.pri_offset,
+            protection: info.pri_protection,
+            max_protection: info.pri_max_protection,
+        };
+        let ranges = &mut files[index].ranges;
+        match ranges.last_mut() {
+            Some(last)
+                if last.end == range.start
+                    && last.offset + (last.end - last.start) == range.offset
+                    && last.protection == range.protection
+                    && last.max_protection == range.max_protection =>
+            {
+                last.end = range.end;
+            }
+            _ => ranges.push(range),
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::darwin::getpid;
+
+    #[test]
+    fn test_region_self() {
+        assert_eq!(std::mem::size_of::<ProcRegionInfo>(), 96);
+        let value = Box::new(0_u64);
+        let address = &*value as *const u64 as u64;
+        let region = proc_pidregioninfo(getpid(), address).unwrap().unwrap();
+        assert!(region.pri_address <= address && address < region.end());
+
+        let code = test_region_self as fn() as usize as u64;
+        let region = proc_pidregionpathinfo(getpid(), code).unwrap().unwrap();
+        let exe = std::env::current_exe().unwrap();
+        assert_eq!(region.path().unwrap().file_name(), exe.file_name());
+    }
+
+    #[test]
+    fn test_mapped_files() {
+        let path = std::env::temp_dir().join(format!("proc_pidinfo-map-{}.dat", getpid().0));
+        std::fs::write(&path, vec![1_u8; 3 * 16384]).unwrap();
+        let file = std::fs::File::open(&path).unwrap();
+        // SAFETY: A fresh read-only shared mapping of an open file, unmapped below.
+        let address = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                3 * 16384,
+                libc::PROT_READ,
+                libc::MAP_SHARED,
+                std::os::fd::AsRawFd::as_raw_fd(&file),
+                0,
+            )
+        };
+        assert_ne!(address, libc::MAP_FAILED);
+        let files = mapped_files(getpid());
+        // SAFETY: The mapping was created above.
+        unsafe { libc::munmap(address, 3 * 16384) };
+        std::fs::remove_file(&path).unwrap();
+
+        let files = files.unwrap();
+        let mapped = files
+            .iter()
+            .find(|mapped| mapped.path.file_name() == path.file_name())
+            .unwrap();
+        assert_eq!(mapped.ranges.len(), 1);
+        assert_eq!(mapped.ranges[0].start, address as u64);
+        assert_eq!(mapped.mapped_size(), 3 * 16384);
+        assert!(mapped.ranges[0].permissions().starts_with("r--/"));
+
+        let exe = std::env::current_exe().unwrap();
+        assert!(files
+            .iter()
+            .any(|mapped| mapped.path.file_name() == exe.file_name()));
+    }
+}
@@ -0,0 +1,437 @@
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use libc::{c_int, c_void};
+
+use super::{last_os_error, Pid};
+
+mod ffi {
+    #[repr(C)]
+    pub struct mach_timebase_info {
+        pub numer: u32,
+        pub denom: u32,
+    }
+
+    extern "C" {
+        pub fn mach_timebase_info(info: *mut mach_timebase_info) -> libc::c_int;
+    }
+}
+
+const RUSAGE_INFO_V4: c_int = 4;
+const RUSAGE_INFO_V5: c_int = 5;
+const RUSAGE_INFO_V6: c_int = 6;
+
+/// Resource usage of a process (`rusage_info_v6`), available from macOS 12. See
+/// [`proc_pid_rusage_v6`].
+///
+/// Times are in Mach absolute time units, which are nanoseconds on Intel but not on Apple
+/// silicon. Use [`mach_ticks_to_duration`] to convert them.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct RusageInfoV6 {
+    pub ri_uuid: [u8; 16],
+    pub ri_user_time: u64,
+    pub ri_system_time: u64,
+    pub ri_pkg_idle_wkups: u64,
+    pub ri_interrupt_wkups: u64,
+    pub ri_pageins: u64,
+    pub ri_wired_size: u64,
+    pub ri_resident_size: u64,
+    pub ri_phys_footprint: u64,
+    pub ri_proc_start_abstime: u64,
+    pub ri_proc_exit_abstime: u64,
+    pub ri_child_user_time: u64,
+    pub ri_child_system_time: u64,
+    pub ri_child_pkg_idle_wkups: u64,
+    pub ri_child_interrupt_wkups: u64,
+    pub ri_child_pageins: u64,
+    pub ri_child_elapsed_abstime: u64,
+    pub ri_diskio_bytesread: u64,
+    pub ri_diskio_byteswritten: u64,
+    pub ri_cpu_time_qos_default: u64,
+    pub ri_cpu_time_qos_maintenance: u64,
+    pub ri_cpu_time_qos_background: u64,
+    pub ri_cpu_time_qos_utility: u64,
+    pub ri_cpu_time_qos_legacy: u64,
+    pub ri_cpu_time_qos_user_initiated: u64,
+    pub ri_cpu_time_qos_user_interactive: u64,
+    pub ri_billed_system_time: u64,
+    pub ri_serviced_system_time: u64,
+    pub ri_logical_writes: u64,
+    pub ri_lifetime_max_phys_footprint: u64,
+    pub ri_instructions: u64,
+    pub ri_cycles: u64,
+    pub ri_billed_energy: u64,
+    pub ri_serviced_energy: u64,
+    pub ri_interval_max_phys_footprint: u64,
+    pub ri_runnable_time: u64,
+    pub ri_flags: u64,
+    /// User time on the performance cores.
+    pub ri_user_ptime: u64,
+    /// System time on the performance cores.
+    pub ri_system_ptime: u64,
+    /// Instructions retired on the performance cores.
+    pub ri_pinstructions: u64,
+    /// Cycles on the performance cores.
+    pub ri_pcycles: u64,
+    /// Energy used, in nanojoules.
+    pub ri_energy_nj: u64,
+    /// Energy used on the performance cores, in nanojoules.
+    pub ri_penergy_nj: u64,
+    /// System time spent in the secure world, eg: in exclaves.
+    pub ri_secure_time_in_system: u64,
+    /// System time spent in the secure world on the performance cores.
+    pub ri_secure_ptime_in_system: u64,
+    /// Memory attributed to the process by the Neural Engine.
+    pub ri_neural_footprint: u64,
+    pub ri_lifetime_max_neural_footprint: u64,
+    pub ri_interval_max_neural_footprint: u64,
+    pub ri_reserved: [u64; 9],
+}
+
+const _: () = assert!(std::mem::size_of::<RusageInfoV6>() == 464);
+
+/// Get the `rusage_info_v6` of a process. Fails with `EINVAL` on kernels older than macOS 12.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let usage = proc_pid_rusage_v6(getpid()).unwrap();
+/// println!("{:?}", mach_ticks_to_duration(usage.ri_user_time));
+/// ```
+pub fn proc_pid_rusage_v6(pid: Pid) -> Result<RusageInfoV6, std::io::Error> {
+    let mut value = std::mem::MaybeUninit::<RusageInfoV6>::uninit();
+    // SAFETY: The kernel fills in the whole struct for this flavor.
+    unsafe {
+        let res = super::ffi::proc_pid_rusage(
+            pid.0 as _,
+            RUSAGE_INFO_V6,
+            value.as_mut_ptr() as *mut *mut c_void,
+        );
+        if res != 0 {
+            return Err(last_os_error());
+        }
+        Ok(value.assume_init())
+    }
+}
+
+/// A `rusage_info` sample of a process, taken with the newest version the kernel supports.
+/// Subtract two samples with [`RusageDelta::between`].
+#[derive(Debug, Clone, Copy)]
+pub struct RusageSample {
+    /// The `rusage_info` version read: 6 from macOS 12, and 4 or 5 before that.
+    pub version: u32,
+    pub taken_at: Instant,
+    /// The counters. Fields added after [`RusageSample::version`] are zero.
+    pub usage: RusageInfoV6,
+}
+
+impl RusageSample {
+    /// Read the newest `rusage_info` version the kernel supports, down to version 4 (macOS
+    /// 10.12).
+    pub fn for_pid(pid: Pid) -> Result<Self, std::io::Error> {
+        for version in [RUSAGE_INFO_V6, RUSAGE_INFO_V5, RUSAGE_INFO_V4] {
+            // SAFETY: Zero is valid for every field. The struct has the full size of
+            // rusage_info_v6, which is checked at compile time, and the older versions are
+            // prefixes of it, so each version the kernel writes fits.
+            let mut usage = unsafe { std::mem::zeroed::<RusageInfoV6>() };
+            // SAFETY: As above.
+            let res = unsafe {
+                super::ffi::proc_pid_rusage(
+                    pid.0 as _,
+                    version,
+                    &mut usage as *mut RusageInfoV6 as *mut *mut c_void,
+                )
+            };
+            if res == 0 {
+                return Ok(Self {
+                    version: version as u32,
+                    taken_at: Instant::now(),
+                    usage,
+                });
+            }
+            let err = last_os_error();
+            if err.raw_os_error() != Some(libc::EINVAL) {
+                return Err(err);
+            }
+        }
+        Err(std::io::Error::from_raw_os_error(libc::EINVAL))
+    }
+}
+
+/// The resource usage of a process between two [`RusageSample`]s.
+///
+/// Counters that the older sample's `rusage_info` version lacks, or that the kernel doesn't
+/// maintain on this machine (eg: instruction counts in a virtual machine), are `None`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RusageDelta {
+    /// The time between the samples.
+    pub elapsed: Duration,
+    pub user_time: Duration,
+    pub system_time: Duration,
+    /// Time spent runnable but waiting for a CPU.
+    pub runnable_time: Duration,
+    pub disk_read_bytes: u64,
+    pub disk_written_bytes: u64,
+    /// Bytes written, including to the cache and compressed memory, as counted for the
+    /// process's write limits.
+    pub logical_written_bytes: u64,
+    pub pageins: u64,
+    /// Wakeups of an idle CPU package, the costliest kind for energy.
+    pub idle_wakeups: u64,
+    pub interrupt_wakeups: u64,
+    pub instructions: Option<u64>,
+    pub cycles: Option<u64>,
+    /// Energy used, in nanojoules. Requires version 6 samples.
+    pub energy_nj: Option<u64>,
+}
+
+impl RusageDelta {
+    /// Subtract an earlier sample from a later one of the same process. Returns `None` if the
+    /// samples are of different processes, ie: the pid was reused in between.
+    ///
+    /// The kernel's counters only ever grow, so a counter that went down has wrapped around.
+    ///
+    /// ```
+    /// use proc_pidinfo::*;
+    ///
+    /// let before = RusageSample::for_pid(getpid()).unwrap();
+    /// std::fs::write(std::env::temp_dir().join("rusage-delta"), [0; 4096]).unwrap();
+    /// let after = RusageSample::for_pid(getpid()).unwrap();
+    /// let delta = RusageDelta::between(&before, &after).unwrap();
+    /// println!("{} bytes written, {:?} energy", delta.logical_written_bytes, delta.energy_nj);
+    /// ```
+    pub fn between(before: &RusageSample, after: &RusageSample) -> Option<Self> {
+        let (old, new) = (&before.usage, &after.usage);
+        if old.ri_proc_start_abstime != new.ri_proc_start_abstime {
+            return None;
+        }
+        let counter = |old: u64, new: u64| new.wrapping_sub(old);
+        let ticks = |old: u64, new: u64| mach_ticks_to_duration(counter(old, new));
+        let has_counters = new.ri_instructions > 0 || new.ri_cycles > 0;
+        let has_energy =
+            before.version.min(after.version) >= RUSAGE_INFO_V6 as u32 && new.ri_energy_nj > 0;
+        Some(Self {
+            elapsed: after.taken_at.saturating_duration_since(before.taken_at),
+            user_time: ticks(old.ri_user_time, new.ri_user_time),
+            system_time: ticks(old.ri_system_time, new.ri_system_time),
+            runnable_time: ticks(old.ri_runnable_time, new.ri_runnable_time),
+            disk_read_bytes: counter(old.ri_diskio_bytesread, new.ri_diskio_bytesread),
+            disk_written_bytes: counter(old.ri_diskio_byteswritten, new.ri_diskio_byteswritten),
+            logical_written_bytes: counter(old.ri_logical_writes, new.ri_logical_writes),
+            pageins: counter(old.ri_pageins, new.ri_pageins),
+            idle_wakeups: counter(old.ri_pkg_idle_wkups, new.ri_pkg_idle_wkups),
+            interrupt_wakeups: counter(old.ri_interrupt_wkups, new.ri_interrupt_wkups),
+            instructions: has_counters.then(|| counter(old.ri_instructions, new.ri_instructions)),
+            cycles: has_counters.then(|| counter(old.ri_cycles, new.ri_cycles)),
+            energy_nj: has_energy.then(|| counter(old.ri_energy_nj, new.ri_energy_nj)),
+        })
+    }
+
+    /// The CPU time used, ie: user and system time.
+    pub fn cpu_time(&self) -> Duration {
+        self.user_time + self.system_time
+    }
+}
+
+/// Convert Mach absolute time units, as used by `rusage_info`, to a duration.
+pub fn mach_ticks_to_duration(ticks: u64) -> Duration {
+    static TIMEBASE: OnceLock<(u32, u32)> = OnceLock::new();
+    let &(numer, denom) = TIMEBASE.get_or_init(|| {
+        let mut info = ffi::mach_timebase_info { numer: 0, denom: 0 };
+        // SAFETY: The struct is valid to write to.
+        let res = unsafe { ffi::mach_timebase_info(&mut info) };
+        if res != 0 || info.denom == 0 {
+            (1, 1)
+        } else {
+            (info.numer, info.denom)
+        }
+    });
+    Duration::from_nanos((ticks as u128 * numer as u128 / denom as u128) as u64)
+}
+
+/// The number of CPU performance levels (`hw.nperflevels`): 2 on Apple silicon with
+/// performance and efficiency cores, and 1 on Intel, where the sysctl doesn't exist.
+pub fn perf_levels() -> u32 {
+    let mut levels: c_int = 0;
+    let mut len = std::mem::size_of::<c_int>();
+    // SAFETY: The output is a single c_int with its size.
+    let res = unsafe {
+        libc::sysctlbyname(
+            c"hw.nperflevels".as_ptr(),
+            &mut levels as *mut c_int as *mut c_void,
+            &mut len,
+            std::ptr::null_mut(),
+            0,
+        )
+    };
+    if res != 0 {
+        return 1;
+    }
+    levels.max(1) as u32
+}
+
+/// CPU usage on one kind of core. See [`CoreTypeSplit`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CoreTypeUsage {
+    pub user_time: Duration,
+    pub system_time: Duration,
+    pub instructions: u64,
+    pub cycles: u64,
+    pub energy_nj: u64,
+}
+
+/// A process's CPU usage split between performance and efficiency cores. See
+/// [`core_type_split`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CoreTypeSplit {
+    pub performance: CoreTypeUsage,
+    pub efficiency: CoreTypeUsage,
+    /// The kernel counts instructions and cycles. Without hardware counters (eg: in a virtual
+    /// machine), these are zero.
+    pub has_counters: bool,
+    /// The kernel estimates energy. Without energy estimates, these are zero.
+    pub has_energy: bool,
+}
+
+impl CoreTypeSplit {
+    /// Split the totals in a `rusage_info_v6`. The efficiency core usage is whatever wasn't
+    /// spent on the performance cores.
+    pub fn from_rusage(usage: &RusageInfoV6) -> Self {
+        let ticks = |total: u64, performance: u64| {
+            (
+                mach_ticks_to_duration(performance),
+                mach_ticks_to_duration(total.saturating_sub(performance)),
+            )
+        };
+        let (p_user, e_user) = ticks(usage.ri_user_time, usage.ri_user_ptime);
+        let (p_system, e_system) = ticks(usage.ri_system_time, usage.ri_system_ptime);
+        Self {
+            performance: CoreTypeUsage {
+                user_time: p_user,
+                system_time: p_system,
+                instructions: usage.ri_pinstructions,
+                cycles: usage.ri_pcycles,
+                energy_nj: usage.ri_penergy_nj,
+            },
+            efficiency: CoreTypeUsage {
+                user_time: e_user,
+                system_time: e_system,
+                instructions: usage.ri_instructions.saturating_sub(usage.ri_pinstructions),
+                cycles: usage.ri_cycles.saturating_sub(usage.ri_pcycles),
+                energy_nj: usage.ri_energy_nj.saturating_sub(usage.ri_penergy_nj),
+            },
+            has_counters: usage.ri_instructions > 0 || usage.ri_cycles > 0,
+            has_energy: usage.ri_energy_nj > 0,
+        }
+    }
+}
+
+/// Get the CPU usage of a process split between performance and efficiency cores.
+///
+/// Returns `None` where there's no split to report: on machines with a single kind of core
+/// (see [`perf_levels`]), or kernels older than macOS 12, which lack `rusage_info_v6`.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// if let Some(split) = core_type_split(getpid()).unwrap() {
+///     println!(
+///         "P: {:?}, E: {:?}",
+///         split.performance.user_time, split.efficiency.user_time
+///     );
+/// }
+/// ```
+pub fn core_type_split(pid: Pid) -> Result<Option<CoreTypeSplit>, std::io::Error> {
+    if perf_levels() < 2 {
+        return Ok(None);
+    }
+    match proc_pid_rusage_v6(pid) {
+        Ok(usage) => Ok(Some(CoreTypeSplit::from_rusage(&usage))),
+        Err(err) if err.raw_os_error() == Some(libc::EINVAL) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::darwin::getpid;
+
+    #[test]
+    fn test_rusage_v6_self() {
+        assert_eq!(std::mem::size_of::<RusageInfoV6>(), 464);
+        let usage = proc_pid_rusage_v6(getpid()).unwrap();
+        assert!(usage.ri_user_ptime <= usage.ri_user_time);
+        assert!(mach_ticks_to_duration(usage.ri_user_time) > Duration::ZERO);
+    }
+
+    #[test]
+    fn test_core_type_split() {
+        let before = proc_pid_rusage_v6(getpid()).unwrap();
+        let Some(split) = core_type_split(getpid()).unwrap() else {
+            assert_eq!(perf_levels(), 1);
+            return;
+        };
+        // Each half is rounded down separately.
+        let total = split.performance.user_time + split.efficiency.user_time;
+        assert!(total + Duration::from_micros(1) >= mach_ticks_to_duration(before.ri_user_time));
+    }
+
+    fn sample(version: u32, secs: u64, update: impl FnOnce(&mut RusageInfoV6)) -> RusageSample {
+        // SAFETY: Zero is valid for every field.
+        let mut usage = unsafe { std::mem::zeroed::<RusageInfoV6>() };
+        usage.ri_proc_start_abstime = 1000;
+        update(&mut usage);
+        RusageSample {
+            version,
+            taken_at: Instant::now() + Duration::from_secs(secs),
+            usage,
+        }
+    }
+
+    #[test]
+    fn test_rusage_delta() {
+        let before = sample(6, 0, |usage| {
+            usage.ri_diskio_bytesread = 100;
+            usage.ri_logical_writes = u64::MAX - 9;
+            usage.ri_energy_nj = 5;
+        });
+        let after = sample(6, 2, |usage| {
+            usage.ri_diskio_bytesread = 600;
+            usage.ri_logical_writes = 10;
+            usage.ri_energy_nj = 50;
+        });
+        let delta = RusageDelta::between(&before, &after).unwrap();
+        assert_eq!(delta.elapsed, Duration::from_secs(2));
+        assert_eq!(delta.disk_read_bytes, 500);
+        // The counter wrapped.
+        assert_eq!(delta.logical_written_bytes, 20);
+        assert_eq!(delta.energy_nj, Some(45));
+        assert_eq!(delta.instructions, None);
+
+        // Energy is only in version 6.
+        let before = sample(4, 0, |_| {});
+        assert_eq!(
+            RusageDelta::between(&before, &after).unwrap().energy_nj,
+            None
+        );
+
+        // A different process.
+        let other = sample(6, 3, |usage| usage.ri_proc_start_abstime = 2000);
+        assert!(RusageDelta::between(&after, &other).is_none());
+    }
+
+    #[test]
+    fn test_rusage_delta_self() {
+        let before = RusageSample::for_pid(getpid()).unwrap();
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_millis(20) {
+            std::hint::black_box(0);
+        }
+        let after = RusageSample::for_pid(getpid()).unwrap();
+        let delta = RusageDelta::between(&before, &after).unwrap();
+        assert!(delta.cpu_time() > Duration::ZERO);
+        assert!(delta.elapsed >= Duration::from_millis(20));
+    }
+}
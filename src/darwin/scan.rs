@@ -0,0 +1,299 @@
+use std::os::fd::AsRawFd;
+
+use super::{
+    getpid, proc_listallpids, proc_pidfdinfo, proc_pidinfo, proc_pidinfo_list,
+    proc_pidinfo_list_bounded, Fd, FlavorSupport, HasFdFlavor, HasFlavor, HasFlavorList, Pid,
+    PipeFdInfo, ProcArchInfo, ProcBSDInfo, ProcBSDShortInfo, ProcCoalitionInfo, ProcFDInfo,
+    ProcFilePortInfo, ProcTaskAllInfo, ProcTaskInfo, ProcUniqueIdentifierInfo, SocketFdInfo,
+    ThreadHandle, ThreadId, VnodeFdInfo, VnodeFdInfoWithPath,
+};
+
+/// Which queries a [`Scanner`] attempts for each process.
+///
+/// [`ProcBSDShortInfo`] is always fetched, as it is available for every process regardless of
+/// privilege. It also identifies the owner of the process, which decides whether the other
+/// queries are attempted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScanPolicy {
+    /// Fetch [`ProcBSDInfo`].
+    pub bsd_info: bool,
+    /// Fetch [`ProcTaskInfo`].
+    pub task_info: bool,
+    /// List file descriptors.
+    pub fds: bool,
+    /// List fileports.
+    pub fileports: bool,
+    /// Attempt the queries above for processes owned by other users. Without root, these
+    /// fail with `EPERM`.
+    pub other_users: bool,
+}
+
+impl ScanPolicy {
+    /// Attempt every query for every process. Suitable when running as root.
+    pub fn as_root() -> Self {
+        Self {
+            bsd_info: true,
+            task_info: true,
+            fds: true,
+            fileports: true,
+            other_users: true,
+        }
+    }
+
+    /// Attempt every query for the current user's processes, and only fetch short info for
+    /// processes owned by other users.
+    pub fn unprivileged() -> Self {
+        Self {
+            other_users: false,
+            ..Self::as_root()
+        }
+    }
+
+    /// Attempt every query for every process, whatever the privileges of the current process,
+    /// so that failures are reported rather than skipped. Used with [`Scanner::smoke_test`] to
+    /// validate the crate against a new macOS release.
+    pub fn smoke_test() -> Self {
+        Self::as_root()
+    }
+
+    /// Pick [`ScanPolicy::as_root`] or [`ScanPolicy::unprivileged`] based on the effective
+    /// user of the current process.
+    pub fn detect() -> Self {
+        // SAFETY: geteuid never fails.
+        if unsafe { libc::geteuid() } == 0 {
+            Self::as_root()
+        } else {
+            Self::unprivileged()
+        }
+    }
+}
+
+/// The result of scanning a single process with a [`Scanner`].
+///
+/// Fields are `None` if the query was skipped by the [`ScanPolicy`] or failed (usually due to
+/// permissions or the process exiting mid-scan).
+#[derive(Debug, Clone)]
+pub struct ProcessScan {
+    pub pid: Pid,
+    pub short_info: ProcBSDShortInfo,
+    pub bsd_info: Option<ProcBSDInfo>,
+    pub task_info: Option<ProcTaskInfo>,
+    pub fds: Option<Vec<ProcFDInfo>>,
+    pub fileports: Option<Vec<ProcFilePortInfo>>,
+}
+
+/// Collects information about processes according to a [`ScanPolicy`].
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let scanner = Scanner::unprivileged();
+/// for process in scanner.scan_all().unwrap() {
+///     println!("{} {:?}", process.pid.0, process.short_info.comm());
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Scanner {
+    policy: ScanPolicy,
+    uid: libc::uid_t,
+}
+
+impl Scanner {
+    /// Create a scanner with a custom policy.
+    pub fn new(policy: ScanPolicy) -> Self {
+        Self {
+            policy,
+            // SAFETY: geteuid never fails.
+            uid: unsafe { libc::geteuid() },
+        }
+    }
+
+    /// Create a scanner with [`ScanPolicy::as_root`].
+    pub fn as_root() -> Self {
+        Self::new(ScanPolicy::as_root())
+    }
+
+    /// Create a scanner with [`ScanPolicy::unprivileged`].
+    pub fn unprivileged() -> Self {
+        Self::new(ScanPolicy::unprivileged())
+    }
+
+    /// Create a scanner with [`ScanPolicy::detect`].
+    pub fn detect() -> Self {
+        Self::new(ScanPolicy::detect())
+    }
+
+    /// The policy used by this scanner.
+    pub fn policy(&self) -> &ScanPolicy {
+        &self.policy
+    }
+
+    /// Scan a single process. Fails if the short info for the process can't be read, usually
+    /// because it has exited.
+    pub fn scan(&self, pid: Pid) -> Result<Option<ProcessScan>, std::io::Error> {
+        let Some(short_info) = proc_pidinfo::<ProcBSDShortInfo>(pid)? else {
+            return Ok(None);
+        };
+        let policy = &self.policy;
+        let allowed = policy.other_users || short_info.pbsi_uid == self.uid;
+        Ok(Some(ProcessScan {
+            pid,
+            short_info,
+            bsd_info: (allowed && policy.bsd_info)
+                .then(|| proc_pidinfo(pid).ok().flatten())
+                .flatten(),
+            task_info: (allowed && policy.task_info)
+                .then(|| proc_pidinfo(pid).ok().flatten())
+                .flatten(),
+            fds: (allowed && policy.fds)
+                .then(|| proc_pidinfo_list(pid).ok())
+                .flatten(),
+            fileports: (allowed && policy.fileports)
+                .then(|| proc_pidinfo_list(pid).ok())
+                .flatten(),
+        }))
+    }
+
+    /// Scan every process on the system, skipping processes that exit mid-scan.
+    pub fn scan_all(&self) -> Result<Vec<ProcessScan>, std::io::Error> {
+        Ok(proc_listallpids()?
+            .into_iter()
+            .filter_map(|pid| self.scan(pid).ok().flatten())
+            .collect())
+    }
+
+    /// Exercise every flavor known to this crate against the current process and `launchd`
+    /// (pid 1), reporting which succeeded. Unlike [`Scanner::scan`], this ignores the
+    /// individual query flags of the policy, and only skips `launchd` if
+    /// [`ScanPolicy::other_users`] is false.
+    ///
+    /// File descriptor flavors are only tried against descriptors the current process opens
+    /// for the purpose.
+    ///
+    /// ```
+    /// use proc_pidinfo::*;
+    ///
+    /// for flavor in Scanner::new(ScanPolicy::smoke_test()).smoke_test() {
+    ///     if !flavor.own_process {
+    ///         println!("{} failed", flavor.name);
+    ///     }
+    /// }
+    /// ```
+    pub fn smoke_test(&self) -> Vec<FlavorSupport> {
+        let own = getpid();
+        let other = self.policy.other_users.then_some(Pid(1));
+
+        let mut flavors = vec![
+            probe_info::<ProcBSDInfo>(own, other),
+            probe_info::<ProcBSDShortInfo>(own, other),
+            probe_info::<ProcTaskInfo>(own, other),
+            probe_info::<ProcTaskAllInfo>(own, other),
+            probe_info::<ProcUniqueIdentifierInfo>(own, other),
+            probe_info::<ProcCoalitionInfo>(own, other),
+            probe_info::<ProcArchInfo>(own, other),
+            probe_list::<ProcFDInfo>(own, other),
+            probe_list::<ProcFilePortInfo>(own, other),
+            probe_list::<ThreadHandle>(own, other),
+            probe_list::<ThreadId>(own, other),
+        ];
+
+        if let Ok(file) = std::fs::File::open("/dev/null") {
+            let fd = Fd(file.as_raw_fd());
+            flavors.push(probe_fd::<VnodeFdInfo>(own, fd));
+            flavors.push(probe_fd::<VnodeFdInfoWithPath>(own, fd));
+        }
+        if let Ok((read, _write)) = std::io::pipe() {
+            flavors.push(probe_fd::<PipeFdInfo>(own, Fd(read.as_raw_fd())));
+        }
+        if let Ok((socket, _peer)) = std::os::unix::net::UnixStream::pair() {
+            flavors.push(probe_fd::<SocketFdInfo>(own, Fd(socket.as_raw_fd())));
+        }
+        flavors
+    }
+}
+
+fn probe_info<T: HasFlavor>(own: Pid, other: Option<Pid>) -> FlavorSupport {
+    FlavorSupport {
+        name: T::FLAVOR.name(),
+        own_process: matches!(proc_pidinfo::<T>(own), Ok(Some(_))),
+        other_processes: other.is_some_and(|pid| matches!(proc_pidinfo::<T>(pid), Ok(Some(_)))),
+    }
+}
+
+fn probe_list<T: HasFlavorList>(own: Pid, other: Option<Pid>) -> FlavorSupport {
+    // A denied list fails with `EPERM` rather than coming back empty, so an empty list, eg: of
+    // fileports, still counts as working.
+    FlavorSupport {
+        name: T::FLAVOR.name(),
+        own_process: proc_pidinfo_list_bounded::<T>(own, 1).is_ok(),
+        other_processes: other.is_some_and(|pid| proc_pidinfo_list_bounded::<T>(pid, 1).is_ok()),
+    }
+}
+
+fn probe_fd<T: HasFdFlavor>(own: Pid, fd: Fd) -> FlavorSupport {
+    FlavorSupport {
+        name: T::FLAVOR.name(),
+        own_process: matches!(proc_pidfdinfo::<T>(own, fd), Ok(Some(_))),
+        other_processes: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::darwin::getpid;
+
+    #[test]
+    fn test_scan_self() {
+        let scan = Scanner::unprivileged().scan(getpid()).unwrap().unwrap();
+        assert_eq!(scan.short_info.pbsi_pid, getpid());
+        assert!(scan.bsd_info.is_some());
+        assert!(scan.task_info.is_some());
+        assert!(!scan.fds.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_scan_other_user_unprivileged() {
+        let scanner = Scanner::unprivileged();
+        let scan = scanner.scan(Pid(1)).unwrap().unwrap();
+        if scan.short_info.pbsi_uid != scanner.uid {
+            assert!(scan.task_info.is_none());
+            assert!(scan.fds.is_none());
+        }
+    }
+
+    #[test]
+    fn test_smoke_test() {
+        let flavors = Scanner::new(ScanPolicy::smoke_test()).smoke_test();
+        let failed = flavors
+            .iter()
+            .filter(|flavor| !flavor.own_process)
+            .map(|flavor| flavor.name)
+            .collect::<Vec<_>>();
+        assert_eq!(failed, Vec::<&str>::new());
+        assert!(flavors
+            .iter()
+            .any(|flavor| flavor.name == "PROC_PIDLISTTHREADS"));
+
+        let flavors = Scanner::unprivileged().smoke_test();
+        assert!(flavors.iter().all(|flavor| !flavor.other_processes));
+    }
+
+    #[test]
+    fn test_smoke_test_denied_lists() {
+        // SAFETY: geteuid never fails.
+        let is_root = unsafe { libc::geteuid() } == 0;
+        let flavors = Scanner::new(ScanPolicy::smoke_test()).smoke_test();
+        for name in ["PROC_PIDLISTFDS", "PROC_PIDLISTTHREADS"] {
+            let flavor = flavors.iter().find(|flavor| flavor.name == name).unwrap();
+            assert!(flavor.own_process, "{name}");
+            assert_eq!(flavor.other_processes, is_root, "{name}");
+        }
+    }
+
+    #[test]
+    fn test_scan_all() {
+        let scans = Scanner::detect().scan_all().unwrap();
+        assert!(scans.iter().any(|scan| scan.pid == getpid()));
+    }
+}
@@ -0,0 +1,114 @@
+use super::{all_short_bsd_info, proc_pidargs, proc_pidpath, ProcArgs, ProcBSDShortInfo};
+
+/// The longest name that fits in [`ProcBSDShortInfo::pbsi_comm`], which is truncated to leave
+/// room for the NUL.
+const COMM_LEN: usize = libc::MAXCOMLEN - 1;
+
+/// Find the processes whose short info matches a predicate, skipping processes that exit
+/// mid-scan. Short info is available for every process, whatever its owner.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// // Processes owned by root.
+/// let found = find_processes(|info| info.pbsi_uid == 0).unwrap();
+/// println!("{} root processes", found.len());
+/// ```
+pub fn find_processes(
+    mut matches: impl FnMut(&ProcBSDShortInfo) -> bool,
+) -> Result<Vec<ProcBSDShortInfo>, std::io::Error> {
+    Ok(all_short_bsd_info()?
+        .into_iter()
+        .filter(|info| matches(info))
+        .collect())
+}
+
+/// Find the processes with the given name, like `pgrep -x`.
+///
+/// The kernel truncates names to 15 bytes. For longer names, the name of the executable is
+/// checked too, so `find_by_name("com.apple.WebKit.Networking")` doesn't also find other
+/// `com.apple.WebKi` processes. Without root, this check only works for the current user's
+/// processes, so other users' processes with a long name are skipped.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// for info in find_by_name("launchd").unwrap() {
+///     println!("launchd is {}", info.pbsi_pid.0);
+/// }
+/// ```
+pub fn find_by_name(name: &str) -> Result<Vec<ProcBSDShortInfo>, std::io::Error> {
+    let truncated = &name.as_bytes()[..name.len().min(COMM_LEN)];
+    find_processes(|info| {
+        if info.comm().map(str::as_bytes) != Ok(truncated) {
+            return false;
+        }
+        name.len() <= COMM_LEN
+            || proc_pidpath(info.pbsi_pid)
+                .is_ok_and(|path| path.file_name().is_some_and(|file| file == name))
+    })
+}
+
+/// Find the processes whose short info and arguments match a predicate, like `pgrep -f`,
+/// returning the arguments too.
+///
+/// Only the current user's processes can be read without root, so other processes are
+/// skipped, as are processes that exit mid-scan. Filter on the short info first where
+/// possible, as reading the arguments is much more expensive.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let found = find_processes_with_args(|_, args| {
+///     args.args.iter().any(|arg| arg.to_str().is_some_and(|arg| arg.ends_with(".js")))
+/// })
+/// .unwrap();
+/// for (info, args) in found {
+///     println!("{} {:?}", info.pbsi_pid.0, args.args);
+/// }
+/// ```
+pub fn find_processes_with_args(
+    mut matches: impl FnMut(&ProcBSDShortInfo, &ProcArgs) -> bool,
+) -> Result<Vec<(ProcBSDShortInfo, ProcArgs)>, std::io::Error> {
+    Ok(all_short_bsd_info()?
+        .into_iter()
+        .filter_map(|info| {
+            let args = proc_pidargs(info.pbsi_pid).ok()?;
+            matches(&info, &args).then_some((info, args))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use super::*;
+    use crate::darwin::getpid;
+    use crate::testutil::TestChild;
+
+    #[test]
+    fn test_find_processes() {
+        let found = find_processes(|info| info.pbsi_pid == getpid()).unwrap();
+        assert_eq!(found.len(), 1);
+
+        let exe = std::env::current_exe().unwrap();
+        let name = exe.file_name().unwrap().to_str().unwrap();
+        let found = find_by_name(name).unwrap();
+        assert!(found.iter().any(|info| info.pbsi_pid == getpid()));
+        assert!(find_by_name("no-such-process-name").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_find_processes_with_args() {
+        let child = TestChild::spawn(Command::new("/bin/sleep").arg("10.25"));
+        let child_pid = child.pid();
+        let found = find_processes_with_args(|info, args| {
+            info.comm() == Ok("sleep") && args.args.iter().any(|arg| arg == "10.25")
+        });
+
+        let found = found.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0.pbsi_pid, child_pid);
+    }
+}
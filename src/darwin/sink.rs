@@ -0,0 +1,143 @@
+use std::sync::mpsc::{Sender, SyncSender};
+
+/// A destination for events produced by watchers and samplers, such as
+/// [`super::ProcessWatcher::forward`] and [`super::FdTablePoller::forward`].
+///
+/// Implemented for channel senders and closures, along with [`TracingSink`] (with the `tracing`
+/// feature) and [`JsonLinesSink`] (with the `serde` feature). Returning an error stops the
+/// producer, which returns that error to its caller.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let (mut tx, rx) = std::sync::mpsc::channel::<ProcessEvent>();
+/// let mut child = std::process::Command::new("/usr/bin/true").spawn().unwrap();
+/// let pid = Pid(child.id());
+/// let mut watcher = ProcessWatcher::new().unwrap();
+/// if watcher.watch(pid, ProcessEvents::EXIT).is_ok() {
+///     std::thread::spawn(move || watcher.forward(&mut tx));
+///     assert_eq!(rx.recv().unwrap().pid(), pid);
+/// }
+/// child.wait().unwrap();
+/// ```
+pub trait Sink<T> {
+    /// Deliver one event.
+    fn send(&mut self, event: T) -> Result<(), std::io::Error>;
+}
+
+impl<T, F: FnMut(T) -> Result<(), std::io::Error>> Sink<T> for F {
+    fn send(&mut self, event: T) -> Result<(), std::io::Error> {
+        self(event)
+    }
+}
+
+/// Fails with [`std::io::ErrorKind::BrokenPipe`] once the receiver is dropped.
+impl<T> Sink<T> for Sender<T> {
+    fn send(&mut self, event: T) -> Result<(), std::io::Error> {
+        Sender::send(self, event).map_err(|_| disconnected())
+    }
+}
+
+/// Blocks while the channel is full, and fails with [`std::io::ErrorKind::BrokenPipe`] once the
+/// receiver is dropped.
+impl<T> Sink<T> for SyncSender<T> {
+    fn send(&mut self, event: T) -> Result<(), std::io::Error> {
+        SyncSender::send(self, event).map_err(|_| disconnected())
+    }
+}
+
+fn disconnected() -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::BrokenPipe, "Receiver disconnected")
+}
+
+/// A [`Sink`] that logs each event with `tracing` at the `INFO` level, using its `Debug`
+/// representation.
+#[cfg(feature = "tracing")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TracingSink;
+
+#[cfg(feature = "tracing")]
+impl<T: std::fmt::Debug> Sink<T> for TracingSink {
+    fn send(&mut self, event: T) -> Result<(), std::io::Error> {
+        tracing::info!(?event, "proc_pidinfo event");
+        Ok(())
+    }
+}
+
+/// A [`Sink`] that writes each event to a writer as a line of JSON.
+#[cfg(feature = "serde")]
+#[derive(Debug)]
+pub struct JsonLinesSink<W> {
+    writer: W,
+}
+
+#[cfg(feature = "serde")]
+impl<W: std::io::Write> JsonLinesSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    /// Unwrap the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize, W: std::io::Write> Sink<T> for JsonLinesSink<W> {
+    fn send(&mut self, event: T) -> Result<(), std::io::Error> {
+        serde_json::to_writer(&mut self.writer, &event)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::darwin::{Pid, ProcessEvent};
+
+    fn produce(sink: &mut impl Sink<ProcessEvent>) -> Result<(), std::io::Error> {
+        for pid in 1..=3 {
+            sink.send(ProcessEvent::Exec { pid: Pid(pid) })?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_channel_sink() {
+        let (mut tx, rx) = std::sync::mpsc::channel();
+        produce(&mut tx).unwrap();
+        drop(tx);
+        assert_eq!(rx.iter().count(), 3);
+
+        let (mut tx, rx) = std::sync::mpsc::sync_channel(3);
+        drop(rx);
+        let err = produce(&mut tx).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::BrokenPipe);
+    }
+
+    #[test]
+    fn test_callback_sink() {
+        let mut seen = vec![];
+        let mut sink = |event: ProcessEvent| {
+            seen.push(event.pid());
+            if seen.len() == 2 {
+                return Err(std::io::Error::other("enough"));
+            }
+            Ok(())
+        };
+        assert!(produce(&mut sink).is_err());
+        assert_eq!(seen, [Pid(1), Pid(2)]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json_lines_sink() {
+        let mut sink = JsonLinesSink::new(vec![]);
+        produce(&mut sink).unwrap();
+        let output = String::from_utf8(sink.into_inner()).unwrap();
+        assert_eq!(output.lines().count(), 3);
+        assert!(output.starts_with(r#"{"Exec":{"pid":1}}"#));
+    }
+}
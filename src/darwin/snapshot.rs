@@ -0,0 +1,122 @@
+use std::fmt;
+use std::time::SystemTime;
+
+use super::{diagnose_with, proc_listallpids, DiagnoseReport, Pid};
+
+/// A [`DiagnoseReport`] for every visible process, produced by [`full_system_report`].
+///
+/// Suitable for attaching to a crash or diagnostics bundle, either as JSON (with the `serde`
+/// feature) or as plain text with the [`fmt::Display`] implementation.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SystemReport {
+    /// When the report was started.
+    pub taken_at: SystemTime,
+    /// The reports, ordered by pid.
+    pub processes: Vec<DiagnoseReport>,
+}
+
+impl SystemReport {
+    /// The report for a process, if it was running when the report was collected.
+    pub fn get(&self, pid: Pid) -> Option<&DiagnoseReport> {
+        self.processes
+            .binary_search_by_key(&pid, |report| report.pid)
+            .ok()
+            .map(|index| &self.processes[index])
+    }
+
+    /// The reports with sections that couldn't be collected, usually for lack of privileges.
+    pub fn incomplete(&self) -> impl Iterator<Item = &DiagnoseReport> {
+        self.processes
+            .iter()
+            .filter(|report| !report.errors.is_empty())
+    }
+
+    /// Write the report as JSON. Requires the `serde` feature.
+    #[cfg(feature = "serde")]
+    pub fn write_json(&self, writer: impl std::io::Write) -> Result<(), std::io::Error> {
+        serde_json::to_writer(writer, self)?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for SystemReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, report) in self.processes.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{report}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Collect a [`DiagnoseReport`] for every process on the system: its BSD info, task info,
+/// executable path and arguments, and, if `with_fds` is true, a summary of its file
+/// descriptors.
+///
+/// Sections that can't be read, usually because the process belongs to another user and the
+/// caller isn't root, are left out of that process's report and recorded in its
+/// [`DiagnoseReport::errors`]. Processes that exit while the report is collected are skipped.
+/// Fails only if the process list can't be read.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let report = full_system_report(false).unwrap();
+/// let own = report.get(getpid()).unwrap();
+/// assert!(own.args.is_some());
+/// println!("{} processes, {} incomplete", report.processes.len(), report.incomplete().count());
+/// ```
+pub fn full_system_report(with_fds: bool) -> Result<SystemReport, std::io::Error> {
+    let taken_at = SystemTime::now();
+    let mut processes = proc_listallpids()?
+        .into_iter()
+        .filter_map(|pid| diagnose_with(pid, with_fds).ok().flatten())
+        .collect::<Vec<_>>();
+    processes.sort_by_key(|report| report.pid);
+    Ok(SystemReport {
+        taken_at,
+        processes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::darwin::getpid;
+
+    #[test]
+    fn test_full_system_report() {
+        let report = full_system_report(true).unwrap();
+        let own = report.get(getpid()).unwrap();
+        assert!(own.identity.path.is_some());
+        assert!(own.resources.is_some());
+        assert!(own.fds.as_ref().unwrap().count >= 3);
+        assert!(report.get(Pid(1)).is_some());
+        assert!(report.processes.windows(2).all(|w| w[0].pid < w[1].pid));
+
+        let report = full_system_report(false).unwrap();
+        let own = report.get(getpid()).unwrap();
+        assert!(own.fds.is_none());
+        assert!(own.errors.is_empty());
+        assert!(report
+            .to_string()
+            .contains(&format!("Process {} ", getpid().0)));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_write_json() {
+        let report = full_system_report(false).unwrap();
+        let mut json = vec![];
+        report.write_json(&mut json).unwrap();
+        let value = serde_json::from_slice::<serde_json::Value>(&json).unwrap();
+        let processes = value["processes"].as_array().unwrap();
+        assert_eq!(processes.len(), report.processes.len());
+        assert!(processes
+            .iter()
+            .any(|process| process["pid"] == serde_json::json!(getpid().0)));
+    }
+}
@@ -0,0 +1,550 @@
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::Path;
+
+use libc::{c_char, c_int};
+
+use super::{
+    libc_str_to_str, HasFdFlavor, ProcFileInfo, ProcPidFdInfoFlavor, VInfoStat, ValueError,
+};
+
+/// The size of a socket address buffer in `un_sockinfo`.
+const SOCK_MAXADDRLEN: usize = 255;
+/// The size of an interface name, including the NUL.
+const IF_NAMESIZE: usize = 16;
+/// The size of a kernel control name, including the NUL.
+const MAX_KCTL_NAME: usize = 96;
+
+/// `soi_kind` values, saying which member of `soi_proto` is valid.
+const SOCKINFO_IN: c_int = 1;
+const SOCKINFO_TCP: c_int = 2;
+const SOCKINFO_UN: c_int = 3;
+const SOCKINFO_NDRV: c_int = 4;
+const SOCKINFO_KERN_EVENT: c_int = 5;
+const SOCKINFO_KERN_CTL: c_int = 6;
+
+/// `insi_vflag` bits.
+const INI_IPV4: u8 = 0x1;
+
+/// Information about a socket buffer (`sockbuf_info`).
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SockbufInfo {
+    /// The number of bytes in the buffer.
+    pub sbi_cc: u32,
+    /// The size of the buffer, eg: `SO_RCVBUF`.
+    pub sbi_hiwat: u32,
+    pub sbi_mbcnt: u32,
+    pub sbi_mbmax: u32,
+    pub sbi_lowat: u32,
+    pub sbi_flags: i16,
+    pub sbi_timeo: i16,
+}
+
+/// The IPv4 part of an [`InSockInfo`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct InSockInfoV4 {
+    pub in4_tos: u8,
+}
+
+/// The IPv6 part of an [`InSockInfo`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct InSockInfoV6 {
+    pub in6_hlim: u8,
+    pub in6_cksum: c_int,
+    pub in6_ifindex: u16,
+    pub in6_hops: i16,
+}
+
+/// Information about an IPv4 or IPv6 socket (`in_sockinfo`). See [`SocketInfo::inet`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct InSockInfo {
+    /// The foreign port, in network byte order. See [`InSockInfo::foreign_port`].
+    pub insi_fport: c_int,
+    /// The local port, in network byte order. See [`InSockInfo::local_port`].
+    pub insi_lport: c_int,
+    pub insi_gencnt: u64,
+    pub insi_flags: u32,
+    pub insi_flow: u32,
+    /// `INI_IPV4` (1) or `INI_IPV6` (2).
+    pub insi_vflag: u8,
+    pub insi_ip_ttl: u8,
+    pub rfu_1: u32,
+    /// The foreign address. IPv4 addresses are in the last four bytes.
+    pub insi_faddr: [u8; 16],
+    /// The local address. IPv4 addresses are in the last four bytes.
+    pub insi_laddr: [u8; 16],
+    pub insi_v4: InSockInfoV4,
+    pub insi_v6: InSockInfoV6,
+}
+
+impl InSockInfo {
+    /// Returns true for IPv4 sockets, and false for IPv6 sockets.
+    pub fn is_ipv4(&self) -> bool {
+        self.insi_vflag & INI_IPV4 != 0
+    }
+
+    fn ip(&self, addr: [u8; 16]) -> IpAddr {
+        if self.is_ipv4() {
+            IpAddr::V4(Ipv4Addr::new(addr[12], addr[13], addr[14], addr[15]))
+        } else {
+            IpAddr::V6(Ipv6Addr::from(addr))
+        }
+    }
+
+    pub fn local_port(&self) -> u16 {
+        u16::from_be(self.insi_lport as u16)
+    }
+
+    pub fn foreign_port(&self) -> u16 {
+        u16::from_be(self.insi_fport as u16)
+    }
+
+    /// The local address, which is unspecified (eg: `0.0.0.0`) for sockets bound to every
+    /// address.
+    pub fn local_addr(&self) -> SocketAddr {
+        SocketAddr::new(self.ip(self.insi_laddr), self.local_port())
+    }
+
+    /// The foreign address, which is unspecified with port 0 for unconnected sockets.
+    pub fn foreign_addr(&self) -> SocketAddr {
+        SocketAddr::new(self.ip(self.insi_faddr), self.foreign_port())
+    }
+}
+
+/// The state of a TCP connection (`TSI_S_*`).
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(i32)]
+pub enum TcpState {
+    CLOSED = 0,
+    LISTEN = 1,
+    SYN_SENT = 2,
+    SYN_RECEIVED = 3,
+    ESTABLISHED = 4,
+    CLOSE_WAIT = 5,
+    FIN_WAIT_1 = 6,
+    CLOSING = 7,
+    LAST_ACK = 8,
+    FIN_WAIT_2 = 9,
+    TIME_WAIT = 10,
+}
+
+impl TcpState {
+    fn from_raw(value: c_int) -> Result<Self, ValueError> {
+        Ok(match value {
+            0 => TcpState::CLOSED,
+            1 => TcpState::LISTEN,
+            2 => TcpState::SYN_SENT,
+            3 => TcpState::SYN_RECEIVED,
+            4 => TcpState::ESTABLISHED,
+            5 => TcpState::CLOSE_WAIT,
+            6 => TcpState::FIN_WAIT_1,
+            7 => TcpState::CLOSING,
+            8 => TcpState::LAST_ACK,
+            9 => TcpState::FIN_WAIT_2,
+            10 => TcpState::TIME_WAIT,
+            _ => return Err(ValueError::UnexpectedEnumValue),
+        })
+    }
+}
+
+/// Information about a TCP socket (`tcp_sockinfo`). See [`SocketInfo::tcp`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct TcpSockInfo {
+    pub tcpsi_ini: InSockInfo,
+    /// See [`TcpSockInfo::state`].
+    pub tcpsi_state: c_int,
+    pub tcpsi_timer: [c_int; 4],
+    pub tcpsi_mss: c_int,
+    pub tcpsi_flags: u32,
+    pub rfu_1: u32,
+    pub tcpsi_tp: u64,
+}
+
+impl TcpSockInfo {
+    pub fn state(&self) -> Result<TcpState, ValueError> {
+        TcpState::from_raw(self.tcpsi_state)
+    }
+}
+
+/// Information about a unix domain socket (`un_sockinfo`). See [`SocketInfo::unix`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct UnSockInfo {
+    /// The [`SocketInfo::soi_so`] of the connected socket, or zero if not connected.
+    pub unsi_conn_so: u64,
+    pub unsi_conn_pcb: u64,
+    /// The bound address, as a `sockaddr_un`.
+    pub unsi_addr: [u8; SOCK_MAXADDRLEN],
+    /// The address of the connected socket, as a `sockaddr_un`.
+    pub unsi_caddr: [u8; SOCK_MAXADDRLEN],
+}
+
+impl UnSockInfo {
+    /// The path this socket is bound to, if any.
+    pub fn path(&self) -> Option<&Path> {
+        sockaddr_un_path(&self.unsi_addr)
+    }
+
+    /// The path the connected socket is bound to, if any. For a client connected to a
+    /// listening socket, this is the path the client connected to.
+    pub fn peer_path(&self) -> Option<&Path> {
+        sockaddr_un_path(&self.unsi_caddr)
+    }
+
+    /// Returns true if the socket is connected to another socket.
+    pub fn is_connected(&self) -> bool {
+        self.unsi_conn_so != 0
+    }
+}
+
+/// Information about a network driver socket (`ndrv_info`), which sends and receives raw
+/// frames on one interface. See [`SocketKind::Ndrv`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct NdrvInfo {
+    pub ndrvsi_if_family: u32,
+    pub ndrvsi_if_unit: u32,
+    pub ndrvsi_if_name: [c_char; IF_NAMESIZE],
+}
+
+impl NdrvInfo {
+    /// The name of the interface, eg: `en`. See [`NdrvInfo::ndrvsi_if_unit`] for its unit.
+    pub fn if_name(&self) -> Result<&str, ValueError> {
+        libc_str_to_str(&self.ndrvsi_if_name)
+    }
+}
+
+/// Information about a kernel event socket (`kern_event_info`). See
+/// [`SocketKind::KernEvent`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct KernEventInfo {
+    pub kesi_vendor_code_filter: u32,
+    pub kesi_class_filter: u32,
+    pub kesi_subclass_filter: u32,
+}
+
+/// Information about a kernel control socket (`kern_ctl_info`), which talks to a kernel
+/// extension or subsystem, eg: `com.apple.net.utun_control` for VPN tunnels. See
+/// [`SocketKind::KernCtl`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct KernCtlInfo {
+    /// The dynamically assigned id of the control.
+    pub kcsi_id: u32,
+    pub kcsi_reg_unit: u32,
+    pub kcsi_flags: u32,
+    pub kcsi_recvbufsize: u32,
+    pub kcsi_sendbufsize: u32,
+    /// The unit the socket is connected to, eg: `utun<unit - 1>`.
+    pub kcsi_unit: u32,
+    pub kcsi_name: [c_char; MAX_KCTL_NAME],
+}
+
+impl KernCtlInfo {
+    /// The name of the control, eg: `com.apple.net.utun_control`.
+    pub fn name(&self) -> Result<&str, ValueError> {
+        libc_str_to_str(&self.kcsi_name)
+    }
+}
+
+/// Decode the path of a `sockaddr_un`, which starts with a length and a family byte.
+fn sockaddr_un_path(addr: &[u8]) -> Option<&Path> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let len = (addr[0] as usize).min(addr.len());
+    if len <= 2 || addr[1] != libc::AF_UNIX as u8 {
+        return None;
+    }
+    let path = &addr[2..len];
+    let path = &path[..path.iter().position(|&b| b == 0).unwrap_or(path.len())];
+    (!path.is_empty()).then(|| Path::new(std::ffi::OsStr::from_bytes(path)))
+}
+
+/// The protocol-specific part of a [`SocketInfo`], selected by [`SocketInfo::soi_kind`].
+/// Use the accessors on [`SocketInfo`] rather than reading this directly.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union SocketInfoProto {
+    pub pri_in: InSockInfo,
+    pub pri_tcp: TcpSockInfo,
+    pub pri_un: UnSockInfo,
+    pub pri_ndrv: NdrvInfo,
+    pub pri_kern_event: KernEventInfo,
+    pub pri_kern_ctl: KernCtlInfo,
+    /// The size of the largest member.
+    _size: [u64; 66],
+}
+
+impl fmt::Debug for SocketInfoProto {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SocketInfoProto").finish_non_exhaustive()
+    }
+}
+
+/// General information about a socket (`socket_info`). See [`SocketFdInfo`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SocketInfo {
+    pub soi_stat: VInfoStat,
+    /// An opaque handle identifying the socket, stable for its lifetime.
+    pub soi_so: u64,
+    pub soi_pcb: u64,
+    /// The socket type, eg: `SOCK_STREAM`.
+    pub soi_type: c_int,
+    pub soi_protocol: c_int,
+    /// The address family, eg: `AF_UNIX`.
+    pub soi_family: c_int,
+    pub soi_options: i16,
+    pub soi_linger: i16,
+    pub soi_state: i16,
+    pub soi_qlen: i16,
+    pub soi_incqlen: i16,
+    pub soi_qlimit: i16,
+    pub soi_timeo: i16,
+    pub soi_error: u16,
+    pub soi_oobmark: u32,
+    pub soi_rcv: SockbufInfo,
+    pub soi_snd: SockbufInfo,
+    /// Which member of [`SocketInfo::soi_proto`] is valid (`SOCKINFO_*`).
+    pub soi_kind: c_int,
+    pub rfu_1: u32,
+    pub soi_proto: SocketInfoProto,
+}
+
+impl SocketInfo {
+    /// The IPv4 or IPv6 socket information, if this is a TCP, UDP or other IP socket.
+    pub fn inet(&self) -> Option<&InSockInfo> {
+        match self.soi_kind {
+            // SAFETY: The kernel fills in pri_in for SOCKINFO_IN.
+            SOCKINFO_IN => Some(unsafe { &self.soi_proto.pri_in }),
+            // SAFETY: The kernel fills in pri_tcp for SOCKINFO_TCP.
+            SOCKINFO_TCP => Some(unsafe { &self.soi_proto.pri_tcp.tcpsi_ini }),
+            _ => None,
+        }
+    }
+
+    /// The TCP socket information, if this is a TCP socket.
+    pub fn tcp(&self) -> Option<&TcpSockInfo> {
+        if self.soi_kind == SOCKINFO_TCP {
+            // SAFETY: The kernel fills in pri_tcp for SOCKINFO_TCP.
+            Some(unsafe { &self.soi_proto.pri_tcp })
+        } else {
+            None
+        }
+    }
+
+    /// The unix domain socket information, if this is a unix domain socket.
+    pub fn unix(&self) -> Option<&UnSockInfo> {
+        if self.soi_kind == SOCKINFO_UN {
+            // SAFETY: The kernel fills in pri_un for SOCKINFO_UN.
+            Some(unsafe { &self.soi_proto.pri_un })
+        } else {
+            None
+        }
+    }
+}
+
+/// What kind of socket a [`SocketInfo`] describes, with its protocol-specific information.
+/// See [`SocketInfo::kind`].
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy)]
+pub enum SocketKind<'a> {
+    Tcp(&'a TcpSockInfo),
+    Udp(&'a InSockInfo),
+    /// A raw IP socket (`SOCK_RAW`), eg: for ICMP.
+    RawIp(&'a InSockInfo),
+    /// Another IP socket.
+    Inet(&'a InSockInfo),
+    Unix(&'a UnSockInfo),
+    /// A routing socket (`AF_ROUTE`), which has no protocol-specific information.
+    Route,
+    /// A network driver socket (`AF_NDRV`).
+    Ndrv(&'a NdrvInfo),
+    /// A kernel event socket (`PF_SYSTEM`, `SYSPROTO_EVENT`).
+    KernEvent(&'a KernEventInfo),
+    /// A kernel control socket (`PF_SYSTEM`, `SYSPROTO_CONTROL`).
+    KernCtl(&'a KernCtlInfo),
+    /// A socket without protocol-specific information, with its family and the raw
+    /// [`SocketInfo::soi_kind`].
+    Other {
+        family: c_int,
+        kind: c_int,
+    },
+}
+
+impl SocketInfo {
+    /// Decode which kind of socket this is.
+    ///
+    /// ```
+    /// use proc_pidinfo::*;
+    ///
+    /// for (fd, info) in open_sockets(getpid()).unwrap() {
+    ///     match info.psi.kind() {
+    ///         SocketKind::KernCtl(ctl) => println!("{} {:?} {}", fd.0, ctl.name(), ctl.kcsi_unit),
+    ///         kind => println!("{} {:?}", fd.0, kind),
+    ///     }
+    /// }
+    /// ```
+    pub fn kind(&self) -> SocketKind<'_> {
+        // SAFETY: Each arm reads the member of soi_proto that the kernel fills in for its
+        // soi_kind.
+        unsafe {
+            match self.soi_kind {
+                SOCKINFO_TCP => SocketKind::Tcp(&self.soi_proto.pri_tcp),
+                SOCKINFO_IN if self.soi_type == libc::SOCK_RAW => {
+                    SocketKind::RawIp(&self.soi_proto.pri_in)
+                }
+                SOCKINFO_IN if self.soi_protocol == libc::IPPROTO_UDP => {
+                    SocketKind::Udp(&self.soi_proto.pri_in)
+                }
+                SOCKINFO_IN => SocketKind::Inet(&self.soi_proto.pri_in),
+                SOCKINFO_UN => SocketKind::Unix(&self.soi_proto.pri_un),
+                SOCKINFO_NDRV => SocketKind::Ndrv(&self.soi_proto.pri_ndrv),
+                SOCKINFO_KERN_EVENT => SocketKind::KernEvent(&self.soi_proto.pri_kern_event),
+                SOCKINFO_KERN_CTL => SocketKind::KernCtl(&self.soi_proto.pri_kern_ctl),
+                _ if self.soi_family == libc::AF_ROUTE => SocketKind::Route,
+                kind => SocketKind::Other {
+                    family: self.soi_family,
+                    kind,
+                },
+            }
+        }
+    }
+}
+
+/// Information about [`ProcFDType::SOCKET`](super::ProcFDType::SOCKET) file descriptors.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SocketFdInfo {
+    pub pfi: ProcFileInfo,
+    pub psi: SocketInfo,
+}
+
+impl HasFdFlavor for SocketFdInfo {
+    const FLAVOR: ProcPidFdInfoFlavor = ProcPidFdInfoFlavor::PROC_PIDFDSOCKETINFO;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::fd::AsRawFd;
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    use super::*;
+    use crate::darwin::{proc_pidfdinfo_self, Fd};
+
+    #[test]
+    fn test_unix_socket() {
+        assert_eq!(std::mem::size_of::<SocketFdInfo>(), 792);
+
+        let dir = std::env::temp_dir().join(format!("proc_pidinfo-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("socket");
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let client = UnixStream::connect(&path).unwrap();
+        let (server, _) = listener.accept().unwrap();
+
+        let info = |fd: i32| {
+            proc_pidfdinfo_self::<SocketFdInfo>(Fd(fd))
+                .unwrap()
+                .unwrap()
+                .psi
+        };
+        let listener_info = info(listener.as_raw_fd());
+        let client_info = info(client.as_raw_fd());
+        let server_info = info(server.as_raw_fd());
+        assert_eq!(client_info.soi_family, libc::AF_UNIX);
+
+        let listener_un = listener_info.unix().unwrap();
+        assert!(!listener_un.is_connected());
+        assert_eq!(listener_un.path().unwrap().file_name(), path.file_name());
+
+        let client_un = client_info.unix().unwrap();
+        let server_un = server_info.unix().unwrap();
+        assert_eq!(client_un.unsi_conn_so, server_info.soi_so);
+        assert_eq!(server_un.unsi_conn_so, client_info.soi_so);
+        assert_eq!(client_un.peer_path().unwrap().file_name(), path.file_name());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_tcp_socket() {
+        assert_eq!(std::mem::size_of::<InSockInfo>(), 80);
+        assert_eq!(std::mem::size_of::<TcpSockInfo>(), 120);
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = std::net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let info = |fd: i32| {
+            proc_pidfdinfo_self::<SocketFdInfo>(Fd(fd))
+                .unwrap()
+                .unwrap()
+                .psi
+        };
+
+        let listener_info = info(listener.as_raw_fd());
+        let tcp = listener_info.tcp().unwrap();
+        assert_eq!(tcp.state(), Ok(TcpState::LISTEN));
+        assert!(tcp.tcpsi_ini.is_ipv4());
+        assert_eq!(tcp.tcpsi_ini.local_addr(), listener.local_addr().unwrap());
+
+        let client_info = info(client.as_raw_fd());
+        let inet = client_info.inet().unwrap();
+        assert_eq!(inet.local_addr(), client.local_addr().unwrap());
+        assert_eq!(inet.foreign_addr(), client.peer_addr().unwrap());
+        assert!(client_info.unix().is_none());
+        assert!(matches!(client_info.kind(), SocketKind::Tcp(_)));
+    }
+
+    #[test]
+    fn test_socket_kinds() {
+        assert_eq!(std::mem::size_of::<KernCtlInfo>(), 120);
+
+        let info = |fd: i32| {
+            proc_pidfdinfo_self::<SocketFdInfo>(Fd(fd))
+                .unwrap()
+                .unwrap()
+                .psi
+        };
+        let socket = |domain, ty, protocol| {
+            // SAFETY: Creates a new socket, owned by the returned OwnedFd.
+            let fd = unsafe { libc::socket(domain, ty, protocol) };
+            assert!(fd >= 0, "{}", std::io::Error::last_os_error());
+            // SAFETY: The descriptor was just created, and nothing else owns it.
+            unsafe { <std::os::fd::OwnedFd as std::os::fd::FromRawFd>::from_raw_fd(fd) }
+        };
+
+        let udp = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        match info(udp.as_raw_fd()).kind() {
+            SocketKind::Udp(inet) => assert_eq!(inet.local_addr(), udp.local_addr().unwrap()),
+            kind => panic!("{kind:?}"),
+        }
+
+        let route = socket(libc::AF_ROUTE, libc::SOCK_RAW, 0);
+        assert!(matches!(info(route.as_raw_fd()).kind(), SocketKind::Route));
+
+        // `SYSPROTO_EVENT` and `SYSPROTO_CONTROL`, which `libc` only has for macOS.
+        const SYSPROTO_EVENT: c_int = 1;
+        const SYSPROTO_CONTROL: c_int = 2;
+
+        // An unconnected control socket has no control yet, and so no name.
+        let ctl = socket(libc::PF_SYSTEM, libc::SOCK_DGRAM, SYSPROTO_CONTROL);
+        match info(ctl.as_raw_fd()).kind() {
+            SocketKind::KernCtl(ctl) => assert_eq!(ctl.name(), Ok("")),
+            kind => panic!("{kind:?}"),
+        }
+
+        let event = socket(libc::PF_SYSTEM, libc::SOCK_RAW, SYSPROTO_EVENT);
+        assert!(matches!(
+            info(event.as_raw_fd()).kind(),
+            SocketKind::KernEvent(_)
+        ));
+    }
+}
@@ -0,0 +1,150 @@
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use super::{
+    mach_ticks_to_duration, proc_pid_rusage_v6, proc_pidinfo, proc_pidinfo_list, proc_pidpath, Pid,
+    ProcBSDShortInfo, ProcFDInfo, ProcTaskAllInfo, ProcUniqueIdentifierInfo,
+};
+
+/// The commonly wanted numbers about a process, gathered from whichever flavors are available.
+///
+/// [`ProcTaskAllInfo`] is tried first, falling back to [`ProcBSDShortInfo`], which is available
+/// for every process. Values that couldn't be read (without root, most of them for other
+/// users' processes) are `None`.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let stats = ProcessStats::for_pid(getpid()).unwrap().unwrap();
+/// println!(
+///     "{} uses {:?} bytes and {:?} of CPU, with {:?} files open",
+///     stats.name, stats.rss_bytes, stats.cpu_time, stats.open_files
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ProcessStats {
+    pub pid: Pid,
+    pub ppid: Pid,
+    /// The effective user.
+    pub uid: libc::uid_t,
+    /// The full process name where available, otherwise the name truncated to 15 bytes.
+    pub name: String,
+    /// The path of the executable.
+    pub path: Option<PathBuf>,
+    pub start_time: Option<SystemTime>,
+    /// [`ProcUniqueIdentifierInfo::p_uniqueid`], which tells apart processes that share a pid.
+    /// Unlike the start time, it can be read for every process.
+    pub unique_id: Option<u64>,
+    /// Resident memory.
+    pub rss_bytes: Option<u64>,
+    /// Virtual memory, which on macOS includes large shared regions and is rarely useful.
+    pub virtual_bytes: Option<u64>,
+    /// The memory attributed to the process, as shown by Activity Monitor.
+    pub footprint_bytes: Option<u64>,
+    /// User and system CPU time together.
+    pub cpu_time: Option<Duration>,
+    pub user_time: Option<Duration>,
+    pub system_time: Option<Duration>,
+    pub thread_count: Option<u32>,
+    /// The number of open file descriptors.
+    pub open_files: Option<usize>,
+    pub disk_read_bytes: Option<u64>,
+    pub disk_written_bytes: Option<u64>,
+}
+
+impl ProcessStats {
+    /// Gather the stats for a process. Fails if the short info for the process can't be read,
+    /// usually because it has exited. Every other failure leaves the matching fields `None`.
+    pub fn for_pid(pid: Pid) -> Result<Option<Self>, std::io::Error> {
+        let Some(short_info) = proc_pidinfo::<ProcBSDShortInfo>(pid)? else {
+            return Ok(None);
+        };
+        let all_info = proc_pidinfo::<ProcTaskAllInfo>(pid).ok().flatten();
+        let usage = proc_pid_rusage_v6(pid).ok();
+
+        let name = all_info
+            .as_ref()
+            .and_then(|info| info.pbsd.name_string().ok())
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| short_info.comm().unwrap_or_default().to_owned());
+        let task = all_info.as_ref().map(|info| info.ptinfo);
+        let user_time = task
+            .map(|task| task.total_user())
+            .or_else(|| usage.map(|usage| mach_ticks_to_duration(usage.ri_user_time)));
+        let system_time = task
+            .map(|task| task.total_system())
+            .or_else(|| usage.map(|usage| mach_ticks_to_duration(usage.ri_system_time)));
+
+        Ok(Some(Self {
+            pid,
+            ppid: short_info.pbsi_ppid,
+            uid: short_info.pbsi_uid,
+            name,
+            path: proc_pidpath(pid).ok(),
+            start_time: all_info.as_ref().map(|info| {
+                SystemTime::UNIX_EPOCH
+                    + Duration::from_secs(info.pbsd.pbi_start_tvsec)
+                    + Duration::from_micros(info.pbsd.pbi_start_tvusec)
+            }),
+            unique_id: proc_pidinfo::<ProcUniqueIdentifierInfo>(pid)
+                .ok()
+                .flatten()
+                .map(|info| info.p_uniqueid),
+            rss_bytes: task
+                .map(|task| task.pti_resident_size)
+                .or_else(|| usage.map(|usage| usage.ri_resident_size)),
+            virtual_bytes: task.map(|task| task.pti_virtual_size),
+            footprint_bytes: usage.map(|usage| usage.ri_phys_footprint),
+            cpu_time: user_time
+                .zip(system_time)
+                .map(|(user, system)| user + system),
+            user_time,
+            system_time,
+            thread_count: task.map(|task| task.pti_threadnum as u32),
+            open_files: proc_pidinfo_list::<ProcFDInfo>(pid)
+                .ok()
+                .map(|fds| fds.len()),
+            disk_read_bytes: usage.map(|usage| usage.ri_diskio_bytesread),
+            disk_written_bytes: usage.map(|usage| usage.ri_diskio_byteswritten),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::darwin::getpid;
+
+    #[test]
+    fn test_process_stats_self() {
+        let stats = ProcessStats::for_pid(getpid()).unwrap().unwrap();
+        assert_eq!(stats.pid, getpid());
+        assert_eq!(stats.path, std::env::current_exe().ok());
+        assert!(stats.rss_bytes.unwrap() > 0);
+        assert!(stats.footprint_bytes.unwrap() > 0);
+        assert!(stats.thread_count.unwrap() >= 1);
+        assert!(stats.open_files.unwrap() >= 3);
+        assert!(stats.start_time.unwrap() <= SystemTime::now());
+        assert!(stats.unique_id.is_some());
+        assert_eq!(
+            stats.cpu_time,
+            Some(stats.user_time.unwrap() + stats.system_time.unwrap())
+        );
+    }
+
+    #[test]
+    fn test_process_stats_other_user() {
+        // SAFETY: geteuid never fails.
+        if unsafe { libc::geteuid() } == 0 {
+            return;
+        }
+        let stats = ProcessStats::for_pid(Pid(1)).unwrap().unwrap();
+        assert_eq!(stats.name, "launchd");
+        assert_eq!(stats.ppid, Pid(0));
+        assert_eq!(stats.start_time, None);
+        assert!(stats.unique_id.is_some());
+        assert_eq!(stats.rss_bytes, None);
+        assert_eq!(stats.open_files, None);
+    }
+}
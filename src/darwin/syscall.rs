@@ -0,0 +1,193 @@
+//! The `proc_info` system call, issued directly rather than through libproc's wrappers.
+//! Requires the `syscall` feature.
+//!
+//! libproc wraps only some of the call numbers behind `proc_info`, and some wrappers reject
+//! flavors they don't know about. This module passes everything through to the kernel as raw
+//! bytes, for building on when this crate doesn't have a typed API yet. The call numbers and
+//! the layout of each reply are private kernel interfaces that may change between releases.
+//!
+//! ```
+//! use proc_pidinfo::syscall::*;
+//! use proc_pidinfo::*;
+//!
+//! // PROC_PIDT_SHORTBSDINFO
+//! let mut buf = [0; 64];
+//! let len = pidinfo(getpid(), 13, 0, &mut buf).unwrap();
+//! assert_eq!(len, std::mem::size_of::<ProcBSDShortInfo>());
+//! ```
+
+use libc::{c_int, c_void};
+
+use super::{last_os_error, Fd, Pid};
+
+mod ffi {
+    use libc::{c_int, c_void};
+
+    extern "C" {
+        pub fn __proc_info(
+            callnum: c_int,
+            pid: c_int,
+            flavor: c_int,
+            arg: u64,
+            buffer: *mut c_void,
+            buffersize: c_int,
+        ) -> c_int;
+    }
+}
+
+/// A `proc_info` call number (`PROC_INFO_CALL_*` in `<sys/proc_info_private.h>`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ProcInfoCall(i32);
+
+impl ProcInfoCall {
+    /// List pids, as `proc_listpids`.
+    pub const LISTPIDS: Self = Self(0x1);
+    /// Information about a process, as `proc_pidinfo`.
+    pub const PIDINFO: Self = Self(0x2);
+    /// Information about a file descriptor, as `proc_pidfdinfo`.
+    pub const PIDFDINFO: Self = Self(0x3);
+    /// Read the kernel message buffer, as `proc_kmsgbuf`. Requires root.
+    pub const KERNMSGBUF: Self = Self(0x4);
+    /// Change a setting of the current process, as `proc_setpcontrol`.
+    pub const SETCONTROL: Self = Self(0x5);
+    /// Information about a fileport, as `proc_pidfileportinfo`.
+    pub const PIDFILEPORTINFO: Self = Self(0x6);
+    /// Terminate a process, as `proc_terminate`.
+    pub const TERMINATE: Self = Self(0x7);
+    /// Query or change the dirty state of a process, as `proc_track_dirty`.
+    pub const DIRTYCONTROL: Self = Self(0x8);
+    /// Resource usage, as `proc_pid_rusage`.
+    pub const PIDRUSAGE: Self = Self(0x9);
+    /// Information about the process a request originated from.
+    pub const PIDORIGINATORINFO: Self = Self(0xa);
+    /// List coalitions.
+    pub const LISTCOALITIONS: Self = Self(0xb);
+    /// Whether a process can use the foreground hardware.
+    pub const CANUSEFGHW: Self = Self(0xc);
+    /// Information about a kqueue that isn't attached to a descriptor.
+    pub const PIDDYNKQUEUEINFO: Self = Self(0xd);
+    /// Information about kevents with user data.
+    pub const UDATA_INFO: Self = Self(0xe);
+    /// Register dyld's image list with the kernel.
+    pub const SET_DYLD_IMAGES: Self = Self(0xf);
+    /// Terminate a process, recording the reason.
+    pub const TERMINATE_RSR: Self = Self(0x10);
+
+    /// A call number this crate doesn't name.
+    pub const fn from_raw(call: i32) -> Self {
+        Self(call)
+    }
+
+    /// The raw call number.
+    pub const fn as_raw(self) -> i32 {
+        self.0
+    }
+}
+
+/// Issue a `proc_info` call. Returns the kernel's result, which for queries is the number of
+/// bytes written into `buf`.
+///
+/// # Safety
+///
+/// The kernel trusts the caller with the meaning of `arg`, which some calls and flavors read
+/// as a user address, and some calls change the state of a process rather than reading it,
+/// eg: [`ProcInfoCall::TERMINATE`] or [`ProcInfoCall::SET_DYLD_IMAGES`]. The caller must pass
+/// the arguments the kernel expects for `call` and `flavor`. Prefer the safe wrappers
+/// [`listpids`], [`pidinfo`] and [`pidfdinfo`] where possible.
+pub unsafe fn proc_info(
+    call: ProcInfoCall,
+    pid: Pid,
+    flavor: i32,
+    arg: u64,
+    buf: &mut [u8],
+) -> Result<usize, std::io::Error> {
+    let buffersize = buf.len().min(c_int::MAX as usize) as c_int;
+    // SAFETY: The kernel writes at most `buffersize` bytes; the rest is forwarded to the
+    // caller.
+    let res = unsafe {
+        ffi::__proc_info(
+            call.0,
+            pid.0 as c_int,
+            flavor,
+            arg,
+            buf.as_mut_ptr() as *mut c_void,
+            buffersize,
+        )
+    };
+    if res < 0 {
+        return Err(last_os_error());
+    }
+    Ok(res as usize)
+}
+
+/// List pids matching a `PROC_*` type (eg: `PROC_ALL_PIDS`, 1) and type-specific argument,
+/// as raw bytes. Returns the number of bytes written.
+pub fn listpids(r#type: u32, typeinfo: u32, buf: &mut [u8]) -> Result<usize, std::io::Error> {
+    // SAFETY: The pid is the list type, and the argument is a plain number.
+    unsafe { proc_info(ProcInfoCall::LISTPIDS, Pid(r#type), 0, typeinfo as u64, buf) }
+}
+
+/// Query any `PROC_PID*` flavor of a process, as raw bytes. Returns the number of bytes
+/// written. Unlike libproc's `proc_pidinfo`, flavors are passed through unchecked.
+pub fn pidinfo(pid: Pid, flavor: i32, arg: u64, buf: &mut [u8]) -> Result<usize, std::io::Error> {
+    // SAFETY: PIDINFO flavors only read the process, and treat the argument as a number (a
+    // thread handle or an address in the target process).
+    unsafe { proc_info(ProcInfoCall::PIDINFO, pid, flavor, arg, buf) }
+}
+
+/// Query any `PROC_PIDFD*` flavor of a file descriptor, as raw bytes. Returns the number of
+/// bytes written.
+pub fn pidfdinfo(pid: Pid, fd: Fd, flavor: i32, buf: &mut [u8]) -> Result<usize, std::io::Error> {
+    // SAFETY: PIDFDINFO flavors only read the descriptor.
+    unsafe { proc_info(ProcInfoCall::PIDFDINFO, pid, flavor, fd.0 as u64, buf) }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::fd::AsRawFd;
+
+    use super::*;
+    use crate::darwin::{
+        getpid, proc_pidfdinfo_raw, proc_pidinfo_raw, ProcPidFdInfoFlavor, ProcPidInfoFlavor,
+    };
+
+    #[test]
+    fn test_pidinfo_matches_libproc() {
+        let flavor = ProcPidInfoFlavor::PROC_PIDT_SHORTBSDINFO as i32;
+        let mut direct = [0; 64];
+        let mut libproc = [0; 64];
+        let len = pidinfo(getpid(), flavor, 0, &mut direct).unwrap();
+        assert_eq!(
+            len,
+            proc_pidinfo_raw(getpid(), flavor, 0, &mut libproc).unwrap()
+        );
+        assert_eq!(direct[..len], libproc[..len]);
+
+        assert!(pidinfo(getpid(), 0x7fff, 0, &mut direct).is_err());
+    }
+
+    #[test]
+    fn test_pidfdinfo() {
+        let file = std::fs::File::open("/dev/null").unwrap();
+        let fd = Fd(file.as_raw_fd());
+        let flavor = ProcPidFdInfoFlavor::PROC_PIDFDVNODEPATHINFO as i32;
+        let mut direct = vec![0; 4096];
+        let mut libproc = vec![0; 4096];
+        let len = pidfdinfo(getpid(), fd, flavor, &mut direct).unwrap();
+        assert_eq!(
+            len,
+            proc_pidfdinfo_raw(getpid(), fd, flavor, &mut libproc).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_listpids() {
+        let mut buf = vec![0; 1024 * 1024];
+        let len = listpids(1, 0, &mut buf).unwrap();
+        let pids = buf[..len]
+            .chunks_exact(4)
+            .map(|pid| u32::from_ne_bytes(pid.try_into().unwrap()))
+            .collect::<Vec<_>>();
+        assert!(pids.contains(&getpid().0));
+    }
+}
@@ -0,0 +1,270 @@
+//! Process information from the `kern.proc` sysctls, which, unlike [`super::proc_pidinfo`],
+//! work for every process, including zombies and hardened system processes that fail with
+//! `EPERM`.
+
+use std::fmt;
+use std::time::{Duration, SystemTime};
+
+use libc::{c_char, c_int, c_void};
+
+use super::{last_os_error, libc_str_to_str, Pid, ProcStatus, ValueError};
+
+/// `P_LP64` in [`KinfoProc::flags`]: the process is 64-bit.
+pub const P_LP64: i32 = 0x0000_0004;
+/// `P_SYSTEM` in [`KinfoProc::flags`]: a system process, eg: `kernel_task`.
+pub const P_SYSTEM: i32 = 0x0000_0200;
+/// `P_TRACED` in [`KinfoProc::flags`]: the process is being debugged.
+pub const P_TRACED: i32 = 0x0000_0800;
+/// `P_EXEC` in [`KinfoProc::flags`]: the process has called `exec`.
+pub const P_EXEC: i32 = 0x0000_4000;
+/// `P_TRANSLATED` in [`KinfoProc::flags`]: the process runs under Rosetta.
+pub const P_TRANSLATED: i32 = 0x0002_0000;
+
+/// A process's `struct kinfo_proc`, which is a 64-bit `extern_proc` followed by an `eproc`.
+/// Returned by [`kinfo_proc`] and [`kinfo_proc_all`].
+///
+/// Only the fields that are still filled in by the kernel are exposed, through accessors.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct KinfoProc {
+    // `kp_proc.p_starttime`, which shares a union with two list pointers.
+    p_starttime_sec: i64,
+    p_starttime_usec: i32,
+    _pad0: [u8; 20],
+    p_flag: i32,
+    p_stat: u8,
+    _pad1: [u8; 3],
+    p_pid: i32,
+    _pad2: [u8; 199],
+    p_comm: [c_char; libc::MAXCOMLEN + 1],
+    _pad3: [u8; 132],
+    // `kp_eproc.e_pcred`.
+    p_ruid: u32,
+    p_svuid: u32,
+    p_rgid: u32,
+    p_svgid: u32,
+    _pad4: [u8; 12],
+    // `kp_eproc.e_ucred`.
+    cr_uid: u32,
+    _pad5: [u8; 4],
+    // The first of `cr_groups`, which is the effective group.
+    cr_gid: u32,
+    _pad6: [u8; 128],
+    e_ppid: i32,
+    e_pgid: i32,
+    _pad7: [u8; 80],
+}
+
+const _: () = assert!(std::mem::size_of::<KinfoProc>() == 648);
+
+impl KinfoProc {
+    pub fn pid(&self) -> Pid {
+        Pid(self.p_pid as u32)
+    }
+
+    pub fn ppid(&self) -> Pid {
+        Pid(self.e_ppid as u32)
+    }
+
+    pub fn pgid(&self) -> u32 {
+        self.e_pgid as u32
+    }
+
+    pub fn status(&self) -> Result<ProcStatus, ValueError> {
+        ProcStatus::from_raw(self.p_stat as u32)
+    }
+
+    /// The `P_*` flags, eg: [`P_TRACED`]. These are not the `PROC_FLAG_*` flags of
+    /// [`super::ProcBSDInfo::pbi_flags`].
+    pub fn flags(&self) -> i32 {
+        self.p_flag
+    }
+
+    /// When the process started.
+    pub fn start_time(&self) -> SystemTime {
+        SystemTime::UNIX_EPOCH
+            + Duration::from_secs(self.p_starttime_sec as u64)
+            + Duration::from_micros(self.p_starttime_usec as u64)
+    }
+
+    /// The command name, truncated to 16 bytes.
+    pub fn comm(&self) -> Result<&str, ValueError> {
+        libc_str_to_str(&self.p_comm)
+    }
+
+    /// The effective user.
+    pub fn uid(&self) -> libc::uid_t {
+        self.cr_uid
+    }
+
+    /// The effective group.
+    pub fn gid(&self) -> libc::gid_t {
+        self.cr_gid
+    }
+
+    pub fn ruid(&self) -> libc::uid_t {
+        self.p_ruid
+    }
+
+    pub fn rgid(&self) -> libc::gid_t {
+        self.p_rgid
+    }
+
+    pub fn svuid(&self) -> libc::uid_t {
+        self.p_svuid
+    }
+
+    pub fn svgid(&self) -> libc::gid_t {
+        self.p_svgid
+    }
+
+    /// The command name truncated as `pbsi_comm` is, which is one byte shorter than
+    /// [`KinfoProc::comm`].
+    pub(crate) fn short_comm(&self) -> [c_char; libc::MAXCOMLEN] {
+        let mut comm = [0; libc::MAXCOMLEN];
+        comm[..libc::MAXCOMLEN - 1].copy_from_slice(&self.p_comm[..libc::MAXCOMLEN - 1]);
+        comm
+    }
+}
+
+impl fmt::Debug for KinfoProc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KinfoProc")
+            .field("pid", &self.pid())
+            .field("ppid", &self.ppid())
+            .field("pgid", &self.pgid())
+            .field("status", &self.status())
+            .field("flags", &format_args!("{:#x}", self.flags()))
+            .field("start_time", &self.start_time())
+            .field("comm", &self.comm())
+            .field("uid", &self.uid())
+            .field("gid", &self.gid())
+            .finish()
+    }
+}
+
+/// Call a `kern.proc` sysctl into `buffer`, returning the number of bytes written.
+fn sysctl_into(mib: &mut [c_int], buffer: &mut [KinfoProc]) -> Result<usize, std::io::Error> {
+    let mut len = std::mem::size_of_val(buffer);
+    // SAFETY: The output is a buffer with its size.
+    let res = unsafe {
+        libc::sysctl(
+            mib.as_mut_ptr(),
+            mib.len() as u32,
+            buffer.as_mut_ptr() as *mut c_void,
+            &mut len,
+            std::ptr::null_mut(),
+            0,
+        )
+    };
+    if res != 0 {
+        return Err(last_os_error());
+    }
+    Ok(len)
+}
+
+/// The [`KinfoProc`] of a process, or `None` if there is no such process.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let info = sysctl::kinfo_proc(Pid(1)).unwrap().unwrap();
+/// assert_eq!(info.comm(), Ok("launchd"));
+/// assert_eq!(info.ppid(), Pid(0));
+/// ```
+pub fn kinfo_proc(pid: Pid) -> Result<Option<KinfoProc>, std::io::Error> {
+    let mut mib = [
+        libc::CTL_KERN,
+        libc::KERN_PROC,
+        libc::KERN_PROC_PID,
+        pid.0 as c_int,
+    ];
+    // SAFETY: Zero is valid for every field.
+    let mut buffer = [unsafe { std::mem::zeroed::<KinfoProc>() }];
+    // The sysctl succeeds with no output for a missing process.
+    if sysctl_into(&mut mib, &mut buffer)? < std::mem::size_of::<KinfoProc>() {
+        return Ok(None);
+    }
+    Ok(Some(buffer[0]))
+}
+
+/// The [`KinfoProc`] of every process, in one call. Unlike [`super::proc_listallpids`]
+/// followed by [`super::proc_pidinfo`], this includes processes that `proc_pidinfo` refuses.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// for info in sysctl::kinfo_proc_all().unwrap() {
+///     println!("{} {:?} {:?}", info.pid().0, info.comm(), info.status());
+/// }
+/// ```
+pub fn kinfo_proc_all() -> Result<Vec<KinfoProc>, std::io::Error> {
+    let mut mib = [libc::CTL_KERN, libc::KERN_PROC, libc::KERN_PROC_ALL];
+    loop {
+        let mut len = 0;
+        // SAFETY: A null output asks for the size.
+        let res = unsafe {
+            libc::sysctl(
+                mib.as_mut_ptr(),
+                mib.len() as u32,
+                std::ptr::null_mut(),
+                &mut len,
+                std::ptr::null_mut(),
+                0,
+            )
+        };
+        if res != 0 {
+            return Err(last_os_error());
+        }
+        // Leave room for processes started between the two calls.
+        let count = len / std::mem::size_of::<KinfoProc>() + 16;
+        // SAFETY: Zero is valid for every field.
+        let mut buffer = vec![unsafe { std::mem::zeroed::<KinfoProc>() }; count];
+        match sysctl_into(&mut mib, &mut buffer) {
+            Ok(len) => {
+                buffer.truncate(len / std::mem::size_of::<KinfoProc>());
+                return Ok(buffer);
+            }
+            Err(err) if err.raw_os_error() == Some(libc::ENOMEM) => continue,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::darwin::{getpid, proc_pidinfo, ProcBSDInfo};
+
+    #[test]
+    fn test_kinfo_proc() {
+        let info = kinfo_proc(getpid()).unwrap().unwrap();
+        let bsd = proc_pidinfo::<ProcBSDInfo>(getpid()).unwrap().unwrap();
+        assert_eq!(info.pid(), getpid());
+        assert_eq!(info.ppid(), bsd.pbi_ppid);
+        assert_eq!(info.pgid(), bsd.pbi_pgid);
+        assert_eq!(info.uid(), bsd.pbi_uid);
+        assert_eq!(info.status(), bsd.status());
+        assert_eq!(info.comm(), bsd.comm());
+        assert_ne!(info.flags() & P_LP64, 0);
+        assert_ne!(info.flags() & P_EXEC, 0);
+        assert_eq!(
+            info.start_time(),
+            SystemTime::UNIX_EPOCH
+                + Duration::from_secs(bsd.pbi_start_tvsec)
+                + Duration::from_micros(bsd.pbi_start_tvusec)
+        );
+        assert!(kinfo_proc(Pid(99_999_999)).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_kinfo_proc_all() {
+        let all = kinfo_proc_all().unwrap();
+        assert!(all.iter().any(|info| info.pid() == getpid()));
+        let kernel = all.iter().find(|info| info.pid() == Pid(0)).unwrap();
+        assert_ne!(kernel.flags() & P_SYSTEM, 0);
+        let launchd = all.iter().find(|info| info.pid() == Pid(1)).unwrap();
+        assert_eq!(launchd.uid(), 0);
+        assert_eq!(launchd.comm(), Ok("launchd"));
+    }
+}
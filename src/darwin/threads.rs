@@ -0,0 +1,629 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use libc::{c_char, c_int};
+
+use super::{
+    libc_str_to_str, libproc_call, mach_ticks_to_duration, perf_levels, proc_pidinfo_list,
+    proc_pidinfo_with_arg, ArgFlavor, HasFlavorArg, HasFlavorList, Pid, ProcNameExt,
+    ProcPidInfoFlavor, ValueError, VnodeInfoPath,
+};
+
+mod ffi {
+    use libc::{kern_return_t, mach_port_t};
+
+    extern "C" {
+        pub static mach_task_self_: mach_port_t;
+        pub fn mach_port_deallocate(task: mach_port_t, name: mach_port_t) -> kern_return_t;
+    }
+}
+
+/// The `thread_policy_get` flavor for the requested QoS tier.
+const THREAD_QOS_POLICY: c_int = 9;
+
+/// The kernel's maximum thread name length, including the NUL.
+const MAXTHREADNAMESIZE: usize = 64;
+
+/// An opaque handle for a thread in a process. Usable with [`super::proc_pidinfo_list`] and
+/// [`proc_pidthreadinfo`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct ThreadHandle(pub u64);
+
+impl From<ThreadHandle> for u64 {
+    fn from(thread: ThreadHandle) -> u64 {
+        thread.0
+    }
+}
+
+impl HasFlavorList for ThreadHandle {
+    const FLAVOR: ProcPidInfoFlavor = ProcPidInfoFlavor::PROC_PIDLISTTHREADS;
+}
+
+/// The system-wide ID of a thread in a process, as returned by `pthread_threadid_np`. Usable
+/// with [`super::proc_pidinfo_list`] (from macOS 11) and [`proc_pidthreadcounts`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct ThreadId(pub u64);
+
+impl From<ThreadId> for u64 {
+    fn from(thread: ThreadId) -> u64 {
+        thread.0
+    }
+}
+
+impl HasFlavorList for ThreadId {
+    const FLAVOR: ProcPidInfoFlavor = ProcPidInfoFlavor::PROC_PIDLISTTHREADIDS;
+}
+
+/// Information about a single thread. See [`proc_pidthreadinfo`].
+///
+/// Times are in nanoseconds. Priorities are Mach scheduler priorities, where higher is more
+/// urgent.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct ProcThreadInfo {
+    pub pth_user_time: u64,
+    pub pth_system_time: u64,
+    /// Scaled CPU usage, where 1000 is 100% of one CPU.
+    pub pth_cpu_usage: i32,
+    pub pth_policy: i32,
+    pub pth_run_state: i32,
+    pub pth_flags: i32,
+    pub pth_sleep_time: i32,
+    /// The priority the thread is currently scheduled at.
+    pub pth_curpri: i32,
+    /// The base priority of the thread.
+    pub pth_priority: i32,
+    pub pth_maxpriority: i32,
+    pub pth_name: [c_char; MAXTHREADNAMESIZE],
+}
+
+impl ProcNameExt for ProcThreadInfo {
+    fn raw_name(&self) -> &[c_char] {
+        &self.pth_name
+    }
+}
+
+impl ArgFlavor for ProcThreadInfo {
+    const FLAVOR: ProcPidInfoFlavor = ProcPidInfoFlavor::PROC_PIDTHREADINFO;
+}
+
+impl HasFlavorArg for ProcThreadInfo {
+    type Arg = ThreadHandle;
+}
+
+impl ProcThreadInfo {
+    pub fn name(&self) -> Result<&str, ValueError> {
+        libc_str_to_str(&self.pth_name)
+    }
+
+    /// An owned copy of [`ProcThreadInfo::name`].
+    pub fn name_string(&self) -> Result<String, ValueError> {
+        self.name().map(str::to_owned)
+    }
+
+    /// How far the current priority is above the base priority. The kernel raises a thread's
+    /// priority when a higher-priority thread is waiting on it (through a turnstile, for a lock
+    /// it holds) or when it has a QoS override, so a positive boost usually means the thread is
+    /// blocking more important work.
+    ///
+    /// Returns 0 if the thread isn't boosted. A thread can also run below its base priority, for
+    /// example when throttled, which isn't reported as a negative boost.
+    pub fn priority_boost(&self) -> i32 {
+        (self.pth_curpri - self.pth_priority).max(0)
+    }
+
+    /// Returns true if the thread is running above its base priority. See
+    /// [`ProcThreadInfo::priority_boost`].
+    pub fn is_boosted(&self) -> bool {
+        self.priority_boost() > 0
+    }
+}
+
+/// Get information about a single thread of a process, using a handle from
+/// [`super::proc_pidinfo_list`].
+///
+/// Fails with `ESRCH` if the process or thread no longer exists, and with `EPERM` if the
+/// caller isn't allowed to inspect the process.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// for thread in proc_pidinfo_list_self::<ThreadHandle>().unwrap() {
+///     if let Some(info) = proc_pidthreadinfo(getpid(), thread).unwrap() {
+///         println!(
+///             "{:?}: priority {} (base {})",
+///             info.name(),
+///             info.pth_curpri,
+///             info.pth_priority,
+///         );
+///     }
+/// }
+/// ```
+pub fn proc_pidthreadinfo(
+    pid: Pid,
+    thread: ThreadHandle,
+) -> Result<Option<ProcThreadInfo>, std::io::Error> {
+    proc_pidinfo_with_arg(pid, thread)
+}
+
+/// Information about a single thread, and the file it is doing I/O on
+/// (`proc_threadwithpathinfo`). See [`proc_pidthreadpathinfo`].
+#[repr(C)]
+#[derive(Debug)]
+pub struct ProcThreadWithPathInfo {
+    pub pt: ProcThreadInfo,
+    /// The vnode the thread is using, eg: the file it is blocked reading. The path is empty if
+    /// the thread isn't in a filesystem call.
+    pub pvip: VnodeInfoPath,
+}
+
+impl ArgFlavor for ProcThreadWithPathInfo {
+    const FLAVOR: ProcPidInfoFlavor = ProcPidInfoFlavor::PROC_PIDTHREADPATHINFO;
+}
+
+impl HasFlavorArg for ProcThreadWithPathInfo {
+    type Arg = ThreadHandle;
+}
+
+impl ProcThreadWithPathInfo {
+    /// The path of the file the thread is using, or `None` if it isn't using one.
+    pub fn path(&self) -> Result<Option<&Path>, ValueError> {
+        let path = self.pvip.path()?;
+        Ok((!path.as_os_str().is_empty()).then_some(path))
+    }
+
+    /// An owned copy of [`ProcThreadWithPathInfo::path`].
+    pub fn to_path_buf(&self) -> Result<Option<PathBuf>, ValueError> {
+        self.path().map(|path| path.map(Path::to_path_buf))
+    }
+}
+
+/// Like [`proc_pidthreadinfo`], and also returns the path of the file the thread is doing I/O
+/// on. Useful for finding threads stuck in slow filesystem calls, eg: on a network mount.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// for thread in proc_pidinfo_list_self::<ThreadHandle>().unwrap() {
+///     if let Some(info) = proc_pidthreadpathinfo(getpid(), thread).unwrap() {
+///         if let Some(path) = info.path().unwrap() {
+///             println!("{:?} is using {}", info.pt.name(), path.display());
+///         }
+///     }
+/// }
+/// ```
+pub fn proc_pidthreadpathinfo(
+    pid: Pid,
+    thread: ThreadHandle,
+) -> Result<Option<ProcThreadWithPathInfo>, std::io::Error> {
+    proc_pidinfo_with_arg(pid, thread)
+}
+
+/// The counters of a thread on one kind of core (`proc_threadcounts_data`). See
+/// [`ThreadCounts`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProcThreadCountsData {
+    /// Instructions retired. Zero without hardware counters, eg: in a virtual machine.
+    pub ptcd_instructions: u64,
+    pub ptcd_cycles: u64,
+    /// User time, in Mach absolute time units.
+    pub ptcd_user_time_mach: u64,
+    /// System time, in Mach absolute time units.
+    pub ptcd_system_time_mach: u64,
+    /// Energy used, in nanojoules.
+    pub ptcd_energy_nj: u64,
+}
+
+impl ProcThreadCountsData {
+    pub fn user_time(&self) -> Duration {
+        mach_ticks_to_duration(self.ptcd_user_time_mach)
+    }
+
+    pub fn system_time(&self) -> Duration {
+        mach_ticks_to_duration(self.ptcd_system_time_mach)
+    }
+}
+
+/// The size of the header of `struct proc_threadcounts` (`ptc_len` and two reserved fields),
+/// which is followed by one [`ProcThreadCountsData`] per CPU performance level.
+const PROC_THREADCOUNTS_HEADER_SIZE: usize = 8;
+
+/// The CPU usage of a thread, split by the kind of core it ran on. See
+/// [`proc_pidthreadcounts`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThreadCounts {
+    pub thread: ThreadId,
+    /// The counters for each CPU performance level, from the fastest. On Apple silicon these
+    /// are the performance and then the efficiency cores; Intel has a single level.
+    pub levels: Vec<ProcThreadCountsData>,
+}
+
+impl ThreadCounts {
+    /// The counters for the performance cores, if there is more than one kind of core.
+    pub fn performance(&self) -> Option<&ProcThreadCountsData> {
+        (self.levels.len() > 1).then(|| &self.levels[0])
+    }
+
+    /// The counters for the efficiency cores, if there is more than one kind of core.
+    pub fn efficiency(&self) -> Option<&ProcThreadCountsData> {
+        (self.levels.len() > 1).then(|| &self.levels[self.levels.len() - 1])
+    }
+
+    /// The counters summed over every kind of core.
+    pub fn total(&self) -> ProcThreadCountsData {
+        self.levels
+            .iter()
+            .fold(ProcThreadCountsData::default(), |total, level| {
+                ProcThreadCountsData {
+                    ptcd_instructions: total.ptcd_instructions + level.ptcd_instructions,
+                    ptcd_cycles: total.ptcd_cycles + level.ptcd_cycles,
+                    ptcd_user_time_mach: total.ptcd_user_time_mach + level.ptcd_user_time_mach,
+                    ptcd_system_time_mach: total.ptcd_system_time_mach
+                        + level.ptcd_system_time_mach,
+                    ptcd_energy_nj: total.ptcd_energy_nj + level.ptcd_energy_nj,
+                }
+            })
+    }
+}
+
+/// Get the CPU time, instructions, cycles and energy of a thread of a process, split by the
+/// kind of core it ran on (`PROC_PIDTHREADCOUNTS`, from macOS 13).
+///
+/// Fails with `ESRCH` if the process or thread no longer exists, and with `EINVAL` on older
+/// kernels.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// for thread in proc_pidinfo_list_self::<ThreadId>().unwrap() {
+///     let Ok(Some(counts)) = proc_pidthreadcounts(getpid(), thread) else {
+///         continue;
+///     };
+///     if let (Some(p), Some(e)) = (counts.performance(), counts.efficiency()) {
+///         println!("{}: P {:?}, E {:?}", thread.0, p.user_time(), e.user_time());
+///     }
+/// }
+/// ```
+pub fn proc_pidthreadcounts(
+    pid: Pid,
+    thread: ThreadId,
+) -> Result<Option<ThreadCounts>, std::io::Error> {
+    let header = PROC_THREADCOUNTS_HEADER_SIZE;
+    let entry = std::mem::size_of::<ProcThreadCountsData>();
+    let capacity = perf_levels() as usize;
+    // Allocate as u64s for alignment.
+    let mut buffer = vec![0_u64; (header + capacity * entry) / 8];
+    // SAFETY: The kernel writes at most the length of the buffer.
+    let len = libproc_call(|| unsafe {
+        super::ffi::proc_pidinfo(
+            pid.0 as _,
+            ProcPidInfoFlavor::PROC_PIDTHREADCOUNTS as c_int,
+            thread.0,
+            buffer.as_mut_ptr() as *mut libc::c_void,
+            (buffer.len() * 8) as c_int,
+        )
+    })?;
+    if len < header {
+        return Ok(None);
+    }
+    // SAFETY: The buffer holds a header followed by `(len - header) / entry` entries, and is
+    // aligned for both.
+    let levels = unsafe {
+        std::slice::from_raw_parts(
+            (buffer.as_ptr() as *const u8).add(header) as *const ProcThreadCountsData,
+            ((len - header) / entry).min(capacity),
+        )
+    };
+    Ok(Some(ThreadCounts {
+        thread,
+        levels: levels.to_vec(),
+    }))
+}
+
+/// Get the [`ThreadCounts`] of every thread of a process, skipping threads that exit while
+/// they're read. See [`proc_pidthreadcounts`].
+pub fn thread_counts(pid: Pid) -> Result<Vec<ThreadCounts>, std::io::Error> {
+    let mut counts = vec![];
+    for thread in proc_pidinfo_list::<ThreadId>(pid)? {
+        match proc_pidthreadcounts(pid, thread) {
+            Ok(Some(thread)) => counts.push(thread),
+            Ok(None) => {}
+            Err(err) if err.raw_os_error() == Some(libc::ESRCH) => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(counts)
+}
+
+/// The QoS tier a thread requested (`THREAD_QOS_*`).
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(i32)]
+pub enum ThreadQos {
+    UNSPECIFIED = 0,
+    MAINTENANCE = 1,
+    BACKGROUND = 2,
+    UTILITY = 3,
+    /// The default tier for threads that don't set one.
+    LEGACY = 4,
+    USER_INITIATED = 5,
+    USER_INTERACTIVE = 6,
+}
+
+impl ThreadQos {
+    fn from_raw(tier: c_int) -> Result<Self, ValueError> {
+        match tier {
+            0 => Ok(ThreadQos::UNSPECIFIED),
+            1 => Ok(ThreadQos::MAINTENANCE),
+            2 => Ok(ThreadQos::BACKGROUND),
+            3 => Ok(ThreadQos::UTILITY),
+            4 => Ok(ThreadQos::LEGACY),
+            5 => Ok(ThreadQos::USER_INITIATED),
+            6 => Ok(ThreadQos::USER_INTERACTIVE),
+            _ => Err(ValueError::UnexpectedEnumValue),
+        }
+    }
+
+    /// Returns true if, on Apple silicon, threads at this tier are confined to the efficiency
+    /// cores. Higher tiers may run on either kind of core.
+    pub fn efficiency_cores_only(self) -> bool {
+        matches!(self, ThreadQos::MAINTENANCE | ThreadQos::BACKGROUND)
+    }
+}
+
+/// How a thread of the current process is bound to CPUs. See [`thread_bindings_self`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThreadBinding {
+    /// The handle, as listed by [`super::proc_pidinfo_list`].
+    pub thread: ThreadHandle,
+    /// The system-wide thread ID, as returned by `pthread_threadid_np`.
+    pub thread_id: u64,
+    /// The affinity tag set with `THREAD_AFFINITY_POLICY`. `None` if the thread has no tag, or
+    /// on Apple silicon, which doesn't support affinity.
+    pub affinity_tag: Option<i32>,
+    /// The requested QoS tier, which decides whether the thread may use the performance cores.
+    pub qos: Option<ThreadQos>,
+}
+
+/// List how each thread of the current process is bound to CPUs.
+///
+/// macOS doesn't pin threads to cores. The closest equivalents are affinity tags, which hint
+/// that threads should share a cache (Intel only), and QoS tiers, which decide whether a thread
+/// may run on the performance cores of Apple silicon. Reading these needs the thread's Mach
+/// port, so only the current process is supported.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// for binding in thread_bindings_self().unwrap() {
+///     let info = proc_pidthreadinfo(getpid(), binding.thread).unwrap();
+///     let name = info.as_ref().and_then(|info| info.name().ok());
+///     println!("{:?}: {:?}", name, binding.qos);
+/// }
+/// ```
+pub fn thread_bindings_self() -> Result<Vec<ThreadBinding>, std::io::Error> {
+    // SAFETY: The task port is set up before any Rust code runs, and never changes.
+    let task = unsafe { ffi::mach_task_self_ };
+    // SAFETY: The current task port is valid.
+    unsafe { task_threads(task, thread_binding) }
+        .map_err(|kr| std::io::Error::other(format!("task_threads failed: {kr}")))
+}
+
+/// Call `f` with each thread of a task, collecting the results. Threads that exit before `f`
+/// reads them should return `None`. Fails with the Mach error if the threads can't be listed.
+///
+/// # Safety
+///
+/// `task` must be a valid task port, not just a name port.
+pub(crate) unsafe fn task_threads<T>(
+    task: libc::mach_port_t,
+    mut f: impl FnMut(libc::thread_act_t) -> Option<T>,
+) -> Result<Vec<T>, libc::kern_return_t> {
+    // SAFETY: The task port is set up before any Rust code runs, and never changes.
+    let task_self = unsafe { ffi::mach_task_self_ };
+    let mut list: libc::thread_act_array_t = std::ptr::null_mut();
+    let mut count = 0;
+    // SAFETY: The kernel allocates the list and returns its length.
+    let kr = unsafe { libc::task_threads(task, &mut list, &mut count) };
+    if kr != libc::KERN_SUCCESS {
+        return Err(kr);
+    }
+    // SAFETY: The list holds `count` thread ports until it's deallocated below.
+    let threads = unsafe { std::slice::from_raw_parts(list, count as usize) };
+    let results = threads
+        .iter()
+        .filter_map(|&thread| {
+            let result = f(thread);
+            // SAFETY: We own a send right to each port in the list.
+            unsafe { ffi::mach_port_deallocate(task_self, thread) };
+            result
+        })
+        .collect();
+    // SAFETY: The list was allocated by the kernel in our address space.
+    unsafe {
+        libc::vm_deallocate(
+            task_self,
+            list as libc::vm_address_t,
+            std::mem::size_of_val(threads) as libc::vm_size_t,
+        )
+    };
+    Ok(results)
+}
+
+/// Read the identifiers of a thread, or `None` if the thread has exited.
+pub(crate) fn thread_identifier(
+    thread: libc::thread_act_t,
+) -> Option<libc::thread_identifier_info> {
+    let mut identifier = libc::thread_identifier_info {
+        thread_id: 0,
+        thread_handle: 0,
+        dispatch_qaddr: 0,
+    };
+    let mut count = libc::THREAD_IDENTIFIER_INFO_COUNT;
+    // SAFETY: The output struct and its size in integers are valid.
+    let kr = unsafe {
+        libc::thread_info(
+            thread,
+            libc::THREAD_IDENTIFIER_INFO as _,
+            &mut identifier as *mut _ as libc::thread_info_t,
+            &mut count,
+        )
+    };
+    (kr == libc::KERN_SUCCESS).then_some(identifier)
+}
+
+/// Read the binding of a thread, or `None` if the thread has exited.
+fn thread_binding(thread: libc::thread_act_t) -> Option<ThreadBinding> {
+    let identifier = thread_identifier(thread)?;
+
+    let mut affinity = libc::thread_affinity_policy { affinity_tag: 0 };
+    let affinity_tag = thread_policy(
+        thread,
+        libc::THREAD_AFFINITY_POLICY,
+        &mut affinity as *mut _ as libc::thread_policy_t,
+        libc::THREAD_AFFINITY_POLICY_COUNT,
+    )
+    .then_some(affinity.affinity_tag)
+    .filter(|&tag| tag != 0);
+
+    // struct thread_qos_policy { qos_tier, tier_importance }
+    let mut qos = [0 as libc::integer_t; 2];
+    let qos = thread_policy(thread, THREAD_QOS_POLICY, qos.as_mut_ptr(), qos.len() as _)
+        .then(|| ThreadQos::from_raw(qos[0]).ok())
+        .flatten();
+
+    Some(ThreadBinding {
+        thread: ThreadHandle(identifier.thread_handle),
+        thread_id: identifier.thread_id,
+        affinity_tag,
+        qos,
+    })
+}
+
+/// Call `thread_policy_get`, returning true on success.
+fn thread_policy(
+    thread: libc::thread_act_t,
+    flavor: c_int,
+    info: libc::thread_policy_t,
+    mut count: libc::mach_msg_type_number_t,
+) -> bool {
+    let mut get_default = 0;
+    // SAFETY: The caller passes a buffer of `count` integers.
+    let kr =
+        unsafe { libc::thread_policy_get(thread, flavor as _, info, &mut count, &mut get_default) };
+    kr == libc::KERN_SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::darwin::{getpid, proc_pidinfo_list};
+
+    #[test]
+    fn test_thread_path_info_self() {
+        let threads = proc_pidinfo_list::<ThreadHandle>(getpid()).unwrap();
+        let info = proc_pidthreadpathinfo(getpid(), threads[0])
+            .unwrap()
+            .unwrap();
+        let plain = proc_pidthreadinfo(getpid(), threads[0]).unwrap().unwrap();
+        assert_eq!(info.pt.pth_maxpriority, plain.pth_maxpriority);
+        assert_eq!(info.pt.name(), plain.name());
+        // The thread may or may not be in a filesystem call, but the path must decode.
+        info.path().unwrap();
+    }
+
+    #[test]
+    fn test_thread_info_self() {
+        let name = "proc-pidinfo-test-thread";
+        let (tx, rx) = std::sync::mpsc::channel::<()>();
+        let thread = std::thread::Builder::new()
+            .name(name.to_owned())
+            .spawn(move || rx.recv())
+            .unwrap();
+
+        // Thread names are set asynchronously by the new thread.
+        let mut found = None;
+        for _ in 0..100 {
+            let threads = proc_pidinfo_list::<ThreadHandle>(getpid()).unwrap();
+            assert!(threads.len() >= 2);
+            found = threads
+                .into_iter()
+                .filter_map(|thread| proc_pidthreadinfo(getpid(), thread).ok().flatten())
+                .find(|info| info.name() == Ok(name));
+            if found.is_some() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        let info = found.unwrap();
+        assert!(info.pth_maxpriority >= info.pth_priority);
+        assert!(!info.is_boosted() || info.priority_boost() > 0);
+
+        drop(tx);
+        thread.join().unwrap().unwrap_err();
+    }
+
+    #[test]
+    fn test_thread_bindings_self() {
+        let (tx, rx) = std::sync::mpsc::channel::<()>();
+        let (id_tx, id_rx) = std::sync::mpsc::channel();
+        let thread = std::thread::spawn(move || {
+            let mut id = 0;
+            // SAFETY: Both calls only affect and read the current thread.
+            unsafe {
+                libc::pthread_set_qos_class_self_np(libc::qos_class_t::QOS_CLASS_BACKGROUND, 0);
+                libc::pthread_threadid_np(0 as libc::pthread_t, &mut id);
+            }
+            id_tx.send(id).unwrap();
+            rx.recv()
+        });
+        let id = id_rx.recv().unwrap();
+
+        let bindings = thread_bindings_self().unwrap();
+        let binding = bindings
+            .iter()
+            .find(|binding| binding.thread_id == id)
+            .unwrap();
+        assert_eq!(binding.qos, Some(ThreadQos::BACKGROUND));
+        assert!(binding.qos.unwrap().efficiency_cores_only());
+        let threads = proc_pidinfo_list::<ThreadHandle>(getpid()).unwrap();
+        assert!(threads.contains(&binding.thread));
+
+        drop(tx);
+        thread.join().unwrap().unwrap_err();
+    }
+
+    #[test]
+    fn test_thread_info_missing() {
+        let err = proc_pidthreadinfo(getpid(), ThreadHandle(1)).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::ESRCH));
+        let thread = proc_pidinfo_list::<ThreadHandle>(getpid()).unwrap()[0];
+        let err = proc_pidthreadinfo(Pid(99_999_999), thread).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::ESRCH));
+    }
+
+    #[test]
+    fn test_thread_counts_self() {
+        let ids = proc_pidinfo_list::<ThreadId>(getpid()).unwrap();
+        let mut own_id = 0;
+        // SAFETY: The current thread is valid.
+        assert_eq!(
+            unsafe { libc::pthread_threadid_np(libc::pthread_self(), &mut own_id) },
+            0
+        );
+        assert!(ids.contains(&ThreadId(own_id)));
+
+        let counts = match proc_pidthreadcounts(getpid(), ThreadId(own_id)) {
+            // Before macOS 13.
+            Err(err) if err.raw_os_error() == Some(libc::EINVAL) => return,
+            result => result.unwrap().unwrap(),
+        };
+        assert_eq!(counts.levels.len(), perf_levels() as usize);
+        assert!(counts.total().user_time() > Duration::ZERO);
+        assert_eq!(counts.performance().is_some(), perf_levels() > 1);
+        assert!(thread_counts(getpid()).unwrap().len() <= ids.len() + 1);
+    }
+}
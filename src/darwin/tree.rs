@@ -0,0 +1,195 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::{all_short_bsd_info, Pid, ProcBSDShortInfo};
+
+/// A snapshot of the process tree, linking each process to its parent.
+///
+/// Processes come and go while the snapshot is taken, so a parent may be missing (it exited,
+/// and its children haven't been reparented yet) or its pid may have been reused, which can
+/// even produce a cycle. Processes whose parent is missing are roots, and cycles are broken
+/// at their lowest pid, so every process is reachable from [`ProcessTree::roots`] exactly
+/// once.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let tree = ProcessTree::snapshot().unwrap();
+/// for root in tree.roots() {
+///     println!("{} has {} descendants", root.0, tree.descendants(root).len());
+/// }
+/// println!("{:?}", tree.children(getpid()));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ProcessTree {
+    infos: BTreeMap<Pid, ProcBSDShortInfo>,
+    parents: BTreeMap<Pid, Pid>,
+    children: BTreeMap<Pid, Vec<Pid>>,
+}
+
+impl ProcessTree {
+    /// Snapshot every process on the system. See [`all_short_bsd_info`].
+    pub fn snapshot() -> Result<Self, std::io::Error> {
+        Ok(Self::from_infos(all_short_bsd_info()?))
+    }
+
+    /// Build a tree from short info read earlier, eg: by a [`Scanner`](super::Scanner).
+    pub fn from_infos(infos: impl IntoIterator<Item = ProcBSDShortInfo>) -> Self {
+        let infos = infos
+            .into_iter()
+            .map(|info| (info.pbsi_pid, info))
+            .collect::<BTreeMap<_, _>>();
+        let mut parents = infos
+            .values()
+            .filter(|info| info.pbsi_ppid != info.pbsi_pid && infos.contains_key(&info.pbsi_ppid))
+            .map(|info| (info.pbsi_pid, info.pbsi_ppid))
+            .collect::<BTreeMap<_, _>>();
+
+        // Follow each chain of parents to a root, or back into itself.
+        let mut done = BTreeSet::new();
+        for &start in infos.keys() {
+            let mut path = vec![];
+            let mut pid = start;
+            while !done.contains(&pid) {
+                if let Some(index) = path.iter().position(|&seen| seen == pid) {
+                    let lowest = path[index..].iter().min().copied().unwrap();
+                    parents.remove(&lowest);
+                    break;
+                }
+                path.push(pid);
+                match parents.get(&pid) {
+                    Some(&parent) => pid = parent,
+                    None => break,
+                }
+            }
+            done.extend(path);
+        }
+
+        let mut children = BTreeMap::<Pid, Vec<Pid>>::new();
+        for (&child, &parent) in &parents {
+            children.entry(parent).or_default().push(child);
+        }
+        Self {
+            infos,
+            parents,
+            children,
+        }
+    }
+
+    /// The number of processes in the snapshot.
+    pub fn len(&self) -> usize {
+        self.infos.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.infos.is_empty()
+    }
+
+    /// The short info of a process, if it is in the snapshot.
+    pub fn get(&self, pid: Pid) -> Option<&ProcBSDShortInfo> {
+        self.infos.get(&pid)
+    }
+
+    /// Every process in the snapshot, in pid order.
+    pub fn iter(&self) -> impl Iterator<Item = &ProcBSDShortInfo> {
+        self.infos.values()
+    }
+
+    /// The parent of a process, or `None` for a root.
+    pub fn parent(&self, pid: Pid) -> Option<Pid> {
+        self.parents.get(&pid).copied()
+    }
+
+    /// The processes without a parent in the snapshot, in pid order. On macOS, this is usually
+    /// just `kernel_task` (pid 0), plus any processes whose parent exited mid-snapshot.
+    pub fn roots(&self) -> Vec<Pid> {
+        self.infos
+            .keys()
+            .copied()
+            .filter(|pid| !self.parents.contains_key(pid))
+            .collect()
+    }
+
+    /// The direct children of a process, in pid order.
+    pub fn children(&self, pid: Pid) -> &[Pid] {
+        self.children.get(&pid).map_or(&[], Vec::as_slice)
+    }
+
+    /// Every descendant of a process, not including the process itself. Parents come before
+    /// their children, so reverse the list to visit children first, eg: to signal a subtree
+    /// from the leaves up.
+    pub fn descendants(&self, pid: Pid) -> Vec<Pid> {
+        let mut descendants = vec![];
+        let mut stack = self.children(pid).iter().rev().copied().collect::<Vec<_>>();
+        while let Some(pid) = stack.pop() {
+            descendants.push(pid);
+            stack.extend(self.children(pid).iter().rev());
+        }
+        descendants
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::darwin::{getpid, proc_pidinfo_self};
+    use crate::testutil::TestChild;
+
+    fn info(pid: u32, ppid: u32) -> ProcBSDShortInfo {
+        let mut info = proc_pidinfo_self::<ProcBSDShortInfo>().unwrap().unwrap();
+        info.pbsi_pid = Pid(pid);
+        info.pbsi_ppid = Pid(ppid);
+        info
+    }
+
+    #[test]
+    fn test_tree_shape() {
+        let tree = ProcessTree::from_infos([
+            info(0, 0),
+            info(1, 0),
+            info(10, 1),
+            info(11, 10),
+            info(12, 10),
+            info(20, 99),
+            // A cycle, from pid reuse.
+            info(30, 31),
+            info(31, 30),
+        ]);
+        assert_eq!(tree.len(), 8);
+        assert_eq!(tree.roots(), vec![Pid(0), Pid(20), Pid(30)]);
+        assert_eq!(tree.parent(Pid(11)), Some(Pid(10)));
+        assert_eq!(tree.parent(Pid(0)), None);
+        assert_eq!(tree.children(Pid(10)), &[Pid(11), Pid(12)]);
+        assert_eq!(tree.children(Pid(30)), &[Pid(31)]);
+        assert_eq!(
+            tree.descendants(Pid(0)),
+            vec![Pid(1), Pid(10), Pid(11), Pid(12)]
+        );
+        assert!(tree.descendants(Pid(12)).is_empty());
+    }
+
+    #[test]
+    fn test_tree_snapshot() {
+        let child = TestChild::sleep();
+        let tree = ProcessTree::snapshot();
+        let child = child.pid();
+
+        let tree = tree.unwrap();
+        assert_eq!(tree.parent(child), Some(getpid()));
+        assert!(tree.children(getpid()).contains(&child));
+        let mut root = getpid();
+        while let Some(parent) = tree.parent(root) {
+            root = parent;
+        }
+        assert!(tree.roots().contains(&root));
+        assert!(tree.descendants(root).contains(&getpid()));
+        assert_eq!(
+            tree.roots().len()
+                + tree
+                    .roots()
+                    .iter()
+                    .map(|&pid| tree.descendants(pid).len())
+                    .sum::<usize>(),
+            tree.len()
+        );
+    }
+}
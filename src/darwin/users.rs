@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::sync::{Mutex, OnceLock};
+
+use libc::{c_char, c_int, gid_t, uid_t};
+
+use super::{ProcBSDInfo, ProcBSDInfoOwned, ProcBSDShortInfo, VInfoStat};
+
+/// The largest buffer to try for a single `getpwuid_r`/`getgrgid_r` entry. Groups with many
+/// members can be large.
+const MAX_ENTRY_BUFFER: usize = 1024 * 1024;
+
+type NameCache<T> = OnceLock<Mutex<HashMap<T, Option<String>>>>;
+
+static USERS: NameCache<uid_t> = OnceLock::new();
+static GROUPS: NameCache<gid_t> = OnceLock::new();
+
+fn cached<T: Copy + Eq + std::hash::Hash>(
+    cache: &NameCache<T>,
+    id: T,
+    lookup: impl FnOnce(T) -> Option<String>,
+) -> Option<String> {
+    let cache = cache.get_or_init(Default::default);
+    if let Some(name) = cache.lock().unwrap().get(&id) {
+        return name.clone();
+    }
+    // Look up without the lock held, as directory services can be slow.
+    let name = lookup(id);
+    cache.lock().unwrap().insert(id, name.clone());
+    name
+}
+
+/// Call a reentrant `get*_r` function, growing the buffer until the entry fits. Returns the
+/// name read by `name` from the entry, or `None` if there is no entry.
+///
+/// # Safety
+///
+/// `get` must behave like `getpwuid_r`, and `name` must return a NUL-terminated string.
+unsafe fn lookup<T>(
+    get: impl Fn(*mut T, *mut c_char, usize, *mut *mut T) -> c_int,
+    name: impl Fn(&T) -> *const c_char,
+) -> Option<String> {
+    let mut buffer = vec![0 as c_char; 1024];
+    loop {
+        let mut entry = std::mem::MaybeUninit::<T>::uninit();
+        let mut result = std::ptr::null_mut();
+        let res = get(
+            entry.as_mut_ptr(),
+            buffer.as_mut_ptr(),
+            buffer.len(),
+            &mut result,
+        );
+        if res == libc::ERANGE && buffer.len() < MAX_ENTRY_BUFFER {
+            buffer.resize(buffer.len() * 2, 0);
+            continue;
+        }
+        if res != 0 || result.is_null() {
+            return None;
+        }
+        // SAFETY: The entry was filled in, and its strings point into the buffer.
+        let name = unsafe { CStr::from_ptr(name(entry.assume_init_ref())) };
+        return Some(name.to_string_lossy().into_owned());
+    }
+}
+
+/// Look up the name of a user, eg: `root` for 0. Returns `None` if the user doesn't exist.
+///
+/// Results, including missing users, are cached for the life of the process. See
+/// [`clear_name_cache`].
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// assert_eq!(user_name(0).as_deref(), Some("root"));
+/// ```
+pub fn user_name(uid: uid_t) -> Option<String> {
+    cached(&USERS, uid, |uid| {
+        // SAFETY: getpwuid_r writes the entry and its strings into the buffers it's given.
+        unsafe {
+            lookup(
+                |pwd, buf, len, result| libc::getpwuid_r(uid, pwd, buf, len, result),
+                |pwd: &libc::passwd| pwd.pw_name,
+            )
+        }
+    })
+}
+
+/// Look up the name of a group, eg: `wheel` for 0. Returns `None` if the group doesn't exist.
+///
+/// Results, including missing groups, are cached for the life of the process. See
+/// [`clear_name_cache`].
+pub fn group_name(gid: gid_t) -> Option<String> {
+    cached(&GROUPS, gid, |gid| {
+        // SAFETY: getgrgid_r writes the entry and its strings into the buffers it's given.
+        unsafe {
+            lookup(
+                |grp, buf, len, result| libc::getgrgid_r(gid, grp, buf, len, result),
+                |grp: &libc::group| grp.gr_name,
+            )
+        }
+    })
+}
+
+/// Forget the names cached by [`user_name`] and [`group_name`], eg: after users are added.
+pub fn clear_name_cache() {
+    for cache in [&USERS, &GROUPS] {
+        if let Some(cache) = cache.get() {
+            cache.lock().unwrap().clear();
+        }
+    }
+}
+
+impl ProcBSDInfo {
+    /// The name of the effective user. See [`user_name`].
+    pub fn username(&self) -> Option<String> {
+        user_name(self.pbi_uid)
+    }
+
+    /// The name of the effective group. See [`group_name`].
+    pub fn groupname(&self) -> Option<String> {
+        group_name(self.pbi_gid)
+    }
+}
+
+impl ProcBSDInfoOwned {
+    /// The name of the effective user. See [`user_name`].
+    pub fn username(&self) -> Option<String> {
+        user_name(self.uid)
+    }
+
+    /// The name of the effective group. See [`group_name`].
+    pub fn groupname(&self) -> Option<String> {
+        group_name(self.gid)
+    }
+}
+
+impl ProcBSDShortInfo {
+    /// The name of the effective user. See [`user_name`].
+    ///
+    /// ```
+    /// use proc_pidinfo::*;
+    ///
+    /// for info in all_short_bsd_info().unwrap() {
+    ///     println!("{} {:?}", info.pbsi_pid.0, info.username());
+    /// }
+    /// ```
+    pub fn username(&self) -> Option<String> {
+        user_name(self.pbsi_uid)
+    }
+
+    /// The name of the effective group. See [`group_name`].
+    pub fn groupname(&self) -> Option<String> {
+        group_name(self.pbsi_gid)
+    }
+}
+
+impl VInfoStat {
+    /// The name of the user that owns the file. See [`user_name`].
+    pub fn username(&self) -> Option<String> {
+        user_name(self.vst_uid)
+    }
+
+    /// The name of the group that owns the file. See [`group_name`].
+    pub fn groupname(&self) -> Option<String> {
+        group_name(self.vst_gid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::darwin::proc_pidinfo_self;
+
+    #[test]
+    fn test_names() {
+        assert_eq!(user_name(0).as_deref(), Some("root"));
+        assert_eq!(group_name(0).as_deref(), Some("wheel"));
+        assert_eq!(user_name(3_000_000_000), None);
+        assert_eq!(group_name(3_000_000_000), None);
+        clear_name_cache();
+        assert_eq!(user_name(0).as_deref(), Some("root"));
+
+        let info = proc_pidinfo_self::<ProcBSDShortInfo>().unwrap().unwrap();
+        // SAFETY: geteuid never fails.
+        let euid = unsafe { libc::geteuid() };
+        assert_eq!(info.username(), user_name(euid));
+        assert!(info.username().is_some());
+    }
+}
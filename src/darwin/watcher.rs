@@ -0,0 +1,299 @@
+use std::collections::VecDeque;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::time::Duration;
+
+use libc::c_int;
+
+use super::{Pid, Sink};
+
+/// A set of process lifecycle events to watch for with [`ProcessWatcher::watch`].
+///
+/// Sets can be combined with `|`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessEvents(u32);
+
+impl ProcessEvents {
+    /// The process exited.
+    pub const EXIT: Self = Self(libc::NOTE_EXIT);
+    /// The process exited, and the exit status should be reported. Only valid for children of
+    /// the current process.
+    pub const EXIT_STATUS: Self = Self(libc::NOTE_EXIT | libc::NOTE_EXITSTATUS);
+    /// The process called `fork`.
+    pub const FORK: Self = Self(libc::NOTE_FORK);
+    /// The process called `exec`.
+    pub const EXEC: Self = Self(libc::NOTE_EXEC);
+    /// Exit, fork and exec events.
+    pub const ALL: Self = Self(libc::NOTE_EXIT | libc::NOTE_FORK | libc::NOTE_EXEC);
+
+    /// Returns true if all events in `other` are also in this set.
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl std::ops::BitOr for ProcessEvents {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// A lifecycle event delivered by a [`ProcessWatcher`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum ProcessEvent {
+    /// The process exited. If the process was watched with [`ProcessEvents::EXIT_STATUS`], the
+    /// raw wait status is included (see `libc::WEXITSTATUS` and friends).
+    Exit { pid: Pid, status: Option<c_int> },
+    /// The process called `fork`.
+    Fork { pid: Pid },
+    /// The process called `exec`.
+    Exec { pid: Pid },
+}
+
+impl ProcessEvent {
+    /// The process this event is about.
+    pub fn pid(&self) -> Pid {
+        match *self {
+            ProcessEvent::Exit { pid, .. } => pid,
+            ProcessEvent::Fork { pid } => pid,
+            ProcessEvent::Exec { pid } => pid,
+        }
+    }
+}
+
+/// Watches processes for exit, fork and exec events using a `kqueue` with `EVFILT_PROC`.
+///
+/// Watches are removed automatically by the kernel once a process exits.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let mut child = std::process::Command::new("/usr/bin/true").spawn().unwrap();
+/// let pid = Pid(child.id());
+/// let mut watcher = ProcessWatcher::new().unwrap();
+/// // The child may already be gone, in which case the watch fails with ESRCH.
+/// if watcher.watch(pid, ProcessEvents::EXIT).is_ok() {
+///     let event = watcher.wait(None).unwrap().unwrap();
+///     assert_eq!(event, ProcessEvent::Exit { pid, status: None });
+/// }
+/// child.wait().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct ProcessWatcher {
+    kq: OwnedFd,
+    pending: VecDeque<ProcessEvent>,
+}
+
+impl ProcessWatcher {
+    /// Create a new watcher with its own `kqueue`.
+    pub fn new() -> Result<Self, std::io::Error> {
+        // SAFETY: kqueue has no memory safety requirements, and we take ownership of the fd.
+        let kq = unsafe {
+            let fd = libc::kqueue();
+            if fd < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            OwnedFd::from_raw_fd(fd)
+        };
+        Ok(Self {
+            kq,
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// Start watching a process for the given events, replacing any existing watch for it.
+    ///
+    /// Fails with `ESRCH` if the process doesn't exist (or has already exited).
+    pub fn watch(&self, pid: Pid, events: ProcessEvents) -> Result<(), std::io::Error> {
+        self.change(pid, libc::EV_ADD | libc::EV_ENABLE, events.0)
+    }
+
+    /// Stop watching a process.
+    pub fn unwatch(&self, pid: Pid) -> Result<(), std::io::Error> {
+        self.change(pid, libc::EV_DELETE, 0)
+    }
+
+    fn change(&self, pid: Pid, flags: u16, fflags: u32) -> Result<(), std::io::Error> {
+        let change = libc::kevent {
+            ident: pid.0 as _,
+            filter: libc::EVFILT_PROC,
+            flags,
+            fflags,
+            data: 0,
+            udata: std::ptr::null_mut(),
+        };
+        // SAFETY: We pass one valid change and no output buffer.
+        let res = unsafe {
+            libc::kevent(
+                self.kq.as_raw_fd(),
+                &change,
+                1,
+                std::ptr::null_mut(),
+                0,
+                std::ptr::null(),
+            )
+        };
+        if res < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Wait for the next event. Returns `Ok(None)` if `timeout` elapses first, or waits forever
+    /// if `timeout` is `None`.
+    pub fn wait(
+        &mut self,
+        timeout: Option<Duration>,
+    ) -> Result<Option<ProcessEvent>, std::io::Error> {
+        if let Some(event) = self.pending.pop_front() {
+            return Ok(Some(event));
+        }
+
+        let timeout = timeout.map(|timeout| libc::timespec {
+            tv_sec: timeout.as_secs() as _,
+            tv_nsec: timeout.subsec_nanos() as _,
+        });
+        // SAFETY: An all-zero kevent is a valid value.
+        let mut event: libc::kevent = unsafe { std::mem::zeroed() };
+        // SAFETY: We pass no changes and a single valid output event.
+        let res = unsafe {
+            libc::kevent(
+                self.kq.as_raw_fd(),
+                std::ptr::null(),
+                0,
+                &mut event,
+                1,
+                timeout
+                    .as_ref()
+                    .map_or(std::ptr::null(), |timeout| timeout as *const _),
+            )
+        };
+        if res < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        if res == 0 {
+            return Ok(None);
+        }
+        if event.flags & libc::EV_ERROR != 0 {
+            return Err(std::io::Error::from_raw_os_error(event.data as _));
+        }
+
+        // A single kevent may carry several events, so report them in lifecycle order.
+        let pid = Pid(event.ident as _);
+        if event.fflags & libc::NOTE_FORK != 0 {
+            self.pending.push_back(ProcessEvent::Fork { pid });
+        }
+        if event.fflags & libc::NOTE_EXEC != 0 {
+            self.pending.push_back(ProcessEvent::Exec { pid });
+        }
+        if event.fflags & libc::NOTE_EXIT != 0 {
+            let status = (event.fflags & libc::NOTE_EXITSTATUS != 0).then_some(event.data as _);
+            self.pending.push_back(ProcessEvent::Exit { pid, status });
+        }
+        Ok(self.pending.pop_front())
+    }
+
+    /// A blocking iterator over events. Interrupted waits are retried.
+    pub fn events(&mut self) -> impl Iterator<Item = Result<ProcessEvent, std::io::Error>> + '_ {
+        std::iter::from_fn(move || loop {
+            match self.wait(None) {
+                Ok(Some(event)) => return Some(Ok(event)),
+                Ok(None) => continue,
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(err) => return Some(Err(err)),
+            }
+        })
+    }
+
+    /// Push events into a [`Sink`] until waiting for events fails or the sink returns an error.
+    pub fn forward(&mut self, sink: &mut impl Sink<ProcessEvent>) -> Result<(), std::io::Error> {
+        for event in self.events() {
+            sink.send(event?)?;
+        }
+        Ok(())
+    }
+}
+
+impl AsRawFd for ProcessWatcher {
+    /// The underlying `kqueue`, which becomes readable when events are available.
+    fn as_raw_fd(&self) -> RawFd {
+        self.kq.as_raw_fd()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::darwin::getpid;
+    use crate::testutil::TestChild;
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    #[test]
+    fn test_watch_exit_status() {
+        let mut child = TestChild::spawn(
+            Command::new("/bin/sh")
+                .args(["-c", "read x; exit 3"])
+                .stdin(Stdio::piped()),
+        );
+        let pid = child.pid();
+        let mut watcher = ProcessWatcher::new().unwrap();
+        watcher.watch(pid, ProcessEvents::EXIT_STATUS).unwrap();
+        child.stdin.take().unwrap().write_all(b"\n").unwrap();
+
+        let event = watcher
+            .wait(Some(Duration::from_secs(10)))
+            .unwrap()
+            .unwrap();
+        let ProcessEvent::Exit {
+            pid: event_pid,
+            status: Some(status),
+        } = event
+        else {
+            panic!("unexpected event {event:?}");
+        };
+        assert_eq!(event_pid, pid);
+        assert_eq!(libc::WEXITSTATUS(status), 3);
+        child.wait().unwrap();
+    }
+
+    #[test]
+    fn test_watch_fork() {
+        let mut child = TestChild::spawn(
+            Command::new("/bin/sh")
+                .args(["-c", "read x; /usr/bin/true; exit 0"])
+                .stdin(Stdio::piped()),
+        );
+        let pid = child.pid();
+        let mut watcher = ProcessWatcher::new().unwrap();
+        watcher.watch(pid, ProcessEvents::ALL).unwrap();
+        child.stdin.take().unwrap().write_all(b"\n").unwrap();
+
+        let events = watcher
+            .events()
+            .map(|event| event.unwrap())
+            .take_while(|event| !matches!(event, ProcessEvent::Exit { .. }))
+            .collect::<Vec<_>>();
+        assert!(events.contains(&ProcessEvent::Fork { pid }));
+        child.wait().unwrap();
+    }
+
+    #[test]
+    fn test_watch_missing_process() {
+        let watcher = ProcessWatcher::new().unwrap();
+        let err = watcher
+            .watch(Pid(i32::MAX as _), ProcessEvents::EXIT)
+            .unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::ESRCH));
+    }
+
+    #[test]
+    fn test_wait_timeout() {
+        let mut watcher = ProcessWatcher::new().unwrap();
+        watcher.watch(getpid(), ProcessEvents::ALL).unwrap();
+        let event = watcher.wait(Some(Duration::from_millis(10))).unwrap();
+        assert!(event.is_none());
+    }
+}
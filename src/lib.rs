@@ -1,8 +1,12 @@
 #![cfg_attr(
-    not(target_vendor = "apple"),
-    doc = "NOTE: This library is only supported on macOS, iOS and other Apple platforms."
+    not(any(target_vendor = "apple", target_os = "linux")),
+    doc = "NOTE: This library is only supported on macOS, iOS and other Apple platforms. With the \
+           `stubs` feature, the core API is available, but every query fails."
+)]
+#![cfg_attr(
+    target_os = "linux",
+    doc = "NOTE: On Linux, this library implements a subset of the macOS API using `/proc`."
 )]
-
 #![cfg_attr(target_vendor = "apple", doc = include_str!("../README.md"))]
 
 #[cfg(target_vendor = "apple")]
@@ -10,3 +14,18 @@ pub use darwin::*;
 
 #[cfg(target_vendor = "apple")]
 mod darwin;
+
+#[cfg(any(
+    target_os = "linux",
+    all(feature = "stubs", not(target_vendor = "apple"))
+))]
+pub use portable::*;
+
+#[cfg(any(
+    target_os = "linux",
+    all(feature = "stubs", not(target_vendor = "apple"))
+))]
+mod portable;
+
+#[cfg(all(test, any(target_vendor = "apple", target_os = "linux")))]
+mod testutil;
@@ -0,0 +1,541 @@
+//! The core query API for platforms other than Apple's, where it is implemented by a
+//! backend: [`linux`] reads `/proc`, and [`stubs`] (with the `stubs` feature) fails every
+//! query with [`std::io::ErrorKind::Unsupported`].
+//!
+//! The types mirror the macOS structs, so code that uses them compiles everywhere. Fields
+//! without an equivalent on the current platform are zero.
+
+use std::ffi::c_char;
+use std::path::{Path, PathBuf};
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(not(target_os = "linux"))]
+mod stubs;
+
+#[cfg(target_os = "linux")]
+pub use linux::{proc_listallpids, proc_pidpath};
+#[cfg(not(target_os = "linux"))]
+pub use stubs::{proc_listallpids, proc_pidpath};
+
+/// The length of `comm`, which Linux truncates to 15 bytes.
+const MAXCOMLEN: usize = 16;
+const MAXPATHLEN: usize = 4096;
+
+/// A wrapper around a process ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct Pid(pub u32);
+
+/// Get the current process ID. This is equivalent to `std::process::id()`.
+pub fn getpid() -> Pid {
+    Pid(std::process::id())
+}
+
+#[allow(private_bounds)]
+impl Pid {
+    /// Get any info struct for this process. See [`proc_pidinfo`].
+    pub fn info<T: HasFlavor>(self) -> Result<Option<T>, std::io::Error> {
+        proc_pidinfo(self)
+    }
+
+    /// Get the [`ProcBSDInfo`] for this process.
+    pub fn bsd_info(self) -> Result<Option<ProcBSDInfo>, std::io::Error> {
+        proc_pidinfo(self)
+    }
+
+    /// Get the [`ProcBSDShortInfo`] for this process.
+    pub fn bsd_short_info(self) -> Result<Option<ProcBSDShortInfo>, std::io::Error> {
+        proc_pidinfo(self)
+    }
+
+    /// Get the [`ProcTaskInfo`] for this process.
+    pub fn task_info(self) -> Result<Option<ProcTaskInfo>, std::io::Error> {
+        proc_pidinfo(self)
+    }
+
+    /// List the open file descriptors of this process. See [`proc_pidinfo_list`].
+    pub fn fds(self) -> Result<Vec<ProcFDInfo>, std::io::Error> {
+        proc_pidinfo_list(self)
+    }
+
+    /// Get an info struct for one of this process's file descriptors. See [`proc_pidfdinfo`].
+    pub fn fd_info<T: HasFdFlavor>(self, fd: Fd) -> Result<Option<T>, std::io::Error> {
+        proc_pidfdinfo(self, fd)
+    }
+}
+
+/// A wrapper around a file descriptor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct Fd(pub i32);
+
+/// An error that occurs when an unexpected value is encountered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueError {
+    UnexpectedEnumValue,
+    InvalidString,
+}
+
+/// Convert a C string to a Rust string.
+fn c_str_to_str(array: &[c_char]) -> Result<&str, ValueError> {
+    std::str::from_utf8(c_str_bytes(array)).map_err(|_| ValueError::InvalidString)
+}
+
+/// The bytes of a C string, up to the first NUL or the end of the array.
+fn c_str_bytes(array: &[c_char]) -> &[u8] {
+    let nul_index = array.iter().position(|&c| c == 0).unwrap_or(array.len());
+    // SAFETY: c_char and u8 have the same size and alignment.
+    unsafe { std::slice::from_raw_parts(array.as_ptr() as *const u8, nul_index) }
+}
+
+/// Decoded access to the name carried by a struct as a fixed-size C string. Implemented by
+/// [`ProcBSDInfo`] and [`ProcBSDShortInfo`].
+pub trait ProcNameExt {
+    /// The raw name: a NUL-terminated C string, or the whole array if the name fills it.
+    fn raw_name(&self) -> &[c_char];
+
+    /// The name, failing if it isn't valid UTF-8.
+    fn name(&self) -> Result<&str, ValueError> {
+        c_str_to_str(self.raw_name())
+    }
+
+    /// An owned copy of [`ProcNameExt::name`].
+    fn name_string(&self) -> Result<String, ValueError> {
+        self.name().map(str::to_owned)
+    }
+
+    /// The name, with invalid UTF-8 replaced.
+    fn name_lossy(&self) -> String {
+        String::from_utf8_lossy(c_str_bytes(self.raw_name())).into_owned()
+    }
+}
+
+/// Copy bytes into a C string array, truncating to leave room for the NUL.
+#[cfg(target_os = "linux")]
+fn to_c_str<const N: usize>(bytes: &[u8]) -> [c_char; N] {
+    let mut array = [0; N];
+    for (dst, &src) in array.iter_mut().zip(&bytes[..bytes.len().min(N - 1)]) {
+        *dst = src as c_char;
+    }
+    array
+}
+
+/// A trait for types that have a flavor.
+trait HasFlavor: Sized {
+    fn read(pid: Pid) -> Result<Self, std::io::Error>;
+}
+
+trait HasFlavorList: Sized {
+    fn read_list(pid: Pid) -> Result<Vec<Self>, std::io::Error>;
+}
+
+/// For `proc_pidfdinfo`.
+trait HasFdFlavor: Sized {
+    fn read_fd(pid: Pid, fd: Fd) -> Result<Option<Self>, std::io::Error>;
+}
+
+/// The scheduling state of a process, from `pbi_status` or `pbsi_status`.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum ProcStatus {
+    /// Being created by `fork`.
+    SIDL = 1,
+    /// Runnable.
+    SRUN = 2,
+    /// Sleeping, including uninterruptible sleep and idle kernel threads.
+    SSLEEP = 3,
+    /// Stopped, eg: by `SIGSTOP` or a debugger.
+    SSTOP = 4,
+    /// Exited, but not yet reaped by its parent.
+    SZOMB = 5,
+}
+
+impl ProcStatus {
+    fn from_raw(status: u32) -> Result<Self, ValueError> {
+        match status {
+            1 => Ok(ProcStatus::SIDL),
+            2 => Ok(ProcStatus::SRUN),
+            3 => Ok(ProcStatus::SSLEEP),
+            4 => Ok(ProcStatus::SSTOP),
+            5 => Ok(ProcStatus::SZOMB),
+            _ => Err(ValueError::UnexpectedEnumValue),
+        }
+    }
+}
+
+/// Basic information about a process.
+#[derive(Debug, Clone, Copy)]
+pub struct ProcBSDInfo {
+    /// Always 0 on Linux.
+    pub pbi_flags: u32,
+    pub pbi_status: u32,
+    /// Always 0 on Linux.
+    pub pbi_xstatus: u32,
+    pub pbi_pid: Pid,
+    pub pbi_ppid: Pid,
+    pub pbi_uid: u32,
+    pub pbi_gid: u32,
+    pub pbi_ruid: u32,
+    pub pbi_rgid: u32,
+    pub pbi_svuid: u32,
+    pub pbi_svgid: u32,
+    pub rfu_1: u32,
+    pub pbi_comm: [c_char; MAXCOMLEN],
+    /// The same as `pbi_comm`, as Linux has no longer name.
+    pub pbi_name: [c_char; 2 * MAXCOMLEN],
+    /// The number of open file descriptors, or 0 if they can't be listed.
+    pub pbi_nfiles: u32,
+    pub pbi_pgid: u32,
+    /// Always 0 on Linux.
+    pub pbi_pjobc: u32,
+    pub e_tdev: u32,
+    pub e_tpgid: u32,
+    pub pbi_nice: i32,
+    pub pbi_start_tvsec: u64,
+    pub pbi_start_tvusec: u64,
+}
+
+impl ProcBSDInfo {
+    pub fn status(&self) -> Result<ProcStatus, ValueError> {
+        ProcStatus::from_raw(self.pbi_status)
+    }
+
+    pub fn comm(&self) -> Result<&str, ValueError> {
+        c_str_to_str(&self.pbi_comm)
+    }
+
+    /// The process name, which is the same as [`ProcBSDInfo::comm`] on Linux.
+    pub fn name(&self) -> Result<&str, ValueError> {
+        c_str_to_str(self.raw_name())
+    }
+}
+
+impl ProcNameExt for ProcBSDInfo {
+    fn raw_name(&self) -> &[c_char] {
+        if self.pbi_name[0] == 0 {
+            &self.pbi_comm
+        } else {
+            &self.pbi_name
+        }
+    }
+}
+
+/// A subset of [`ProcBSDInfo`].
+#[derive(Debug, Clone, Copy)]
+pub struct ProcBSDShortInfo {
+    pub pbsi_pid: Pid,
+    pub pbsi_ppid: Pid,
+    pub pbsi_pgid: u32,
+    pub pbsi_status: u32,
+    pub pbsi_comm: [c_char; MAXCOMLEN],
+    /// Always 0 on Linux.
+    pub pbsi_flags: u32,
+    pub pbsi_uid: u32,
+    pub pbsi_gid: u32,
+    pub pbsi_ruid: u32,
+    pub pbsi_rgid: u32,
+    pub pbsi_svuid: u32,
+    pub pbsi_svgid: u32,
+    pub pbsi_rfu: u32,
+}
+
+impl ProcBSDShortInfo {
+    pub fn comm(&self) -> Result<&str, ValueError> {
+        c_str_to_str(&self.pbsi_comm)
+    }
+
+    pub fn status(&self) -> Result<ProcStatus, ValueError> {
+        ProcStatus::from_raw(self.pbsi_status)
+    }
+}
+
+impl ProcNameExt for ProcBSDShortInfo {
+    fn raw_name(&self) -> &[c_char] {
+        &self.pbsi_comm
+    }
+}
+
+/// Task information about a process. Times are in nanoseconds, and fields without a Linux
+/// equivalent are 0.
+#[derive(Debug, Clone, Copy)]
+pub struct ProcTaskInfo {
+    pub pti_virtual_size: u64,
+    pub pti_resident_size: u64,
+    pub pti_total_user: u64,
+    pub pti_total_system: u64,
+    pub pti_threads_user: u64,
+    pub pti_threads_system: u64,
+    pub pti_policy: i32,
+    /// Minor and major page faults.
+    pub pti_faults: i32,
+    /// Major page faults.
+    pub pti_pageins: i32,
+    pub pti_cow_faults: i32,
+    pub pti_messages_sent: i32,
+    pub pti_messages_received: i32,
+    pub pti_syscalls_mach: i32,
+    pub pti_syscalls_unix: i32,
+    /// Voluntary and involuntary context switches.
+    pub pti_csw: i32,
+    pub pti_threadnum: i32,
+    /// Always 0 on Linux, which doesn't report running threads per process.
+    pub pti_numrunning: i32,
+    pub pti_priority: i32,
+}
+
+impl ProcTaskInfo {
+    /// CPU time spent in user mode by every thread, including threads that have exited.
+    pub fn total_user(&self) -> std::time::Duration {
+        std::time::Duration::from_nanos(self.pti_total_user)
+    }
+
+    /// CPU time spent in the kernel by every thread, including threads that have exited.
+    pub fn total_system(&self) -> std::time::Duration {
+        std::time::Duration::from_nanos(self.pti_total_system)
+    }
+
+    /// CPU time spent in user mode by the threads that are still running.
+    pub fn threads_user(&self) -> std::time::Duration {
+        std::time::Duration::from_nanos(self.pti_threads_user)
+    }
+
+    /// CPU time spent in the kernel by the threads that are still running.
+    pub fn threads_system(&self) -> std::time::Duration {
+        std::time::Duration::from_nanos(self.pti_threads_system)
+    }
+}
+
+/// Get an info struct for a given process.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let info = proc_pidinfo::<ProcBSDShortInfo>(getpid()).unwrap().unwrap();
+/// assert_eq!(info.pbsi_pid, getpid());
+/// ```
+#[allow(private_bounds)]
+pub fn proc_pidinfo<T: HasFlavor>(pid: Pid) -> Result<Option<T>, std::io::Error> {
+    T::read(pid).map(Some)
+}
+
+/// Get an info struct for the current process.
+#[allow(private_bounds)]
+pub fn proc_pidinfo_self<T: HasFlavor>() -> Result<Option<T>, std::io::Error> {
+    proc_pidinfo(getpid())
+}
+
+/// The type of a file descriptor, using the macOS names. Linux descriptors are mapped to the
+/// closest type: `epoll` descriptors are [`ProcFDType::KQUEUE`], and `inotify` and
+/// `fanotify` descriptors are [`ProcFDType::FSEVENTS`].
+#[allow(non_camel_case_types)]
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProcFDType {
+    ATALK,
+    VNODE,
+    SOCKET,
+    PSHM,
+    PSEM,
+    KQUEUE,
+    PIPE,
+    FSEVENTS,
+    NETPOLICY,
+    CHANNEL,
+    NEXUS,
+    /// A type this crate doesn't know about yet, with its raw value.
+    Unknown(u32),
+}
+
+impl ProcFDType {
+    fn from_raw(value: u32) -> Self {
+        match value {
+            0 => ProcFDType::ATALK,
+            1 => ProcFDType::VNODE,
+            2 => ProcFDType::SOCKET,
+            3 => ProcFDType::PSHM,
+            4 => ProcFDType::PSEM,
+            5 => ProcFDType::KQUEUE,
+            6 => ProcFDType::PIPE,
+            7 => ProcFDType::FSEVENTS,
+            9 => ProcFDType::NETPOLICY,
+            10 => ProcFDType::CHANNEL,
+            11 => ProcFDType::NEXUS,
+            value => ProcFDType::Unknown(value),
+        }
+    }
+
+    /// The kernel's `PROX_FDTYPE_*` value.
+    pub fn as_raw(self) -> u32 {
+        match self {
+            ProcFDType::ATALK => 0,
+            ProcFDType::VNODE => 1,
+            ProcFDType::SOCKET => 2,
+            ProcFDType::PSHM => 3,
+            ProcFDType::PSEM => 4,
+            ProcFDType::KQUEUE => 5,
+            ProcFDType::PIPE => 6,
+            ProcFDType::FSEVENTS => 7,
+            ProcFDType::NETPOLICY => 9,
+            ProcFDType::CHANNEL => 10,
+            ProcFDType::NEXUS => 11,
+            ProcFDType::Unknown(value) => value,
+        }
+    }
+}
+
+/// Information about file descriptors. Usable with [`proc_pidinfo_list`].
+#[derive(Debug, Clone, Copy)]
+pub struct ProcFDInfo {
+    pub proc_fd: Fd,
+    /// The [`ProcFDType`], or `u32::MAX` for descriptors with no macOS equivalent, such as
+    /// `eventfd`.
+    pub proc_fdtype: u32,
+}
+
+impl ProcFDInfo {
+    pub fn fd_type(&self) -> ProcFDType {
+        ProcFDType::from_raw(self.proc_fdtype)
+    }
+}
+
+/// List info structs for a given process.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// for fd in proc_pidinfo_list::<ProcFDInfo>(getpid()).unwrap() {
+///     println!("{:?} {:?}", fd.proc_fd, fd.fd_type());
+/// }
+/// ```
+#[allow(private_bounds)]
+pub fn proc_pidinfo_list<T: HasFlavorList>(pid: Pid) -> Result<Vec<T>, std::io::Error> {
+    T::read_list(pid)
+}
+
+/// List info structs for the current process.
+#[allow(private_bounds)]
+pub fn proc_pidinfo_list_self<T: HasFlavorList>() -> Result<Vec<T>, std::io::Error> {
+    proc_pidinfo_list(getpid())
+}
+
+/// Information about an open file, from `/proc/<pid>/fdinfo`.
+#[derive(Debug, Clone, Copy)]
+pub struct ProcFileInfo {
+    /// The `O_*` flags the file was opened with.
+    pub fi_openflags: u32,
+    pub fi_status: u32,
+    pub fi_offset: i64,
+    pub fi_type: i32,
+    pub fi_guardflags: u32,
+}
+
+/// The `stat` of a file, as for [`std::fs::Metadata`].
+#[derive(Debug, Clone, Copy)]
+pub struct VInfoStat {
+    pub vst_dev: u32,
+    pub vst_mode: u16,
+    pub vst_nlink: u16,
+    pub vst_ino: u64,
+    pub vst_uid: u32,
+    pub vst_gid: u32,
+    pub vst_atime: i64,
+    pub vst_atimensec: i64,
+    pub vst_mtime: i64,
+    pub vst_mtimensec: i64,
+    pub vst_ctime: i64,
+    pub vst_ctimensec: i64,
+    /// Always 0 on Linux.
+    pub vst_birthtime: i64,
+    /// Always 0 on Linux.
+    pub vst_birthtimensec: i64,
+    pub vst_size: i64,
+    pub vst_blocks: i64,
+    pub vst_blksize: i32,
+    pub vst_flags: u32,
+    pub vst_gen: u32,
+    pub vst_rdev: u32,
+    pub vst_qspare: [i64; 2],
+}
+
+/// General information about a vnode.
+#[derive(Debug, Clone, Copy)]
+pub struct VnodeInfo {
+    pub vi_stat: VInfoStat,
+    pub vi_type: i32,
+    pub vi_pad: i32,
+    pub vi_fsid: [i32; 2],
+}
+
+/// Path information about a vnode.
+#[derive(Debug, Clone)]
+pub struct VnodeInfoPath {
+    pub vip_vi: VnodeInfo,
+    pub vip_path: [c_char; MAXPATHLEN],
+}
+
+impl VnodeInfoPath {
+    pub fn path(&self) -> Result<&Path, ValueError> {
+        let bytes = c_str_bytes(&self.vip_path);
+        #[cfg(unix)]
+        let path = Path::new(<std::ffi::OsStr as std::os::unix::ffi::OsStrExt>::from_bytes(bytes));
+        #[cfg(not(unix))]
+        let path = Path::new(std::str::from_utf8(bytes).map_err(|_| ValueError::InvalidString)?);
+        Ok(path)
+    }
+
+    /// An owned copy of [`VnodeInfoPath::path`].
+    pub fn to_path_buf(&self) -> Result<PathBuf, ValueError> {
+        self.path().map(Path::to_path_buf)
+    }
+}
+
+/// Information about [`ProcFDType::VNODE`] file descriptors.
+#[derive(Debug, Clone, Copy)]
+pub struct VnodeFdInfo {
+    pub pfi: ProcFileInfo,
+    pub pvi: VnodeInfo,
+}
+
+/// Information about [`ProcFDType::VNODE`] file descriptors, including the path.
+#[derive(Debug, Clone)]
+pub struct VnodeFdInfoWithPath {
+    pub pfi: ProcFileInfo,
+    pub pvip: VnodeInfoPath,
+}
+
+impl VnodeFdInfoWithPath {
+    pub fn path(&self) -> Result<&Path, ValueError> {
+        self.pvip.path()
+    }
+
+    /// An owned copy of [`VnodeFdInfoWithPath::path`].
+    pub fn to_path_buf(&self) -> Result<PathBuf, ValueError> {
+        self.pvip.to_path_buf()
+    }
+}
+
+/// Get an info struct for a given process and file descriptor. Returns `None` if the
+/// descriptor isn't of the requested type, and fails with `EBADF` if it isn't open.
+///
+/// ```
+/// use proc_pidinfo::*;
+/// use std::os::fd::AsRawFd;
+///
+/// let file = std::fs::File::open("/dev/null").unwrap();
+/// let info = proc_pidfdinfo::<VnodeFdInfoWithPath>(getpid(), Fd(file.as_raw_fd()))
+///     .unwrap()
+///     .unwrap();
+/// assert_eq!(info.path().unwrap(), std::path::Path::new("/dev/null"));
+/// ```
+#[allow(private_bounds)]
+pub fn proc_pidfdinfo<T: HasFdFlavor>(pid: Pid, fd: Fd) -> Result<Option<T>, std::io::Error> {
+    T::read_fd(pid, fd)
+}
+
+/// Get an info struct for one of the current process's file descriptors.
+#[allow(private_bounds)]
+pub fn proc_pidfdinfo_self<T: HasFdFlavor>(fd: Fd) -> Result<Option<T>, std::io::Error> {
+    proc_pidfdinfo(getpid(), fd)
+}
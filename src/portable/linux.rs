@@ -0,0 +1,513 @@
+//! Implements the portable API by reading `/proc`.
+//!
+//! Times in [`ProcTaskInfo`] are nanoseconds, rather than Mach absolute time units.
+
+use std::ffi::OsString;
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+use super::{
+    to_c_str, Fd, HasFdFlavor, HasFlavor, HasFlavorList, Pid, ProcBSDInfo, ProcBSDShortInfo,
+    ProcFDInfo, ProcFDType, ProcFileInfo, ProcStatus, ProcTaskInfo, VInfoStat, VnodeFdInfo,
+    VnodeFdInfoWithPath, VnodeInfo, VnodeInfoPath,
+};
+
+const ESRCH: i32 = 3;
+const EBADF: i32 = 9;
+
+/// The kernel's `USER_HZ`, the unit of times in `/proc/<pid>/stat`. It is 100 on every
+/// mainstream architecture.
+const USER_HZ: u64 = 100;
+
+impl ProcStatus {
+    /// Map a state letter from `/proc/<pid>/stat`.
+    fn from_state(state: &str) -> Option<Self> {
+        match state {
+            "R" => Some(ProcStatus::SRUN),
+            "S" | "D" | "I" | "W" | "P" => Some(ProcStatus::SSLEEP),
+            "T" | "t" => Some(ProcStatus::SSTOP),
+            "Z" | "X" | "x" => Some(ProcStatus::SZOMB),
+            _ => None,
+        }
+    }
+}
+
+/// The fields of `/proc/<pid>/stat` after the command name.
+struct Stat {
+    comm: Vec<u8>,
+    fields: Vec<u64>,
+    status: u32,
+}
+
+impl Stat {
+    // Indices into `fields`, which starts at `ppid`.
+    const PPID: usize = 0;
+    const PGRP: usize = 1;
+    const TTY_NR: usize = 3;
+    const TPGID: usize = 4;
+    const MINFLT: usize = 6;
+    const MAJFLT: usize = 8;
+    const UTIME: usize = 10;
+    const STIME: usize = 11;
+    const PRIORITY: usize = 14;
+    const NICE: usize = 15;
+    const NUM_THREADS: usize = 16;
+    const STARTTIME: usize = 18;
+    const VSIZE: usize = 19;
+
+    fn read(pid: Pid) -> Result<Self, std::io::Error> {
+        let stat = read_proc(pid, "stat")?;
+        // The command name is in parentheses, and may itself contain spaces or parentheses.
+        let (Some(open), Some(close)) = (
+            stat.iter().position(|&b| b == b'('),
+            stat.iter().rposition(|&b| b == b')'),
+        ) else {
+            return Err(malformed("stat"));
+        };
+        let rest = std::str::from_utf8(&stat[close + 1..]).map_err(|_| malformed("stat"))?;
+        let mut rest = rest.split_whitespace();
+        let state = rest.next().ok_or_else(|| malformed("stat"))?;
+        // Some fields are signed, and some (eg: `rsslim`) use the full unsigned range.
+        let fields = rest
+            .map(|field| {
+                field
+                    .parse::<u64>()
+                    .or_else(|_| field.parse::<i64>().map(|n| n as u64))
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| malformed("stat"))?;
+        if fields.len() <= Self::VSIZE {
+            return Err(malformed("stat"));
+        }
+        Ok(Self {
+            comm: stat[open + 1..close].to_vec(),
+            fields,
+            status: ProcStatus::from_state(state).map_or(0, |status| status as u32),
+        })
+    }
+
+    fn get(&self, index: usize) -> u64 {
+        self.fields[index]
+    }
+
+    fn ticks_to_nanos(ticks: u64) -> u64 {
+        ticks.saturating_mul(1_000_000_000 / USER_HZ)
+    }
+}
+
+/// The `Uid:` and `Gid:` lines of `/proc/<pid>/status`: real, effective, saved and filesystem
+/// ids.
+struct Ids {
+    uid: [u32; 4],
+    gid: [u32; 4],
+}
+
+impl Ids {
+    fn read(pid: Pid) -> Result<Self, std::io::Error> {
+        let status = read_proc(pid, "status")?;
+        let status = String::from_utf8_lossy(&status);
+        let ids = |key: &str| -> Result<[u32; 4], std::io::Error> {
+            let line = status_value(&status, key).ok_or_else(|| malformed("status"))?;
+            let mut ids = [0; 4];
+            for (id, value) in ids.iter_mut().zip(line.split_whitespace()) {
+                *id = value.parse().map_err(|_| malformed("status"))?;
+            }
+            Ok(ids)
+        };
+        Ok(Self {
+            uid: ids("Uid")?,
+            gid: ids("Gid")?,
+        })
+    }
+}
+
+/// Find `key:` in `/proc/<pid>/status` and return the rest of the line.
+fn status_value<'a>(status: &'a str, key: &str) -> Option<&'a str> {
+    status.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        (name == key).then(|| value.trim())
+    })
+}
+
+/// Read `/proc/<pid>/<name>`, reporting a missing process as `ESRCH` like libproc does.
+fn read_proc(pid: Pid, name: &str) -> Result<Vec<u8>, std::io::Error> {
+    std::fs::read(format!("/proc/{}/{name}", pid.0)).map_err(not_found_to_esrch)
+}
+
+fn not_found_to_esrch(err: std::io::Error) -> std::io::Error {
+    if err.kind() == std::io::ErrorKind::NotFound {
+        std::io::Error::from_raw_os_error(ESRCH)
+    } else {
+        err
+    }
+}
+
+fn malformed(name: &str) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!("Malformed /proc/<pid>/{name}"),
+    )
+}
+
+impl HasFlavor for ProcBSDInfo {
+    fn read(pid: Pid) -> Result<Self, std::io::Error> {
+        let stat = Stat::read(pid)?;
+        let ids = Ids::read(pid)?;
+        let nfiles = std::fs::read_dir(format!("/proc/{}/fd", pid.0))
+            .map_or(0, |entries| entries.count() as u32);
+        let start = boot_time()?.saturating_mul(1_000_000)
+            + Stat::ticks_to_nanos(stat.get(Stat::STARTTIME)) / 1000;
+        Ok(Self {
+            pbi_flags: 0,
+            pbi_status: stat.status,
+            pbi_xstatus: 0,
+            pbi_pid: pid,
+            pbi_ppid: Pid(stat.get(Stat::PPID) as u32),
+            pbi_uid: ids.uid[1],
+            pbi_gid: ids.gid[1],
+            pbi_ruid: ids.uid[0],
+            pbi_rgid: ids.gid[0],
+            pbi_svuid: ids.uid[2],
+            pbi_svgid: ids.gid[2],
+            rfu_1: 0,
+            pbi_comm: to_c_str(&stat.comm),
+            pbi_name: to_c_str(&stat.comm),
+            pbi_nfiles: nfiles,
+            pbi_pgid: stat.get(Stat::PGRP) as u32,
+            pbi_pjobc: 0,
+            e_tdev: stat.get(Stat::TTY_NR) as u32,
+            e_tpgid: stat.get(Stat::TPGID) as u32,
+            pbi_nice: stat.get(Stat::NICE) as i64 as i32,
+            pbi_start_tvsec: start / 1_000_000,
+            pbi_start_tvusec: start % 1_000_000,
+        })
+    }
+}
+
+/// The boot time in seconds since the epoch, from `/proc/stat`.
+fn boot_time() -> Result<u64, std::io::Error> {
+    let stat = std::fs::read_to_string("/proc/stat")?;
+    stat.lines()
+        .find_map(|line| line.strip_prefix("btime ")?.trim().parse().ok())
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, "Malformed /proc/stat"))
+}
+
+impl HasFlavor for ProcBSDShortInfo {
+    fn read(pid: Pid) -> Result<Self, std::io::Error> {
+        let stat = Stat::read(pid)?;
+        let ids = Ids::read(pid)?;
+        Ok(Self {
+            pbsi_pid: pid,
+            pbsi_ppid: Pid(stat.get(Stat::PPID) as u32),
+            pbsi_pgid: stat.get(Stat::PGRP) as u32,
+            pbsi_status: stat.status,
+            pbsi_comm: to_c_str(&stat.comm),
+            pbsi_flags: 0,
+            pbsi_uid: ids.uid[1],
+            pbsi_gid: ids.gid[1],
+            pbsi_ruid: ids.uid[0],
+            pbsi_rgid: ids.gid[0],
+            pbsi_svuid: ids.uid[2],
+            pbsi_svgid: ids.gid[2],
+            pbsi_rfu: 0,
+        })
+    }
+}
+
+impl HasFlavor for ProcTaskInfo {
+    fn read(pid: Pid) -> Result<Self, std::io::Error> {
+        let stat = Stat::read(pid)?;
+        let status = read_proc(pid, "status")?;
+        let status = String::from_utf8_lossy(&status);
+        let number = |key: &str| -> u64 {
+            status_value(&status, key)
+                .and_then(|value| value.split_whitespace().next()?.parse().ok())
+                .unwrap_or(0)
+        };
+        let user = Stat::ticks_to_nanos(stat.get(Stat::UTIME));
+        let system = Stat::ticks_to_nanos(stat.get(Stat::STIME));
+        Ok(Self {
+            pti_virtual_size: stat.get(Stat::VSIZE),
+            pti_resident_size: number("VmRSS") * 1024,
+            pti_total_user: user,
+            pti_total_system: system,
+            pti_threads_user: user,
+            pti_threads_system: system,
+            pti_policy: 0,
+            pti_faults: (stat.get(Stat::MINFLT) + stat.get(Stat::MAJFLT)) as i32,
+            pti_pageins: stat.get(Stat::MAJFLT) as i32,
+            pti_cow_faults: 0,
+            pti_messages_sent: 0,
+            pti_messages_received: 0,
+            pti_syscalls_mach: 0,
+            pti_syscalls_unix: 0,
+            pti_csw: (number("voluntary_ctxt_switches") + number("nonvoluntary_ctxt_switches"))
+                as i32,
+            pti_threadnum: stat.get(Stat::NUM_THREADS) as i32,
+            pti_numrunning: 0,
+            pti_priority: stat.get(Stat::PRIORITY) as i64 as i32,
+        })
+    }
+}
+
+/// Classify a descriptor from the target of its `/proc/<pid>/fd` link.
+fn fd_type_of_link(link: &Path) -> u32 {
+    let link = link.as_os_str().as_bytes();
+    let fd_type = if link.starts_with(b"/") {
+        ProcFDType::VNODE
+    } else if link.starts_with(b"socket:") {
+        ProcFDType::SOCKET
+    } else if link.starts_with(b"pipe:") {
+        ProcFDType::PIPE
+    } else if link == b"anon_inode:[eventpoll]" {
+        ProcFDType::KQUEUE
+    } else if link == b"anon_inode:inotify" || link == b"anon_inode:[fanotify]" {
+        ProcFDType::FSEVENTS
+    } else {
+        return u32::MAX;
+    };
+    fd_type.as_raw()
+}
+
+impl HasFlavorList for ProcFDInfo {
+    fn read_list(pid: Pid) -> Result<Vec<Self>, std::io::Error> {
+        let dir = format!("/proc/{}/fd", pid.0);
+        let mut fds = vec![];
+        for entry in std::fs::read_dir(&dir).map_err(not_found_to_esrch)? {
+            let entry = entry?;
+            let Some(fd) = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.parse().ok())
+            else {
+                continue;
+            };
+            // Skip descriptors that are closed mid-scan.
+            let Ok(link) = std::fs::read_link(entry.path()) else {
+                continue;
+            };
+            fds.push(ProcFDInfo {
+                proc_fd: Fd(fd),
+                proc_fdtype: fd_type_of_link(&link),
+            });
+        }
+        fds.sort_by_key(|fd| fd.proc_fd);
+        Ok(fds)
+    }
+}
+
+impl ProcFileInfo {
+    fn read(pid: Pid, fd: Fd) -> Result<Self, std::io::Error> {
+        let fdinfo = read_proc(pid, &format!("fdinfo/{}", fd.0))?;
+        let fdinfo = String::from_utf8_lossy(&fdinfo);
+        let offset = status_value(&fdinfo, "pos").and_then(|pos| pos.parse().ok());
+        let flags =
+            status_value(&fdinfo, "flags").and_then(|flags| u32::from_str_radix(flags, 8).ok());
+        Ok(Self {
+            fi_openflags: flags.unwrap_or(0),
+            fi_status: 0,
+            fi_offset: offset.unwrap_or(0),
+            fi_type: ProcFDType::VNODE.as_raw() as i32,
+            fi_guardflags: 0,
+        })
+    }
+}
+
+impl From<&std::fs::Metadata> for VInfoStat {
+    fn from(metadata: &std::fs::Metadata) -> Self {
+        Self {
+            vst_dev: metadata.dev() as u32,
+            vst_mode: metadata.mode() as u16,
+            vst_nlink: metadata.nlink() as u16,
+            vst_ino: metadata.ino(),
+            vst_uid: metadata.uid(),
+            vst_gid: metadata.gid(),
+            vst_atime: metadata.atime(),
+            vst_atimensec: metadata.atime_nsec(),
+            vst_mtime: metadata.mtime(),
+            vst_mtimensec: metadata.mtime_nsec(),
+            vst_ctime: metadata.ctime(),
+            vst_ctimensec: metadata.ctime_nsec(),
+            vst_birthtime: 0,
+            vst_birthtimensec: 0,
+            vst_size: metadata.size() as i64,
+            vst_blocks: metadata.blocks() as i64,
+            vst_blksize: metadata.blksize() as i32,
+            vst_flags: 0,
+            vst_gen: 0,
+            vst_rdev: metadata.rdev() as u32,
+            vst_qspare: [0; 2],
+        }
+    }
+}
+
+/// Read a vnode descriptor, or `None` if the descriptor isn't a file.
+fn read_vnode(
+    pid: Pid,
+    fd: Fd,
+) -> Result<Option<(ProcFileInfo, VnodeInfo, PathBuf)>, std::io::Error> {
+    let link = format!("/proc/{}/fd/{}", pid.0, fd.0);
+    let path = match std::fs::read_link(&link) {
+        Ok(path) => path,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            // Distinguish a closed descriptor from an exited process.
+            read_proc(pid, "stat")?;
+            return Err(std::io::Error::from_raw_os_error(EBADF));
+        }
+        Err(err) => return Err(err),
+    };
+    if fd_type_of_link(&path) != ProcFDType::VNODE.as_raw() {
+        return Ok(None);
+    }
+    // The link itself resolves to the open file, even if it has been deleted.
+    let metadata = std::fs::metadata(&link)?;
+    let vnode = VnodeInfo {
+        vi_stat: VInfoStat::from(&metadata),
+        vi_type: 0,
+        vi_pad: 0,
+        vi_fsid: [metadata.dev() as i32, 0],
+    };
+    Ok(Some((ProcFileInfo::read(pid, fd)?, vnode, path)))
+}
+
+impl HasFdFlavor for VnodeFdInfo {
+    fn read_fd(pid: Pid, fd: Fd) -> Result<Option<Self>, std::io::Error> {
+        Ok(read_vnode(pid, fd)?.map(|(pfi, pvi, _)| Self { pfi, pvi }))
+    }
+}
+
+impl HasFdFlavor for VnodeFdInfoWithPath {
+    fn read_fd(pid: Pid, fd: Fd) -> Result<Option<Self>, std::io::Error> {
+        Ok(read_vnode(pid, fd)?.map(|(pfi, vip_vi, path)| Self {
+            pfi,
+            pvip: VnodeInfoPath {
+                vip_vi,
+                vip_path: to_c_str(path.as_os_str().as_bytes()),
+            },
+        }))
+    }
+}
+
+/// List the ids of all processes on the system.
+pub fn proc_listallpids() -> Result<Vec<Pid>, std::io::Error> {
+    let mut pids = std::fs::read_dir("/proc")?
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+        .map(Pid)
+        .collect::<Vec<_>>();
+    pids.sort();
+    Ok(pids)
+}
+
+/// Get the path of the executable of a given process.
+///
+/// ```
+/// use proc_pidinfo::*;
+///
+/// let path = proc_pidpath(getpid()).unwrap();
+/// assert_eq!(path, std::env::current_exe().unwrap().canonicalize().unwrap());
+/// ```
+pub fn proc_pidpath(pid: Pid) -> Result<PathBuf, std::io::Error> {
+    std::fs::read_link(format!("/proc/{}/exe", pid.0))
+        .map(|path| PathBuf::from(OsString::from_vec(path.into_os_string().into_vec())))
+        .map_err(not_found_to_esrch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::portable::{
+        getpid, proc_pidfdinfo_self, proc_pidinfo, proc_pidinfo_list, ProcNameExt, ProcStatus,
+    };
+    use crate::testutil::TestChild;
+    use std::io::{Seek, SeekFrom, Write};
+    use std::os::fd::AsRawFd;
+
+    #[test]
+    fn test_bsd_info_self() {
+        let pid = getpid();
+        let info = proc_pidinfo::<ProcBSDInfo>(pid).unwrap().unwrap();
+        assert_eq!(info.pbi_pid, pid);
+        // The state is that of the main thread, which is waiting for the tests to finish.
+        assert!(matches!(
+            info.status(),
+            Ok(ProcStatus::SRUN | ProcStatus::SSLEEP)
+        ));
+        assert!(info.pbi_nfiles >= 3);
+        let started = std::time::UNIX_EPOCH + std::time::Duration::from_secs(info.pbi_start_tvsec);
+        assert!(started.elapsed().unwrap().as_secs() < 24 * 60 * 60 * 365);
+
+        let short = proc_pidinfo::<ProcBSDShortInfo>(pid).unwrap().unwrap();
+        assert_eq!(short.pbsi_ppid, info.pbi_ppid);
+        assert_eq!(short.comm(), info.comm());
+        assert!(!short.comm().unwrap().is_empty());
+        assert_eq!(info.name(), info.comm());
+        assert_eq!(short.name_lossy(), info.name_string().unwrap());
+    }
+
+    #[test]
+    fn test_task_info_self() {
+        let info = getpid().task_info().unwrap().unwrap();
+        assert!(info.pti_resident_size > 0);
+        assert!(info.pti_virtual_size >= info.pti_resident_size);
+        assert!(info.pti_threadnum >= 1);
+        assert_eq!(info.total_user().as_nanos(), info.pti_total_user as u128);
+    }
+
+    #[test]
+    fn test_fds_self() {
+        let mut file = std::fs::File::create(
+            std::env::temp_dir().join(format!("proc-pidinfo-linux-{}", std::process::id())),
+        )
+        .unwrap();
+        file.write_all(b"hello world").unwrap();
+        file.seek(SeekFrom::Start(5)).unwrap();
+        let (read, _write) = std::io::pipe().unwrap();
+        let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+
+        let fds = getpid().fds().unwrap();
+        let fd_type = |fd: i32| {
+            fds.iter()
+                .find(|info| info.proc_fd == Fd(fd))
+                .unwrap()
+                .fd_type()
+        };
+        assert_eq!(fd_type(file.as_raw_fd()), ProcFDType::VNODE);
+        assert_eq!(fd_type(read.as_raw_fd()), ProcFDType::PIPE);
+        assert_eq!(fd_type(socket.as_raw_fd()), ProcFDType::SOCKET);
+
+        let info = proc_pidfdinfo_self::<VnodeFdInfoWithPath>(Fd(file.as_raw_fd()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(info.pfi.fi_offset, 5);
+        assert_eq!(info.pvip.vip_vi.vi_stat.vst_size, 11);
+        assert!(info
+            .path()
+            .unwrap()
+            .ends_with(format!("proc-pidinfo-linux-{}", std::process::id())));
+        std::fs::remove_file(info.path().unwrap()).unwrap();
+
+        let pipe = proc_pidfdinfo_self::<VnodeFdInfo>(Fd(read.as_raw_fd())).unwrap();
+        assert!(pipe.is_none());
+    }
+
+    #[test]
+    fn test_missing_process() {
+        let mut child = TestChild::sleep();
+        let pid = child.pid();
+        assert!(proc_listallpids().unwrap().contains(&pid));
+        assert!(proc_pidpath(pid).unwrap().ends_with("sleep"));
+        child.kill().unwrap();
+        child.wait().unwrap();
+        let err = proc_pidinfo::<ProcBSDShortInfo>(pid).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(ESRCH));
+        let err = proc_pidinfo_list::<ProcFDInfo>(pid).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(ESRCH));
+    }
+
+    #[test]
+    fn test_closed_fd() {
+        let err = proc_pidfdinfo_self::<VnodeFdInfo>(Fd(i32::MAX)).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(EBADF));
+    }
+}
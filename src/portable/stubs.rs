@@ -0,0 +1,77 @@
+//! Implements the portable API on platforms without a backend, failing every query with
+//! [`std::io::ErrorKind::Unsupported`].
+
+use std::path::PathBuf;
+
+use super::{
+    Fd, HasFdFlavor, HasFlavor, HasFlavorList, Pid, ProcBSDInfo, ProcBSDShortInfo, ProcFDInfo,
+    ProcTaskInfo, VnodeFdInfo, VnodeFdInfoWithPath,
+};
+
+fn unsupported() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "process information is not supported on this platform",
+    )
+}
+
+impl HasFlavor for ProcBSDInfo {
+    fn read(_pid: Pid) -> Result<Self, std::io::Error> {
+        Err(unsupported())
+    }
+}
+
+impl HasFlavor for ProcBSDShortInfo {
+    fn read(_pid: Pid) -> Result<Self, std::io::Error> {
+        Err(unsupported())
+    }
+}
+
+impl HasFlavor for ProcTaskInfo {
+    fn read(_pid: Pid) -> Result<Self, std::io::Error> {
+        Err(unsupported())
+    }
+}
+
+impl HasFlavorList for ProcFDInfo {
+    fn read_list(_pid: Pid) -> Result<Vec<Self>, std::io::Error> {
+        Err(unsupported())
+    }
+}
+
+impl HasFdFlavor for VnodeFdInfo {
+    fn read_fd(_pid: Pid, _fd: Fd) -> Result<Option<Self>, std::io::Error> {
+        Err(unsupported())
+    }
+}
+
+impl HasFdFlavor for VnodeFdInfoWithPath {
+    fn read_fd(_pid: Pid, _fd: Fd) -> Result<Option<Self>, std::io::Error> {
+        Err(unsupported())
+    }
+}
+
+/// List the ids of all processes on the system. Always fails on this platform.
+pub fn proc_listallpids() -> Result<Vec<Pid>, std::io::Error> {
+    Err(unsupported())
+}
+
+/// Get the path of the executable of a given process. Always fails on this platform.
+pub fn proc_pidpath(_pid: Pid) -> Result<PathBuf, std::io::Error> {
+    Err(unsupported())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::portable::{getpid, proc_pidinfo, proc_pidinfo_list};
+
+    #[test]
+    fn test_unsupported() {
+        let err = proc_pidinfo::<ProcTaskInfo>(getpid()).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+        let err = proc_pidinfo_list::<ProcFDInfo>(getpid()).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+        assert!(proc_listallpids().is_err());
+    }
+}
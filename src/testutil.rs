@@ -0,0 +1,48 @@
+//! Helpers shared by the tests of both implementations.
+
+use std::ops::{Deref, DerefMut};
+use std::process::{Child, Command};
+
+use crate::Pid;
+
+/// A child process for a test to inspect. It is killed and reaped when dropped, so a failing
+/// test doesn't leave it behind.
+#[derive(Debug)]
+pub(crate) struct TestChild(Child);
+
+impl TestChild {
+    /// Spawn `/bin/sleep 10`.
+    pub(crate) fn sleep() -> Self {
+        Self::spawn(Command::new("/bin/sleep").arg("10"))
+    }
+
+    pub(crate) fn spawn(command: &mut Command) -> Self {
+        Self(command.spawn().unwrap())
+    }
+
+    pub(crate) fn pid(&self) -> Pid {
+        Pid(self.0.id())
+    }
+}
+
+impl Deref for TestChild {
+    type Target = Child;
+
+    fn deref(&self) -> &Child {
+        &self.0
+    }
+}
+
+impl DerefMut for TestChild {
+    fn deref_mut(&mut self) -> &mut Child {
+        &mut self.0
+    }
+}
+
+impl Drop for TestChild {
+    fn drop(&mut self) {
+        // Either may fail if the test already reaped the child.
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
@@ -0,0 +1,68 @@
+#![cfg(all(target_vendor = "apple", feature = "cli"))]
+
+//! Runs the `pidinfo` binary against the test process.
+
+use std::process::{Command, Output};
+
+fn pidinfo(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_pidinfo"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn pidinfo_json(args: &[&str]) -> Vec<serde_json::Value> {
+    let output = pidinfo(&[&["--json"], args].concat());
+    assert!(output.status.success(), "{output:?}");
+    serde_json::from_slice::<serde_json::Value>(&output.stdout)
+        .unwrap()
+        .as_array()
+        .unwrap()
+        .clone()
+}
+
+#[test]
+fn test_fds() {
+    let file = std::env::current_exe().unwrap();
+    let _open = std::fs::File::open(&file).unwrap();
+    let pid = std::process::id().to_string();
+    let rows = pidinfo_json(&["fds", &pid]);
+    assert!(rows.iter().any(|row| row["type"] == "VNODE"
+        && row["detail"]
+            .as_str()
+            .is_some_and(|path| path.ends_with(file.file_name().unwrap().to_str().unwrap()))));
+
+    let output = pidinfo(&["fds", &pid]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("FD"));
+}
+
+#[test]
+fn test_task_and_sockets() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let local = listener.local_addr().unwrap().to_string();
+    let pid = std::process::id().to_string();
+    let rows = pidinfo_json(&["task", &pid]);
+    assert!(rows
+        .iter()
+        .any(|row| row["metric"] == "threads" && row["value"].as_u64() > Some(0)));
+    let rows = pidinfo_json(&["sockets", &pid]);
+    let row = rows.iter().find(|row| row["local"] == local).unwrap();
+    assert_eq!(row["family"], "inet");
+    assert_eq!(row["protocol"], "tcp");
+    assert_eq!(row["foreign"], "0.0.0.0:0");
+    assert_eq!(row["state"], "LISTEN");
+}
+
+#[test]
+fn test_tree() {
+    let rows = pidinfo_json(&["tree"]);
+    let pid = std::process::id();
+    assert!(rows.iter().any(|row| row["pid"] == pid));
+}
+
+#[test]
+fn test_usage() {
+    assert_eq!(pidinfo(&[]).status.code(), Some(2));
+    assert_eq!(pidinfo(&["fds", "nope"]).status.code(), Some(2));
+}
@@ -0,0 +1,185 @@
+//! Spawns a copy of the current test binary as a helper process that opens known resources,
+//! so tests can check what the crate reports about another process.
+//!
+//! Each test file that uses the helper must contain:
+//!
+//! ```ignore
+//! #[test]
+//! fn helper() {
+//!     common::helper_main();
+//! }
+//! ```
+
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::fd::AsRawFd;
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, Command, Stdio};
+
+use proc_pidinfo::{Fd, Pid};
+
+const HELPER_ENV: &str = "PROC_PIDINFO_TEST_HELPER";
+const CHILD_ENV: &str = "PROC_PIDINFO_TEST_CHILD";
+const REPORT_PREFIX: &str = "HELPER_REPORT";
+
+/// A running helper process. The helper exits when this is dropped.
+pub struct Helper {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    report: HashMap<String, String>,
+}
+
+impl Helper {
+    /// Start the helper and wait until it has opened its resources.
+    pub fn spawn() -> Self {
+        let mut child = Command::new(std::env::current_exe().unwrap())
+            .args(["--exact", "helper", "--nocapture", "--test-threads=1"])
+            .env(HELPER_ENV, "1")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdin = child.stdin.take();
+        let mut stdout = BufReader::new(child.stdout.take().unwrap());
+        let mut line = String::new();
+        // The test harness prints its own output before the helper's report.
+        let report = loop {
+            line.clear();
+            assert_ne!(
+                stdout.read_line(&mut line).unwrap(),
+                0,
+                "helper exited early"
+            );
+            if let Some(report) = line.trim_end().strip_prefix(REPORT_PREFIX) {
+                break report
+                    .split_whitespace()
+                    .filter_map(|entry| entry.split_once('='))
+                    .map(|(key, value)| (key.to_owned(), value.to_owned()))
+                    .collect();
+            }
+        };
+        Self {
+            child,
+            stdin,
+            report,
+        }
+    }
+
+    pub fn pid(&self) -> Pid {
+        Pid(self.child.id())
+    }
+
+    /// A descriptor that the helper reported.
+    pub fn fd(&self, name: &str) -> Fd {
+        Fd(self.report[name].parse().unwrap())
+    }
+
+    /// A path that the helper reported.
+    pub fn path(&self, name: &str) -> PathBuf {
+        PathBuf::from(&self.report[name])
+    }
+}
+
+impl Drop for Helper {
+    fn drop(&mut self) {
+        // Closing stdin tells the helper to exit.
+        drop(self.stdin.take());
+        let _ = self.child.wait();
+    }
+}
+
+/// Run a test in a copy of the test binary, for tests that change process-wide state such as
+/// resource limits. Returns true in the copy, where the test should do its work, and false
+/// after checking that the copy passed.
+///
+/// ```ignore
+/// #[test]
+/// fn test_something() {
+///     if !common::in_child("test_something") {
+///         return;
+///     }
+///     // ...
+/// }
+/// ```
+pub fn in_child(test: &str) -> bool {
+    if std::env::var_os(CHILD_ENV).is_some() {
+        return true;
+    }
+    let status = Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", test, "--test-threads=1"])
+        .env(CHILD_ENV, "1")
+        .status()
+        .unwrap();
+    assert!(status.success(), "{test} failed in a child process");
+    false
+}
+
+/// The offset the helper seeks to in its file.
+pub const FILE_OFFSET: i64 = 5;
+
+/// The body of the helper. Does nothing unless running as a helper.
+pub fn helper_main() {
+    if std::env::var_os(HELPER_ENV).is_none() {
+        return;
+    }
+    let pid = std::process::id();
+
+    // The kernel reports resolved paths, eg: `/private/var` rather than `/var`.
+    let path = std::env::temp_dir()
+        .canonicalize()
+        .unwrap()
+        .join(format!("proc_pidinfo_helper_{pid}"));
+    let mut file = std::fs::File::create(&path).unwrap();
+    file.write_all(b"hello world").unwrap();
+    std::io::Seek::seek(&mut file, std::io::SeekFrom::Start(FILE_OFFSET as u64)).unwrap();
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let (pipe_read, pipe_write) = std::io::pipe().unwrap();
+
+    // POSIX semaphores and shared memory live in the fd table, and stay open after unlinking.
+    let sem_name = CString::new(format!("/proc_pidinfo_sem_{pid}")).unwrap();
+    // SAFETY: The name is a valid C string, and the mode and value match O_CREAT.
+    let sem = unsafe {
+        libc::sem_open(
+            sem_name.as_ptr(),
+            libc::O_CREAT,
+            0o600 as libc::c_uint,
+            0 as libc::c_uint,
+        )
+    };
+    assert_ne!(sem, libc::SEM_FAILED);
+    let shm_name = CString::new(format!("/proc_pidinfo_shm_{pid}")).unwrap();
+    // SAFETY: The name is a valid C string, and the mode matches O_CREAT.
+    let shm = unsafe {
+        libc::shm_open(
+            shm_name.as_ptr(),
+            libc::O_CREAT | libc::O_RDWR,
+            0o600 as libc::c_uint,
+        )
+    };
+    assert!(shm >= 0);
+    // SAFETY: The names are valid C strings.
+    unsafe {
+        libc::sem_unlink(sem_name.as_ptr());
+        libc::shm_unlink(shm_name.as_ptr());
+    }
+
+    println!(
+        "{REPORT_PREFIX} file={} path={} listener={} pipe_read={} pipe_write={} shm={}",
+        file.as_raw_fd(),
+        path.display(),
+        listener.as_raw_fd(),
+        pipe_read.as_raw_fd(),
+        pipe_write.as_raw_fd(),
+        shm,
+    );
+    std::io::stdout().flush().unwrap();
+
+    // Hold everything open until the parent closes stdin.
+    let _ = std::io::stdin().read_to_end(&mut vec![]);
+    std::fs::remove_file(&path).unwrap();
+    std::process::exit(0);
+}
@@ -0,0 +1,74 @@
+#![cfg(target_vendor = "apple")]
+
+mod common;
+
+use common::{Helper, FILE_OFFSET};
+use proc_pidinfo::*;
+
+#[test]
+fn helper() {
+    common::helper_main();
+}
+
+fn fd_type(helper: &Helper, fd: Fd) -> ProcFDType {
+    let fds = proc_pidinfo_list::<ProcFDInfo>(helper.pid()).unwrap();
+    let info = fds.iter().find(|info| info.proc_fd == fd).unwrap();
+    info.fd_type()
+}
+
+#[test]
+fn test_helper_vnode() {
+    let helper = Helper::spawn();
+    let fd = helper.fd("file");
+    assert_eq!(fd_type(&helper, fd), ProcFDType::VNODE);
+
+    let vnode = proc_pidfdinfo::<VnodeFdInfoWithPath>(helper.pid(), fd)
+        .unwrap()
+        .unwrap();
+    assert_eq!(vnode.to_path_buf().unwrap(), helper.path("path"));
+    assert_eq!(vnode.pfi.fi_offset, FILE_OFFSET);
+    assert_eq!(vnode.pvip.vip_vi.vi_stat.vst_size, 11);
+}
+
+#[test]
+fn test_helper_pipe() {
+    let helper = Helper::spawn();
+    let (read, write) = (helper.fd("pipe_read"), helper.fd("pipe_write"));
+    assert_eq!(fd_type(&helper, read), ProcFDType::PIPE);
+    assert_eq!(fd_type(&helper, write), ProcFDType::PIPE);
+
+    let read = proc_pidfdinfo::<PipeFdInfo>(helper.pid(), read)
+        .unwrap()
+        .unwrap();
+    let write = proc_pidfdinfo::<PipeFdInfo>(helper.pid(), write)
+        .unwrap()
+        .unwrap();
+    assert_eq!(read.pipe_info.pipe_peerhandle, write.pipe_info.pipe_handle);
+    assert_eq!(write.pipe_info.pipe_peerhandle, read.pipe_info.pipe_handle);
+
+    // A pipe isn't a vnode.
+    let res = proc_pidfdinfo::<VnodeFdInfo>(helper.pid(), helper.fd("pipe_read"));
+    assert!(!matches!(res, Ok(Some(_))));
+}
+
+#[test]
+fn test_helper_socket_and_ipc() {
+    let helper = Helper::spawn();
+    assert_eq!(fd_type(&helper, helper.fd("listener")), ProcFDType::SOCKET);
+    assert_eq!(fd_type(&helper, helper.fd("shm")), ProcFDType::PSHM);
+
+    let fds = proc_pidinfo_list::<ProcFDInfo>(helper.pid()).unwrap();
+    assert!(fds.iter().any(|info| info.fd_type() == ProcFDType::PSEM));
+}
+
+#[test]
+fn test_helper_exit() {
+    let helper = Helper::spawn();
+    let pid = helper.pid();
+    assert!(proc_listallpids().unwrap().contains(&pid));
+    drop(helper);
+    assert!(!matches!(
+        proc_pidinfo::<ProcBSDShortInfo>(pid),
+        Ok(Some(_))
+    ));
+}
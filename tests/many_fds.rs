@@ -0,0 +1,69 @@
+#![cfg(target_vendor = "apple")]
+
+//! Lists the descriptors of a process with tens of thousands of them open. This raises
+//! `RLIMIT_NOFILE`, so it runs in a child process rather than alongside other tests.
+
+mod common;
+
+use std::collections::HashSet;
+use std::os::fd::AsRawFd;
+
+use libc::{c_int, c_void};
+use proc_pidinfo::*;
+
+// Opens as many descriptors as the limits allow (up to ~70k) and checks that the list
+// contains all of them.
+#[test]
+fn test_proc_pidinfo_list_many_fds() {
+    if !common::in_child("test_proc_pidinfo_list_many_fds") {
+        return;
+    }
+    const TARGET: u64 = 70_000;
+    let mut maxfiles: c_int = 0;
+    let mut len = std::mem::size_of::<c_int>();
+    // SAFETY: The output buffer is a c_int and its size is passed along.
+    let res = unsafe {
+        libc::sysctlbyname(
+            c"kern.maxfilesperproc".as_ptr(),
+            &mut maxfiles as *mut c_int as *mut c_void,
+            &mut len,
+            std::ptr::null_mut(),
+            0,
+        )
+    };
+    assert_eq!(res, 0);
+
+    // SAFETY: getrlimit/setrlimit only read and write the struct we pass.
+    let limit = unsafe {
+        let mut rlim: libc::rlimit = std::mem::zeroed();
+        assert_eq!(libc::getrlimit(libc::RLIMIT_NOFILE, &mut rlim), 0);
+        rlim.rlim_cur = rlim.rlim_max.min(maxfiles as u64).min(TARGET + 1024);
+        libc::setrlimit(libc::RLIMIT_NOFILE, &rlim);
+        assert_eq!(libc::getrlimit(libc::RLIMIT_NOFILE, &mut rlim), 0);
+        rlim.rlim_cur
+    };
+
+    // Leave some headroom for the test harness.
+    let count = limit.saturating_sub(512).min(TARGET);
+    let null = std::fs::File::open("/dev/null").unwrap();
+    let mut opened = vec![];
+    for _ in 0..count {
+        // SAFETY: dup has no memory safety requirements.
+        let fd = unsafe { libc::dup(null.as_raw_fd()) };
+        if fd < 0 {
+            break;
+        }
+        opened.push(fd);
+    }
+
+    let fds = proc_pidinfo_list_self::<ProcFDInfo>();
+    for fd in &opened {
+        // SAFETY: We own these descriptors.
+        unsafe { libc::close(*fd) };
+    }
+
+    let fds = fds.unwrap();
+    assert!(fds.len() >= opened.len());
+    let listed = fds.iter().map(|fd| fd.proc_fd.0).collect::<HashSet<_>>();
+    assert!(opened.iter().all(|fd| listed.contains(fd)));
+}